@@ -51,7 +51,7 @@ fn main() {
         .expect("Reading configuration");
 
     let (user_path, allow_overwrite) = CONFIG
-        .read(|conf| (conf.user_path.clone(), conf.allow_overwrite.clone()))
+        .read(|conf| (conf.user_path.clone(), conf.allow_overwrite))
         .expect("Read config");
 
     println!(
@@ -0,0 +1,64 @@
+// This reads an example configuration like `config.rs` does, but keeps the
+// database in a `StaticDatabase` instead of a `lazy_static`.
+// If it doesn't find one, it uses your default configuration
+//
+// You can create one by writing this file to `/tmp/config.ron`:
+// ```
+// ---
+// user_path: /tmp/nope
+// allow_overwrite: true
+// ```
+//
+
+#[macro_use]
+extern crate serde_derive;
+
+use rustbreak::backend::PathBackend;
+use rustbreak::deser::Ron;
+use rustbreak::global::StaticDatabase;
+use rustbreak::PathDatabase;
+use std::default::Default;
+use std::path::PathBuf;
+
+static CONFIG: StaticDatabase<Config, PathBackend, Ron> = StaticDatabase::new();
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Config {
+    user_path: PathBuf,
+    allow_overwrite: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            user_path: PathBuf::from("/tmp"),
+            allow_overwrite: false,
+        }
+    }
+}
+
+fn main() {
+    let config = CONFIG
+        .get_or_try_init(|| {
+            let db = PathDatabase::load_from_path_or_default(PathBuf::from("/tmp/config.ron"))?;
+            db.load()?;
+            Ok(db)
+        })
+        .expect("Config to load");
+
+    let _conf: Config = config
+        .read(|conf| conf.clone())
+        .expect("Reading configuration");
+
+    // Anywhere else in the program, the database is opened already.
+    let (user_path, allow_overwrite) = CONFIG
+        .get()
+        .expect("Config was opened in main")
+        .read(|conf| (conf.user_path.clone(), conf.allow_overwrite))
+        .expect("Read config");
+
+    println!(
+        "The current configuration is: {:?} and {}",
+        user_path, allow_overwrite
+    );
+}
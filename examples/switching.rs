@@ -1,7 +1,7 @@
 #[macro_use]
 extern crate serde_derive;
 
-use rustbreak::deser::{Ron, Yaml};
+use rustbreak::deser::{Ron, SafeYaml};
 use rustbreak::{backend::FileBackend, FileDatabase};
 
 #[derive(Eq, PartialEq, Debug, Serialize, Deserialize, Clone)]
@@ -46,7 +46,7 @@ fn do_main() -> Result<(), rustbreak::RustbreakError> {
     // Now lets switch it
 
     let db = db
-        .with_deser(Yaml)
+        .with_deser(SafeYaml)
         .with_backend(FileBackend::from_path_or_create("test.yml").map(|p| p.0)?);
     db.save()?;
 
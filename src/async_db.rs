@@ -0,0 +1,588 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! An async variant of the [`Database`](crate::Database).
+//!
+//! [`AsyncDatabase`] mirrors the blocking API, but uses `tokio` locks and an
+//! [`AsyncBackend`] so that loading and saving don't block the executor. The
+//! closures passed to [`AsyncDatabase::read`] and [`AsyncDatabase::write`] are
+//! still synchronous, they should not do any I/O.
+//!
+//! De/serialization happens on the calling task, just like in the blocking
+//! version.
+//!
+//! **Important**: This module is only available with the `tokio` feature.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # extern crate rustbreak;
+//! # extern crate tokio;
+//! # use std::collections::HashMap;
+//! use rustbreak::{async_db::AsyncMemoryDatabase, deser::Ron};
+//!
+//! # fn main() {
+//! # let func = async {
+//! let db = AsyncMemoryDatabase::<HashMap<u32, String>, Ron>::memory(HashMap::new());
+//!
+//! db.write(|db| {
+//!     db.insert(0, String::from("world"));
+//! })
+//! .await;
+//! db.save().await?;
+//!
+//! let value = db.read(|db| db.get(&0).cloned()).await;
+//! assert_eq!(Some(String::from("world")), value);
+//! # Ok::<(), rustbreak::RustbreakError>(())
+//! # };
+//! # tokio::runtime::Builder::new_current_thread()
+//! #     .build()
+//! #     .unwrap()
+//! #     .block_on(func)
+//! #     .unwrap();
+//! # }
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::backend::{Backend, MemoryBackend};
+use crate::error;
+use crate::DeSerializer;
+
+/// The async counterpart of the [`Backend`] trait.
+///
+/// The same rules apply: it should always read and save in full the data
+/// that it is passed.
+///
+/// **Important**: You can only return custom errors if the `other_errors`
+/// feature is enabled
+#[async_trait]
+pub trait AsyncBackend: Send {
+    /// Read the all data from the backend.
+    async fn get_data(&mut self) -> error::BackendResult<Vec<u8>>;
+
+    /// Write the whole slice to the backend.
+    async fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()>;
+}
+
+#[async_trait]
+impl AsyncBackend for Box<dyn AsyncBackend> {
+    async fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        (**self).get_data().await
+    }
+
+    async fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        (**self).put_data(data).await
+    }
+}
+
+#[async_trait]
+impl<T: AsyncBackend> AsyncBackend for Box<T> {
+    async fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        (**self).get_data().await
+    }
+
+    async fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        (**self).put_data(data).await
+    }
+}
+
+/// The memory backend never blocks, so it can be used in both worlds.
+#[async_trait]
+impl AsyncBackend for MemoryBackend {
+    async fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        Backend::get_data(self)
+    }
+
+    async fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        Backend::put_data(self, data)
+    }
+}
+
+/// An async backend using a file, see [`FileBackend`](crate::backend::FileBackend).
+#[derive(Debug)]
+pub struct AsyncFileBackend(tokio::fs::File);
+
+impl AsyncFileBackend {
+    /// Use an already open [`File`](tokio::fs::File) as the backend.
+    #[must_use]
+    pub fn from_file(file: tokio::fs::File) -> Self {
+        Self(file)
+    }
+
+    /// Return the inner File.
+    #[must_use]
+    pub fn into_inner(self) -> tokio::fs::File {
+        self.0
+    }
+
+    /// Opens a new [`AsyncFileBackend`] for a given path.
+    /// Errors when the file doesn't yet exist.
+    pub async fn from_path_or_fail<P: AsRef<Path>>(path: P) -> error::BackendResult<Self> {
+        Ok(Self(
+            tokio::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(path)
+                .await?,
+        ))
+    }
+
+    /// Opens a new [`AsyncFileBackend`] for a given path.
+    /// Creates a file if it doesn't yet exist.
+    ///
+    /// Returns the [`AsyncFileBackend`] and whether the file already existed.
+    pub async fn from_path_or_create<P: AsRef<Path>>(
+        path: P,
+    ) -> error::BackendResult<(Self, bool)> {
+        let exists = is_file(path.as_ref()).await;
+        let file = tokio::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .await?;
+        Ok((Self(file), exists))
+    }
+}
+
+#[async_trait]
+impl AsyncBackend for AsyncFileBackend {
+    async fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        use tokio::io::AsyncReadExt;
+
+        let mut buffer = vec![];
+        self.0.seek(std::io::SeekFrom::Start(0)).await?;
+        self.0.read_to_end(&mut buffer).await?;
+        Ok(buffer)
+    }
+
+    async fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        self.0.seek(std::io::SeekFrom::Start(0)).await?;
+        self.0.set_len(0).await?;
+        self.0.write_all(data).await?;
+        self.0.sync_all().await?;
+        Ok(())
+    }
+}
+
+/// An async backend using a file given the path, see
+/// [`PathBackend`](crate::backend::PathBackend).
+///
+/// Features atomic saves: the data is written to a temporary file next to the
+/// database file, which is then renamed over it.
+#[derive(Debug)]
+pub struct AsyncPathBackend {
+    path: PathBuf,
+}
+
+impl AsyncPathBackend {
+    /// Opens a new [`AsyncPathBackend`] for a given path.
+    /// Errors when the file doesn't yet exist.
+    pub async fn from_path_or_fail(path: PathBuf) -> error::BackendResult<Self> {
+        tokio::fs::OpenOptions::new().read(true).open(&path).await?;
+        Ok(Self { path })
+    }
+
+    /// Opens a new [`AsyncPathBackend`] for a given path.
+    /// Creates a file if it doesn't yet exist.
+    ///
+    /// Returns the [`AsyncPathBackend`] and whether the file already existed.
+    pub async fn from_path_or_create(path: PathBuf) -> error::BackendResult<(Self, bool)> {
+        let exists = is_file(&path).await;
+        tokio::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .await?;
+        Ok((Self { path }, exists))
+    }
+
+    /// Create a temporary file next to the database file.
+    async fn create_temp_file(&self) -> error::BackendResult<(PathBuf, tokio::fs::File)> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        #[allow(clippy::or_fun_call)] // `Path::new` is a zero cost conversion
+        let dir = self.path.parent().unwrap_or(Path::new("."));
+        let name = self
+            .path
+            .file_name()
+            .map_or_else(|| "rustbreak".into(), |name| name.to_string_lossy());
+        loop {
+            let temp_path = dir.join(format!(
+                ".{}.{}.{}.tmp",
+                name,
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            match tokio::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&temp_path)
+                .await
+            {
+                Ok(file) => return Ok((temp_path, file)),
+                Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => return Err(e.into()),
+                Err(_) => {}
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AsyncBackend for AsyncPathBackend {
+    async fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        Ok(tokio::fs::read(&self.path).await?)
+    }
+
+    /// Write the byte slice to the backend. This uses an atomic save.
+    async fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        let (temp_path, mut file) = self.create_temp_file().await?;
+        let written = async {
+            file.write_all(data).await?;
+            file.sync_all().await?;
+            drop(file);
+            tokio::fs::rename(&temp_path, &self.path).await
+        }
+        .await;
+        if let Err(e) = written {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
+
+async fn is_file(path: &Path) -> bool {
+    tokio::fs::metadata(path)
+        .await
+        .is_ok_and(|meta| meta.is_file())
+}
+
+/// The async counterpart of [`Database`](crate::Database).
+///
+/// It has the same 3 type generics, the backend has to implement
+/// [`AsyncBackend`] instead of [`Backend`].
+///
+/// Since `tokio` locks can not be poisoned, reading and writing can not
+/// fail.
+#[derive(Debug)]
+pub struct AsyncDatabase<Data, Back, DeSer> {
+    data: RwLock<Data>,
+    backend: Mutex<Back>,
+    deser: DeSer,
+}
+
+impl<Data, Back, DeSer> AsyncDatabase<Data, Back, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send + Sync,
+    Back: AsyncBackend,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    /// Write lock the database and get write access to the `Data` container.
+    ///
+    /// See [`Database::write`](crate::Database::write).
+    pub async fn write<T, R>(&self, task: T) -> R
+    where
+        T: FnOnce(&mut Data) -> R,
+    {
+        let mut lock = self.data.write().await;
+        task(&mut lock)
+    }
+
+    /// Read lock the database and get read access to the `Data` container.
+    ///
+    /// See [`Database::read`](crate::Database::read).
+    pub async fn read<T, R>(&self, task: T) -> R
+    where
+        T: FnOnce(&Data) -> R,
+    {
+        let lock = self.data.read().await;
+        task(&lock)
+    }
+
+    /// Read lock the database and get access to the underlying struct.
+    pub async fn borrow_data(&self) -> RwLockReadGuard<'_, Data> {
+        self.data.read().await
+    }
+
+    /// Write lock the database and get access to the underlying struct.
+    pub async fn borrow_data_mut(&self) -> RwLockWriteGuard<'_, Data> {
+        self.data.write().await
+    }
+
+    /// Load data from backend and return this data.
+    async fn load_from_backend(backend: &mut Back, deser: &DeSer) -> error::Result<Data> {
+        Ok(deser.deserialize(&backend.get_data().await?[..])?)
+    }
+
+    /// Like [`Self::load`] but returns the write lock to data it used.
+    async fn load_get_data_lock(&self) -> error::Result<RwLockWriteGuard<'_, Data>> {
+        let mut backend = self.backend.lock().await;
+        let fresh_data = Self::load_from_backend(&mut backend, &self.deser).await?;
+        drop(backend);
+
+        let mut data = self.data.write().await;
+        *data = fresh_data;
+        Ok(data)
+    }
+
+    /// Load the data from the backend.
+    pub async fn load(&self) -> error::Result<()> {
+        self.load_get_data_lock().await.map(|_| ())
+    }
+
+    /// Serialize `data` and write it to the backend.
+    async fn save_data(&self, data: &Data) -> error::Result<()> {
+        let ser = self.deser.serialize(data)?;
+        let mut backend = self.backend.lock().await;
+        backend.put_data(&ser).await?;
+        Ok(())
+    }
+
+    /// Flush the data structure to the backend.
+    pub async fn save(&self) -> error::Result<()> {
+        let data = self.data.read().await;
+        self.save_data(&data).await
+    }
+
+    /// Puts the data as is into memory.
+    ///
+    /// To save the data afterwards, call with `save` true.
+    pub async fn put_data(&self, new_data: Data, save: bool) -> error::Result<()> {
+        let mut data = self.data.write().await;
+        *data = new_data;
+        if save {
+            self.save_data(&data).await
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Create a database from its constituents.
+    pub fn from_parts(data: Data, backend: Back, deser: DeSer) -> Self {
+        Self {
+            data: RwLock::new(data),
+            backend: Mutex::new(backend),
+            deser,
+        }
+    }
+
+    /// Break a database into its individual parts.
+    pub fn into_inner(self) -> (Data, Back, DeSer) {
+        (
+            self.data.into_inner(),
+            self.backend.into_inner(),
+            self.deser,
+        )
+    }
+}
+
+impl<Data, Back, DeSer> AsyncDatabase<Data, Back, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Clone + Send + Sync,
+    Back: AsyncBackend,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    /// Get a clone of the data as it is in memory right now.
+    ///
+    /// To make sure you have the latest data, call this method with `load`
+    /// true.
+    pub async fn get_data(&self, load: bool) -> error::Result<Data> {
+        if load {
+            Ok(self.load_get_data_lock().await?.clone())
+        } else {
+            Ok(self.data.read().await.clone())
+        }
+    }
+}
+
+/// An async database backed by a file.
+pub type AsyncFileDatabase<D, DS> = AsyncDatabase<D, AsyncFileBackend, DS>;
+
+impl<Data, DeSer> AsyncDatabase<Data, AsyncFileBackend, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send + Sync,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    /// Create new [`AsyncFileDatabase`] from the file at [`Path`], and load
+    /// the contents.
+    pub async fn load_from_path<S>(path: S) -> error::Result<Self>
+    where
+        S: AsRef<Path>,
+    {
+        let mut backend = AsyncFileBackend::from_path_or_fail(path).await?;
+        let deser = DeSer::default();
+        let data = Self::load_from_backend(&mut backend, &deser).await?;
+        Ok(Self::from_parts(data, backend, deser))
+    }
+
+    /// Load [`AsyncFileDatabase`] at `path` or initialise with `closure`.
+    ///
+    /// If the file does not exist, `closure` is called and the database is
+    /// initialised with it's return value.
+    pub async fn load_from_path_or_else<S, C>(path: S, closure: C) -> error::Result<Self>
+    where
+        S: AsRef<Path>,
+        C: FnOnce() -> Data,
+    {
+        let (mut backend, exists) = AsyncFileBackend::from_path_or_create(path).await?;
+        let deser = DeSer::default();
+        let data = if exists {
+            Self::load_from_backend(&mut backend, &deser).await?
+        } else {
+            let data = closure();
+            backend.put_data(&deser.serialize(&data)?).await?;
+            data
+        };
+        Ok(Self::from_parts(data, backend, deser))
+    }
+}
+
+/// An async database backed by a file, using atomic saves.
+pub type AsyncPathDatabase<D, DS> = AsyncDatabase<D, AsyncPathBackend, DS>;
+
+impl<Data, DeSer> AsyncDatabase<Data, AsyncPathBackend, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send + Sync,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    /// Create new [`AsyncPathDatabase`] from the file at [`Path`], and load
+    /// the contents.
+    pub async fn load_from_path(path: PathBuf) -> error::Result<Self> {
+        let mut backend = AsyncPathBackend::from_path_or_fail(path).await?;
+        let deser = DeSer::default();
+        let data = Self::load_from_backend(&mut backend, &deser).await?;
+        Ok(Self::from_parts(data, backend, deser))
+    }
+
+    /// Load [`AsyncPathDatabase`] at `path` or initialise with `closure`.
+    ///
+    /// If the file does not exist, `closure` is called and the database is
+    /// initialised with it's return value.
+    pub async fn load_from_path_or_else<C>(path: PathBuf, closure: C) -> error::Result<Self>
+    where
+        C: FnOnce() -> Data,
+    {
+        let (mut backend, exists) = AsyncPathBackend::from_path_or_create(path).await?;
+        let deser = DeSer::default();
+        let data = if exists {
+            Self::load_from_backend(&mut backend, &deser).await?
+        } else {
+            let data = closure();
+            backend.put_data(&deser.serialize(&data)?).await?;
+            data
+        };
+        Ok(Self::from_parts(data, backend, deser))
+    }
+}
+
+impl<Data, DeSer> AsyncDatabase<Data, AsyncPathBackend, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send + Sync + Default,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    /// Load [`AsyncPathDatabase`] at `path` or initialise with
+    /// `Data::default()`.
+    pub async fn load_from_path_or_default(path: PathBuf) -> error::Result<Self> {
+        Self::load_from_path_or_else(path, Data::default).await
+    }
+}
+
+/// An async database backed by a byte vector (`Vec<u8>`).
+pub type AsyncMemoryDatabase<D, DS> = AsyncDatabase<D, MemoryBackend, DS>;
+
+impl<Data, DeSer> AsyncDatabase<Data, MemoryBackend, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send + Sync,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    /// Create new in-memory database.
+    pub fn memory(data: Data) -> Self {
+        Self::from_parts(data, MemoryBackend::new(), DeSer::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AsyncBackend, AsyncFileBackend, AsyncMemoryDatabase, AsyncPathDatabase};
+    use crate::deser::Ron;
+    use std::collections::HashMap;
+
+    type TestData = HashMap<usize, String>;
+
+    fn test_data() -> TestData {
+        let mut data = HashMap::new();
+        data.insert(1, "Hello World".to_string());
+        data.insert(100, "Rustbreak".to_string());
+        data
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("could not build runtime")
+            .block_on(future)
+    }
+
+    #[test]
+    fn memory_save_load() {
+        block_on(async {
+            let db = AsyncMemoryDatabase::<TestData, Ron>::memory(test_data());
+            db.save().await.expect("Rustbreak save error");
+            db.write(HashMap::clear).await;
+            db.load().await.expect("Rustbreak load error");
+            assert_eq!(test_data(), *db.borrow_data().await);
+        });
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn path_database_roundtrip() {
+        block_on(async {
+            let dir = tempfile::tempdir().expect("could not create temporary directory");
+            let path = dir.path().join("rustbreak_async.db");
+            let db = AsyncPathDatabase::<TestData, Ron>::load_from_path_or_default(path.clone())
+                .await
+                .expect("could not create database");
+            db.put_data(test_data(), true)
+                .await
+                .expect("could not put data");
+            drop(db);
+
+            let db = AsyncPathDatabase::<TestData, Ron>::load_from_path(path)
+                .await
+                .expect("could not load database");
+            assert_eq!(test_data(), db.get_data(false).await.expect("no data"));
+            dir.close().expect("Error while deleting temp directory!");
+        });
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn file_backend() {
+        block_on(async {
+            let file = tempfile::tempfile().expect("could not create temporary file");
+            let mut backend = AsyncFileBackend::from_file(tokio::fs::File::from_std(file));
+            let data = [4, 5, 1, 6, 8, 1];
+            let data2 = [3, 99, 127, 6];
+
+            backend.put_data(&data).await.expect("could not put data");
+            assert_eq!(backend.get_data().await.expect("could not get data"), data);
+
+            backend.put_data(&data2).await.expect("could not put data");
+            assert_eq!(backend.get_data().await.expect("could not get data"), data2);
+        });
+    }
+}
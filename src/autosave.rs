@@ -0,0 +1,677 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Automatic persistence of a database.
+//!
+//! Set a policy with
+//! [`Database::set_autosave_policy`](crate::Database::set_autosave_policy).
+//! [`AutoSavePolicy::AfterEveryWrite`] works on its own, the other policies
+//! are driven by the [`AutoSaveHandle`] returned from
+//! [`Database::start_autosave`](crate::Database::start_autosave).
+//!
+//! # Examples
+//!
+//! ```rust
+//! # extern crate rustbreak;
+//! use rustbreak::{autosave::AutoSavePolicy, deser::Ron, MemoryDatabase};
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//!
+//! # fn main() {
+//! # let func = || -> Result<(), Box<dyn std::error::Error>> {
+//! let db = Arc::new(MemoryDatabase::<Vec<u32>, Ron>::memory(vec![])?);
+//! db.set_autosave_policy(AutoSavePolicy::Debounced(Duration::from_millis(100)))?;
+//! let autosave = db.start_autosave();
+//!
+//! // Both writes end up in a single save, 100ms after the last one.
+//! db.write(|db| db.push(1))?;
+//! db.write(|db| db.push(2))?;
+//!
+//! // Stops the background thread and saves the pending changes.
+//! autosave.shutdown()?;
+//! # return Ok(()); };
+//! # func().unwrap();
+//! # }
+//! ```
+
+use std::fmt;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Weak};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::backend::Backend;
+use crate::coalesce::AwaitSave;
+use crate::error::{self, RustbreakError};
+use crate::registry::Flush;
+use crate::{Database, DeSerializer};
+
+/// When a database should persist itself.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AutoSavePolicy {
+    /// Only save when [`Database::save`](crate::Database::save) is called.
+    #[default]
+    Never,
+    /// Save at the end of every [`Database::write`](crate::Database::write),
+    /// [`Database::write_safe`](crate::Database::write_safe) and
+    /// [`Database::put_data`](crate::Database::put_data).
+    ///
+    /// A failed save is returned from the write that triggered it, the
+    /// change stays in memory.
+    AfterEveryWrite,
+    /// Save once no write happened for the given duration.
+    Debounced(Duration),
+    /// Save every time the given duration passed, if the database
+    /// [is dirty](crate::Database::is_dirty).
+    Periodic(Duration),
+    /// Save once the [`AutoSaveHandle`] is shut down or dropped.
+    OnDrop,
+}
+
+struct State {
+    policy: AutoSavePolicy,
+    /// When the latest write that was not saved by a debounced save happened.
+    pending: Option<Instant>,
+    /// The first error a background save ran into.
+    error: Option<RustbreakError>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+/// The auto-save configuration of a database.
+pub(crate) struct AutoSave {
+    shared: Arc<Shared>,
+}
+
+impl Default for AutoSave {
+    fn default() -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    policy: AutoSavePolicy::default(),
+                    pending: None,
+                    error: None,
+                }),
+                changed: Condvar::new(),
+            }),
+        }
+    }
+}
+
+impl fmt::Debug for AutoSave {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let policy = self.shared.lock().ok().map(|state| state.policy);
+        f.debug_struct("AutoSave")
+            .field("policy", &policy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> error::Result<MutexGuard<'_, State>> {
+        self.state.lock().map_err(|_| RustbreakError::Poison)
+    }
+}
+
+impl AutoSave {
+    pub(crate) fn policy(&self) -> error::Result<AutoSavePolicy> {
+        Ok(self.shared.lock()?.policy)
+    }
+
+    pub(crate) fn set_policy(&self, policy: AutoSavePolicy) -> error::Result<()> {
+        let mut state = self.shared.lock()?;
+        state.policy = policy;
+        self.shared.changed.notify_all();
+        Ok(())
+    }
+
+    /// Record that the data was written to, returns whether it should be
+    /// saved right away.
+    pub(crate) fn record_write(&self) -> error::Result<bool> {
+        let mut state = self.shared.lock()?;
+        match state.policy {
+            AutoSavePolicy::AfterEveryWrite => Ok(true),
+            AutoSavePolicy::Debounced(_) => {
+                // Debouncing measures from the last write, so restart the
+                // clock on every one of them.
+                state.pending = Some(Instant::now());
+                self.shared.changed.notify_all();
+                Ok(false)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Start the background thread for `db`, which has to own this
+    /// configuration.
+    pub(crate) fn start(&self, db: Arc<dyn Flush>) -> AutoSaveHandle {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let shared = Arc::clone(&self.shared);
+            let db = Arc::downgrade(&db);
+            let stop = Arc::clone(&stop);
+            std::thread::spawn(move || run(&shared, &db, &stop))
+        };
+        AutoSaveHandle {
+            shared: Arc::clone(&self.shared),
+            db,
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+/// The body of the background thread.
+fn run(shared: &Shared, db: &Weak<dyn Flush>, stop: &AtomicBool) {
+    let Ok(mut state) = shared.lock() else {
+        return;
+    };
+    while !stop.load(Ordering::SeqCst) {
+        let due = match state.policy {
+            AutoSavePolicy::Periodic(period) => match shared.changed.wait_timeout(state, period) {
+                Ok((guard, timeout)) => {
+                    state = guard;
+                    // An early wake up means the policy changed or we are
+                    // stopping, both are checked again.
+                    timeout.timed_out() && state.policy == AutoSavePolicy::Periodic(period)
+                }
+                Err(_) => return,
+            },
+            AutoSavePolicy::Debounced(delay) => match state.pending {
+                Some(last) if last.elapsed() >= delay => {
+                    state.pending = None;
+                    true
+                }
+                Some(last) => {
+                    state = match shared
+                        .changed
+                        .wait_timeout(state, delay.saturating_sub(last.elapsed()))
+                    {
+                        Ok((state, _)) => state,
+                        Err(_) => return,
+                    };
+                    false
+                }
+                None => {
+                    state = match shared.changed.wait(state) {
+                        Ok(state) => state,
+                        Err(_) => return,
+                    };
+                    false
+                }
+            },
+            _ => {
+                state = match shared.changed.wait(state) {
+                    Ok(state) => state,
+                    Err(_) => return,
+                };
+                false
+            }
+        };
+        if !due || stop.load(Ordering::SeqCst) {
+            continue;
+        }
+
+        // Saving takes the database locks, do not hold ours meanwhile.
+        drop(state);
+        let result = match db.upgrade() {
+            Some(db) => db.flush(),
+            None => return,
+        };
+        state = match shared.lock() {
+            Ok(state) => state,
+            Err(_) => return,
+        };
+        if let Err(e) = result {
+            state.error.get_or_insert(e);
+        }
+    }
+}
+
+/// Drives the auto-save policy of a database in the background.
+///
+/// Created by [`Database::start_autosave`](crate::Database::start_autosave).
+/// Dropping the handle stops the background thread and performs the final
+/// save, but has to ignore any error. Use [`AutoSaveHandle::shutdown`] to
+/// handle them.
+#[must_use = "the background thread is stopped when the handle is dropped"]
+pub struct AutoSaveHandle {
+    shared: Arc<Shared>,
+    db: Arc<dyn Flush>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl fmt::Debug for AutoSaveHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AutoSaveHandle")
+            .field("running", &self.thread.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl AutoSaveHandle {
+    /// Stop the background thread and persist what has not been saved yet.
+    ///
+    /// The final save happens for [`AutoSavePolicy::Debounced`] if a write is
+    /// still waiting, and for [`AutoSavePolicy::Periodic`] and
+    /// [`AutoSavePolicy::OnDrop`] if the database
+    /// [is dirty](crate::Database::is_dirty).
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error a background save ran into, or the error of
+    /// the final save.
+    pub fn shutdown(mut self) -> error::Result<()> {
+        self.stop_thread()
+    }
+
+    fn stop_thread(&mut self) -> error::Result<()> {
+        let Some(thread) = self.thread.take() else {
+            return Ok(());
+        };
+        {
+            // Setting the flag while holding the lock makes sure the thread
+            // either sees it or is already waiting for the notification.
+            let _state = self.shared.lock()?;
+            self.stop.store(true, Ordering::SeqCst);
+            self.shared.changed.notify_all();
+        }
+        thread.join().map_err(|_| RustbreakError::Poison)?;
+
+        let mut state = self.shared.lock()?;
+        let needs_save = match state.policy {
+            AutoSavePolicy::Debounced(_) => state.pending.take().is_some(),
+            AutoSavePolicy::Periodic(_) | AutoSavePolicy::OnDrop => true,
+            AutoSavePolicy::Never | AutoSavePolicy::AfterEveryWrite => false,
+        };
+        let background_error = state.error.take();
+        drop(state);
+
+        let result = if needs_save { self.db.flush() } else { Ok(()) };
+        match background_error {
+            Some(e) => Err(e),
+            None => result,
+        }
+    }
+}
+
+impl Drop for AutoSaveHandle {
+    fn drop(&mut self) {
+        // Errors can not be reported from drop, `shutdown` exists for that.
+        let _ = self.stop_thread();
+    }
+}
+
+/// A database that is saved when it is dropped, if it
+/// [is dirty](Database::is_dirty).
+///
+/// Created by [`Database::auto_save_on_drop`]. It dereferences to the
+/// wrapped [`Database`], so it can be used just like one. Errors can not be
+/// reported from a destructor, call [`SaveOnDrop::close`] to handle them.
+#[derive(Debug)]
+pub struct SaveOnDrop<Data, Back, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send,
+    Back: Backend,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    db: Option<Database<Data, Back, DeSer>>,
+}
+
+impl<Data, Back, DeSer> SaveOnDrop<Data, Back, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send,
+    Back: Backend,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    pub(crate) fn new(db: Database<Data, Back, DeSer>) -> Self {
+        Self { db: Some(db) }
+    }
+
+    /// Save the database and drop it, returning any error of the save.
+    pub fn close(mut self) -> error::Result<()> {
+        match self.db.take() {
+            Some(db) => db.save_if_dirty().map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Unwrap the database without saving it.
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn into_inner(mut self) -> Database<Data, Back, DeSer> {
+        // `db` is only ever taken by a method consuming `self`.
+        self.db.take().expect("database was already taken")
+    }
+}
+
+impl<Data, Back, DeSer> Deref for SaveOnDrop<Data, Back, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send,
+    Back: Backend,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    type Target = Database<Data, Back, DeSer>;
+
+    fn deref(&self) -> &Self::Target {
+        // `db` is only ever taken by a method consuming `self`.
+        self.db.as_ref().expect("database was already taken")
+    }
+}
+
+impl<Data, Back, DeSer> Drop for SaveOnDrop<Data, Back, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send,
+    Back: Backend,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    fn drop(&mut self) {
+        if let Some(db) = self.db.take() {
+            // Errors can not be reported from drop, `close` exists for that.
+            let _ = db.save_if_dirty();
+        }
+    }
+}
+
+/// Saves a database one last time when the program shuts down.
+///
+/// Created by
+/// [`Database::flush_on_shutdown`](crate::Database::flush_on_shutdown), or
+/// for every registered database by
+/// [`registry::flush_on_shutdown`](crate::registry::flush_on_shutdown).
+/// Hold it in `main`: when it is dropped, the database is saved if it
+/// [is dirty](crate::Database::is_dirty). With the `signals` feature on UNIX,
+/// the same happens when the process receives `SIGINT` or `SIGTERM`, before
+/// the signal terminates it.
+///
+/// Errors can not be reported from a destructor or a signal, call
+/// [`ShutdownGuard::flush`] at the end of `main` to handle them.
+#[must_use = "the database is saved when the guard is dropped"]
+pub struct ShutdownGuard {
+    db: Option<Arc<dyn Flush>>,
+    #[cfg(all(unix, feature = "signals"))]
+    listeners: Vec<crate::signals::ListenerId>,
+}
+
+impl fmt::Debug for ShutdownGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShutdownGuard")
+            .field("armed", &self.db.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ShutdownGuard {
+    /// Only listening for signals can fail.
+    #[cfg_attr(not(all(unix, feature = "signals")), allow(clippy::unnecessary_wraps))]
+    pub(crate) fn new(db: Arc<dyn Flush>) -> error::Result<Self> {
+        #[cfg(all(unix, feature = "signals"))]
+        let listeners = {
+            use crate::signals::{listen, Signal};
+
+            let mut listeners = Vec::new();
+            for signal in [Signal::Interrupt, Signal::Terminate] {
+                let db = Arc::downgrade(&db);
+                let listener = listen(signal, move || {
+                    if let Some(db) = db.upgrade() {
+                        // The process is about to end, there is no one left
+                        // to report the error to.
+                        let _ = db.flush();
+                    }
+                });
+                match listener {
+                    Ok(listener) => listeners.push(listener),
+                    Err(e) => {
+                        listeners.into_iter().for_each(crate::signals::unlisten);
+                        return Err(crate::error::BackendError::Io(e).into());
+                    }
+                }
+            }
+            listeners
+        };
+        Ok(Self {
+            db: Some(db),
+            #[cfg(all(unix, feature = "signals"))]
+            listeners,
+        })
+    }
+
+    /// Save the database now if it is dirty, and disarm the guard.
+    pub fn flush(mut self) -> error::Result<()> {
+        match self.db.take() {
+            Some(db) => db.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for ShutdownGuard {
+    fn drop(&mut self) {
+        #[cfg(all(unix, feature = "signals"))]
+        self.listeners.drain(..).for_each(crate::signals::unlisten);
+        if let Some(db) = self.db.take() {
+            // Errors can not be reported from drop, `flush` exists for that.
+            let _ = db.flush();
+        }
+    }
+}
+
+/// A save running in the background.
+///
+/// Created by [`Database::save_async`](crate::Database::save_async). Dropping
+/// the handle lets the save finish on its own, without a way to learn whether
+/// it succeeded.
+#[derive(Debug)]
+#[must_use = "dropping the handle ignores the result of the save"]
+pub struct SaveHandle {
+    thread: JoinHandle<error::Result<()>>,
+}
+
+impl SaveHandle {
+    pub(crate) fn spawn<F>(save: F) -> Self
+    where
+        F: FnOnce() -> error::Result<()> + Send + 'static,
+    {
+        Self {
+            thread: std::thread::spawn(save),
+        }
+    }
+
+    /// Whether the save finished, so that [`SaveHandle::wait`] returns
+    /// without blocking.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Wait for the save to finish and return its result.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the save, or [`RustbreakError::Poison`] if it
+    /// panicked.
+    pub fn wait(self) -> error::Result<()> {
+        self.thread.join().map_err(|_| RustbreakError::Poison)?
+    }
+}
+
+/// A save queued with [`Database::queue_save`](crate::Database::queue_save).
+///
+/// The ticket keeps the database alive.
+#[must_use = "a ticket does nothing unless it is waited on"]
+pub struct SaveTicket {
+    db: Arc<dyn AwaitSave>,
+    ticket: u64,
+}
+
+impl fmt::Debug for SaveTicket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SaveTicket")
+            .field("ticket", &self.ticket)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SaveTicket {
+    pub(crate) fn new(db: Arc<dyn AwaitSave>, ticket: u64) -> Self {
+        Self { db, ticket }
+    }
+
+    /// Whether the data this ticket was queued for, or newer data, is saved.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RustbreakError::Poison`] if a save panicked.
+    pub fn is_durable(&self) -> error::Result<bool> {
+        self.db.is_saved(self.ticket)
+    }
+
+    /// Wait until the data this ticket was queued for, or newer data, is
+    /// saved.
+    ///
+    /// # Errors
+    ///
+    /// If the background writer failed before saving the data, this saves in
+    /// the calling thread and returns the error of that save.
+    pub fn wait(self) -> error::Result<()> {
+        self.db.wait_for_save(self.ticket)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AutoSavePolicy;
+    use crate::deser::Ron;
+    use crate::{MemoryDatabase, PathDatabase};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tempfile::NamedTempFile;
+
+    type Db = MemoryDatabase<Vec<u32>, Ron>;
+
+    #[test]
+    fn after_every_write_saves() {
+        let db = Db::memory(vec![]).expect("could not create database");
+        db.set_autosave_policy(AutoSavePolicy::AfterEveryWrite)
+            .expect("could not set policy");
+        db.write(|data| data.push(1))
+            .expect("rustbreak write error");
+        db.write(Vec::clear).expect("rustbreak write error");
+        db.write_safe(|data| data.push(2))
+            .expect("rustbreak write error");
+        assert_eq!(vec![2], db.get_data(true).expect("could not get data"));
+    }
+
+    #[test]
+    fn shutdown_guard_saves_on_drop() {
+        let db = Arc::new(Db::memory(vec![]).expect("could not create database"));
+        let guard = db.flush_on_shutdown().expect("could not install guard");
+        db.write(|data| data.push(1))
+            .expect("rustbreak write error");
+        drop(guard);
+        assert!(!db.is_dirty());
+
+        let guard = db.flush_on_shutdown().expect("could not install guard");
+        db.write(|data| data.push(2))
+            .expect("rustbreak write error");
+        guard.flush().expect("could not flush");
+        assert_eq!(vec![1, 2], db.get_data(true).expect("could not get data"));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn debounced_saves_after_quiet_period() {
+        let db = Arc::new(Db::memory(vec![]).expect("could not create database"));
+        db.set_autosave_policy(AutoSavePolicy::Debounced(Duration::from_millis(100)))
+            .expect("could not set policy");
+        let handle = db.start_autosave();
+
+        db.write(|data| data.push(1))
+            .expect("rustbreak write error");
+        std::thread::sleep(Duration::from_millis(500));
+        db.write(|data| data.push(2))
+            .expect("rustbreak write error");
+        db.load().expect("rustbreak load error");
+        // The second write was still being debounced when we loaded.
+        assert_eq!(vec![1], db.get_data(false).expect("could not get data"));
+
+        db.write(|data| data.push(3))
+            .expect("rustbreak write error");
+        handle.shutdown().expect("autosave failed");
+        assert_eq!(vec![1, 3], db.get_data(true).expect("could not get data"));
+    }
+
+    #[test]
+    fn on_drop_saves_when_the_handle_is_dropped() {
+        let db = Arc::new(Db::memory(vec![]).expect("could not create database"));
+        db.set_autosave_policy(AutoSavePolicy::OnDrop)
+            .expect("could not set policy");
+        let handle = db.start_autosave();
+        db.write(|data| data.push(1))
+            .expect("rustbreak write error");
+        drop(handle);
+        assert_eq!(vec![1], db.get_data(true).expect("could not get data"));
+    }
+
+    #[test]
+    fn save_async_saves_in_the_background() {
+        let db = Arc::new(Db::memory(vec![]).expect("could not create database"));
+        db.write(|data| data.push(1))
+            .expect("rustbreak write error");
+        let handle = db.save_async();
+        handle.wait().expect("background save failed");
+        assert!(!db.is_dirty());
+        assert_eq!(vec![1], db.get_data(true).expect("could not get data"));
+    }
+
+    #[test]
+    fn queued_saves_become_durable() {
+        let db = Arc::new(Db::memory(vec![]).expect("could not create database"));
+        let tickets: Vec<_> = (0..50)
+            .map(|i| {
+                db.write(|data| data.push(i))
+                    .expect("rustbreak write error");
+                db.queue_save().expect("could not queue save")
+            })
+            .collect();
+        for ticket in tickets {
+            ticket.wait().expect("background save failed");
+        }
+        assert!(!db.is_dirty());
+        assert_eq!(50, db.get_data(true).expect("could not get data").len());
+    }
+
+    #[test]
+    fn save_on_drop_saves_unless_unwrapped() {
+        let file = NamedTempFile::new().expect("could not create temporary file");
+        let path = file.path().to_owned();
+
+        let db = PathDatabase::<Vec<u32>, Ron>::create_at_path(path.clone(), vec![])
+            .expect("could not create database")
+            .auto_save_on_drop();
+        db.write(|data| data.push(1))
+            .expect("rustbreak write error");
+        drop(db);
+
+        let db = PathDatabase::<Vec<u32>, Ron>::load_from_path(path.clone())
+            .expect("could not load database")
+            .auto_save_on_drop();
+        assert_eq!(vec![1], db.get_data(false).expect("could not get data"));
+        db.write(|data| data.push(2))
+            .expect("rustbreak write error");
+        drop(db.into_inner());
+
+        let db =
+            PathDatabase::<Vec<u32>, Ron>::load_from_path(path).expect("could not load database");
+        assert_eq!(vec![1], db.get_data(false).expect("could not get data"));
+    }
+}
@@ -0,0 +1,377 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{Backend, BackendLock, BackendMetadata, BackendWriter, DetachedReader, Fingerprint};
+use crate::error;
+
+/// A backend that appends a record to an audit log for every change to
+/// another backend.
+///
+/// Every save and [quarantine](Backend::quarantine) appends a line with the
+/// time in UTC, the actor set with [`AuditedBackend::with_actor`], the
+/// operation, the number of bytes and whether it succeeded:
+///
+/// ```text
+/// 2024-05-01T12:30:00.250Z actor=billing op=save bytes=118 result=ok
+/// ```
+///
+/// With [`AuditedBackend::with_diff`], a save is followed by the lines that
+/// changed, the old ones prefixed with `-` and the new ones with `+`. Only
+/// the persisted state is audited: changes in memory show up with the save
+/// that stores them.
+///
+/// The log is only ever appended to, and every record is written at once, so
+/// several processes can share one log. If a record can not be written, the
+/// operation returns the error, even though the inner backend completed it.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::backend::{AuditedBackend, Backend, PathBackend};
+///
+/// # fn main() -> rustbreak::error::BackendResult<()> {
+/// # let dir = tempfile::tempdir()?;
+/// # let path = dir.path().join("accounts.ron");
+/// let (inner, _) = PathBackend::from_path_or_create(path.clone())?;
+/// // Records go to `accounts.ron.audit.log`
+/// let mut backend = AuditedBackend::next_to(inner, &path)?
+///     .with_actor("billing")
+///     .with_diff(true);
+/// backend.put_data(b"(balance: 10)")?;
+/// assert!(std::fs::read_to_string(backend.log_path())?.contains("op=save"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct AuditedBackend<B> {
+    inner: B,
+    log: File,
+    log_path: PathBuf,
+    actor: String,
+    diff: bool,
+}
+
+impl<B: Backend> AuditedBackend<B> {
+    /// Audit `inner`, appending the records to the file at `log_path`, which
+    /// is created if it does not exist.
+    pub fn new<P: AsRef<Path>>(inner: B, log_path: P) -> error::BackendResult<Self> {
+        let log_path = log_path.as_ref().to_path_buf();
+        let log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)?;
+        Ok(Self {
+            inner,
+            log,
+            log_path,
+            actor: "-".to_owned(),
+            diff: false,
+        })
+    }
+
+    /// Audit `inner`, which stores its data at `path`, in the file next to
+    /// it named `<path>.audit.log`.
+    pub fn next_to<P: AsRef<Path>>(inner: B, path: P) -> error::BackendResult<Self> {
+        let mut log_path = path.as_ref().as_os_str().to_owned();
+        log_path.push(".audit.log");
+        Self::new(inner, log_path)
+    }
+
+    /// Record `actor` as who made the changes, `-` by default.
+    ///
+    /// Whitespace in the name is replaced with `_`, so that records stay easy
+    /// to parse.
+    #[must_use]
+    pub fn with_actor<S: AsRef<str>>(mut self, actor: S) -> Self {
+        self.actor = actor
+            .as_ref()
+            .chars()
+            .map(|c| if c.is_whitespace() { '_' } else { c })
+            .collect();
+        self
+    }
+
+    /// Also record the lines every save changed.
+    ///
+    /// This reads the old data from the inner backend before every save, and
+    /// saves without a [`Backend::writer`], so it costs a read and a copy of
+    /// the data. Data that is not UTF-8 is only recorded as changed.
+    #[must_use]
+    pub fn with_diff(mut self, diff: bool) -> Self {
+        self.diff = diff;
+        self
+    }
+
+    /// The path of the audit log.
+    #[must_use]
+    pub fn log_path(&self) -> &Path {
+        &self.log_path
+    }
+
+    /// Get a reference to the inner backend.
+    pub fn get_ref(&self) -> &B {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner backend.
+    ///
+    /// Changes made through it are not audited.
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.inner
+    }
+
+    /// Consume the `AuditedBackend` and return the inner backend.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+/// Append the record of `operation` to `log`, followed by `details`.
+fn append<T>(
+    log: &mut File,
+    actor: &str,
+    operation: &str,
+    bytes: usize,
+    result: &error::BackendResult<T>,
+    details: &str,
+) -> error::BackendResult<()> {
+    let outcome = match result {
+        Ok(_) => "ok".to_owned(),
+        Err(e) => format!("failed: {e}").replace('\n', " "),
+    };
+    let record = format!(
+        "{} actor={actor} op={operation} bytes={bytes} result={outcome}\n{details}",
+        timestamp(SystemTime::now()),
+    );
+    log.write_all(record.as_bytes())?;
+    Ok(())
+}
+
+/// Format `time` as RFC 3339 in UTC, with milliseconds.
+fn timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+
+    // Howard Hinnant's `civil_from_days`, for days since 1970-01-01.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis(),
+    )
+}
+
+/// The lines that differ between `old` and `new`, each prefixed with `-` or
+/// `+` and indented by two spaces.
+///
+/// Only the range between the longest common prefix and suffix of lines is
+/// reported, which is what changed for the usual single edit.
+fn diff(old: &[u8], new: &[u8]) -> String {
+    let (Ok(old), Ok(new)) = (std::str::from_utf8(old), std::str::from_utf8(new)) else {
+        return if old == new {
+            String::new()
+        } else {
+            "  binary data changed\n".to_owned()
+        };
+    };
+    let old: Vec<_> = old.lines().collect();
+    let new: Vec<_> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut diff = String::new();
+    for line in &old[prefix..old.len() - suffix] {
+        diff.push_str("  -");
+        diff.push_str(line);
+        diff.push('\n');
+    }
+    for line in &new[prefix..new.len() - suffix] {
+        diff.push_str("  +");
+        diff.push_str(line);
+        diff.push('\n');
+    }
+    diff
+}
+
+impl<B: Backend> Backend for AuditedBackend<B> {
+    fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        self.inner.get_data()
+    }
+
+    fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        let details = if self.diff {
+            // Missing data, for example before the first save, reads as empty.
+            let old = self.inner.get_data().unwrap_or_default();
+            diff(&old, data)
+        } else {
+            String::new()
+        };
+        let result = self.inner.put_data(data);
+        let details = if result.is_ok() {
+            details
+        } else {
+            String::new()
+        };
+        append(
+            &mut self.log,
+            &self.actor,
+            "save",
+            data.len(),
+            &result,
+            &details,
+        )?;
+        result
+    }
+
+    /// Wrap the writer of the inner backend, recording the save when it is
+    /// finished. Without a writer if diffs are recorded, so that
+    /// [`Backend::put_data`] gets the whole data.
+    fn writer(&mut self) -> error::BackendResult<Option<Box<dyn BackendWriter + '_>>> {
+        if self.diff {
+            return Ok(None);
+        }
+        let Some(inner) = self.inner.writer()? else {
+            return Ok(None);
+        };
+        Ok(Some(Box::new(AuditedWriter {
+            inner,
+            log: &mut self.log,
+            actor: &self.actor,
+            bytes: 0,
+        })))
+    }
+
+    fn quarantine(&mut self) -> error::BackendResult<()> {
+        let result = self.inner.quarantine();
+        append(&mut self.log, &self.actor, "quarantine", 0, &result, "")?;
+        result
+    }
+
+    fn fingerprint(&mut self) -> error::BackendResult<Option<Fingerprint>> {
+        self.inner.fingerprint()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+
+    fn metadata(&mut self) -> error::BackendResult<Option<BackendMetadata>> {
+        self.inner.metadata()
+    }
+
+    fn lock_exclusive(&mut self) -> error::BackendResult<Option<BackendLock>> {
+        self.inner.lock_exclusive()
+    }
+
+    /// Reads are not audited, so the inner backend can be read detached.
+    fn detached_reader(&mut self) -> error::BackendResult<Option<Box<dyn DetachedReader>>> {
+        self.inner.detached_reader()
+    }
+}
+
+/// The [`BackendWriter`] of an [`AuditedBackend`].
+struct AuditedWriter<'a> {
+    inner: Box<dyn BackendWriter + 'a>,
+    log: &'a mut File,
+    actor: &'a str,
+    bytes: usize,
+}
+
+impl Write for AuditedWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl BackendWriter for AuditedWriter<'_> {
+    fn finish(self: Box<Self>) -> error::BackendResult<()> {
+        let Self {
+            inner,
+            log,
+            actor,
+            bytes,
+        } = *self;
+        let result = inner.finish();
+        append(log, actor, "save", bytes, &result, "")?;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{timestamp, AuditedBackend};
+    use crate::backend::{Backend, MemoryBackend, PathBackend};
+    use std::io::Write;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn timestamps_are_rfc3339() {
+        assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        let leap_day = UNIX_EPOCH + Duration::from_millis(951_827_696_789);
+        assert_eq!(timestamp(leap_day), "2000-02-29T12:34:56.789Z");
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn audited_backend_records_saves() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let data_path = dir.path().join("data.ron");
+        let mut backend = AuditedBackend::next_to(MemoryBackend::new(), &data_path)
+            .expect("could not open log")
+            .with_actor("test user")
+            .with_diff(true);
+        assert_eq!(backend.log_path(), dir.path().join("data.ron.audit.log"));
+        backend.put_data(b"a\nb\nc").expect("could not put data");
+        backend.put_data(b"a\nB\nc").expect("could not put data");
+
+        let log = std::fs::read_to_string(backend.log_path()).expect("could not read log");
+        let lines: Vec<_> = log.lines().collect();
+        assert_eq!(lines.len(), 7, "{log}");
+        assert!(lines[0].ends_with(" actor=test_user op=save bytes=5 result=ok"));
+        assert_eq!(lines[1..4], ["  +a", "  +b", "  +c"]);
+        assert_eq!(lines[5..], ["  -b", "  +B"]);
+
+        let mut backend = AuditedBackend::new(PathBackend::new(data_path), backend.log_path())
+            .expect("could not open log");
+        let mut writer = backend
+            .writer()
+            .expect("could not get writer")
+            .expect("path backends have a writer");
+        writer.write_all(b"streamed").expect("could not write");
+        writer.finish().expect("could not finish");
+        let log = std::fs::read_to_string(backend.log_path()).expect("could not read log");
+        let last = log.lines().last().expect("log is empty");
+        assert!(last.ends_with(" actor=- op=save bytes=8 result=ok"));
+    }
+}
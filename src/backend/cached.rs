@@ -0,0 +1,160 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use super::{Backend, BackendLock, BackendMetadata, Fingerprint};
+use crate::error;
+
+/// A backend that keeps the data read or written last in memory, and serves
+/// reads from there.
+///
+/// This avoids reading the same data again when loading repeatedly, for
+/// example when polling a configuration file. If the inner backend has a
+/// [`Fingerprint`], it is checked on every read, and the data is read again
+/// if it changed. Otherwise the cache is only cleared by
+/// [`CachedBackend::invalidate`].
+///
+/// Saves always go through [`Backend::put_data`] of the inner backend, the
+/// database can not stream into its [`Backend::writer`].
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::backend::{Backend, CachedBackend, MemoryBackend};
+///
+/// # fn main() -> rustbreak::error::BackendResult<()> {
+/// let mut backend = CachedBackend::new(MemoryBackend::new());
+/// backend.put_data(b"Hello")?;
+/// assert_eq!(backend.get_data()?, b"Hello");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct CachedBackend<B> {
+    inner: B,
+    /// The cached data, and the fingerprint of the inner backend it belongs
+    /// to.
+    cache: Option<(Vec<u8>, Option<Fingerprint>)>,
+}
+
+impl<B: Backend> CachedBackend<B> {
+    /// Cache the data of `inner`.
+    pub fn new(inner: B) -> Self {
+        Self { inner, cache: None }
+    }
+
+    /// Clear the cache, so that the next read goes to the inner backend.
+    pub fn invalidate(&mut self) {
+        self.cache = None;
+    }
+
+    /// Whether the data is currently cached.
+    #[must_use]
+    pub fn is_cached(&self) -> bool {
+        self.cache.is_some()
+    }
+
+    /// Get a reference to the inner backend.
+    pub fn get_ref(&self) -> &B {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner backend.
+    ///
+    /// This clears the cache, as the data may be changed through it.
+    pub fn get_mut(&mut self) -> &mut B {
+        self.invalidate();
+        &mut self.inner
+    }
+
+    /// Consume the `CachedBackend` and return the inner backend.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B: Backend> Backend for CachedBackend<B> {
+    fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        let fingerprint = self.inner.fingerprint()?;
+        if let Some((data, cached)) = &self.cache {
+            if fingerprint.is_none() || *cached == fingerprint {
+                return Ok(data.clone());
+            }
+        }
+        let data = self.inner.get_data()?;
+        self.cache = Some((data.clone(), fingerprint));
+        Ok(data)
+    }
+
+    fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        self.invalidate();
+        self.inner.put_data(data)?;
+        let fingerprint = self.inner.fingerprint()?;
+        self.cache = Some((data.to_vec(), fingerprint));
+        Ok(())
+    }
+
+    fn quarantine(&mut self) -> error::BackendResult<()> {
+        self.invalidate();
+        self.inner.quarantine()
+    }
+
+    fn fingerprint(&mut self) -> error::BackendResult<Option<Fingerprint>> {
+        self.inner.fingerprint()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        match &self.cache {
+            Some((data, _)) => Some(data.len()),
+            None => self.inner.size_hint(),
+        }
+    }
+
+    /// Another process may change the data while it is locked, so the cache
+    /// is dropped once the lock is taken.
+    fn lock_exclusive(&mut self) -> error::BackendResult<Option<BackendLock>> {
+        let lock = self.inner.lock_exclusive()?;
+        self.invalidate();
+        Ok(lock)
+    }
+
+    fn metadata(&mut self) -> error::BackendResult<Option<BackendMetadata>> {
+        self.inner.metadata()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Backend, CachedBackend};
+    use crate::backend::{MemoryBackend, StreamBackend};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_cached_backend() {
+        // A stream has no fingerprint, so changes are not noticed.
+        let mut backend = CachedBackend::new(StreamBackend::new(Cursor::new(b"old".to_vec())));
+        assert!(!backend.is_cached());
+        assert_eq!(backend.get_data().expect("could not get data"), b"old");
+        assert!(backend.is_cached());
+
+        backend.inner.get_mut().get_mut()[..].copy_from_slice(b"new");
+        assert_eq!(backend.get_data().expect("could not get data"), b"old");
+        backend.invalidate();
+        assert_eq!(backend.get_data().expect("could not get data"), b"new");
+
+        backend.put_data(b"put").expect("could not put data");
+        assert_eq!(backend.get_data().expect("could not get data"), b"put");
+    }
+
+    #[test]
+    fn test_cached_backend_fingerprint() {
+        let mut backend = CachedBackend::new(MemoryBackend::new());
+        backend.put_data(b"old").expect("could not put data");
+        assert!(backend.is_cached());
+
+        // Changing the data changes the fingerprint, and so the cache.
+        backend.inner.put_data(b"new").expect("could not put data");
+        assert_eq!(backend.get_data().expect("could not get data"), b"new");
+    }
+}
@@ -0,0 +1,497 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::{Backend, BackendLock, BackendMetadata, SyncPolicy, Syncer};
+use crate::error::{self, BackendError};
+
+/// The bytes every delta file starts with.
+const MAGIC: &[u8; 4] = b"RBDL";
+/// The version of the delta layout.
+const DELTA_VERSION: u8 = 1;
+/// Magic, delta version, base length and CRC32, result length and CRC32.
+const HEADER_LEN: usize = 4 + 1 + 8 + 4 + 8 + 4;
+
+/// An operation copying a range of the base.
+const COPY: u8 = 0;
+/// An operation inserting new bytes.
+const INSERT: u8 = 1;
+
+/// The multiplier of the rolling hash.
+const PRIME: u32 = 0x0100_0193;
+
+/// A backend that saves the difference to a base file, instead of rewriting
+/// the whole file on every save.
+///
+/// The data is stored in two files: the base file at the given path, and a
+/// delta file next to it, which has `.delta` appended to its name. Saving
+/// compares the data to the base, and writes the parts that changed to the
+/// delta file, replacing the previous delta. Once the delta grows larger
+/// than a percentage of the base, set with
+/// [`DeltaBackend::with_consolidate_percent`], the data is written to the
+/// base instead, and the delta file is removed.
+///
+/// Both files are replaced atomically. The delta records a checksum of the
+/// base it was computed against, so a delta that is left behind when the
+/// program crashes while consolidating is ignored. Between consolidations,
+/// the base file holds the data of the last consolidation, so other
+/// programs can still read it.
+///
+/// The delta is found by matching blocks of the base in the new data, like
+/// `rsync` does, so it stays small for changes anywhere in the data, even
+/// ones that shift the rest of it. The base is kept in memory to compare
+/// against.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::backend::{Backend, DeltaBackend};
+///
+/// # fn main() -> rustbreak::error::BackendResult<()> {
+/// # let dir = tempfile::tempdir().unwrap();
+/// let mut backend = DeltaBackend::new(dir.path().join("db.ron")).with_block_size(16);
+/// let mut data = vec![b'a'; 4096];
+/// backend.put_data(&data)?;
+///
+/// data[2048] = b'b';
+/// backend.put_data(&data)?;
+/// assert!(backend.delta_path().exists());
+/// assert_eq!(backend.get_data()?, data);
+///
+/// backend.consolidate()?;
+/// assert!(!backend.delta_path().exists());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct DeltaBackend {
+    path: PathBuf,
+    delta_path: PathBuf,
+    /// The contents of the base file, as of the last read or write.
+    base: Option<Vec<u8>>,
+    block_size: usize,
+    consolidate_percent: u8,
+    sync: Syncer,
+}
+
+impl DeltaBackend {
+    /// A backend storing the base at `path`, without touching the file
+    /// system.
+    ///
+    /// A missing base file is read as empty data.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        let path = path.into();
+        let mut delta_path = path.clone().into_os_string();
+        delta_path.push(".delta");
+        Self {
+            path,
+            delta_path: delta_path.into(),
+            base: None,
+            block_size: 1024,
+            consolidate_percent: 50,
+            sync: Syncer::default(),
+        }
+    }
+
+    /// Match blocks of `size` bytes of the base, the default is `1024`.
+    ///
+    /// Smaller blocks make for smaller deltas, but take more memory and time
+    /// to index. At least one byte is used.
+    #[must_use]
+    pub fn with_block_size(mut self, size: usize) -> Self {
+        self.block_size = size.max(1);
+        self
+    }
+
+    /// Write the data to the base once the delta would be larger than
+    /// `percent` percent of the base, the default is `50`.
+    #[must_use]
+    pub fn with_consolidate_percent(mut self, percent: u8) -> Self {
+        self.consolidate_percent = percent;
+        self
+    }
+
+    /// Choose when saves are synced to disk, see [`SyncPolicy`].
+    #[must_use]
+    pub fn with_sync_policy(mut self, policy: SyncPolicy) -> Self {
+        self.sync.policy = policy;
+        self
+    }
+
+    /// The path of the base file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The path of the delta file.
+    #[must_use]
+    pub fn delta_path(&self) -> &Path {
+        &self.delta_path
+    }
+
+    /// Write the current data to the base, and remove the delta.
+    pub fn consolidate(&mut self) -> error::BackendResult<()> {
+        let data = self.get_data()?;
+        self.write_base(data)
+    }
+
+    fn write_base(&mut self, data: Vec<u8>) -> error::BackendResult<()> {
+        let sync = self.sync.should_sync();
+        write_atomic(&self.path, &data, sync)?;
+        match std::fs::remove_file(&self.delta_path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        self.base = Some(data);
+        Ok(())
+    }
+}
+
+impl Backend for DeltaBackend {
+    /// Read the base and apply the delta to it.
+    fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        let base = read_optional(&self.path)?.unwrap_or_default();
+        let data = match read_optional(&self.delta_path)? {
+            Some(delta) => apply(&base, &delta)?,
+            None => None,
+        };
+        let data = data.unwrap_or_else(|| base.clone());
+        self.base = Some(base);
+        Ok(data)
+    }
+
+    /// Write the delta file, or the data to the base if the delta would be
+    /// too large.
+    fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        let base = match self.base.take() {
+            Some(base) => base,
+            None => read_optional(&self.path)?.unwrap_or_default(),
+        };
+        if !base.is_empty() {
+            let delta = encode(&base, data, self.block_size);
+            let limit = base
+                .len()
+                .saturating_mul(usize::from(self.consolidate_percent));
+            if delta.len().saturating_mul(100) <= limit {
+                let sync = self.sync.should_sync();
+                let result = write_atomic(&self.delta_path, &delta, sync);
+                self.base = Some(base);
+                return result;
+            }
+        }
+        self.write_base(data.to_vec())
+    }
+
+    /// The length of the base file.
+    fn size_hint(&self) -> Option<usize> {
+        let len = std::fs::metadata(&self.path).ok()?.len();
+        usize::try_from(len).ok()
+    }
+
+    /// Lock a sidecar file next to the base file, which has `.lck` appended
+    /// to its name, like a [`PathBackend`](super::PathBackend) does.
+    fn lock_exclusive(&mut self) -> error::BackendResult<Option<BackendLock>> {
+        super::path::lock_sidecar(&self.path).map(Some)
+    }
+
+    /// The combined length of the base and delta files, and the time the
+    /// later of them was modified.
+    fn metadata(&mut self) -> error::BackendResult<Option<BackendMetadata>> {
+        let mut found = None;
+        for path in [&self.path, &self.delta_path] {
+            match std::fs::metadata(path) {
+                Ok(metadata) => {
+                    let file = BackendMetadata::from_metadata(&metadata);
+                    let total = found.get_or_insert(BackendMetadata::new(0, None));
+                    total.len += file.len;
+                    total.modified = total.modified.max(file.modified);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// Read the file at `path`, `None` if it does not exist.
+fn read_optional(path: &Path) -> error::BackendResult<Option<Vec<u8>>> {
+    match std::fs::read(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Replace the file at `path` with `data`, using a temporary file next to
+/// it.
+fn write_atomic(path: &Path, data: &[u8], sync: bool) -> error::BackendResult<()> {
+    let mut tempf = tempfile::NamedTempFile::new_in(super::path::parent_dir(path))?;
+    tempf.write_all(data)?;
+    if sync {
+        tempf.as_file().sync_all()?;
+    }
+    tempf.persist(path)?;
+    Ok(())
+}
+
+/// A part of the data, taken from the base or inserted.
+#[derive(Debug, PartialEq, Eq)]
+enum Op<'a> {
+    Copy { offset: usize, len: usize },
+    Insert(&'a [u8]),
+}
+
+fn hash(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |h: u32, &b| {
+        h.wrapping_mul(PRIME).wrapping_add(u32::from(b))
+    })
+}
+
+/// The operations building `new` from `base`, matching the blocks of `base`
+/// anywhere in `new`.
+fn diff<'a>(base: &[u8], new: &'a [u8], block: usize) -> Vec<Op<'a>> {
+    let mut ops = Vec::new();
+    if base.len() < block || new.len() < block {
+        if !new.is_empty() {
+            ops.push(Op::Insert(new));
+        }
+        return ops;
+    }
+
+    let mut index: HashMap<u32, Vec<usize>> = HashMap::new();
+    for offset in (0..=base.len() - block).step_by(block) {
+        index
+            .entry(hash(&base[offset..offset + block]))
+            .or_default()
+            .push(offset);
+    }
+    // The factor of the byte leaving the window.
+    let leaving = (1..block).fold(1, |p: u32, _| p.wrapping_mul(PRIME));
+
+    let mut literal = 0;
+    let mut i = 0;
+    let mut h = hash(&new[..block]);
+    loop {
+        let found = index.get(&h).and_then(|offsets| {
+            offsets
+                .iter()
+                .copied()
+                .find(|&offset| base[offset..offset + block] == new[i..i + block])
+        });
+        if let Some(mut offset) = found {
+            let mut start = i;
+            while start > literal && offset > 0 && base[offset - 1] == new[start - 1] {
+                start -= 1;
+                offset -= 1;
+            }
+            let mut end = i + block;
+            while end < new.len()
+                && offset + (end - start) < base.len()
+                && base[offset + (end - start)] == new[end]
+            {
+                end += 1;
+            }
+            if literal < start {
+                ops.push(Op::Insert(&new[literal..start]));
+            }
+            match ops.last_mut() {
+                Some(Op::Copy { offset: o, len }) if *o + *len == offset => *len += end - start,
+                _ => ops.push(Op::Copy {
+                    offset,
+                    len: end - start,
+                }),
+            }
+            i = end;
+            literal = end;
+            if i + block > new.len() {
+                break;
+            }
+            h = hash(&new[i..i + block]);
+        } else {
+            if i + block >= new.len() {
+                break;
+            }
+            h = h
+                .wrapping_sub(u32::from(new[i]).wrapping_mul(leaving))
+                .wrapping_mul(PRIME)
+                .wrapping_add(u32::from(new[i + block]));
+            i += 1;
+        }
+    }
+    if literal < new.len() {
+        ops.push(Op::Insert(&new[literal..]));
+    }
+    ops
+}
+
+/// Encode the delta turning `base` into `new`.
+fn encode(base: &[u8], new: &[u8], block: usize) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(HEADER_LEN);
+    buf.extend_from_slice(MAGIC);
+    buf.push(DELTA_VERSION);
+    buf.extend_from_slice(&(base.len() as u64).to_le_bytes());
+    buf.extend_from_slice(&crc32fast::hash(base).to_le_bytes());
+    buf.extend_from_slice(&(new.len() as u64).to_le_bytes());
+    buf.extend_from_slice(&crc32fast::hash(new).to_le_bytes());
+    for op in diff(base, new, block) {
+        match op {
+            Op::Copy { offset, len } => {
+                buf.push(COPY);
+                buf.extend_from_slice(&(offset as u64).to_le_bytes());
+                buf.extend_from_slice(&(len as u64).to_le_bytes());
+            }
+            Op::Insert(bytes) => {
+                buf.push(INSERT);
+                buf.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+                buf.extend_from_slice(bytes);
+            }
+        }
+    }
+    buf
+}
+
+fn corrupt(reason: &str) -> BackendError {
+    BackendError::Corrupt(reason.to_string())
+}
+
+/// Split `n` bytes off the front of `bytes`.
+fn take<'a>(bytes: &mut &'a [u8], n: usize) -> error::BackendResult<&'a [u8]> {
+    if bytes.len() < n {
+        return Err(corrupt("delta is truncated"));
+    }
+    let (head, tail) = bytes.split_at(n);
+    *bytes = tail;
+    Ok(head)
+}
+
+fn take_u64(bytes: &mut &[u8]) -> error::BackendResult<u64> {
+    let mut array = [0; 8];
+    array.copy_from_slice(take(bytes, 8)?);
+    Ok(u64::from_le_bytes(array))
+}
+
+fn take_u32(bytes: &mut &[u8]) -> error::BackendResult<u32> {
+    let mut array = [0; 4];
+    array.copy_from_slice(take(bytes, 4)?);
+    Ok(u32::from_le_bytes(array))
+}
+
+fn take_usize(bytes: &mut &[u8]) -> error::BackendResult<usize> {
+    usize::try_from(take_u64(bytes)?).map_err(|_| corrupt("delta out of range"))
+}
+
+/// Apply `delta` to `base`, `None` if it was computed against another base.
+fn apply(base: &[u8], mut delta: &[u8]) -> error::BackendResult<Option<Vec<u8>>> {
+    if take(&mut delta, 4)? != MAGIC {
+        return Err(corrupt("missing delta header"));
+    }
+    if take(&mut delta, 1)? != [DELTA_VERSION] {
+        return Err(corrupt("unknown delta version"));
+    }
+    let base_len = take_u64(&mut delta)?;
+    let base_crc = take_u32(&mut delta)?;
+    if base_len != base.len() as u64 || base_crc != crc32fast::hash(base) {
+        return Ok(None);
+    }
+    let len = take_usize(&mut delta)?;
+    let crc = take_u32(&mut delta)?;
+
+    let mut data = Vec::with_capacity(len);
+    while !delta.is_empty() {
+        match take(&mut delta, 1)?[0] {
+            COPY => {
+                let offset = take_usize(&mut delta)?;
+                let len = take_usize(&mut delta)?;
+                let range = offset
+                    .checked_add(len)
+                    .and_then(|end| base.get(offset..end))
+                    .ok_or_else(|| corrupt("delta out of range"))?;
+                data.extend_from_slice(range);
+            }
+            INSERT => {
+                let len = take_usize(&mut delta)?;
+                data.extend_from_slice(take(&mut delta, len)?);
+            }
+            _ => return Err(corrupt("unknown delta operation")),
+        }
+    }
+    if data.len() != len || crc32fast::hash(&data) != crc {
+        return Err(corrupt("delta checksum does not match"));
+    }
+    Ok(Some(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply, diff, encode, DeltaBackend, Op};
+    use crate::backend::Backend;
+
+    #[test]
+    fn test_delta_diff() {
+        let base = b"0123456789abcdefghij";
+        let new = b"0123456789XXabcdefghij!";
+        let ops = diff(base, new, 4);
+        assert_eq!(
+            ops,
+            [
+                Op::Copy { offset: 0, len: 10 },
+                Op::Insert(b"XX"),
+                Op::Copy {
+                    offset: 10,
+                    len: 10
+                },
+                Op::Insert(b"!"),
+            ]
+        );
+        let delta = encode(base, new, 4);
+        assert_eq!(
+            apply(base, &delta).expect("could not apply"),
+            Some(new.to_vec())
+        );
+        assert_eq!(apply(b"other base", &delta).expect("could not apply"), None);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_delta_backend() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let path = dir.path().join("db");
+        let mut backend = DeltaBackend::new(&path).with_block_size(32);
+        assert_eq!(backend.get_data().expect("could not get data"), b"");
+
+        let mut data: Vec<u8> = (0..10_000_u32).flat_map(u32::to_le_bytes).collect();
+        backend.put_data(&data).expect("could not put data");
+        assert!(!backend.delta_path().exists());
+
+        data.splice(100..104, *b"inserted");
+        data[30_000] = 0xff;
+        backend.put_data(&data).expect("could not put data");
+        let delta_len = std::fs::metadata(backend.delta_path())
+            .expect("no delta")
+            .len();
+        assert!(delta_len < 200);
+        let mut reopened = DeltaBackend::new(&path);
+        assert_eq!(reopened.get_data().expect("could not get data"), data);
+
+        // Large changes are written to the base.
+        let data = vec![7; 30_000];
+        backend.put_data(&data).expect("could not put data");
+        assert!(!backend.delta_path().exists());
+        assert_eq!(std::fs::read(&path).expect("could not read"), data);
+
+        // A delta against another base is stale.
+        backend
+            .put_data(&vec![7; 29_999])
+            .expect("could not put data");
+        std::fs::write(&path, b"replaced").expect("could not write");
+        assert_eq!(backend.get_data().expect("could not get data"), b"replaced");
+    }
+}
@@ -0,0 +1,184 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use super::{Backend, BackendLock, BackendMetadata, Fingerprint};
+use crate::error::{self, BackendError};
+
+/// The bytes every envelope starts with.
+const MAGIC: &[u8; 4] = b"RBRK";
+/// The version of the envelope layout itself.
+const ENVELOPE_VERSION: u8 = 1;
+/// Magic, envelope version, format, schema version, payload length and CRC32.
+const HEADER_LEN: usize = 4 + 1 + 4 + 4 + 8 + 4;
+
+/// A backend that wraps the data of another backend in a checksummed
+/// envelope.
+///
+/// The envelope is a header holding magic bytes, a format identifier chosen
+/// by you (for example `*b"RON "`), a schema version, the payload length and
+/// a CRC32 of the payload. Reading data that is not a valid envelope, was
+/// written with another format, or was only partially written, fails with
+/// [`BackendError::Corrupt`] instead of handing it to the `DeSer`.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::backend::{Backend, Enveloped, MemoryBackend};
+///
+/// # fn main() -> rustbreak::error::BackendResult<()> {
+/// let mut backend = Enveloped::new(MemoryBackend::new(), *b"RON ").with_schema_version(2);
+/// backend.put_data(b"()")?;
+/// assert_eq!(backend.get_data()?, b"()");
+/// assert_eq!(backend.loaded_schema_version(), Some(2));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Enveloped<B> {
+    inner: B,
+    format: [u8; 4],
+    schema_version: u32,
+    loaded_schema_version: Option<u32>,
+}
+
+impl<B> Enveloped<B> {
+    /// Wrap `inner`, tagging the data with `format`.
+    pub fn new(inner: B, format: [u8; 4]) -> Self {
+        Self {
+            inner,
+            format,
+            schema_version: 0,
+            loaded_schema_version: None,
+        }
+    }
+
+    /// Tag written data with the given schema version, the default is `0`.
+    #[must_use]
+    pub fn with_schema_version(mut self, version: u32) -> Self {
+        self.schema_version = version;
+        self
+    }
+
+    /// The schema version of the last successfully read envelope.
+    #[must_use]
+    pub fn loaded_schema_version(&self) -> Option<u32> {
+        self.loaded_schema_version
+    }
+
+    /// Return the wrapped backend.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+fn corrupt(reason: &str) -> BackendError {
+    BackendError::Corrupt(reason.to_string())
+}
+
+impl<B: Backend> Backend for Enveloped<B> {
+    fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        let mut data = self.inner.get_data()?;
+        if data.len() < HEADER_LEN || &data[..4] != MAGIC {
+            return Err(corrupt("missing envelope header"));
+        }
+        let (header, payload) = data.split_at(HEADER_LEN);
+        if header[4] != ENVELOPE_VERSION {
+            return Err(corrupt("unknown envelope version"));
+        }
+        if header[5..9] != self.format {
+            return Err(corrupt("unexpected data format"));
+        }
+        let schema_version = u32::from_le_bytes(to_array(&header[9..13]));
+        let len = u64::from_le_bytes(to_array(&header[13..21]));
+        let crc = u32::from_le_bytes(to_array(&header[21..25]));
+        if payload.len() as u64 != len {
+            return Err(corrupt("payload length does not match"));
+        }
+        if crc32fast::hash(payload) != crc {
+            return Err(corrupt("payload checksum does not match"));
+        }
+        self.loaded_schema_version = Some(schema_version);
+        data.drain(..HEADER_LEN);
+        Ok(data)
+    }
+
+    fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        let mut buf = Vec::with_capacity(HEADER_LEN + data.len());
+        buf.extend_from_slice(MAGIC);
+        buf.push(ENVELOPE_VERSION);
+        buf.extend_from_slice(&self.format);
+        buf.extend_from_slice(&self.schema_version.to_le_bytes());
+        buf.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        buf.extend_from_slice(&crc32fast::hash(data).to_le_bytes());
+        buf.extend_from_slice(data);
+        self.inner.put_data(&buf)
+    }
+
+    fn quarantine(&mut self) -> error::BackendResult<()> {
+        self.inner.quarantine()
+    }
+
+    fn fingerprint(&mut self) -> error::BackendResult<Option<Fingerprint>> {
+        self.inner.fingerprint()
+    }
+
+    /// The size hint of the inner backend, without the header.
+    fn size_hint(&self) -> Option<usize> {
+        self.inner
+            .size_hint()
+            .map(|len| len.saturating_sub(HEADER_LEN))
+    }
+
+    fn lock_exclusive(&mut self) -> error::BackendResult<Option<BackendLock>> {
+        self.inner.lock_exclusive()
+    }
+
+    /// The metadata of the inner backend, including the header.
+    fn metadata(&mut self) -> error::BackendResult<Option<BackendMetadata>> {
+        self.inner.metadata()
+    }
+}
+
+fn to_array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    let mut array = [0; N];
+    array.copy_from_slice(bytes);
+    array
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Enveloped, HEADER_LEN};
+    use crate::backend::{Backend, MemoryBackend};
+    use crate::error::BackendError;
+
+    #[test]
+    fn test_enveloped_roundtrip() {
+        let mut backend = Enveloped::new(MemoryBackend::new(), *b"TEST").with_schema_version(3);
+        let data = [4, 5, 1, 6, 8, 1];
+
+        backend.put_data(&data).expect("could not put data");
+        assert_eq!(backend.get_data().expect("could not get data"), data);
+        assert_eq!(backend.loaded_schema_version(), Some(3));
+    }
+
+    #[test]
+    fn test_enveloped_detects_corruption() {
+        let mut backend = Enveloped::new(MemoryBackend::new(), *b"TEST");
+        backend.put_data(&[4, 5, 1, 6]).expect("could not put data");
+        let stored = backend.inner.get_data().expect("could not get data");
+
+        let mut flipped = stored.clone();
+        flipped[HEADER_LEN] ^= 1;
+        let truncated = &stored[..stored.len() - 1];
+        for bad in [&flipped[..], truncated, &[4, 5, 1, 6]] {
+            backend.inner.put_data(bad).expect("could not put data");
+            assert!(matches!(backend.get_data(), Err(BackendError::Corrupt(_))));
+        }
+
+        backend.inner.put_data(&stored).expect("could not put data");
+        let mut other = Enveloped::new(backend.into_inner(), *b"ELSE");
+        assert!(matches!(other.get_data(), Err(BackendError::Corrupt(_))));
+    }
+}
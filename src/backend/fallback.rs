@@ -0,0 +1,143 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use super::{Backend, BackendLock, BackendMetadata, BackendWriter, Fingerprint};
+use crate::error;
+
+/// A backend that reads from a list of backends in order, but only writes to
+/// the first one.
+///
+/// Reading tries the primary backend first, and each fallback after it until
+/// one succeeds. This allows to ship default data inside the binary and
+/// to persist changes of the user on disk.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::backend::{Backend, FallbackBackend, MemoryBackend, PathBackend};
+///
+/// # fn main() -> rustbreak::error::BackendResult<()> {
+/// # let dir = tempfile::tempdir()?;
+/// let path = dir.path().join("settings.ron");
+/// let (user, _exists) = PathBackend::from_path_or_create(path)?;
+/// let mut backend = FallbackBackend::new(user)
+///     .with_fallback(MemoryBackend::from(b"(volume: 11)".to_vec()));
+///
+/// // The user has no file yet, so the defaults are read.
+/// assert_eq!(backend.get_data()?, b"(volume: 11)");
+/// backend.put_data(b"(volume: 3)")?;
+/// assert_eq!(backend.get_data()?, b"(volume: 3)");
+/// # Ok(())
+/// # }
+/// ```
+pub struct FallbackBackend {
+    /// The primary backend comes first, it is never empty.
+    backends: Vec<Box<dyn Backend>>,
+}
+
+impl std::fmt::Debug for FallbackBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FallbackBackend")
+            .field("backends", &self.backends.len())
+            .finish()
+    }
+}
+
+impl FallbackBackend {
+    /// Read from and write to `primary`.
+    pub fn new<B: Backend + 'static>(primary: B) -> Self {
+        Self {
+            backends: vec![Box::new(primary)],
+        }
+    }
+
+    /// Read from `fallback` if all backends before it fail.
+    #[must_use]
+    pub fn with_fallback<B: Backend + 'static>(mut self, fallback: B) -> Self {
+        self.backends.push(Box::new(fallback));
+        self
+    }
+
+    /// Get the primary backend.
+    pub fn primary(&mut self) -> &mut dyn Backend {
+        &mut *self.backends[0]
+    }
+}
+
+impl Backend for FallbackBackend {
+    /// Read from the first backend that does not fail, or return the error
+    /// of the last one.
+    ///
+    /// Empty data counts as missing too, so that an empty file created by
+    /// [`PathBackend::from_path_or_create`](super::PathBackend::from_path_or_create)
+    /// falls back to the defaults.
+    fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        let mut result = Ok(Vec::new());
+        for backend in &mut self.backends {
+            result = backend.get_data();
+            if matches!(&result, Ok(data) if !data.is_empty()) {
+                break;
+            }
+        }
+        result
+    }
+
+    fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        self.backends[0].put_data(data)
+    }
+
+    fn writer(&mut self) -> error::BackendResult<Option<Box<dyn BackendWriter + '_>>> {
+        self.backends[0].writer()
+    }
+
+    fn quarantine(&mut self) -> error::BackendResult<()> {
+        self.backends[0].quarantine()
+    }
+
+    /// The fingerprint of the primary backend.
+    fn fingerprint(&mut self) -> error::BackendResult<Option<Fingerprint>> {
+        self.backends[0].fingerprint()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.backends.iter().find_map(Backend::size_hint)
+    }
+
+    /// Lock the primary backend, the only one that is written.
+    fn lock_exclusive(&mut self) -> error::BackendResult<Option<BackendLock>> {
+        self.backends[0].lock_exclusive()
+    }
+
+    /// The metadata of the primary backend.
+    fn metadata(&mut self) -> error::BackendResult<Option<BackendMetadata>> {
+        self.backends[0].metadata()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Backend, FallbackBackend};
+    use crate::backend::{MemoryBackend, PathBackend};
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_fallback_backend() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let path = dir.path().join("data");
+        let mut backend = FallbackBackend::new(PathBackend::new(path.join("missing")))
+            .with_fallback(PathBackend::new(path.clone()))
+            .with_fallback(MemoryBackend::from(b"default".to_vec()));
+
+        assert_eq!(backend.get_data().expect("could not get data"), b"default");
+        std::fs::write(&path, b"backup").expect("could not write backup");
+        assert_eq!(backend.get_data().expect("could not get data"), b"backup");
+
+        backend
+            .put_data(b"user")
+            .expect_err("writing to a missing directory succeeded");
+        std::fs::write(&path, b"").expect("could not write backup");
+        assert_eq!(backend.get_data().expect("could not get data"), b"default");
+    }
+}
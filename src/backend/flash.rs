@@ -0,0 +1,274 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::convert::TryFrom;
+
+use embedded_storage::nor_flash::{NorFlash, NorFlashError, NorFlashErrorKind};
+
+use super::{Backend, Fingerprint};
+use crate::error::{self, BackendError};
+
+/// The length of the data and its CRC32, both as little endian `u32`.
+const HEADER_LEN: usize = 8;
+
+/// A backend that stores the data in a region of a NOR flash, through the
+/// [`embedded-storage`](embedded_storage) traits.
+///
+/// The region starts with a header holding the length and a CRC32 of the
+/// data, followed by the data itself. An erased region reads as empty data,
+/// and data that does not match its checksum fails with
+/// [`BackendError::Corrupt`]. Errors of the flash are reported as
+/// [`BackendError::Flash`].
+///
+/// Every save erases the sectors the data needs and then writes the header
+/// and the data, padded to the write size of the flash. This means the data
+/// is lost if the power fails in between, and that every save costs an erase
+/// cycle, so save sparingly. A compact format like
+/// [`Postcard`](crate::deser::Postcard) keeps the number of sectors low.
+///
+/// **Important**: This backend is only available with the `embedded` feature.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use embedded_storage::nor_flash::NorFlash;
+/// use rustbreak::backend::{Backend, FlashBackend};
+///
+/// fn save_settings<F: NorFlash>(flash: F, settings: &[u8]) -> rustbreak::error::BackendResult<()> {
+///     // The last 16 KiB of the flash belong to the settings.
+///     let size = 16 * 1024;
+///     let offset = flash.capacity() as u32 - size;
+///     let mut backend = FlashBackend::new(flash, offset, size)?;
+///     backend.put_data(settings)
+/// }
+/// ```
+#[derive(Debug)]
+pub struct FlashBackend<F> {
+    flash: F,
+    offset: u32,
+    size: u32,
+}
+
+impl<F: NorFlash> FlashBackend<F> {
+    /// Store the data in the `size` bytes of `flash` starting at `offset`.
+    ///
+    /// Both have to be multiples of the erase size of the flash, and the
+    /// region must lie within it.
+    pub fn new(flash: F, offset: u32, size: u32) -> error::BackendResult<Self> {
+        let erase_size = u32::try_from(F::ERASE_SIZE).unwrap_or(u32::MAX);
+        if !offset.is_multiple_of(erase_size) || !size.is_multiple_of(erase_size) {
+            return Err(BackendError::Flash(NorFlashErrorKind::NotAligned));
+        }
+        let end = usize::try_from(u64::from(offset) + u64::from(size)).unwrap_or(usize::MAX);
+        if end > flash.capacity() || (size as usize) < HEADER_LEN {
+            return Err(BackendError::Flash(NorFlashErrorKind::OutOfBounds));
+        }
+        Ok(Self {
+            flash,
+            offset,
+            size,
+        })
+    }
+
+    /// The flash the data is stored in.
+    pub fn flash(&self) -> &F {
+        &self.flash
+    }
+
+    /// Get back the flash the data is stored in.
+    pub fn into_inner(self) -> F {
+        self.flash
+    }
+
+    /// Read the first `len` bytes of the region.
+    fn read(&mut self, len: usize) -> error::BackendResult<Vec<u8>> {
+        let mut buf = vec![0; round_up(len, F::READ_SIZE)];
+        self.flash
+            .read(self.offset, &mut buf)
+            .map_err(flash_error)?;
+        buf.truncate(len);
+        Ok(buf)
+    }
+
+    /// The length and checksum of the stored data, `None` if the region is
+    /// erased.
+    fn header(&mut self) -> error::BackendResult<Option<(usize, u32)>> {
+        let header = self.read(HEADER_LEN)?;
+        if header.iter().all(|&b| b == 0xFF) {
+            return Ok(None);
+        }
+        let len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let crc = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        if len > self.size as usize - HEADER_LEN {
+            return Err(BackendError::Corrupt(format!(
+                "the stored length {len} does not fit the flash region"
+            )));
+        }
+        Ok(Some((len, crc)))
+    }
+}
+
+fn round_up(len: usize, align: usize) -> usize {
+    len.div_ceil(align) * align
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn flash_error<E: NorFlashError>(err: E) -> BackendError {
+    BackendError::Flash(err.kind())
+}
+
+impl<F: NorFlash> Backend for FlashBackend<F> {
+    fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        let Some((len, crc)) = self.header()? else {
+            return Ok(Vec::new());
+        };
+        let mut data = self.read(HEADER_LEN + len)?;
+        data.drain(..HEADER_LEN);
+        if crc32fast::hash(&data) != crc {
+            return Err(BackendError::Corrupt(
+                "the checksum of the stored data does not match".to_string(),
+            ));
+        }
+        Ok(data)
+    }
+
+    fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        let len = u32::try_from(data.len())
+            .ok()
+            .filter(|&len| len as usize <= self.size as usize - HEADER_LEN)
+            .ok_or(BackendError::Flash(NorFlashErrorKind::OutOfBounds))?;
+
+        let mut buf = Vec::with_capacity(round_up(HEADER_LEN + data.len(), F::WRITE_SIZE));
+        buf.extend_from_slice(&len.to_le_bytes());
+        buf.extend_from_slice(&crc32fast::hash(data).to_le_bytes());
+        buf.extend_from_slice(data);
+        buf.resize(buf.capacity(), 0xFF);
+
+        let erase_len = u32::try_from(round_up(buf.len(), F::ERASE_SIZE).min(self.size as usize))
+            .unwrap_or(self.size);
+        self.flash
+            .erase(self.offset, self.offset + erase_len)
+            .map_err(flash_error)?;
+        self.flash.write(self.offset, &buf).map_err(flash_error)
+    }
+
+    /// The header of the stored data, which holds its checksum.
+    fn fingerprint(&mut self) -> error::BackendResult<Option<Fingerprint>> {
+        Ok(Some(Fingerprint::from_contents(&self.read(HEADER_LEN)?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FlashBackend;
+    use crate::backend::Backend;
+    use crate::error::BackendError;
+    use embedded_storage::nor_flash::{
+        check_erase, check_read, check_write, ErrorType, NorFlash, NorFlashErrorKind, ReadNorFlash,
+    };
+
+    /// A NOR flash in memory, which can only clear bits until erased.
+    #[derive(Debug)]
+    struct Flash {
+        data: Vec<u8>,
+        erases: usize,
+    }
+
+    impl ErrorType for Flash {
+        type Error = NorFlashErrorKind;
+    }
+
+    impl ReadNorFlash for Flash {
+        const READ_SIZE: usize = 4;
+
+        fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            check_read(self, offset, bytes.len())?;
+            let offset = offset as usize;
+            bytes.copy_from_slice(&self.data[offset..offset + bytes.len()]);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            self.data.len()
+        }
+    }
+
+    impl NorFlash for Flash {
+        const WRITE_SIZE: usize = 4;
+        const ERASE_SIZE: usize = 64;
+
+        fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+            check_erase(self, from, to)?;
+            self.data[from as usize..to as usize].fill(0xFF);
+            self.erases += 1;
+            Ok(())
+        }
+
+        fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+            check_write(self, offset, bytes.len())?;
+            for (old, new) in self.data[offset as usize..].iter_mut().zip(bytes) {
+                *old &= new;
+            }
+            Ok(())
+        }
+    }
+
+    fn flash() -> Flash {
+        Flash {
+            data: vec![0xFF; 256],
+            erases: 0,
+        }
+    }
+
+    #[test]
+    fn flash_round_trip() {
+        let mut backend = FlashBackend::new(flash(), 64, 128).expect("could not create");
+        assert_eq!(backend.get_data().expect("could not get"), b"");
+        let empty = backend.fingerprint().expect("could not get fingerprint");
+
+        let long: Vec<u8> = (0..100).collect();
+        backend.put_data(&long).expect("could not put");
+        assert_eq!(backend.get_data().expect("could not get"), long);
+        assert_ne!(
+            backend.fingerprint().expect("could not get fingerprint"),
+            empty
+        );
+        backend.put_data(b"short").expect("could not put");
+        assert_eq!(backend.get_data().expect("could not get"), b"short");
+
+        assert!(matches!(
+            backend.put_data(&[0; 121]),
+            Err(BackendError::Flash(NorFlashErrorKind::OutOfBounds))
+        ));
+        assert_eq!(backend.get_data().expect("could not get"), b"short");
+
+        let flash = backend.into_inner();
+        assert_eq!(flash.erases, 2);
+        assert!(flash.data[..64].iter().all(|&b| b == 0xFF));
+        assert!(flash.data[192..].iter().all(|&b| b == 0xFF));
+    }
+
+    #[test]
+    fn flash_detects_corruption() {
+        let mut backend = FlashBackend::new(flash(), 0, 256).expect("could not create");
+        backend.put_data(b"data").expect("could not put");
+        let mut flash = backend.into_inner();
+        flash.data[9] = 0;
+        let mut backend = FlashBackend::new(flash, 0, 256).expect("could not create");
+        assert!(matches!(backend.get_data(), Err(BackendError::Corrupt(_))));
+    }
+
+    #[test]
+    fn flash_region_checks() {
+        assert!(matches!(
+            FlashBackend::new(flash(), 32, 64),
+            Err(BackendError::Flash(NorFlashErrorKind::NotAligned))
+        ));
+        assert!(matches!(
+            FlashBackend::new(flash(), 192, 128),
+            Err(BackendError::Flash(NorFlashErrorKind::OutOfBounds))
+        ));
+    }
+}
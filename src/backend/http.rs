@@ -0,0 +1,258 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::io::Read;
+
+use super::{Backend, Fingerprint};
+use crate::error::{self, BackendError};
+
+/// What is known about the data on the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Remote {
+    /// Nothing was read or written yet.
+    Unknown,
+    /// The server had no data.
+    Missing,
+    /// The `ETag` of the data as it was last read or written, `None` if the
+    /// server did not send one.
+    Tagged(Option<String>),
+}
+
+/// A backend that loads the data with a `GET` from a URL, and saves it with
+/// a `PUT` to the same URL.
+///
+/// The `ETag` the server sends with the data is kept, and every `PUT` sends
+/// it back in an `If-Match` header, so that a save fails with
+/// [`BackendError::Conflict`] instead of overwriting data someone else saved
+/// in between. If the server had no data, the `PUT` is sent with
+/// `If-None-Match: *` instead. Servers that do not support conditional
+/// requests simply ignore them.
+///
+/// A `404 Not Found` reads as empty data, any other response that is not a
+/// success fails with [`BackendError::Http`]. The [`Backend::fingerprint`]
+/// is the `ETag` of a `HEAD` request, so that
+/// [`Database::load_if_modified`](crate::Database::load_if_modified) only
+/// downloads changed data.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # extern crate rustbreak;
+/// use rustbreak::backend::{Backend, HttpBackend};
+///
+/// # fn main() -> rustbreak::error::BackendResult<()> {
+/// let mut backend = HttpBackend::new("https://artifacts.example.com/state/app.ron")
+///     .with_header("Authorization", "Bearer secret");
+/// let data = backend.get_data()?;
+/// backend.put_data(&data)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct HttpBackend {
+    agent: ureq::Agent,
+    url: String,
+    headers: Vec<(String, String)>,
+    remote: Remote,
+}
+
+impl HttpBackend {
+    /// Load from and save to `url`.
+    pub fn new<S: Into<String>>(url: S) -> Self {
+        Self::with_agent(ureq::Agent::new(), url)
+    }
+
+    /// Load from and save to `url` with `agent`, for example to configure
+    /// timeouts, a proxy or TLS.
+    pub fn with_agent<S: Into<String>>(agent: ureq::Agent, url: S) -> Self {
+        Self {
+            agent,
+            url: url.into(),
+            headers: Vec::new(),
+            remote: Remote::Unknown,
+        }
+    }
+
+    /// Send the header `name: value` with every request, for example for
+    /// authentication.
+    #[must_use]
+    pub fn with_header<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// The URL the data is stored at.
+    #[must_use]
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The `ETag` of the data as it was last read or written, if the server
+    /// sent one.
+    #[must_use]
+    pub fn etag(&self) -> Option<&str> {
+        match &self.remote {
+            Remote::Tagged(etag) => etag.as_deref(),
+            _ => None,
+        }
+    }
+
+    fn request(&self, method: &str) -> ureq::Request {
+        self.headers.iter().fold(
+            self.agent.request(method, &self.url),
+            |req, (name, value)| req.set(name, value),
+        )
+    }
+}
+
+fn etag(response: &ureq::Response) -> Option<String> {
+    response.header("ETag").map(str::to_string)
+}
+
+fn http_error(err: ureq::Error) -> BackendError {
+    match err {
+        ureq::Error::Status(412, _) => BackendError::Conflict,
+        err => BackendError::Http(Box::new(err)),
+    }
+}
+
+impl Backend for HttpBackend {
+    fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        let response = match self.request("GET").call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(404, _)) => {
+                self.remote = Remote::Missing;
+                return Ok(Vec::new());
+            }
+            Err(err) => return Err(http_error(err)),
+        };
+        let tag = etag(&response);
+        let mut data = Vec::new();
+        response.into_reader().read_to_end(&mut data)?;
+        self.remote = Remote::Tagged(tag);
+        Ok(data)
+    }
+
+    fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        let request = match &self.remote {
+            Remote::Unknown | Remote::Tagged(None) => self.request("PUT"),
+            Remote::Missing => self.request("PUT").set("If-None-Match", "*"),
+            Remote::Tagged(Some(etag)) => self.request("PUT").set("If-Match", etag),
+        };
+        let response = request.send_bytes(data).map_err(http_error)?;
+        self.remote = Remote::Tagged(etag(&response));
+        Ok(())
+    }
+
+    fn fingerprint(&mut self) -> error::BackendResult<Option<Fingerprint>> {
+        match self.request("HEAD").call() {
+            Ok(response) => {
+                Ok(etag(&response).map(|tag| Fingerprint::from_contents(tag.as_bytes())))
+            }
+            Err(ureq::Error::Status(404, _)) => Ok(Some(Fingerprint::from_contents(&[]))),
+            Err(err) => Err(http_error(err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HttpBackend;
+    use crate::backend::Backend;
+    use crate::error::BackendError;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    /// Serve a single document with versioned `ETag`s, honoring `If-Match`
+    /// and `If-None-Match: *`.
+    fn serve() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("could not bind");
+        let url = format!("http://{}/doc", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let mut doc: Option<(Vec<u8>, u32)> = None;
+            for stream in listener.incoming() {
+                let mut stream = BufReader::new(stream.expect("could not accept"));
+                let mut line = String::new();
+                stream.read_line(&mut line).unwrap();
+                let method = line.split(' ').next().unwrap_or_default().to_string();
+                let (mut len, mut if_match, mut if_none_match) = (0, None, false);
+                loop {
+                    line.clear();
+                    stream.read_line(&mut line).unwrap();
+                    let Some((name, value)) = line.trim_end().split_once(": ") else {
+                        break;
+                    };
+                    match name.to_ascii_lowercase().as_str() {
+                        "content-length" => len = value.parse().unwrap(),
+                        "if-match" => if_match = Some(value.to_string()),
+                        "if-none-match" => if_none_match = true,
+                        _ => {}
+                    }
+                }
+                let mut body = vec![0; len];
+                stream.read_exact(&mut body).unwrap();
+
+                let tag = |version: u32| format!("\"v{version}\"");
+                let current = doc.as_ref().map(|(_, version)| tag(*version));
+                let (status, etag, body) = match (method.as_str(), &doc) {
+                    ("GET" | "HEAD", None) => ("404 Not Found", None, Vec::new()),
+                    ("GET", Some((data, _))) => ("200 OK", current, data.clone()),
+                    ("HEAD", Some(_)) => ("200 OK", current, Vec::new()),
+                    ("PUT", _)
+                        if (if_none_match && doc.is_some())
+                            || if_match.is_some_and(|tag| Some(tag) != current) =>
+                    {
+                        ("412 Precondition Failed", None, Vec::new())
+                    }
+                    ("PUT", _) => {
+                        let version = doc.as_ref().map_or(1, |(_, version)| version + 1);
+                        doc = Some((body, version));
+                        ("204 No Content", Some(tag(version)), Vec::new())
+                    }
+                    _ => ("405 Method Not Allowed", None, Vec::new()),
+                };
+                let mut stream = stream.into_inner();
+                write!(stream, "HTTP/1.1 {status}\r\nConnection: close\r\n").unwrap();
+                if let Some(etag) = etag {
+                    write!(stream, "ETag: {etag}\r\n").unwrap();
+                }
+                if method != "HEAD" {
+                    write!(stream, "Content-Length: {}\r\n", body.len()).unwrap();
+                }
+                stream.write_all(b"\r\n").unwrap();
+                stream.write_all(&body).unwrap();
+            }
+        });
+        url
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn http_round_trip_with_etags() {
+        let url = serve();
+        let mut backend = HttpBackend::new(url.clone());
+        assert_eq!(backend.get_data().expect("could not get"), b"");
+        backend.put_data(b"one").expect("could not put");
+        assert_eq!(backend.etag(), Some("\"v1\""));
+        assert_eq!(backend.get_data().expect("could not get"), b"one");
+
+        // Someone else saves in between.
+        let mut other = HttpBackend::new(url);
+        let before = backend.fingerprint().expect("could not get fingerprint");
+        assert_eq!(other.get_data().expect("could not get"), b"one");
+        other.put_data(b"two").expect("could not put");
+        assert_ne!(
+            backend.fingerprint().expect("could not get fingerprint"),
+            before
+        );
+        assert!(matches!(
+            backend.put_data(b"three"),
+            Err(BackendError::Conflict)
+        ));
+
+        assert_eq!(backend.get_data().expect("could not get"), b"two");
+        backend.put_data(b"three").expect("could not put");
+        assert_eq!(backend.etag(), Some("\"v3\""));
+    }
+}
@@ -0,0 +1,209 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use super::{Backend, BackendLock, BackendMetadata, BackendWriter, Fingerprint};
+use crate::error;
+
+use std::io::{self, Write};
+use std::time::Instant;
+
+/// A backend that emits [`tracing`] events for every read and write of
+/// another backend.
+///
+/// Every operation runs in a `debug` span named after it, with a `backend`
+/// field holding the name of the backend. When it finishes, an event with
+/// the number of bytes and the elapsed time is emitted, at `debug` level on
+/// success and at `warn` level on failure.
+///
+/// **Important**: This can only be used if the `tracing` feature is enabled
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::backend::{Backend, InstrumentedBackend, MemoryBackend};
+///
+/// # fn main() -> rustbreak::error::BackendResult<()> {
+/// let mut backend = InstrumentedBackend::new(MemoryBackend::new()).with_name("settings");
+/// backend.put_data(b"Hello")?;
+/// assert_eq!(backend.get_data()?, b"Hello");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct InstrumentedBackend<B> {
+    inner: B,
+    name: &'static str,
+}
+
+impl<B: Backend> InstrumentedBackend<B> {
+    /// Instrument `inner`, named after its type.
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            name: std::any::type_name::<B>(),
+        }
+    }
+
+    /// Set the name recorded in the `backend` field of the spans.
+    #[must_use]
+    pub fn with_name(mut self, name: &'static str) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// Get a reference to the inner backend.
+    pub fn get_ref(&self) -> &B {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner backend.
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.inner
+    }
+
+    /// Consume the `InstrumentedBackend` and return the inner backend.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+/// Emit the event for an operation that handled `bytes` and started at
+/// `start`.
+fn finished<T>(operation: &str, result: &error::BackendResult<T>, bytes: usize, start: Instant) {
+    let elapsed = start.elapsed();
+    match result {
+        Ok(_) => tracing::debug!(bytes, ?elapsed, "{operation} finished"),
+        Err(error) => tracing::warn!(bytes, ?elapsed, %error, "{operation} failed"),
+    }
+}
+
+impl<B: Backend> Backend for InstrumentedBackend<B> {
+    fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        let _span = tracing::debug_span!("get_data", backend = self.name).entered();
+        let start = Instant::now();
+        let result = self.inner.get_data();
+        let bytes = result.as_ref().map_or(0, Vec::len);
+        finished("get_data", &result, bytes, start);
+        result
+    }
+
+    fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        let _span = tracing::debug_span!("put_data", backend = self.name).entered();
+        let start = Instant::now();
+        let result = self.inner.put_data(data);
+        finished("put_data", &result, data.len(), start);
+        result
+    }
+
+    /// Wrap the writer of the inner backend, counting the bytes written.
+    ///
+    /// The span covers the whole save, from getting the writer until it is
+    /// finished.
+    fn writer(&mut self) -> error::BackendResult<Option<Box<dyn BackendWriter + '_>>> {
+        let span = tracing::debug_span!("writer", backend = self.name);
+        let start = Instant::now();
+        let entered = span.enter();
+        let writer = self.inner.writer();
+        drop(entered);
+        match writer {
+            Ok(Some(inner)) => Ok(Some(Box::new(InstrumentedWriter {
+                inner,
+                span,
+                bytes: 0,
+                start,
+            }))),
+            Ok(None) => Ok(None),
+            Err(error) => {
+                let result = Err(error);
+                span.in_scope(|| finished("writer", &result, 0, start));
+                result
+            }
+        }
+    }
+
+    fn quarantine(&mut self) -> error::BackendResult<()> {
+        let _span = tracing::debug_span!("quarantine", backend = self.name).entered();
+        let start = Instant::now();
+        let result = self.inner.quarantine();
+        finished("quarantine", &result, 0, start);
+        result
+    }
+
+    fn fingerprint(&mut self) -> error::BackendResult<Option<Fingerprint>> {
+        self.inner.fingerprint()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+
+    fn metadata(&mut self) -> error::BackendResult<Option<BackendMetadata>> {
+        self.inner.metadata()
+    }
+
+    fn lock_exclusive(&mut self) -> error::BackendResult<Option<BackendLock>> {
+        self.inner.lock_exclusive()
+    }
+}
+
+/// The [`BackendWriter`] of an [`InstrumentedBackend`].
+struct InstrumentedWriter<'a> {
+    inner: Box<dyn BackendWriter + 'a>,
+    span: tracing::Span,
+    bytes: usize,
+    start: Instant,
+}
+
+impl Write for InstrumentedWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl BackendWriter for InstrumentedWriter<'_> {
+    fn finish(self: Box<Self>) -> error::BackendResult<()> {
+        let Self {
+            inner,
+            span,
+            bytes,
+            start,
+        } = *self;
+        let _span = span.entered();
+        let result = inner.finish();
+        finished("writer", &result, bytes, start);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Backend, InstrumentedBackend};
+    use crate::backend::{MemoryBackend, PathBackend};
+    use std::io::Write;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_instrumented_backend() {
+        let mut backend = InstrumentedBackend::new(MemoryBackend::new());
+        backend.put_data(b"Hello").expect("could not put data");
+        assert_eq!(backend.get_data().expect("could not get data"), b"Hello");
+
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let mut backend = InstrumentedBackend::new(PathBackend::new(dir.path().join("data")));
+        let mut writer = backend
+            .writer()
+            .expect("could not get writer")
+            .expect("path backends have a writer");
+        writer.write_all(b"World").expect("could not write");
+        writer.finish().expect("could not finish");
+        assert_eq!(backend.get_data().expect("could not get data"), b"World");
+    }
+}
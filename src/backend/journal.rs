@@ -0,0 +1,487 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::convert::TryFrom;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use super::{Backend, BackendLock, BackendMetadata, Fingerprint, SyncPolicy, Syncer};
+use crate::error::{self, BackendError};
+
+/// The version of the log layout.
+const LOG_VERSION: u8 = 1;
+/// Magic and log version.
+const HEADER_LEN: usize = 4 + 1;
+/// Record kind, payload length and CRC32.
+const RECORD_HEADER_LEN: usize = 1 + 8 + 4;
+
+/// The bytes every journal starts with.
+const MAGIC: [u8; 4] = *b"RBJL";
+/// A record holding the whole data.
+const SNAPSHOT: u8 = 0;
+/// A record replacing the middle of the previous data, keeping a prefix and
+/// a suffix of it.
+const PATCH: u8 = 1;
+
+/// A file of checksummed records, which are only ever appended.
+///
+/// The file starts with four magic bytes identifying what the records mean,
+/// and the log version. A record is its kind, the length of its payload, a
+/// CRC32 of the payload, and the payload. A record that is incomplete or
+/// does not match its checksum ends the log, and is overwritten by the next
+/// append.
+#[derive(Debug)]
+pub(crate) struct RecordLog {
+    path: PathBuf,
+    file: File,
+    magic: [u8; 4],
+    /// The end of the last complete record, `0` if there is no header yet.
+    end: u64,
+    /// The number of complete records.
+    records: usize,
+    sync: Syncer,
+}
+
+impl RecordLog {
+    /// Open the log at `path`, creating it if it doesn't yet exist.
+    ///
+    /// The records are only read by [`RecordLog::replay`].
+    pub(crate) fn open(path: PathBuf, magic: [u8; 4]) -> error::BackendResult<Self> {
+        let file = open_file(&path)?;
+        Ok(Self {
+            path,
+            file,
+            magic,
+            end: 0,
+            records: 0,
+            sync: Syncer::default(),
+        })
+    }
+
+    pub(crate) fn set_sync_policy(&mut self, policy: SyncPolicy) {
+        self.sync.policy = policy;
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The number of records, as of the last replay or write.
+    pub(crate) fn records(&self) -> usize {
+        self.records
+    }
+
+    pub(crate) fn fingerprint(&self) -> error::BackendResult<Fingerprint> {
+        Ok(Fingerprint::from_metadata(&self.file.metadata()?))
+    }
+
+    /// Call `record` with the kind and payload of every complete record, in
+    /// order.
+    ///
+    /// Fails with [`BackendError::Corrupt`] if the file is not a log with the
+    /// right magic bytes.
+    pub(crate) fn replay<F, E>(&mut self, mut record: F) -> Result<(), E>
+    where
+        F: FnMut(u8, &[u8]) -> Result<(), E>,
+        E: From<BackendError>,
+    {
+        let mut log = Vec::new();
+        self.file
+            .seek(SeekFrom::Start(0))
+            .and_then(|_| self.file.read_to_end(&mut log))
+            .map_err(BackendError::from)?;
+        if log.is_empty() {
+            self.end = 0;
+            self.records = 0;
+            return Ok(());
+        }
+        if log.len() < HEADER_LEN || log[..4] != self.magic {
+            return Err(corrupt("missing log header").into());
+        }
+        if log[4] != LOG_VERSION {
+            return Err(corrupt("unknown log version").into());
+        }
+
+        let mut offset = HEADER_LEN;
+        let mut records = 0;
+        while let Some((kind, payload)) = read_record(&log[offset..]) {
+            record(kind, payload)?;
+            offset += RECORD_HEADER_LEN + payload.len();
+            records += 1;
+        }
+        self.end = offset as u64;
+        self.records = records;
+        Ok(())
+    }
+
+    /// Append `records` in a single write.
+    ///
+    /// The log has to be replayed first, so that its end is known.
+    pub(crate) fn append(&mut self, records: &[(u8, &[u8])]) -> error::BackendResult<()> {
+        let mut buf = Vec::new();
+        if self.end == 0 {
+            buf = self.header();
+        }
+        for (kind, payload) in records {
+            push_record(&mut buf, *kind, payload);
+        }
+
+        // Drop what is left of a partially written record.
+        self.file.set_len(self.end)?;
+        self.file.seek(SeekFrom::Start(self.end))?;
+        self.file.write_all(&buf)?;
+        if self.sync.should_sync() {
+            self.file.sync_all()?;
+        }
+        self.end += buf.len() as u64;
+        self.records += records.len();
+        Ok(())
+    }
+
+    /// Replace the log with a single record.
+    ///
+    /// The record is written to a temporary file, which then replaces the
+    /// log, so the log is not lost if this fails.
+    pub(crate) fn rewrite(&mut self, kind: u8, payload: &[u8]) -> error::BackendResult<()> {
+        let mut tempf = tempfile::NamedTempFile::new_in(super::path::parent_dir(&self.path))?;
+        let mut buf = self.header();
+        push_record(&mut buf, kind, payload);
+        tempf.write_all(&buf)?;
+        if self.sync.should_sync() {
+            tempf.as_file().sync_all()?;
+        }
+        tempf.persist(&self.path)?;
+
+        self.file = open_file(&self.path)?;
+        self.end = buf.len() as u64;
+        self.records = 1;
+        Ok(())
+    }
+
+    fn header(&self) -> Vec<u8> {
+        let mut buf = self.magic.to_vec();
+        buf.push(LOG_VERSION);
+        buf
+    }
+}
+
+/// A backend that appends changes to a journal file, instead of rewriting
+/// the whole file on every save.
+///
+/// Every save appends a record to the file: either a snapshot of the whole
+/// data, or a patch that only holds the bytes that differ from the previous
+/// save. For formats like RON, YAML or JSON, changing a few fields of a large
+/// database makes for a small patch. Loading replays the records.
+///
+/// Every record is checksummed. A record that was only partially written,
+/// because the program crashed during a save, is ignored when loading and
+/// overwritten by the next save, so the journal does not need atomic renames
+/// to stay consistent.
+///
+/// The journal grows with every save, until it is compacted into a single
+/// snapshot, either by calling [`JournalBackend::compact`], or automatically
+/// once it holds the number of records set with
+/// [`JournalBackend::with_compact_after`].
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::backend::{Backend, JournalBackend};
+///
+/// # fn main() -> rustbreak::error::BackendResult<()> {
+/// # let dir = tempfile::tempdir().unwrap();
+/// let mut backend = JournalBackend::open(dir.path().join("db.journal"))?;
+/// backend.put_data(b"(apples: 3, pears: 5)")?;
+/// backend.put_data(b"(apples: 4, pears: 5)")?;
+/// assert_eq!(backend.records(), 2);
+///
+/// backend.compact()?;
+/// assert_eq!(backend.records(), 1);
+/// assert_eq!(backend.get_data()?, b"(apples: 4, pears: 5)");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct JournalBackend {
+    log: RecordLog,
+    /// The data after replaying the journal, `None` until it was read.
+    current: Option<Vec<u8>>,
+    /// Compact once the journal holds this many records, never if `0`.
+    compact_after: usize,
+}
+
+impl JournalBackend {
+    /// Open the journal at `path`, creating it if it doesn't yet exist.
+    ///
+    /// Fails with [`BackendError::Corrupt`] if the file is not a journal.
+    pub fn open<P: AsRef<Path>>(path: P) -> error::BackendResult<Self> {
+        let mut backend = Self {
+            log: RecordLog::open(path.as_ref().to_owned(), MAGIC)?,
+            current: None,
+            compact_after: 64,
+        };
+        backend.replay()?;
+        Ok(backend)
+    }
+
+    /// Compact the journal automatically once it holds `records` records,
+    /// the default is `64`. A `records` of `0` never compacts automatically.
+    #[must_use]
+    pub fn with_compact_after(mut self, records: usize) -> Self {
+        self.compact_after = records;
+        self
+    }
+
+    /// Choose when saves are synced to disk, see [`SyncPolicy`].
+    #[must_use]
+    pub fn with_sync_policy(mut self, policy: SyncPolicy) -> Self {
+        self.log.set_sync_policy(policy);
+        self
+    }
+
+    /// The path of the journal file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        self.log.path()
+    }
+
+    /// The number of records in the journal, as of the last read or write.
+    #[must_use]
+    pub fn records(&self) -> usize {
+        self.log.records()
+    }
+
+    /// Fold the journal into a single snapshot of the current data.
+    ///
+    /// The snapshot is written to a temporary file, which then replaces the
+    /// journal, so the journal is not lost if compacting fails.
+    pub fn compact(&mut self) -> error::BackendResult<()> {
+        let data = match self.current.take() {
+            Some(data) => data,
+            None => self.replay()?,
+        };
+        self.log.rewrite(SNAPSHOT, &data)?;
+        self.current = Some(data);
+        Ok(())
+    }
+
+    /// Read the journal, returning the data.
+    fn replay(&mut self) -> error::BackendResult<Vec<u8>> {
+        let mut data = Vec::new();
+        self.log.replay(|kind, payload| {
+            data = apply(kind, payload, &data)?;
+            Ok::<_, BackendError>(())
+        })?;
+        self.current = Some(data.clone());
+        Ok(data)
+    }
+}
+
+impl Backend for JournalBackend {
+    fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        self.replay()
+    }
+
+    /// Append a patch against the previous data, or a snapshot if the patch
+    /// would not be smaller.
+    ///
+    /// Compacts instead, if the journal is full.
+    fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        let previous = match self.current.take() {
+            Some(previous) => previous,
+            None => self.replay()?,
+        };
+        let records = self.log.records();
+        if self.compact_after > 0 && records >= self.compact_after {
+            self.log.rewrite(SNAPSHOT, data)?;
+        } else {
+            let patch = diff(&previous, data);
+            if records > 0 && patch.len() < data.len() {
+                self.log.append(&[(PATCH, &patch)])?;
+            } else {
+                self.log.append(&[(SNAPSHOT, data)])?;
+            }
+        }
+        self.current = Some(data.to_vec());
+        Ok(())
+    }
+
+    fn fingerprint(&mut self) -> error::BackendResult<Option<Fingerprint>> {
+        self.log.fingerprint().map(Some)
+    }
+
+    /// The length of the data as of the last read or write.
+    fn size_hint(&self) -> Option<usize> {
+        self.current.as_ref().map(Vec::len)
+    }
+
+    /// Lock a sidecar file next to the journal, which has `.lck` appended to
+    /// its name, like a [`PathBackend`](super::PathBackend) does.
+    fn lock_exclusive(&mut self) -> error::BackendResult<Option<BackendLock>> {
+        super::path::lock_sidecar(self.log.path()).map(Some)
+    }
+
+    /// The metadata of the journal file.
+    fn metadata(&mut self) -> error::BackendResult<Option<BackendMetadata>> {
+        let metadata = std::fs::metadata(self.log.path())?;
+        Ok(Some(BackendMetadata::from_metadata(&metadata)))
+    }
+}
+
+fn open_file(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+}
+
+fn corrupt(reason: &str) -> BackendError {
+    BackendError::Corrupt(reason.to_string())
+}
+
+fn push_record(buf: &mut Vec<u8>, kind: u8, payload: &[u8]) {
+    buf.reserve(RECORD_HEADER_LEN + payload.len());
+    buf.push(kind);
+    buf.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    buf.extend_from_slice(&crc32fast::hash(payload).to_le_bytes());
+    buf.extend_from_slice(payload);
+}
+
+/// Read the record at the start of `journal`, `None` if it is incomplete or
+/// its checksum does not match.
+fn read_record(journal: &[u8]) -> Option<(u8, &[u8])> {
+    if journal.len() < RECORD_HEADER_LEN {
+        return None;
+    }
+    let len = usize::try_from(u64::from_le_bytes(to_array(&journal[1..9]))).ok()?;
+    let crc = u32::from_le_bytes(to_array(&journal[9..13]));
+    let payload = journal.get(RECORD_HEADER_LEN..RECORD_HEADER_LEN.checked_add(len)?)?;
+    if crc32fast::hash(payload) != crc {
+        return None;
+    }
+    Some((journal[0], payload))
+}
+
+/// A patch turning `old` into `new`: the length of their common prefix and
+/// suffix, followed by the bytes in between.
+fn diff(old: &[u8], new: &[u8]) -> Vec<u8> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let middle = &new[prefix..new.len() - suffix];
+    let mut patch = Vec::with_capacity(16 + middle.len());
+    patch.extend_from_slice(&(prefix as u64).to_le_bytes());
+    patch.extend_from_slice(&(suffix as u64).to_le_bytes());
+    patch.extend_from_slice(middle);
+    patch
+}
+
+/// Apply a record to the data before it.
+fn apply(kind: u8, payload: &[u8], data: &[u8]) -> error::BackendResult<Vec<u8>> {
+    match kind {
+        SNAPSHOT => Ok(payload.to_vec()),
+        PATCH if payload.len() >= 16 => {
+            let prefix = usize::try_from(u64::from_le_bytes(to_array(&payload[..8])));
+            let suffix = usize::try_from(u64::from_le_bytes(to_array(&payload[8..16])));
+            let (Ok(prefix), Ok(suffix)) = (prefix, suffix) else {
+                return Err(corrupt("patch out of range"));
+            };
+            if prefix
+                .checked_add(suffix)
+                .is_none_or(|kept| kept > data.len())
+            {
+                return Err(corrupt("patch out of range"));
+            }
+            let middle = &payload[16..];
+            let mut patched = Vec::with_capacity(prefix + middle.len() + suffix);
+            patched.extend_from_slice(&data[..prefix]);
+            patched.extend_from_slice(middle);
+            patched.extend_from_slice(&data[data.len() - suffix..]);
+            Ok(patched)
+        }
+        _ => Err(corrupt("unknown journal record")),
+    }
+}
+
+fn to_array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    let mut array = [0; N];
+    array.copy_from_slice(bytes);
+    array
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JournalBackend;
+    use crate::backend::Backend;
+    use crate::error::BackendError;
+    use std::io::Write;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_journal_appends_patches() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let path = dir.path().join("journal");
+        let mut backend = JournalBackend::open(&path)
+            .expect("could not open journal")
+            .with_compact_after(0);
+        assert_eq!(backend.get_data().expect("could not get data"), b"");
+
+        let mut data = vec![b'a'; 1000];
+        backend.put_data(&data).expect("could not put data");
+        for i in 0..10 {
+            data[500] = b'0' + i;
+            backend.put_data(&data).expect("could not put data");
+        }
+        assert_eq!(backend.records(), 11);
+        let len = std::fs::metadata(&path).expect("no metadata").len();
+        assert!(len < 2 * 1000);
+
+        // A partially written record is ignored, and overwritten.
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .expect("could not open journal");
+        file.write_all(&[1, 200, 0, 0]).expect("could not write");
+        let mut backend = JournalBackend::open(&path).expect("could not open journal");
+        assert_eq!(backend.get_data().expect("could not get data"), data);
+        data[0] = b'b';
+        backend.put_data(&data).expect("could not put data");
+        assert_eq!(backend.records(), 12);
+
+        backend.compact().expect("could not compact");
+        assert_eq!(backend.records(), 1);
+        let mut backend = JournalBackend::open(&path).expect("could not open journal");
+        assert_eq!(backend.records(), 1);
+        assert_eq!(backend.get_data().expect("could not get data"), data);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_journal_compacts_automatically() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let path = dir.path().join("journal");
+        let mut backend = JournalBackend::open(&path)
+            .expect("could not open journal")
+            .with_compact_after(3);
+        for i in 0..5_u8 {
+            backend.put_data(&[i; 10]).expect("could not put data");
+        }
+        assert_eq!(backend.records(), 2);
+        assert_eq!(backend.get_data().expect("could not get data"), [4; 10]);
+
+        std::fs::write(&path, b"not a journal").expect("could not write");
+        assert!(matches!(
+            JournalBackend::open(&path),
+            Err(BackendError::Corrupt(_))
+        ));
+    }
+}
@@ -0,0 +1,97 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use super::Backend;
+use crate::error::{self, BackendError};
+
+/// A backend that stores the data under a key of the browser's
+/// `localStorage`, for web apps compiled to `wasm32-unknown-unknown`.
+///
+/// A missing key reads as empty data. With [`LocalStorageBackend::session`]
+/// the data is kept in `sessionStorage` instead, which the browser clears
+/// when the tab is closed.
+///
+/// Web storage only holds strings, so the data has to be valid UTF-8, saving
+/// anything else fails with an [`std::io::ErrorKind::InvalidData`] error.
+/// Text formats like Ron or JSON can be stored as they are, binary ones can
+/// be wrapped in [`Armored`](crate::deser::Armored) to store them as base64.
+/// Errors of the storage itself, like a full quota, are reported as
+/// [`BackendError::WebStorage`].
+///
+/// **Important**: This backend is only available with the `wasm` feature, and
+/// only works when running in a browser.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # extern crate rustbreak;
+/// use rustbreak::backend::{Backend, LocalStorageBackend};
+///
+/// # fn main() -> rustbreak::error::BackendResult<()> {
+/// let mut backend = LocalStorageBackend::new("todos")?;
+/// backend.put_data(b"[\"buy milk\"]")?;
+/// assert_eq!(backend.get_data()?, b"[\"buy milk\"]");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct LocalStorageBackend {
+    storage: web_sys::Storage,
+    key: String,
+}
+
+impl LocalStorageBackend {
+    /// Store the data under `key` in the `localStorage` of the window.
+    pub fn new<K: Into<String>>(key: K) -> error::BackendResult<Self> {
+        let storage = window()?.local_storage().map_err(js_error)?;
+        Self::with_storage(storage, "localStorage", key)
+    }
+
+    /// Store the data under `key` in the `sessionStorage` of the window.
+    pub fn session<K: Into<String>>(key: K) -> error::BackendResult<Self> {
+        let storage = window()?.session_storage().map_err(js_error)?;
+        Self::with_storage(storage, "sessionStorage", key)
+    }
+
+    fn with_storage<K: Into<String>>(
+        storage: Option<web_sys::Storage>,
+        name: &str,
+        key: K,
+    ) -> error::BackendResult<Self> {
+        let storage =
+            storage.ok_or_else(|| BackendError::WebStorage(format!("{name} is not available")))?;
+        Ok(Self {
+            storage,
+            key: key.into(),
+        })
+    }
+
+    /// The key the data is stored under.
+    #[must_use]
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+fn window() -> error::BackendResult<web_sys::Window> {
+    web_sys::window().ok_or_else(|| BackendError::WebStorage("there is no window".to_string()))
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn js_error(err: wasm_bindgen::JsValue) -> BackendError {
+    BackendError::WebStorage(err.as_string().unwrap_or_else(|| format!("{err:?}")))
+}
+
+impl Backend for LocalStorageBackend {
+    fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        let data = self.storage.get_item(&self.key).map_err(js_error)?;
+        Ok(data.map(String::into_bytes).unwrap_or_default())
+    }
+
+    fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        let data = std::str::from_utf8(data)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        self.storage.set_item(&self.key, data).map_err(js_error)
+    }
+}
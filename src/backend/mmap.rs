@@ -3,7 +3,14 @@ use super::Backend;
 use crate::error;
 
 use std::cmp;
+use std::convert::TryFrom;
+use std::fs::{File, OpenOptions};
 use std::io;
+use std::path::Path;
+
+/// The length of the header of a file backed map, which holds the length of
+/// the data.
+const HEADER_LEN: usize = 8;
 
 #[derive(Debug)]
 struct Mmap {
@@ -12,65 +19,169 @@ struct Mmap {
     pub end: usize,
     /// Mmap total len
     pub len: usize,
+    /// The mapped file, `None` for an anonymous map.
+    file: Option<File>,
 }
 
 impl Mmap {
     fn new(len: usize) -> io::Result<Self> {
         let inner = memmap::MmapOptions::new().len(len).map_anon()?;
 
-        Ok(Self { inner, end: 0, len })
+        Ok(Self {
+            inner,
+            end: 0,
+            len,
+            file: None,
+        })
+    }
+
+    /// Map `file`, growing it to hold at least `len` bytes of data.
+    ///
+    /// The data is prefixed by its length, which is read back from the file.
+    fn from_file(file: File, len: usize) -> io::Result<Self> {
+        let file_len = usize::try_from(file.metadata()?.len()).unwrap_or(usize::MAX);
+        let len = cmp::max(len, file_len.saturating_sub(HEADER_LEN));
+        if file_len < HEADER_LEN + len {
+            file.set_len((HEADER_LEN + len) as u64)?;
+        }
+        let inner = Self::map_file(&file, HEADER_LEN + len)?;
+
+        let mut header = [0; HEADER_LEN];
+        header.copy_from_slice(&inner[..HEADER_LEN]);
+        let end = usize::try_from(u64::from_le_bytes(header)).unwrap_or(usize::MAX);
+        if end > len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the length in the mmap header exceeds the file",
+            ));
+        }
+        Ok(Self {
+            inner,
+            end,
+            len,
+            file: Some(file),
+        })
+    }
+
+    #[allow(unsafe_code)]
+    fn map_file(file: &File, len: usize) -> io::Result<memmap::MmapMut> {
+        // SAFETY: The map is only valid as long as no one else truncates or
+        // writes to the file, which `MmapStorage::open` documents.
+        unsafe { memmap::MmapOptions::new().len(len).map_mut(file) }
+    }
+
+    /// Where the data starts in the map.
+    fn offset(&self) -> usize {
+        if self.file.is_some() {
+            HEADER_LEN
+        } else {
+            0
+        }
     }
 
     fn as_slice(&self) -> &[u8] {
-        &self.inner[..self.end]
+        &self.inner[self.offset()..self.offset() + self.end]
     }
 
     fn as_mut_slice(&mut self) -> &mut [u8] {
-        &mut self.inner[..self.end]
+        let offset = self.offset();
+        &mut self.inner[offset..offset + self.end]
     }
 
     /// Copies data to mmap and modifies data's end cursor.
     fn write(&mut self, data: &[u8]) -> error::BackendResult<()> {
         if data.len() > self.len {
-            return Err(error::BackendError::Internal(format!(
-                "Unexpected write beyond mmap's backend capacity."
+            return Err(error::BackendError::Internal(String::from(
+                "Unexpected write beyond mmap's backend capacity.",
             )));
         }
         self.end = data.len();
         self.as_mut_slice().copy_from_slice(data);
+        if self.file.is_some() {
+            self.inner[..HEADER_LEN].copy_from_slice(&(self.end as u64).to_le_bytes());
+        }
         Ok(())
     }
 
+    /// Flush the data written last to the file, if there is one.
     fn flush(&mut self) -> io::Result<()> {
-        self.inner.flush()
+        if self.file.is_some() {
+            self.inner.flush_range(0, HEADER_LEN + self.end)
+        } else {
+            self.inner.flush()
+        }
     }
 
-    /// Increases mmap size by `max(old_size*2, new_size)`.
+    /// Changes mmap size to `len`, which must hold the data.
     ///
-    /// Note that it doesn't copy original data
-    fn resize_no_copy(&mut self, new_size: usize) -> io::Result<()> {
-        let len = cmp::max(self.len + self.len, new_size);
-        // Make sure we don't discard old mmap before creating new one;
-        let new_mmap = Self::new(len)?;
-        *self = new_mmap;
+    /// The data is copied into the new map, and if this fails the old map
+    /// is kept as is. An anonymous map keeps at least one byte, as empty
+    /// maps are not supported.
+    fn resize(&mut self, len: usize) -> io::Result<()> {
+        debug_assert!(len >= self.end);
+        if let Some(file) = &self.file {
+            // The file keeps the data, so only the map has to be replaced.
+            // The old map must never reach beyond the end of the file, so
+            // grow the file before and shrink it after mapping.
+            let file_len = (HEADER_LEN + len) as u64;
+            if len > self.len {
+                file.set_len(file_len)?;
+            }
+            self.inner = Self::map_file(file, HEADER_LEN + len)?;
+            self.len = len;
+            if file.metadata()?.len() > file_len {
+                file.set_len(file_len)?;
+            }
+        } else {
+            // Make sure we don't discard old mmap before creating new one;
+            let mut new_mmap = Self::new(cmp::max(len, 1))?;
+            new_mmap.end = self.end;
+            new_mmap.as_mut_slice().copy_from_slice(self.as_slice());
+            *self = new_mmap;
+        }
         Ok(())
     }
 }
 
-/// A backend that uses an nonymous mmap.
-///
-/// The `Backend` automatically creates bigger map
-/// on demand using following strategy:
+/// How [`MmapStorage`] grows its map when the data does not fit anymore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum GrowthStrategy {
+    /// Grow to exactly the size of the data.
+    Exact,
+    /// Double the size, or grow to the size of the data if that is bigger.
+    #[default]
+    Exponential,
+    /// Grow to the size of the data, rounded up to a multiple of the given
+    /// chunk size.
+    Chunked(usize),
+}
+
+impl GrowthStrategy {
+    /// The new capacity for `needed` bytes, growing from `capacity`.
+    fn grow(self, capacity: usize, needed: usize) -> usize {
+        match self {
+            Self::Exact | Self::Chunked(0) => needed,
+            Self::Exponential => cmp::max(capacity.saturating_mul(2), needed),
+            Self::Chunked(chunk) => needed.div_ceil(chunk).saturating_mul(chunk),
+        }
+    }
+}
+
+/// A backend that uses an nonymous mmap, or a memory mapped file.
 ///
-/// - If new data size allows, multiply size by 2.
-/// - Otherwise new data size is used.
+/// The `Backend` automatically creates bigger map on demand, using the
+/// [`GrowthStrategy`] set with [`MmapStorage::with_growth_strategy`]. By
+/// default it doubles the size, or uses the new data size if that is bigger.
 ///
-/// Note that mmap is never shrink back.
+/// Note that mmap is never shrink back automatically, use
+/// [`MmapStorage::shrink_to_fit`] for that.
 ///
 /// Use `Backend` methods to read and write into it.
 #[derive(Debug)]
 pub struct MmapStorage {
     mmap: Mmap,
+    growth: GrowthStrategy,
 }
 
 impl MmapStorage {
@@ -83,7 +194,78 @@ impl MmapStorage {
     pub fn with_size(len: usize) -> error::BackendResult<Self> {
         let mmap = Mmap::new(len)?;
 
-        Ok(Self { mmap })
+        Ok(Self {
+            mmap,
+            growth: GrowthStrategy::default(),
+        })
+    }
+
+    /// Memory map the file at `path`, which has to be created by
+    /// [`MmapStorage::create`] before.
+    ///
+    /// Unlike an anonymous map, the data persists in the file. It starts with
+    /// the length of the data, and is grown with the map.
+    ///
+    /// **Important**: The file must not be changed by anyone else while it
+    /// is mapped, not even by other processes. Rustbreak can not prevent
+    /// this, and reading the data would then be undefined behaviour.
+    pub fn open<P: AsRef<Path>>(path: P) -> error::BackendResult<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        Ok(Self {
+            mmap: Mmap::from_file(file, 0)?,
+            growth: GrowthStrategy::default(),
+        })
+    }
+
+    /// Create the file at `path`, with room for `len` bytes of data, and
+    /// memory map it. An existing file is truncated.
+    ///
+    /// See [`MmapStorage::open`] for details.
+    pub fn create<P: AsRef<Path>>(path: P, len: usize) -> error::BackendResult<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        Ok(Self {
+            mmap: Mmap::from_file(file, len)?,
+            growth: GrowthStrategy::default(),
+        })
+    }
+
+    /// Set how the map grows when the data does not fit anymore.
+    #[must_use]
+    pub fn with_growth_strategy(mut self, growth: GrowthStrategy) -> Self {
+        self.growth = growth;
+        self
+    }
+
+    /// The number of bytes the map can hold without growing.
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.mmap.len
+    }
+
+    /// The number of bytes of data stored in the map.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.mmap.as_slice().len()
+    }
+
+    /// Whether there is no data stored in the map.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.mmap.end == 0
+    }
+
+    /// Shrink the map to the size of the stored data, releasing the rest of
+    /// the memory. A file is truncated accordingly.
+    pub fn shrink_to_fit(&mut self) -> error::BackendResult<()> {
+        if self.mmap.len > self.mmap.end {
+            self.mmap.resize(self.mmap.end)?;
+        }
+        Ok(())
     }
 }
 
@@ -97,17 +279,22 @@ impl Backend for MmapStorage {
 
     fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
         if self.mmap.len < data.len() {
-            self.mmap.resize_no_copy(data.len())?;
+            let len = self.growth.grow(self.mmap.len, data.len());
+            self.mmap.resize(len)?;
         }
         self.mmap.write(data)?;
         self.mmap.flush()?;
         Ok(())
     }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len())
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Backend, MmapStorage};
+    use super::{Backend, GrowthStrategy, MmapStorage};
 
     #[test]
     #[cfg_attr(miri, ignore)]
@@ -132,6 +319,27 @@ mod tests {
         assert_eq!(storage.get_data().expect("To get data"), data);
     }
 
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_mmap_storage_file() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let path = dir.path().join("rustbreak_mmap.db");
+        let data = [4, 5, 1, 6, 8, 1];
+
+        let mut storage = MmapStorage::create(&path, 4).expect("To create mmap storage");
+        assert!(storage.get_data().expect("To get data").is_empty());
+        storage.put_data(&data).expect("To put data");
+        assert_eq!(storage.mmap.len, 8);
+        drop(storage);
+
+        let mut storage = MmapStorage::open(&path).expect("To open mmap storage");
+        assert_eq!(storage.get_data().expect("To get data"), data);
+        storage.put_data(&data[..2]).expect("To put data");
+        drop(storage);
+        let mut storage = MmapStorage::open(&path).expect("To open mmap storage");
+        assert_eq!(storage.get_data().expect("To get data"), data[..2]);
+    }
+
     #[test]
     #[cfg_attr(miri, ignore)]
     fn test_mmap_storage_increase_by_new_data_size() {
@@ -143,4 +351,64 @@ mod tests {
         assert_eq!(storage.mmap.len, data.len());
         assert_eq!(storage.get_data().expect("To get data"), data);
     }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_mmap_storage_growth_strategy() {
+        let data = [4, 5, 1, 6, 8, 1];
+        let mut storage = MmapStorage::with_size(4)
+            .expect("To crate mmap storage")
+            .with_growth_strategy(GrowthStrategy::Exact);
+        storage.put_data(&data).expect("To put data");
+        assert_eq!(storage.capacity(), 6);
+
+        let mut storage = MmapStorage::with_size(4)
+            .expect("To crate mmap storage")
+            .with_growth_strategy(GrowthStrategy::Chunked(5));
+        storage.put_data(&data).expect("To put data");
+        assert_eq!(storage.capacity(), 10);
+        assert_eq!(storage.len(), 6);
+        assert_eq!(storage.get_data().expect("To get data"), data);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_mmap_storage_shrink_to_fit() {
+        let data = [4, 5, 1, 6, 8, 1];
+        let mut storage = MmapStorage::new().expect("To crate mmap storage");
+        storage.put_data(&data).expect("To put data");
+        storage.shrink_to_fit().expect("To shrink");
+        assert_eq!(storage.capacity(), 6);
+        assert_eq!(storage.get_data().expect("To get data"), data);
+
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let path = dir.path().join("rustbreak_mmap.db");
+        let mut storage = MmapStorage::create(&path, 100).expect("To create mmap storage");
+        storage.put_data(&data).expect("To put data");
+        storage.shrink_to_fit().expect("To shrink");
+        assert_eq!(storage.capacity(), 6);
+        drop(storage);
+        assert_eq!(std::fs::metadata(&path).expect("To stat").len(), 14);
+        let mut storage = MmapStorage::open(&path).expect("To open mmap storage");
+        assert_eq!(storage.get_data().expect("To get data"), data);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_mmap_resize_preserves_data() {
+        let data = [4, 5, 1];
+        let mut storage = MmapStorage::with_size(4).expect("To crate mmap storage");
+        storage.put_data(&data).expect("To put data");
+        storage.mmap.resize(64).expect("To resize");
+        assert_eq!(storage.capacity(), 64);
+        assert_eq!(storage.get_data().expect("To get data"), data);
+
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let path = dir.path().join("rustbreak_mmap.db");
+        let mut storage = MmapStorage::create(&path, 4).expect("To create mmap storage");
+        storage.put_data(&data).expect("To put data");
+        storage.mmap.resize(64).expect("To resize");
+        assert_eq!(storage.get_data().expect("To get data"), data);
+        assert_eq!(std::fs::metadata(&path).expect("To stat").len(), 72);
+    }
 }
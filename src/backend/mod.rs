@@ -5,11 +5,16 @@
 //! The persistence backends of the Database.
 //!
 //! A file is a `Backend` through the `FileBackend`, so is a `Vec<u8>` with a
-//! `MemoryBackend`.
+//! `MemoryBackend`, or one shared between databases with a
+//! `SharedMemoryBackend`.
 //!
 //! Implementing your own Backend should be straightforward. Check the `Backend`
 //! documentation for details.
 
+use std::convert::TryFrom;
+use std::io::Write;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
 use crate::error;
 
 /// The Backend Trait.
@@ -25,6 +30,207 @@ pub trait Backend {
 
     /// Write the whole slice to the backend.
     fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()>;
+
+    /// Get a writer that replaces all data in the backend.
+    ///
+    /// If a writer is returned, the database saves by serializing straight
+    /// into it. Backends that can store the data incrementally should
+    /// implement this, so that large databases do not have to be held in
+    /// memory a second time.
+    ///
+    /// The default returns `None`, in which case the database serializes into
+    /// a buffer, which it reuses between saves, and passes it to
+    /// [`Backend::put_data`].
+    fn writer(&mut self) -> error::BackendResult<Option<Box<dyn BackendWriter + '_>>> {
+        Ok(None)
+    }
+
+    /// Set the current data aside, because it could not be loaded.
+    ///
+    /// Called by [`Database::load_or_recover`](crate::Database::load_or_recover)
+    /// before the data is overwritten with the recovered one. The default
+    /// does nothing.
+    fn quarantine(&mut self) -> error::BackendResult<()> {
+        Ok(())
+    }
+
+    /// Get a [`Fingerprint`] of the stored data, which changes whenever the
+    /// data does.
+    ///
+    /// It is used by [`Database::load_if_modified`](crate::Database::load_if_modified)
+    /// to skip loading unchanged data. The default returns `None`, meaning
+    /// the data has to be assumed to have changed.
+    fn fingerprint(&mut self) -> error::BackendResult<Option<Fingerprint>> {
+        Ok(None)
+    }
+
+    /// Get the expected size of the stored data in bytes, if it is known.
+    ///
+    /// The database uses it to preallocate the buffer it serializes into, and
+    /// backends can use it to preallocate the buffer they read into in
+    /// [`Backend::get_data`]. It is only a hint, the data may be smaller or
+    /// larger. The default returns `None`.
+    fn size_hint(&self) -> Option<usize> {
+        None
+    }
+
+    /// Get a reader for the stored data that works without the backend.
+    ///
+    /// The database holds the lock of the backend while using it, so a slow
+    /// load blocks saves and other loads. With a detached reader, loads only
+    /// lock the backend to get the reader, and read after releasing it.
+    /// Only backends whose data is replaced atomically should return one,
+    /// otherwise a load could read the data of a save halfway. The default
+    /// returns `None`.
+    fn detached_reader(&mut self) -> error::BackendResult<Option<Box<dyn DetachedReader>>> {
+        Ok(None)
+    }
+
+    /// Write the data somewhere it does not replace the stored data yet,
+    /// and return a [`StagedWrite`] that replaces it.
+    ///
+    /// A [`DatabaseSet`](crate::set::DatabaseSet) stages the data of all its
+    /// databases before committing any of them, so that a failing write does
+    /// not leave some databases saved and others not. Committing should be
+    /// quick and unlikely to fail, like renaming a file. The default returns
+    /// `None`, in which case the data is passed to [`Backend::put_data`] when
+    /// the others are committed.
+    fn stage(&mut self, data: &[u8]) -> error::BackendResult<Option<Box<dyn StagedWrite>>> {
+        let _ = data;
+        Ok(None)
+    }
+
+    /// Describe the stored data, for status displays and debugging.
+    ///
+    /// The default returns `None`, meaning the backend can not tell. File
+    /// backends also return `None` if the file does not exist yet.
+    fn metadata(&mut self) -> error::BackendResult<Option<BackendMetadata>> {
+        Ok(None)
+    }
+
+    /// Keep other processes from changing the data until the returned
+    /// [`BackendLock`] is dropped, waiting for them to release it first.
+    ///
+    /// [`Database::modify`](crate::Database::modify) holds it while it
+    /// loads, changes and saves the data. The default returns `None`, for
+    /// backends that are only used by one process, or can not be locked.
+    fn lock_exclusive(&mut self) -> error::BackendResult<Option<BackendLock>> {
+        Ok(None)
+    }
+}
+
+/// A lock returned by [`Backend::lock_exclusive`], released when dropped.
+pub struct BackendLock {
+    _guard: Box<dyn std::any::Any>,
+}
+
+impl BackendLock {
+    /// A lock that is released by dropping `guard`, like an open lock file.
+    pub fn new<G: 'static>(guard: G) -> Self {
+        Self {
+            _guard: Box::new(guard),
+        }
+    }
+}
+
+impl std::fmt::Debug for BackendLock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BackendLock").finish_non_exhaustive()
+    }
+}
+
+/// What a backend can tell about the data it stores, see
+/// [`Backend::metadata`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BackendMetadata {
+    /// The size of the stored data in bytes.
+    pub len: u64,
+    /// When the stored data was last modified, if the backend knows.
+    pub modified: Option<std::time::SystemTime>,
+}
+
+impl BackendMetadata {
+    /// Metadata of data of `len` bytes, modified at `modified`.
+    #[must_use]
+    pub fn new(len: u64, modified: Option<std::time::SystemTime>) -> Self {
+        Self { len, modified }
+    }
+
+    /// The length and modification time of a file.
+    #[must_use]
+    pub fn from_metadata(metadata: &std::fs::Metadata) -> Self {
+        Self::new(metadata.len(), metadata.modified().ok())
+    }
+}
+
+/// Identifies a state of the data stored in a backend, see
+/// [`Backend::fingerprint`].
+///
+/// Two fingerprints are equal if the data did not change in between, as far
+/// as the fingerprint can tell: one taken from file metadata misses changes
+/// that keep the length and happen within the resolution of the modification
+/// time of the file system.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fingerprint {
+    modified: Option<std::time::SystemTime>,
+    len: u64,
+    hash: Option<u64>,
+}
+
+impl Fingerprint {
+    /// A fingerprint of the modification time and length of a file.
+    #[must_use]
+    pub fn from_metadata(metadata: &std::fs::Metadata) -> Self {
+        Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            hash: None,
+        }
+    }
+
+    /// A fingerprint of a hash of `data`.
+    ///
+    /// The hash is not stable between runs of the program, so it should not
+    /// be persisted.
+    #[must_use]
+    pub fn from_contents(data: &[u8]) -> Self {
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        data.hash(&mut hasher);
+        Self {
+            modified: None,
+            len: data.len() as u64,
+            hash: Some(hasher.finish()),
+        }
+    }
+}
+
+/// A writer returned by [`Backend::writer`].
+///
+/// The data is only guaranteed to be stored once [`BackendWriter::finish`]
+/// returned successfully. Dropping the writer without finishing it may leave
+/// the backend with the old data, or with partially written data, depending
+/// on the backend.
+pub trait BackendWriter: Write {
+    /// Finish writing and store the data.
+    fn finish(self: Box<Self>) -> error::BackendResult<()>;
+}
+
+/// A reader returned by [`Backend::detached_reader`].
+pub trait DetachedReader: Send {
+    /// Read the data, and the [`Fingerprint`] of exactly that data, like
+    /// [`Backend::get_data`] and [`Backend::fingerprint`] would.
+    fn read(self: Box<Self>) -> error::BackendResult<(Vec<u8>, Option<Fingerprint>)>;
+}
+
+/// Data written by [`Backend::stage`], waiting to replace the stored data.
+///
+/// Dropping it without committing leaves the stored data as it was.
+pub trait StagedWrite {
+    /// Replace the stored data with the staged one.
+    fn commit(self: Box<Self>) -> error::BackendResult<()>;
 }
 
 impl Backend for Box<dyn Backend> {
@@ -37,6 +243,46 @@ impl Backend for Box<dyn Backend> {
         use std::ops::DerefMut;
         self.deref_mut().put_data(data)
     }
+
+    fn writer(&mut self) -> error::BackendResult<Option<Box<dyn BackendWriter + '_>>> {
+        use std::ops::DerefMut;
+        self.deref_mut().writer()
+    }
+
+    fn quarantine(&mut self) -> error::BackendResult<()> {
+        use std::ops::DerefMut;
+        self.deref_mut().quarantine()
+    }
+
+    fn fingerprint(&mut self) -> error::BackendResult<Option<Fingerprint>> {
+        use std::ops::DerefMut;
+        self.deref_mut().fingerprint()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        use std::ops::Deref;
+        self.deref().size_hint()
+    }
+
+    fn detached_reader(&mut self) -> error::BackendResult<Option<Box<dyn DetachedReader>>> {
+        use std::ops::DerefMut;
+        self.deref_mut().detached_reader()
+    }
+
+    fn stage(&mut self, data: &[u8]) -> error::BackendResult<Option<Box<dyn StagedWrite>>> {
+        use std::ops::DerefMut;
+        self.deref_mut().stage(data)
+    }
+
+    fn metadata(&mut self) -> error::BackendResult<Option<BackendMetadata>> {
+        use std::ops::DerefMut;
+        self.deref_mut().metadata()
+    }
+
+    fn lock_exclusive(&mut self) -> error::BackendResult<Option<BackendLock>> {
+        use std::ops::DerefMut;
+        self.deref_mut().lock_exclusive()
+    }
 }
 
 impl<T: Backend> Backend for Box<T> {
@@ -49,52 +295,291 @@ impl<T: Backend> Backend for Box<T> {
         use std::ops::DerefMut;
         self.deref_mut().put_data(data)
     }
+
+    fn writer(&mut self) -> error::BackendResult<Option<Box<dyn BackendWriter + '_>>> {
+        use std::ops::DerefMut;
+        self.deref_mut().writer()
+    }
+
+    fn quarantine(&mut self) -> error::BackendResult<()> {
+        use std::ops::DerefMut;
+        self.deref_mut().quarantine()
+    }
+
+    fn fingerprint(&mut self) -> error::BackendResult<Option<Fingerprint>> {
+        use std::ops::DerefMut;
+        self.deref_mut().fingerprint()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        use std::ops::Deref;
+        self.deref().size_hint()
+    }
+
+    fn detached_reader(&mut self) -> error::BackendResult<Option<Box<dyn DetachedReader>>> {
+        use std::ops::DerefMut;
+        self.deref_mut().detached_reader()
+    }
+
+    fn stage(&mut self, data: &[u8]) -> error::BackendResult<Option<Box<dyn StagedWrite>>> {
+        use std::ops::DerefMut;
+        self.deref_mut().stage(data)
+    }
+
+    fn metadata(&mut self) -> error::BackendResult<Option<BackendMetadata>> {
+        use std::ops::DerefMut;
+        self.deref_mut().metadata()
+    }
+
+    fn lock_exclusive(&mut self) -> error::BackendResult<Option<BackendLock>> {
+        use std::ops::DerefMut;
+        self.deref_mut().lock_exclusive()
+    }
 }
 
 #[cfg(feature = "mmap")]
 mod mmap;
 #[cfg(feature = "mmap")]
-pub use mmap::MmapStorage;
+pub use mmap::{GrowthStrategy, MmapStorage};
 
 mod path;
 pub use path::PathBackend;
 
+mod writer_lock;
+
+mod envelope;
+pub use envelope::Enveloped;
+
+mod stream;
+pub use stream::StreamBackend;
+
+mod tee;
+pub use tee::TeeBackend;
+
+mod fallback;
+pub use fallback::FallbackBackend;
+
+mod cached;
+pub use cached::CachedBackend;
+
+mod delta;
+pub use delta::DeltaBackend;
+
+mod journal;
+pub use journal::JournalBackend;
+pub(crate) use journal::RecordLog;
+
+mod audit;
+pub use audit::AuditedBackend;
+
+#[cfg(feature = "http")]
+mod http;
+#[cfg(feature = "http")]
+pub use http::HttpBackend;
+
+#[cfg(feature = "object_store")]
+mod object;
+#[cfg(feature = "object_store")]
+pub use object::ObjectStoreBackend;
+
+#[cfg(feature = "redis")]
+mod redis;
+#[cfg(feature = "redis")]
+pub use self::redis::RedisBackend;
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteBackend;
+
+#[cfg(feature = "wasm")]
+mod local_storage;
+#[cfg(feature = "wasm")]
+pub use local_storage::LocalStorageBackend;
+
+#[cfg(feature = "embedded")]
+mod flash;
+#[cfg(feature = "embedded")]
+pub use flash::FlashBackend;
+
+#[cfg(feature = "zip")]
+mod zip_entry;
+#[cfg(feature = "zip")]
+pub use zip_entry::ZipEntryBackend;
+
+#[cfg(feature = "tracing")]
+mod instrumented;
+#[cfg(feature = "tracing")]
+pub use instrumented::InstrumentedBackend;
+
+/// Create the directory `path` is in, and all of its parents, if they do
+/// not exist yet.
+pub(crate) fn create_parent_dirs(path: &std::path::Path) -> error::BackendResult<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => Ok(std::fs::create_dir_all(dir)?),
+        _ => Ok(()),
+    }
+}
+
+/// Take an exclusive advisory lock on `file`, without blocking.
+///
+/// The lock is released once the file is closed.
+fn try_lock(file: &std::fs::File) -> error::BackendResult<()> {
+    use fs2::FileExt;
+
+    file.try_lock_exclusive().map_err(|e| {
+        if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() {
+            error::BackendError::Locked
+        } else {
+            e.into()
+        }
+    })
+}
+
+/// When a backend flushes saved data to disk with
+/// [`File::sync_all`](std::fs::File::sync_all).
+///
+/// Syncing makes sure the data survives a crash of the operating system or a
+/// power loss, but it dominates the time a save takes. Without it the data is
+/// still handed to the operating system on every save.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyncPolicy {
+    /// Sync on every save, this is the default.
+    #[default]
+    Always,
+    /// Never sync, leave it to the operating system.
+    Never,
+    /// Sync on every `n`th save.
+    Every(u32),
+    /// Sync on a save if the last sync was at least this long ago.
+    Interval(std::time::Duration),
+}
+
+/// Applies a [`SyncPolicy`].
+#[derive(Debug, Default)]
+struct Syncer {
+    policy: SyncPolicy,
+    /// Saves since the last sync.
+    unsynced: u32,
+    last_sync: Option<std::time::Instant>,
+}
+
+impl Syncer {
+    /// Whether the save that is about to happen should sync.
+    fn should_sync(&mut self) -> bool {
+        let sync = match self.policy {
+            SyncPolicy::Always => true,
+            SyncPolicy::Never => false,
+            SyncPolicy::Every(n) => self.unsynced + 1 >= n,
+            SyncPolicy::Interval(interval) => {
+                self.last_sync.is_none_or(|last| last.elapsed() >= interval)
+            }
+        };
+        if sync {
+            self.unsynced = 0;
+            self.last_sync = Some(std::time::Instant::now());
+        } else {
+            self.unsynced = self.unsynced.saturating_add(1);
+        }
+        sync
+    }
+}
+
 /// A backend using a file.
 #[derive(Debug)]
-pub struct FileBackend(std::fs::File);
+pub struct FileBackend {
+    file: std::fs::File,
+    sync: Syncer,
+    /// Whether the file is locked for the lifetime of the backend, by
+    /// [`FileBackend::from_path_locked`].
+    locked: bool,
+}
 
 impl Backend for FileBackend {
     fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
         use std::io::{Read, Seek, SeekFrom};
 
-        let mut buffer = vec![];
-        self.0.seek(SeekFrom::Start(0))?;
-        self.0.read_to_end(&mut buffer)?;
+        let mut buffer = Vec::with_capacity(self.size_hint().unwrap_or(0));
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.read_to_end(&mut buffer)?;
         Ok(buffer)
     }
 
     fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
         use std::io::{Seek, SeekFrom, Write};
 
-        self.0.seek(SeekFrom::Start(0))?;
-        self.0.set_len(0)?;
-        self.0.write_all(data)?;
-        self.0.sync_all()?;
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.set_len(0)?;
+        self.file.write_all(data)?;
+        if self.sync.should_sync() {
+            self.file.sync_all()?;
+        }
         Ok(())
     }
+
+    fn fingerprint(&mut self) -> error::BackendResult<Option<Fingerprint>> {
+        Ok(Some(Fingerprint::from_metadata(&self.file.metadata()?)))
+    }
+
+    /// The length of the file.
+    fn size_hint(&self) -> Option<usize> {
+        let len = self.file.metadata().ok()?.len();
+        usize::try_from(len).ok()
+    }
+
+    fn metadata(&mut self) -> error::BackendResult<Option<BackendMetadata>> {
+        Ok(Some(BackendMetadata::from_metadata(&self.file.metadata()?)))
+    }
+
+    /// Lock the file itself, waiting for other processes. Backends opened
+    /// with [`FileBackend::from_path_locked`] hold the lock already, and
+    /// return `None`.
+    fn lock_exclusive(&mut self) -> error::BackendResult<Option<BackendLock>> {
+        use fs2::FileExt;
+
+        if self.locked {
+            return Ok(None);
+        }
+        let file = self.file.try_clone()?;
+        file.lock_exclusive()?;
+        Ok(Some(BackendLock::new(Unlock(file))))
+    }
+}
+
+/// Unlocks the file of a [`FileBackend`] when dropped.
+///
+/// Closing a clone of the file does not release the lock, since the
+/// original stays open.
+struct Unlock(std::fs::File);
+
+impl Drop for Unlock {
+    fn drop(&mut self) {
+        let _ = fs2::FileExt::unlock(&self.0);
+    }
 }
 
 impl FileBackend {
     /// Use an already open [`File`](std::fs::File) as the backend.
     #[must_use]
     pub fn from_file(file: std::fs::File) -> Self {
-        Self(file)
+        Self {
+            file,
+            sync: Syncer::default(),
+            locked: false,
+        }
+    }
+
+    /// Choose when saves are synced to disk, see [`SyncPolicy`].
+    #[must_use]
+    pub fn with_sync_policy(mut self, policy: SyncPolicy) -> Self {
+        self.sync.policy = policy;
+        self
     }
 
     /// Return the inner File.
     #[must_use]
     pub fn into_inner(self) -> std::fs::File {
-        self.0
+        self.file
     }
 }
 
@@ -104,7 +589,9 @@ impl FileBackend {
     pub fn from_path_or_fail<P: AsRef<std::path::Path>>(path: P) -> error::BackendResult<Self> {
         use std::fs::OpenOptions;
 
-        Ok(Self(OpenOptions::new().read(true).write(true).open(path)?))
+        Ok(Self::from_file(
+            OpenOptions::new().read(true).write(true).open(path)?,
+        ))
     }
 
     /// Opens a new [`FileBackend`] for a given path.
@@ -118,17 +605,27 @@ impl FileBackend {
 
         let exists = path.as_ref().is_file();
         Ok((
-            Self(
+            Self::from_file(
                 OpenOptions::new()
                     .read(true)
                     .write(true)
                     .create(true)
+                    .truncate(false)
                     .open(path)?,
             ),
             exists,
         ))
     }
 
+    /// Like [`FileBackend::from_path_or_create`], but also creates the
+    /// directories leading to the file if they don't yet exist.
+    pub fn from_path_or_create_recursive<P: AsRef<std::path::Path>>(
+        path: P,
+    ) -> error::BackendResult<(Self, bool)> {
+        create_parent_dirs(path.as_ref())?;
+        Self::from_path_or_create(path)
+    }
+
     /// Opens a new [`FileBackend`] for a given path.
     /// Creates a file if it doesn't yet exist, and calls `closure` with it.
     pub fn from_path_or_create_and<P, C>(path: P, closure: C) -> error::BackendResult<Self>
@@ -138,11 +635,27 @@ impl FileBackend {
     {
         Self::from_path_or_create(path).map(|(mut b, exists)| {
             if !exists {
-                closure(&mut b.0)
+                closure(&mut b.file);
             }
             b
         })
     }
+
+    /// Like [`FileBackend::from_path_or_create`], but also takes an exclusive
+    /// advisory lock on the file.
+    ///
+    /// Fails with [`BackendError::Locked`](error::BackendError::Locked) if
+    /// another process already holds the lock. The lock is released when the
+    /// backend is dropped. Being advisory, it only protects against other
+    /// processes that lock the file too.
+    pub fn from_path_locked<P: AsRef<std::path::Path>>(
+        path: P,
+    ) -> error::BackendResult<(Self, bool)> {
+        let (mut backend, exists) = Self::from_path_or_create(path)?;
+        try_lock(&backend.file)?;
+        backend.locked = true;
+        Ok((backend, exists))
+    }
 }
 
 /// An in memory backend.
@@ -159,22 +672,120 @@ impl MemoryBackend {
     }
 }
 
+impl From<Vec<u8>> for MemoryBackend {
+    /// Construct a Memory Database holding `data`.
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
 impl Backend for MemoryBackend {
     fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
-        println!("Returning data: {:?}", &self.0);
         Ok(self.0.clone())
     }
 
     fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
-        println!("Writing data: {:?}", data);
-        self.0 = data.to_owned();
+        data.clone_into(&mut self.0);
+        Ok(())
+    }
+
+    fn fingerprint(&mut self) -> error::BackendResult<Option<Fingerprint>> {
+        Ok(Some(Fingerprint::from_contents(&self.0)))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+
+    /// The length of the data, memory has no modification time.
+    fn metadata(&mut self) -> error::BackendResult<Option<BackendMetadata>> {
+        Ok(Some(BackendMetadata::new(self.0.len() as u64, None)))
+    }
+}
+
+/// An in memory backend that can be shared.
+///
+/// Clones of it all refer to the same byte vector, so one can be handed to
+/// several databases, which may each load it with their own `Data` type or
+/// `DeSer`. Whatever one of them saves, the others see on their next load.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::backend::{Backend, SharedMemoryBackend};
+///
+/// # fn main() -> rustbreak::error::BackendResult<()> {
+/// let mut producer = SharedMemoryBackend::new();
+/// let mut consumer = producer.clone();
+/// producer.put_data(b"message")?;
+/// assert_eq!(consumer.get_data()?, b"message");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct SharedMemoryBackend(Arc<Mutex<Vec<u8>>>);
+
+impl SharedMemoryBackend {
+    /// Construct a new shared Memory Database.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A copy of the data currently stored.
+    #[must_use]
+    pub fn data(&self) -> Vec<u8> {
+        self.lock().clone()
+    }
+
+    /// Whether `self` and `other` share the same data.
+    #[must_use]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Lock the data. A panic while it was locked can not have left it half
+    /// written, so a poisoned lock is simply taken over.
+    fn lock(&self) -> MutexGuard<'_, Vec<u8>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl From<Vec<u8>> for SharedMemoryBackend {
+    /// Construct a shared Memory Database holding `data`.
+    fn from(data: Vec<u8>) -> Self {
+        Self(Arc::new(Mutex::new(data)))
+    }
+}
+
+impl Backend for SharedMemoryBackend {
+    fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        Ok(self.data())
+    }
+
+    fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        data.clone_into(&mut *self.lock());
         Ok(())
     }
+
+    fn fingerprint(&mut self) -> error::BackendResult<Option<Fingerprint>> {
+        Ok(Some(Fingerprint::from_contents(&self.lock())))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.lock().len())
+    }
+
+    /// The length of the data, memory has no modification time.
+    fn metadata(&mut self) -> error::BackendResult<Option<BackendMetadata>> {
+        Ok(Some(BackendMetadata::new(self.lock().len() as u64, None)))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Backend, FileBackend, MemoryBackend};
+    use super::{Backend, FileBackend, MemoryBackend, SharedMemoryBackend};
     use std::io::{Read, Seek, SeekFrom, Write};
     use tempfile::NamedTempFile;
 
@@ -187,6 +798,45 @@ mod tests {
         assert_eq!(backend.get_data().expect("could not get data"), data);
     }
 
+    #[test]
+    #[cfg(feature = "ron_enc")]
+    fn test_shared_memory_backend() {
+        use crate::deser::Ron;
+        use crate::Database;
+        use std::collections::HashMap;
+
+        let backend = SharedMemoryBackend::new();
+        let producer = Database::from_parts(HashMap::<String, u32>::new(), backend.clone(), Ron);
+        let consumer = Database::from_parts(HashMap::<String, i64>::new(), backend.clone(), Ron);
+        producer
+            .write(|data| data.insert("answer".to_string(), 42))
+            .expect("could not write");
+        producer.save().expect("could not save");
+        consumer.load().expect("could not load");
+        assert_eq!(consumer.read(|data| data["answer"]).unwrap(), 42);
+        assert!(backend.ptr_eq(&consumer.into_inner().unwrap().1));
+        assert!(!backend.ptr_eq(&SharedMemoryBackend::from(backend.data())));
+    }
+
+    #[test]
+    fn test_sync_policy() {
+        use super::{SyncPolicy, Syncer};
+
+        let mut syncer = Syncer {
+            policy: SyncPolicy::Every(3),
+            ..Syncer::default()
+        };
+        let syncs: Vec<_> = (0..6).map(|_| syncer.should_sync()).collect();
+        assert_eq!(syncs, [false, false, true, false, false, true]);
+
+        syncer.policy = SyncPolicy::Interval(std::time::Duration::from_secs(100));
+        assert!(!syncer.should_sync());
+        syncer.policy = SyncPolicy::Never;
+        assert!(!syncer.should_sync());
+        syncer.policy = SyncPolicy::Always;
+        assert!(syncer.should_sync());
+    }
+
     #[test]
     #[cfg_attr(miri, ignore)]
     fn test_file_backend_from_file() {
@@ -202,6 +852,20 @@ mod tests {
         assert_eq!(backend.get_data().expect("could not get data"), data2);
     }
 
+    #[test]
+    fn test_size_hint() {
+        let data = [4, 5, 1, 6, 8, 1];
+        let mut backend = MemoryBackend::new();
+        backend.put_data(&data).expect("could not put data");
+        assert_eq!(backend.size_hint(), Some(6));
+
+        let file = tempfile::tempfile().expect("could not create temporary file");
+        let mut backend = FileBackend::from_file(file);
+        assert_eq!(backend.size_hint(), Some(0));
+        backend.put_data(&data).expect("could not put data");
+        assert_eq!(backend.size_hint(), Some(6));
+    }
+
     #[test]
     #[cfg_attr(miri, ignore)]
     fn test_file_backend_from_path_existing() {
@@ -255,7 +919,7 @@ mod tests {
             assert_eq!(std::io::ErrorKind::NotFound, io_err.kind());
         } else {
             panic!("Wrong kind of error returned: {}", err);
-        };
+        }
         dir.close().expect("Error while deleting temp directory!");
     }
 
@@ -309,7 +973,7 @@ mod tests {
         file_path.push("rustbreak_path_db.db");
         let mut backend = FileBackend::from_path_or_create_and(file_path, |f| {
             f.write_all(b"this is a new file")
-                .expect("could not write to file")
+                .expect("could not write to file");
         })
         .expect("could not create backend");
         assert_eq!(
@@ -322,4 +986,27 @@ mod tests {
         assert_eq!(backend.get_data().expect("could not get data"), data);
         dir.close().expect("Error while deleting temp directory!");
     }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_file_backend_has_no_writer() {
+        // Truncating the file before the data is serialized in full would
+        // lose it if serializing fails.
+        let file = tempfile::tempfile().expect("could not create temporary file");
+        let mut backend = FileBackend::from_file(file);
+        assert!(backend.writer().expect("could not get writer").is_none());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_file_backend_locked() {
+        let file = NamedTempFile::new().expect("could not create temporary file");
+        let (backend, _) =
+            FileBackend::from_path_locked(file.path()).expect("could not create backend");
+        let err = FileBackend::from_path_locked(file.path()).expect_err("file should be locked");
+        assert!(matches!(err, crate::error::BackendError::Locked));
+
+        drop(backend);
+        FileBackend::from_path_locked(file.path()).expect("lock should have been released");
+    }
 }
@@ -0,0 +1,267 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use object_store::path::Path;
+use object_store::{ObjectStore, PutMode, PutOptions, PutPayload, UpdateVersion};
+
+use super::{Backend, Fingerprint};
+use crate::async_db::AsyncBackend;
+use crate::error::{self, BackendError};
+
+/// What is known about the stored object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Remote {
+    /// Nothing was read or written yet.
+    Unknown,
+    /// There was no object.
+    Missing,
+    /// The version of the object as it was last read or written.
+    Version(UpdateVersion),
+}
+
+/// A backend that stores the data as a single object in an [`ObjectStore`],
+/// like Amazon S3, Google Cloud Storage or Azure Blob Storage.
+///
+/// This lets programs without a persistent volume keep their state, for
+/// example in containers. An object that does not exist reads as empty
+/// data.
+///
+/// It is both an [`AsyncBackend`], for the
+/// [`AsyncDatabase`](crate::async_db::AsyncDatabase), and a [`Backend`]. As a
+/// `Backend` it runs the requests on a runtime of its own, so it must not be
+/// used from within a `tokio` runtime.
+///
+/// With [`ObjectStoreBackend::with_conditional_put`], saves fail with
+/// [`BackendError::Conflict`] if someone else saved in between, if the store
+/// supports conditional puts.
+///
+/// **Important**: This backend is only available with the `object_store`
+/// feature. Enable the features of `object_store` for the stores you use.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use object_store::memory::InMemory;
+/// use rustbreak::backend::{Backend, ObjectStoreBackend};
+/// use std::sync::Arc;
+///
+/// # fn main() -> rustbreak::error::BackendResult<()> {
+/// let store = Arc::new(InMemory::new());
+/// let mut backend = ObjectStoreBackend::new(store, "state/app.ron").with_conditional_put(true);
+/// assert_eq!(backend.get_data()?, b"");
+/// backend.put_data(b"(count: 1)")?;
+/// assert_eq!(backend.get_data()?, b"(count: 1)");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ObjectStoreBackend {
+    store: Arc<dyn ObjectStore>,
+    path: Path,
+    conditional: bool,
+    remote: Remote,
+    /// The runtime of the blocking [`Backend`] methods, created on first use.
+    runtime: Option<tokio::runtime::Runtime>,
+}
+
+impl ObjectStoreBackend {
+    /// Store the data at `path` in `store`.
+    pub fn new<P: Into<Path>>(store: Arc<dyn ObjectStore>, path: P) -> Self {
+        Self {
+            store,
+            path: path.into(),
+            conditional: false,
+            remote: Remote::Unknown,
+            runtime: None,
+        }
+    }
+
+    /// Only save if the object was not changed since it was last read or
+    /// written. Disabled by default, since not every store supports it.
+    #[must_use]
+    pub fn with_conditional_put(mut self, conditional: bool) -> Self {
+        self.conditional = conditional;
+        self
+    }
+
+    /// The path of the object.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The store the object is in.
+    #[must_use]
+    pub fn store(&self) -> &Arc<dyn ObjectStore> {
+        &self.store
+    }
+
+    async fn get(&mut self) -> error::BackendResult<Vec<u8>> {
+        let result = match self.store.get(&self.path).await {
+            Ok(result) => result,
+            Err(object_store::Error::NotFound { .. }) => {
+                self.remote = Remote::Missing;
+                return Ok(Vec::new());
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let version = UpdateVersion {
+            e_tag: result.meta.e_tag.clone(),
+            version: result.meta.version.clone(),
+        };
+        let data = result.bytes().await?.to_vec();
+        self.remote = Remote::Version(version);
+        Ok(data)
+    }
+
+    async fn put(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        let mode = match &self.remote {
+            Remote::Missing if self.conditional => PutMode::Create,
+            Remote::Version(version) if self.conditional => PutMode::Update(version.clone()),
+            _ => PutMode::Overwrite,
+        };
+        let payload = PutPayload::from(data.to_vec());
+        match self
+            .store
+            .put_opts(&self.path, payload, PutOptions::from(mode))
+            .await
+        {
+            Ok(result) => {
+                self.remote = Remote::Version(result.into());
+                Ok(())
+            }
+            Err(
+                object_store::Error::Precondition { .. }
+                | object_store::Error::AlreadyExists { .. },
+            ) => Err(BackendError::Conflict),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn head(&self) -> error::BackendResult<Option<Fingerprint>> {
+        match self.store.head(&self.path).await {
+            Ok(meta) => Ok(meta
+                .e_tag
+                .or(meta.version)
+                .map(|tag| Fingerprint::from_contents(tag.as_bytes()))),
+            Err(object_store::Error::NotFound { .. }) => Ok(Some(Fingerprint::from_contents(&[]))),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// The runtime of the blocking methods, give it back with
+    /// [`Self::restore_runtime`].
+    fn take_runtime(&mut self) -> error::BackendResult<tokio::runtime::Runtime> {
+        match self.runtime.take() {
+            Some(runtime) => Ok(runtime),
+            None => Ok(tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?),
+        }
+    }
+
+    fn restore_runtime(&mut self, runtime: tokio::runtime::Runtime) {
+        self.runtime = Some(runtime);
+    }
+}
+
+impl Backend for ObjectStoreBackend {
+    fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        let runtime = self.take_runtime()?;
+        let result = runtime.block_on(self.get());
+        self.restore_runtime(runtime);
+        result
+    }
+
+    fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        let runtime = self.take_runtime()?;
+        let result = runtime.block_on(self.put(data));
+        self.restore_runtime(runtime);
+        result
+    }
+
+    /// The `ETag` or version of the object, checked with a `HEAD` request.
+    fn fingerprint(&mut self) -> error::BackendResult<Option<Fingerprint>> {
+        let runtime = self.take_runtime()?;
+        let result = runtime.block_on(self.head());
+        self.restore_runtime(runtime);
+        result
+    }
+}
+
+#[async_trait]
+impl AsyncBackend for ObjectStoreBackend {
+    async fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        self.get().await
+    }
+
+    async fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        self.put(data).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ObjectStoreBackend;
+    use crate::async_db::AsyncBackend;
+    use crate::backend::Backend;
+    use crate::error::BackendError;
+    use object_store::memory::InMemory;
+    use std::sync::Arc;
+
+    #[test]
+    fn object_store_conditional_puts() {
+        let store = Arc::new(InMemory::new());
+        let mut backend = ObjectStoreBackend::new(store.clone(), "db").with_conditional_put(true);
+        let mut other = ObjectStoreBackend::new(store, "db").with_conditional_put(true);
+        assert_eq!(Backend::get_data(&mut backend).expect("could not get"), b"");
+        assert_eq!(Backend::get_data(&mut other).expect("could not get"), b"");
+
+        Backend::put_data(&mut backend, b"one").expect("could not put");
+        let fingerprint = backend.fingerprint().expect("could not get fingerprint");
+        assert!(matches!(
+            Backend::put_data(&mut other, b"two"),
+            Err(BackendError::Conflict)
+        ));
+        assert_eq!(
+            Backend::get_data(&mut other).expect("could not get"),
+            b"one"
+        );
+        Backend::put_data(&mut other, b"two").expect("could not put");
+        assert_ne!(
+            backend.fingerprint().expect("could not get fingerprint"),
+            fingerprint
+        );
+        assert!(matches!(
+            Backend::put_data(&mut backend, b"three"),
+            Err(BackendError::Conflict)
+        ));
+
+        let mut backend = backend.with_conditional_put(false);
+        Backend::put_data(&mut backend, b"three").expect("could not put");
+
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(async {
+                assert_eq!(
+                    AsyncBackend::get_data(&mut other)
+                        .await
+                        .expect("could not get"),
+                    b"three"
+                );
+                AsyncBackend::put_data(&mut other, b"four")
+                    .await
+                    .expect("could not put");
+            });
+        assert_eq!(
+            Backend::get_data(&mut backend).expect("could not get"),
+            b"four"
+        );
+    }
+}
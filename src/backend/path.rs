@@ -5,11 +5,16 @@
 //! Module which implements the [`PathBackend`], storing data in a file on the
 //! file system (with a path) and featuring atomic saves.
 
-use super::Backend;
+use super::writer_lock::{self, WriterLock};
+use super::{
+    Backend, BackendLock, BackendMetadata, BackendWriter, DetachedReader, Fingerprint, StagedWrite,
+    SyncPolicy, Syncer,
+};
 use crate::error;
+use std::convert::TryFrom;
 use std::fs::OpenOptions;
 use std::path::{Path, PathBuf};
-use tempfile::NamedTempFile;
+use tempfile::{NamedTempFile, TempPath};
 
 /// A [`Backend`] using a file given the path.
 ///
@@ -18,14 +23,171 @@ use tempfile::NamedTempFile;
 #[derive(Debug)]
 pub struct PathBackend {
     path: PathBuf,
+    /// The lock on the sidecar file, if the backend was opened with
+    /// [`PathBackend::from_path_locked`], or with
+    /// [`PathBackend::from_path_single_writer`] and is the writer.
+    lock: Option<WriterLock>,
+    /// Whether another process is the writer.
+    read_only: bool,
+    /// How many backups [`PathBackend::with_backups`] keeps.
+    backups: usize,
+    sync: Syncer,
+    /// The mode set by [`PathBackend::with_mode`].
+    mode: Option<u32>,
+    /// Where temporary files are created, next to the file if `None`.
+    temp_dir: Option<PathBuf>,
+    temp_name: TempName,
+}
+
+/// How the temporary files of a [`PathBackend`] are named.
+#[derive(Debug, Clone)]
+struct TempName {
+    prefix: String,
+    suffix: String,
+}
+
+impl TempName {
+    /// Create a temporary file with this name in `dir`.
+    fn create_in(&self, dir: &Path) -> std::io::Result<NamedTempFile> {
+        tempfile::Builder::new()
+            .prefix(&self.prefix)
+            .suffix(&self.suffix)
+            .tempfile_in(dir)
+    }
 }
 
 impl PathBackend {
+    /// A backend for `path`, without touching the file system.
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            lock: None,
+            read_only: false,
+            backups: 0,
+            sync: Syncer::default(),
+            mode: None,
+            temp_dir: None,
+            temp_name: TempName {
+                prefix: String::from(".tmp"),
+                suffix: String::new(),
+            },
+        }
+    }
+
+    /// Create the temporary files that saves are written to in `dir`,
+    /// instead of next to the database file.
+    ///
+    /// Ideally `dir` is on the same file system as the database file, so that
+    /// the temporary file can be renamed into place. Otherwise it is copied
+    /// next to the database file first, and then renamed.
+    #[must_use]
+    pub fn with_temp_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.temp_dir = Some(dir.into());
+        self
+    }
+
+    /// Name the temporary files `<prefix><random><suffix>`.
+    ///
+    /// The default prefix is `.tmp`, the default suffix is empty.
+    #[must_use]
+    pub fn with_temp_name<P: Into<String>, S: Into<String>>(
+        mut self,
+        prefix: P,
+        suffix: S,
+    ) -> Self {
+        self.temp_name = TempName {
+            prefix: prefix.into(),
+            suffix: suffix.into(),
+        };
+        self
+    }
+
+    /// Write `data` to a temporary file, ready to replace the database file.
+    fn stage_file(&mut self, data: &[u8]) -> error::BackendResult<PathStaged> {
+        use std::io::Write;
+
+        let mut file = self.temp_file()?;
+        file.write_all(data)?;
+        copy_metadata(file.as_file(), &self.path, self.mode)?;
+        let sync = self.sync.should_sync();
+        if sync {
+            file.as_file().sync_all()?;
+        }
+        Ok(PathStaged {
+            file,
+            path: self.path.clone(),
+            temp_name: self.temp_name.clone(),
+            mode: self.mode,
+            backups: self.backups,
+            sync,
+        })
+    }
+
+    /// Create a temporary file to write a save to.
+    fn temp_file(&self) -> std::io::Result<NamedTempFile> {
+        let dir = self
+            .temp_dir
+            .as_deref()
+            .unwrap_or_else(|| parent_dir(&self.path));
+        self.temp_name.create_in(dir)
+    }
+
+    /// Save the file with the given Unix permission bits, for example `0o600`.
+    ///
+    /// The current file gets them right away. Without this, saves keep the
+    /// permissions of the existing file.
+    #[cfg(unix)]
+    pub fn with_mode(mut self, mode: u32) -> error::BackendResult<Self> {
+        use std::os::unix::fs::PermissionsExt;
+
+        if self.path.is_file() {
+            std::fs::set_permissions(&self.path, std::fs::Permissions::from_mode(mode))?;
+        }
+        self.mode = Some(mode);
+        Ok(self)
+    }
+
+    /// Choose when saves are synced to disk, see [`SyncPolicy`].
+    ///
+    /// Without a sync, the rename that replaces the file might reach the
+    /// disk before its contents do, so that a crash of the operating system
+    /// can leave an empty or partial file behind.
+    #[must_use]
+    pub fn with_sync_policy(mut self, policy: SyncPolicy) -> Self {
+        self.sync.policy = policy;
+        self
+    }
+
+    /// Keep the last `count` versions of the database file as backups.
+    ///
+    /// Once a save replaced the file, the previous one is kept as
+    /// `<name>.bak.1`, the previous `<name>.bak.1` becomes `<name>.bak.2`,
+    /// and so on, up to `<name>.bak.<count>`. Older backups are deleted.
+    /// A failed save leaves the backups as they are. A `count` of `0`, the
+    /// default, keeps no backups.
+    #[must_use]
+    pub fn with_backups(mut self, count: usize) -> Self {
+        self.backups = count;
+        self
+    }
+
+    /// The path of the database file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The path of the `n`th backup, `1` being the most recent one.
+    #[must_use]
+    pub fn backup_path(&self, n: usize) -> PathBuf {
+        backup_path(&self.path, n)
+    }
+
     /// Opens a new [`PathBackend`] for a given path.
     /// Errors when the file doesn't yet exist.
     pub fn from_path_or_fail(path: PathBuf) -> error::BackendResult<Self> {
         OpenOptions::new().read(true).open(path.as_path())?;
-        Ok(Self { path })
+        Ok(Self::new(path))
     }
 
     /// Opens a new [`PathBackend`] for a given path.
@@ -37,8 +199,16 @@ impl PathBackend {
         OpenOptions::new()
             .write(true)
             .create(true)
+            .truncate(false)
             .open(path.as_path())?;
-        Ok((Self { path }, exists))
+        Ok((Self::new(path), exists))
+    }
+
+    /// Like [`PathBackend::from_path_or_create`], but also creates the
+    /// directories leading to the file if they don't yet exist.
+    pub fn from_path_or_create_recursive(path: PathBuf) -> error::BackendResult<(Self, bool)> {
+        super::create_parent_dirs(&path)?;
+        Self::from_path_or_create(path)
     }
 
     /// Opens a new [`PathBackend`] for a given path.
@@ -52,22 +222,142 @@ impl PathBackend {
             .read(true)
             .write(true)
             .create(true)
+            .truncate(false)
             .open(path.as_path())?;
         if !exists {
-            closure(&mut file)
+            closure(&mut file);
+        }
+        Ok(Self::new(path))
+    }
+
+    /// Like [`PathBackend::from_path_or_create`], but also takes an exclusive
+    /// advisory lock.
+    ///
+    /// Since saving replaces the database file, the lock is taken on a
+    /// sidecar file next to it, which has `.lck` appended to its name, and
+    /// the PID of the process is written into it. The sidecar file is left
+    /// behind when the backend is dropped, but the lock is released and the
+    /// PID removed.
+    ///
+    /// Fails with [`BackendError::Locked`](error::BackendError::Locked) if
+    /// another process already holds the lock. Being advisory, it only
+    /// protects against other processes that lock the file too.
+    pub fn from_path_locked(path: PathBuf) -> error::BackendResult<(Self, bool)> {
+        let lock = writer_lock::acquire(&path)?.ok_or(error::BackendError::Locked)?;
+
+        let (mut backend, exists) = Self::from_path_or_create(path)?;
+        backend.lock = Some(lock);
+        Ok((backend, exists))
+    }
+
+    /// Like [`PathBackend::from_path_or_create`], but only one process at a
+    /// time can write the database, the others get a read-only backend.
+    ///
+    /// The writer holds the same lock as [`PathBackend::from_path_locked`],
+    /// on the `.lck` sidecar file, which contains its PID until the backend
+    /// is dropped. The operating system releases the lock of a writer that
+    /// crashed, so the next process to open the database becomes the writer.
+    ///
+    /// A read-only backend fails to save with
+    /// [`BackendError::ReadOnly`](error::BackendError::ReadOnly), but loads
+    /// what the writer saved. It does not create the database file, so the
+    /// returned `bool` is `false` if the writer did not save yet. It stays
+    /// read-only even after the writer exits.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// # extern crate tempfile;
+    /// use rustbreak::backend::{Backend, PathBackend};
+    /// use rustbreak::error::BackendError;
+    ///
+    /// # fn main() -> rustbreak::error::BackendResult<()> {
+    /// # let dir = tempfile::tempdir().unwrap();
+    /// # let path = dir.path().join("app.ron");
+    /// let (mut writer, _) = PathBackend::from_path_single_writer(path.clone())?;
+    /// let (mut second, _) = PathBackend::from_path_single_writer(path.clone())?;
+    /// assert!(!writer.is_read_only());
+    /// assert!(second.is_read_only());
+    /// assert_eq!(second.writer_pid(), Some(std::process::id()));
+    ///
+    /// writer.put_data(b"data")?;
+    /// assert_eq!(second.get_data()?, b"data");
+    /// assert!(matches!(second.put_data(b"other"), Err(BackendError::ReadOnly)));
+    ///
+    /// drop((writer, second));
+    /// let (third, _) = PathBackend::from_path_single_writer(path)?;
+    /// assert!(!third.is_read_only());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_path_single_writer(path: PathBuf) -> error::BackendResult<(Self, bool)> {
+        let Some(lock) = writer_lock::acquire(&path)? else {
+            let exists = path.try_exists()?;
+            let mut backend = Self::new(path);
+            backend.read_only = true;
+            return Ok((backend, exists));
+        };
+        let (mut backend, exists) = Self::from_path_or_create(path)?;
+        backend.lock = Some(lock);
+        Ok((backend, exists))
+    }
+
+    /// Whether another process writes the database, see
+    /// [`PathBackend::from_path_single_writer`].
+    #[must_use]
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// The PID of the process that writes the database, as written into the
+    /// sidecar file by [`PathBackend::from_path_single_writer`] and
+    /// [`PathBackend::from_path_locked`].
+    ///
+    /// This is `None` if no backend holding the lock is open. The PID of a
+    /// writer that crashed stays in the file until another one takes over.
+    #[must_use]
+    pub fn writer_pid(&self) -> Option<u32> {
+        writer_lock::holder(&self.path)
+    }
+
+    /// Fail with [`BackendError::ReadOnly`](error::BackendError::ReadOnly)
+    /// if another process writes the database.
+    fn check_writable(&self) -> error::BackendResult<()> {
+        if self.read_only {
+            return Err(error::BackendError::ReadOnly);
         }
-        Ok(Self { path })
+        Ok(())
     }
 }
 
+/// Lock the sidecar file of the database file at `path`, waiting for other
+/// processes.
+///
+/// Backends that replace their files lock this instead, like
+/// [`PathBackend::from_path_locked`] does.
+pub(crate) fn lock_sidecar(path: &Path) -> error::BackendResult<BackendLock> {
+    use fs2::FileExt;
+
+    let lock = writer_lock::open(path)?;
+    lock.lock_exclusive()?;
+    Ok(BackendLock::new(lock))
+}
+
+/// Read the file at `path`, and the fingerprint of the file that was read.
+fn read_file(path: &Path) -> error::BackendResult<(Vec<u8>, Fingerprint)> {
+    use std::io::Read;
+
+    let mut file = OpenOptions::new().read(true).open(path)?;
+    let metadata = file.metadata()?;
+    let mut buffer = Vec::with_capacity(usize::try_from(metadata.len()).unwrap_or(0));
+    file.read_to_end(&mut buffer)?;
+    Ok((buffer, Fingerprint::from_metadata(&metadata)))
+}
+
 impl Backend for PathBackend {
     fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
-        use std::io::Read;
-
-        let mut file = OpenOptions::new().read(true).open(self.path.as_path())?;
-        let mut buffer = vec![];
-        file.read_to_end(&mut buffer)?;
-        Ok(buffer)
+        read_file(&self.path).map(|(data, _)| data)
     }
 
     /// Write the byte slice to the backend. This uses and atomic save.
@@ -75,15 +365,279 @@ impl Backend for PathBackend {
     /// This won't corrupt the existing database file if the program panics
     /// during the save.
     fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
-        use std::io::Write;
+        self.check_writable()?;
+        Box::new(self.stage_file(data)?).commit()
+    }
 
-        #[allow(clippy::or_fun_call)] // `Path::new` is a zero cost conversion
-        let mut tempf = NamedTempFile::new_in(self.path.parent().unwrap_or(Path::new(".")))?;
-        tempf.write_all(data)?;
-        tempf.as_file().sync_all()?;
-        tempf.persist(self.path.as_path())?;
+    /// Write to a temporary file, which replaces the database file once
+    /// finished.
+    ///
+    /// The database file is left untouched if the writer is dropped without
+    /// finishing.
+    fn writer(&mut self) -> error::BackendResult<Option<Box<dyn BackendWriter + '_>>> {
+        self.check_writable()?;
+        let tempf = self.temp_file()?;
+        Ok(Some(Box::new(PathWriter {
+            file: std::io::BufWriter::new(tempf),
+            path: self.path.as_path(),
+            temp_name: &self.temp_name,
+            backups: self.backups,
+            sync: self.sync.should_sync(),
+            mode: self.mode,
+        })))
+    }
+
+    /// Write to a temporary file, which is renamed over the database file
+    /// on commit.
+    fn stage(&mut self, data: &[u8]) -> error::BackendResult<Option<Box<dyn StagedWrite>>> {
+        self.check_writable()?;
+        Ok(Some(Box::new(self.stage_file(data)?)))
+    }
+
+    /// Rename the database file to `<name>.corrupt-<timestamp>`, the
+    /// timestamp being the seconds since the Unix epoch.
+    fn quarantine(&mut self) -> error::BackendResult<()> {
+        self.check_writable()?;
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let mut corrupt_path = self.path.clone().into_os_string();
+        corrupt_path.push(format!(".corrupt-{timestamp}"));
+        std::fs::rename(&self.path, corrupt_path)?;
         Ok(())
     }
+
+    fn fingerprint(&mut self) -> error::BackendResult<Option<Fingerprint>> {
+        Ok(Some(Fingerprint::from_metadata(&std::fs::metadata(
+            &self.path,
+        )?)))
+    }
+
+    /// The length of the database file.
+    fn size_hint(&self) -> Option<usize> {
+        let len = std::fs::metadata(&self.path).ok()?.len();
+        usize::try_from(len).ok()
+    }
+
+    fn metadata(&mut self) -> error::BackendResult<Option<BackendMetadata>> {
+        match std::fs::metadata(&self.path) {
+            Ok(metadata) => Ok(Some(BackendMetadata::from_metadata(&metadata))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Lock the same sidecar file as [`PathBackend::from_path_locked`],
+    /// waiting for other processes. Backends opened with it, or as the
+    /// writer of [`PathBackend::from_path_single_writer`], hold the lock
+    /// already and return `None`. Read-only backends fail, since the writer
+    /// only releases the lock when it is dropped.
+    fn lock_exclusive(&mut self) -> error::BackendResult<Option<BackendLock>> {
+        self.check_writable()?;
+        if self.lock.is_some() {
+            return Ok(None);
+        }
+        lock_sidecar(&self.path).map(Some)
+    }
+
+    /// Saves replace the file atomically, so loads can read it without
+    /// waiting for them.
+    fn detached_reader(&mut self) -> error::BackendResult<Option<Box<dyn DetachedReader>>> {
+        Ok(Some(Box::new(PathReader(self.path.clone()))))
+    }
+}
+
+/// The [`DetachedReader`] of a [`PathBackend`].
+///
+/// Saves replace the file atomically, so it reads either the old or the new
+/// data, even while a save is running.
+struct PathReader(PathBuf);
+
+impl DetachedReader for PathReader {
+    fn read(self: Box<Self>) -> error::BackendResult<(Vec<u8>, Option<Fingerprint>)> {
+        read_file(&self.0).map(|(data, fingerprint)| (data, Some(fingerprint)))
+    }
+}
+
+/// The [`StagedWrite`] of a [`PathBackend`], also used by its saves.
+struct PathStaged {
+    file: NamedTempFile,
+    path: PathBuf,
+    temp_name: TempName,
+    mode: Option<u32>,
+    backups: usize,
+    sync: bool,
+}
+
+impl StagedWrite for PathStaged {
+    fn commit(self: Box<Self>) -> error::BackendResult<()> {
+        let current = keep_current(&self.path, &self.temp_name, self.backups)?;
+        persist(self.file, &self.path, &self.temp_name, self.mode, self.sync)?;
+        rotate_backups(&self.path, current, self.backups)?;
+        Ok(())
+    }
+}
+
+/// The [`BackendWriter`] of a [`PathBackend`].
+struct PathWriter<'a> {
+    file: std::io::BufWriter<NamedTempFile>,
+    path: &'a Path,
+    temp_name: &'a TempName,
+    backups: usize,
+    sync: bool,
+    mode: Option<u32>,
+}
+
+impl std::io::Write for PathWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl BackendWriter for PathWriter<'_> {
+    fn finish(self: Box<Self>) -> error::BackendResult<()> {
+        let tempf = self
+            .file
+            .into_inner()
+            .map_err(std::io::IntoInnerError::into_error)?;
+        copy_metadata(tempf.as_file(), self.path, self.mode)?;
+        if self.sync {
+            tempf.as_file().sync_all()?;
+        }
+        let current = keep_current(self.path, self.temp_name, self.backups)?;
+        persist(tempf, self.path, self.temp_name, self.mode, self.sync)?;
+        rotate_backups(self.path, current, self.backups)?;
+        Ok(())
+    }
+}
+
+/// The directory `path` is in.
+pub(super) fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+/// Replace `path` with `tempf`.
+///
+/// If `tempf` is on another file system, which can not be renamed across,
+/// it is copied next to `path` first, see [`copy_into_place`].
+fn persist(
+    tempf: NamedTempFile,
+    path: &Path,
+    temp_name: &TempName,
+    mode: Option<u32>,
+    sync: bool,
+) -> error::BackendResult<()> {
+    match tempf.persist(path) {
+        Ok(_) => Ok(()),
+        Err(err) if err.error.kind() == std::io::ErrorKind::CrossesDevices => {
+            copy_into_place(err.file.path(), path, temp_name, mode, sync)
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Replace `path` with a copy of the file at `from`.
+///
+/// The copy is a temporary file named `temp_name` next to `path`, with the
+/// same permissions and owner as any other save, renamed into place.
+fn copy_into_place(
+    from: &Path,
+    path: &Path,
+    temp_name: &TempName,
+    mode: Option<u32>,
+    sync: bool,
+) -> error::BackendResult<()> {
+    let local = temp_name.create_in(parent_dir(path))?;
+    std::fs::copy(from, local.path())?;
+    copy_metadata(local.as_file(), path, mode)?;
+    if sync {
+        local.as_file().sync_all()?;
+    }
+    local.persist(path)?;
+    Ok(())
+}
+
+/// Give the temporary file that replaces `path` the permissions of the
+/// current file, or `mode` if given.
+///
+/// On Unix, the owner and group are kept too, if the process is allowed to
+/// change them.
+fn copy_metadata(tempf: &std::fs::File, path: &Path, mode: Option<u32>) -> std::io::Result<()> {
+    let current = match std::fs::metadata(path) {
+        Ok(metadata) => Some(metadata),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        if let Some(current) = &current {
+            // Only root can give files away, not being allowed to is fine.
+            let _ = std::os::unix::fs::fchown(tempf, Some(current.uid()), Some(current.gid()));
+        }
+        if let Some(mode) = mode {
+            return tempf.set_permissions(std::fs::Permissions::from_mode(mode));
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+    match current {
+        Some(current) => tempf.set_permissions(current.permissions()),
+        None => Ok(()),
+    }
+}
+
+fn backup_path(path: &Path, n: usize) -> PathBuf {
+    let mut backup = path.to_owned().into_os_string();
+    backup.push(format!(".bak.{n}"));
+    backup.into()
+}
+
+/// Keep the current file of `path` as a temporary file next to it, to
+/// become its first backup once the new file replaced it.
+///
+/// The current file is hard linked (or copied, where that is not supported),
+/// so that it stays in place until the new one replaces it. If the save
+/// fails, the temporary file is deleted and the backups stay as they are.
+fn keep_current(
+    path: &Path,
+    temp_name: &TempName,
+    count: usize,
+) -> std::io::Result<Option<TempPath>> {
+    use std::fs;
+
+    if count == 0 || !path.is_file() {
+        return Ok(None);
+    }
+    let current = temp_name.create_in(parent_dir(path))?.into_temp_path();
+    fs::remove_file(&current)?;
+    if fs::hard_link(path, &current).is_err() {
+        fs::copy(path, &current)?;
+    }
+    Ok(Some(current))
+}
+
+/// Shift the backups of `path` by one and make `current`, the file kept by
+/// [`keep_current`], the first one.
+fn rotate_backups(path: &Path, current: Option<TempPath>, count: usize) -> std::io::Result<()> {
+    let Some(current) = current else {
+        return Ok(());
+    };
+    for n in (1..count).rev() {
+        let from = backup_path(path, n);
+        if from.is_file() {
+            std::fs::rename(from, backup_path(path, n + 1))?;
+        }
+    }
+    current.persist(backup_path(path, 1)).map_err(|e| e.error)
 }
 
 #[cfg(test)]
@@ -145,10 +699,188 @@ mod tests {
             assert_eq!(std::io::ErrorKind::NotFound, io_err.kind());
         } else {
             panic!("Wrong kind of error returned: {}", err);
-        };
+        }
         dir.close().expect("Error while deleting temp directory!");
     }
 
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_path_backend_quarantine() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let file_path = dir.path().join("rustbreak_path_db.db");
+        let (mut backend, _) =
+            PathBackend::from_path_or_create(file_path).expect("could not create backend");
+        backend.put_data(b"garbage").expect("could not put data");
+
+        backend.quarantine().expect("could not quarantine");
+        let corrupt: Vec<_> = std::fs::read_dir(dir.path())
+            .expect("could not read directory")
+            .map(|entry| entry.expect("could not read entry").path())
+            .collect();
+        assert_eq!(corrupt.len(), 1);
+        let name = corrupt[0]
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+        assert!(name.starts_with("rustbreak_path_db.db.corrupt-"));
+        assert_eq!(std::fs::read(&corrupt[0]).unwrap(), b"garbage");
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_path_backend_backups() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let file_path = dir.path().join("rustbreak_path_db.db");
+        let (backend, _) =
+            PathBackend::from_path_or_create(file_path).expect("could not create backend");
+        let mut backend = backend.with_backups(2);
+
+        for data in [b"one", b"two", b"six", b"ten"] {
+            backend.put_data(data).expect("could not put data");
+        }
+        let mut writer = backend.writer().unwrap().unwrap();
+        writer.write_all(b"new").unwrap();
+        writer.finish().unwrap();
+
+        assert_eq!(backend.get_data().unwrap(), b"new");
+        assert_eq!(std::fs::read(backend.backup_path(1)).unwrap(), b"ten");
+        assert_eq!(std::fs::read(backend.backup_path(2)).unwrap(), b"six");
+        assert!(!backend.backup_path(3).exists());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn failed_saves_do_not_rotate_backups() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let file_path = dir.path().join("rustbreak_path_db.db");
+        let (backend, _) =
+            PathBackend::from_path_or_create(file_path).expect("could not create backend");
+        let mut backend = backend.with_backups(2);
+        backend.put_data(b"one").expect("could not put data");
+        backend.put_data(b"two").expect("could not put data");
+
+        // Removing the staged file makes renaming it into place fail.
+        let staged = backend.stage(b"six").unwrap().unwrap();
+        let temp_files = || {
+            std::fs::read_dir(dir.path())
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| {
+                    path.file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .starts_with(".tmp")
+                })
+                .collect::<Vec<_>>()
+        };
+        for path in temp_files() {
+            std::fs::remove_file(path).unwrap();
+        }
+        assert!(staged.commit().is_err());
+
+        assert_eq!(backend.get_data().unwrap(), b"two");
+        assert_eq!(std::fs::read(backend.backup_path(1)).unwrap(), b"one");
+        // The empty file `from_path_or_create` started with.
+        assert_eq!(std::fs::read(backend.backup_path(2)).unwrap(), b"");
+        assert!(temp_files().is_empty());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    #[cfg_attr(miri, ignore)]
+    fn test_path_backend_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let file_path = dir.path().join("rustbreak_path_db.db");
+        let mode =
+            |path: &std::path::Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        let (mut backend, _) =
+            PathBackend::from_path_or_create(file_path.clone()).expect("could not create backend");
+        std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o640)).unwrap();
+        backend.put_data(b"one").expect("could not put data");
+        assert_eq!(mode(&file_path), 0o640);
+
+        let mut backend = backend.with_mode(0o600).expect("could not set mode");
+        assert_eq!(mode(&file_path), 0o600);
+        std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        let mut writer = backend.writer().unwrap().unwrap();
+        writer.write_all(b"two").unwrap();
+        writer.finish().unwrap();
+        assert_eq!(mode(&file_path), 0o600);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    #[cfg_attr(miri, ignore)]
+    fn test_copy_into_place_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let scratch = tempfile::NamedTempFile::new().expect("could not create temporary file");
+        let file_path = dir.path().join("rustbreak_path_db.db");
+        let mode =
+            |path: &std::path::Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        std::fs::write(&file_path, b"old").unwrap();
+        std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        std::fs::write(scratch.path(), b"new").unwrap();
+        assert_eq!(mode(scratch.path()), 0o600);
+        let temp_name = super::TempName {
+            prefix: String::from("save-"),
+            suffix: String::from("~"),
+        };
+
+        super::copy_into_place(scratch.path(), &file_path, &temp_name, None, false)
+            .expect("could not copy");
+        assert_eq!(std::fs::read(&file_path).unwrap(), b"new");
+        assert_eq!(mode(&file_path), 0o644);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        super::copy_into_place(scratch.path(), &file_path, &temp_name, Some(0o640), false)
+            .expect("could not copy");
+        assert_eq!(mode(&file_path), 0o640);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_path_backend_temp_dir_and_name() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let scratch = tempfile::tempdir().expect("could not create temporary directory");
+        let file_path = dir.path().join("rustbreak_path_db.db");
+        let (backend, _) =
+            PathBackend::from_path_or_create(file_path.clone()).expect("could not create backend");
+        let mut backend = backend
+            .with_temp_dir(scratch.path())
+            .with_temp_name("save-", "~");
+
+        let mut writer = backend.writer().unwrap().unwrap();
+        writer.write_all(b"data").unwrap();
+        let temp: Vec<_> = std::fs::read_dir(scratch.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(temp.len(), 1);
+        assert!(temp[0].starts_with("save-") && temp[0].ends_with('~'));
+        writer.finish().unwrap();
+
+        assert_eq!(std::fs::read(&file_path).unwrap(), b"data");
+        assert_eq!(std::fs::read_dir(scratch.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_path_backend_create_recursive() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let file_path = dir.path().join("a").join("b").join("rustbreak_path_db.db");
+        let (mut backend, existed) = PathBackend::from_path_or_create_recursive(file_path)
+            .expect("could not create backend");
+        assert!(!existed);
+
+        backend.put_data(b"data").expect("could not put data");
+        assert_eq!(backend.get_data().expect("could not get data"), b"data");
+    }
+
     // If the file already exists, the closure shouldn't be called.
     #[test]
     #[cfg_attr(miri, ignore)]
@@ -173,7 +905,7 @@ mod tests {
         file_path.push("rustbreak_path_db.db");
         let mut backend = PathBackend::from_path_or_create_and(file_path, |f| {
             f.write_all(b"this is a new file")
-                .expect("could not write to file")
+                .expect("could not write to file");
         })
         .expect("could not create backend");
         assert_eq!(
@@ -186,4 +918,73 @@ mod tests {
         assert_eq!(backend.get_data().expect("could not get data"), data);
         dir.close().expect("Error while deleting temp directory!");
     }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_path_backend_writer() {
+        let file = NamedTempFile::new().expect("could not create temporary file");
+        let (mut backend, _) = PathBackend::from_path_or_create(file.path().to_owned())
+            .expect("could not create backend");
+        backend.put_data(&[1, 2, 3]).expect("could not put data");
+
+        // An unfinished writer does not touch the file.
+        let mut writer = backend
+            .writer()
+            .expect("could not get writer")
+            .expect("path backend should stream");
+        writer.write_all(&[4, 5]).expect("could not write");
+        drop(writer);
+        assert_eq!(backend.get_data().expect("could not get data"), [1, 2, 3]);
+
+        let mut writer = backend
+            .writer()
+            .expect("could not get writer")
+            .expect("path backend should stream");
+        writer.write_all(&[4, 5]).expect("could not write");
+        writer.finish().expect("could not finish");
+        assert_eq!(backend.get_data().expect("could not get data"), [4, 5]);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_path_backend_detached_reader() {
+        let file = NamedTempFile::new().expect("could not create temporary file");
+        let (mut backend, _) = PathBackend::from_path_or_create(file.path().to_owned())
+            .expect("could not create backend");
+        backend.put_data(&[1, 2, 3]).expect("could not put data");
+
+        let reader = backend
+            .detached_reader()
+            .expect("could not get reader")
+            .expect("path backend should have a detached reader");
+        // The reader sees saves made after it was created.
+        backend.put_data(&[4, 5]).expect("could not put data");
+        let (data, fingerprint) = reader.read().expect("could not read");
+        assert_eq!(data, [4, 5]);
+        assert_eq!(
+            fingerprint,
+            backend.fingerprint().expect("could not get fingerprint")
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_path_backend_locked() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let file_path = dir.path().join("rustbreak_path_db.db");
+        let (mut backend, existed) =
+            PathBackend::from_path_locked(file_path.clone()).expect("could not create backend");
+        assert!(!existed);
+        let err =
+            PathBackend::from_path_locked(file_path.clone()).expect_err("file should be locked");
+        assert!(matches!(err, crate::error::BackendError::Locked));
+
+        // Saving replaces the database file, but not the lock.
+        backend.put_data(&[1, 2, 3]).expect("could not put data");
+        PathBackend::from_path_locked(file_path.clone()).expect_err("file should be locked");
+
+        drop(backend);
+        PathBackend::from_path_locked(file_path).expect("lock should have been released");
+        dir.close().expect("Error while deleting temp directory!");
+    }
 }
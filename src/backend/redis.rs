@@ -0,0 +1,200 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::convert::TryFrom;
+use std::time::Duration;
+
+use super::Backend;
+use crate::error;
+
+/// A backend that stores the data under a single Redis key.
+///
+/// Several replicas of a service can share one document this way. A missing
+/// key reads as empty data. With [`RedisBackend::with_ttl`], every save sets
+/// the key to expire, so that the state is dropped once nobody saves it
+/// anymore.
+///
+/// The connection is opened on first use, and again after a request failed.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # extern crate rustbreak;
+/// use rustbreak::backend::{Backend, RedisBackend};
+/// use std::time::Duration;
+///
+/// # fn main() -> rustbreak::error::BackendResult<()> {
+/// let mut backend = RedisBackend::open("redis://127.0.0.1/", "app:state")?
+///     .with_ttl(Duration::from_secs(3600));
+/// backend.put_data(b"(sessions: [])")?;
+/// assert_eq!(backend.get_data()?, b"(sessions: [])");
+/// # Ok(())
+/// # }
+/// ```
+pub struct RedisBackend {
+    client: redis::Client,
+    key: String,
+    ttl: Option<Duration>,
+    connection: Option<redis::Connection>,
+}
+
+impl RedisBackend {
+    /// Store the data under `key` on the server at `url`, like
+    /// `redis://127.0.0.1/`.
+    ///
+    /// This only checks the URL, the connection is opened on first use.
+    pub fn open<K: Into<String>>(url: &str, key: K) -> error::BackendResult<Self> {
+        Ok(Self::new(redis::Client::open(url)?, key))
+    }
+
+    /// Store the data under `key` with `client`.
+    pub fn new<K: Into<String>>(client: redis::Client, key: K) -> Self {
+        Self {
+            client,
+            key: key.into(),
+            ttl: None,
+            connection: None,
+        }
+    }
+
+    /// Let the key expire `ttl` after each save. By default it never
+    /// expires. It is rounded up to whole milliseconds.
+    #[must_use]
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// The key the data is stored under.
+    #[must_use]
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Run `cmd` on the connection, dropping it if that fails.
+    fn query<T: redis::FromRedisValue>(&mut self, cmd: &redis::Cmd) -> error::BackendResult<T> {
+        let connection = match &mut self.connection {
+            Some(connection) => connection,
+            None => self.connection.insert(self.client.get_connection()?),
+        };
+        let result = cmd.query(connection);
+        if result.is_err() {
+            self.connection = None;
+        }
+        Ok(result?)
+    }
+}
+
+impl std::fmt::Debug for RedisBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisBackend")
+            .field("client", &self.client)
+            .field("key", &self.key)
+            .field("ttl", &self.ttl)
+            .field("connected", &self.connection.is_some())
+            .finish()
+    }
+}
+
+impl Backend for RedisBackend {
+    fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        let data: Option<Vec<u8>> = self.query(redis::cmd("GET").arg(&self.key))?;
+        Ok(data.unwrap_or_default())
+    }
+
+    fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(&self.key).arg(data);
+        if let Some(ttl) = self.ttl {
+            let millis = ttl.as_nanos().div_ceil(1_000_000).max(1);
+            cmd.arg("PX").arg(u64::try_from(millis).unwrap_or(u64::MAX));
+        }
+        self.query::<()>(&cmd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RedisBackend;
+    use crate::backend::Backend;
+    use std::collections::HashMap;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    type Store = Arc<Mutex<HashMap<Vec<u8>, (Vec<u8>, Option<u64>)>>>;
+
+    /// Answer `GET` and `SET key value [PX millis]` like Redis, recording the
+    /// expiry instead of applying it.
+    fn serve(store: Store) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("could not bind");
+        let url = format!("redis://{}/", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let store = store.clone();
+                std::thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.expect("could not accept"));
+                    let mut line = String::new();
+                    while reader.read_line(&mut line).unwrap_or(0) > 0 {
+                        let count: usize = line.trim_end()[1..].parse().unwrap();
+                        let mut args = Vec::new();
+                        for _ in 0..count {
+                            line.clear();
+                            reader.read_line(&mut line).unwrap();
+                            let len: usize = line.trim_end()[1..].parse().unwrap();
+                            let mut arg = vec![0; len + 2];
+                            reader.read_exact(&mut arg).unwrap();
+                            arg.truncate(len);
+                            args.push(arg);
+                        }
+                        line.clear();
+
+                        let mut store = store.lock().unwrap();
+                        let reply = match args[0].to_ascii_uppercase().as_slice() {
+                            b"GET" => match store.get(&args[1]) {
+                                Some((value, _)) => {
+                                    let mut reply = format!("${}\r\n", value.len()).into_bytes();
+                                    reply.extend_from_slice(value);
+                                    reply.extend_from_slice(b"\r\n");
+                                    reply
+                                }
+                                None => b"$-1\r\n".to_vec(),
+                            },
+                            b"SET" => {
+                                let ttl = args
+                                    .get(4)
+                                    .map(|ms| String::from_utf8_lossy(ms).parse().unwrap());
+                                store.insert(args[1].clone(), (args[2].clone(), ttl));
+                                b"+OK\r\n".to_vec()
+                            }
+                            _ => b"-ERR unknown command\r\n".to_vec(),
+                        };
+                        reader.get_mut().write_all(&reply).unwrap();
+                    }
+                });
+            }
+        });
+        url
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn redis_get_and_set_with_ttl() {
+        let store = Store::default();
+        let url = serve(store.clone());
+        let mut backend = RedisBackend::open(&url, "state").expect("could not open");
+        assert_eq!(backend.get_data().expect("could not get"), b"");
+        backend.put_data(b"one").expect("could not put");
+        assert_eq!(backend.get_data().expect("could not get"), b"one");
+        assert_eq!(store.lock().unwrap()[&b"state"[..]].1, None);
+
+        let mut backend = backend.with_ttl(std::time::Duration::from_micros(1500));
+        backend.put_data(b"two").expect("could not put");
+        assert_eq!(
+            store.lock().unwrap()[&b"state"[..]],
+            (b"two".to_vec(), Some(2))
+        );
+        assert_eq!(backend.key(), "state");
+    }
+}
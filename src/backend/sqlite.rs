@@ -0,0 +1,207 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::path::Path;
+
+use rusqlite::{Connection, OptionalExtension};
+
+use super::Backend;
+use crate::error;
+
+/// The table the data is stored in by default.
+const DEFAULT_TABLE: &str = "rustbreak";
+/// The row the data is stored in by default.
+const DEFAULT_KEY: &str = "default";
+
+/// A backend that stores the data as a BLOB in a `SQLite` database.
+///
+/// The data lives in a table with a `name` and a `data` column, one row per
+/// key, so several databases can share one table with
+/// [`SqliteBackend::with_key`]. The table is created if it does not exist. A
+/// missing row reads as empty data.
+///
+/// Every save is a single statement, so `SQLite` makes it atomic and durable.
+/// [`SqliteBackend::open`] also switches the file to write-ahead logging.
+/// Since the data can sit in the same file as other tables,
+/// [`SqliteBackend::connection`] gives access to the connection for your own
+/// queries.
+///
+/// **Important**: This backend is only available with the `sqlite` feature.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::backend::{Backend, SqliteBackend};
+///
+/// # fn main() -> rustbreak::error::BackendResult<()> {
+/// # let dir = tempfile::tempdir()?;
+/// # let path = dir.path().join("app.db");
+/// let mut backend = SqliteBackend::open(&path)?.with_key("settings");
+/// assert_eq!(backend.get_data()?, b"");
+/// backend.put_data(b"(theme: \"dark\")")?;
+/// assert_eq!(backend.get_data()?, b"(theme: \"dark\")");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SqliteBackend {
+    connection: Connection,
+    table: String,
+    key: String,
+}
+
+impl SqliteBackend {
+    /// Open the `SQLite` file at `path`, creating it if it does not exist, and
+    /// store the data in the `rustbreak` table.
+    ///
+    /// The file is switched to write-ahead logging.
+    pub fn open<P: AsRef<Path>>(path: P) -> error::BackendResult<Self> {
+        let connection = Connection::open(path)?;
+        connection.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+        Self::from_connection(connection)
+    }
+
+    /// Store the data in the `rustbreak` table of `connection`.
+    pub fn from_connection(connection: Connection) -> error::BackendResult<Self> {
+        Self::with_table(connection, DEFAULT_TABLE)
+    }
+
+    /// Store the data in the table `table` of `connection`, creating it if
+    /// it does not exist.
+    pub fn with_table<T: Into<String>>(
+        connection: Connection,
+        table: T,
+    ) -> error::BackendResult<Self> {
+        let table = quote(&table.into());
+        connection.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {table} (name TEXT PRIMARY KEY NOT NULL, data BLOB NOT NULL)"
+            ),
+            [],
+        )?;
+        Ok(Self {
+            connection,
+            table,
+            key: DEFAULT_KEY.to_string(),
+        })
+    }
+
+    /// Store the data in the row `key` instead of `default`.
+    #[must_use]
+    pub fn with_key<K: Into<String>>(mut self, key: K) -> Self {
+        self.key = key.into();
+        self
+    }
+
+    /// The key of the row the data is stored in.
+    #[must_use]
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// The connection to the `SQLite` database.
+    #[must_use]
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Get back the connection to the `SQLite` database.
+    #[must_use]
+    pub fn into_connection(self) -> Connection {
+        self.connection
+    }
+}
+
+/// Quote `name` as an SQL identifier.
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+impl Backend for SqliteBackend {
+    fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        let data = self
+            .connection
+            .query_row(
+                &format!("SELECT data FROM {} WHERE name = ?1", self.table),
+                [&self.key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(data.unwrap_or_default())
+    }
+
+    fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        self.connection.execute(
+            &format!(
+                "INSERT INTO {} (name, data) VALUES (?1, ?2) \
+                 ON CONFLICT (name) DO UPDATE SET data = excluded.data",
+                self.table
+            ),
+            rusqlite::params![self.key, data],
+        )?;
+        Ok(())
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.connection
+            .query_row(
+                &format!("SELECT length(data) FROM {} WHERE name = ?1", self.table),
+                [&self.key],
+                |row| row.get(0),
+            )
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SqliteBackend;
+    use crate::backend::Backend;
+    use rusqlite::Connection;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn sqlite_rows_per_key() {
+        let dir = tempfile::tempdir().expect("could not create dir");
+        let path = dir.path().join("data.db");
+        let mut backend = SqliteBackend::open(&path).expect("could not open");
+        assert_eq!(backend.get_data().expect("could not get"), b"");
+        backend.put_data(b"one").expect("could not put");
+        backend.put_data(b"two").expect("could not put");
+        assert_eq!(backend.get_data().expect("could not get"), b"two");
+        assert_eq!(backend.size_hint(), Some(3));
+
+        let mut other = SqliteBackend::open(&path)
+            .expect("could not open")
+            .with_key("other");
+        assert_eq!(other.get_data().expect("could not get"), b"");
+        other.put_data(b"three").expect("could not put");
+        assert_eq!(backend.get_data().expect("could not get"), b"two");
+        let rows: i64 = backend
+            .connection()
+            .query_row("SELECT count(*) FROM rustbreak", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(rows, 2);
+    }
+
+    #[test]
+    fn sqlite_custom_table() {
+        let connection = Connection::open_in_memory().expect("could not open");
+        connection
+            .execute("CREATE TABLE users (id INTEGER PRIMARY KEY)", [])
+            .unwrap();
+        let mut backend =
+            SqliteBackend::with_table(connection, "my \"state\"").expect("could not create");
+        backend.put_data(b"data").expect("could not put");
+        assert_eq!(backend.get_data().expect("could not get"), b"data");
+        let connection = backend.into_connection();
+        let data: Vec<u8> = connection
+            .query_row("SELECT data FROM \"my \"\"state\"\"\"", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(data, b"data");
+    }
+}
@@ -0,0 +1,124 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use super::Backend;
+use crate::error;
+
+use std::convert::TryFrom;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// A backend using any stream that can be read, written and seeked, like a
+/// `Cursor<Vec<u8>>` or a wrapper around some custom I/O object.
+///
+/// The data is always written from the start of the stream. As a generic
+/// stream can not be truncated, writing less data than before leaves the
+/// rest of the old data behind it. The backend remembers how much it wrote
+/// and only reads that much back, but a new `StreamBackend` over the same
+/// stream reads everything up to its end. Use [`FileBackend`] for files, or
+/// a format that ignores trailing bytes if this matters.
+///
+/// [`FileBackend`]: super::FileBackend
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::backend::{Backend, StreamBackend};
+/// use std::io::Cursor;
+///
+/// # fn main() -> rustbreak::error::BackendResult<()> {
+/// let mut backend = StreamBackend::new(Cursor::new(Vec::new()));
+/// backend.put_data(b"Hello")?;
+/// assert_eq!(backend.get_data()?, b"Hello");
+/// assert_eq!(backend.into_inner().into_inner(), b"Hello");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct StreamBackend<T> {
+    stream: T,
+    /// The length of the data written last, if any was written.
+    len: Option<u64>,
+}
+
+impl<T: Read + Write + Seek> StreamBackend<T> {
+    /// Use `stream` as backend.
+    pub fn new(stream: T) -> Self {
+        Self { stream, len: None }
+    }
+}
+
+impl<T> StreamBackend<T> {
+    /// Get a reference to the underlying stream.
+    pub fn get_ref(&self) -> &T {
+        &self.stream
+    }
+
+    /// Get a mutable reference to the underlying stream.
+    ///
+    /// Writing to it directly is not tracked, the backend still only reads
+    /// back as much data as it wrote last.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.stream
+    }
+
+    /// Consume the backend and return the underlying stream.
+    pub fn into_inner(self) -> T {
+        self.stream
+    }
+}
+
+impl<T: Read + Write + Seek> Backend for StreamBackend<T> {
+    fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        self.stream.seek(SeekFrom::Start(0))?;
+        let mut buffer = vec![];
+        match self.len {
+            Some(len) => {
+                buffer.reserve(usize::try_from(len).unwrap_or(0));
+                (&mut self.stream).take(len).read_to_end(&mut buffer)?;
+            }
+            None => {
+                self.stream.read_to_end(&mut buffer)?;
+            }
+        }
+        Ok(buffer)
+    }
+
+    fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        self.stream.seek(SeekFrom::Start(0))?;
+        self.stream.write_all(data)?;
+        self.stream.flush()?;
+        self.len = Some(data.len() as u64);
+        Ok(())
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.len.and_then(|len| usize::try_from(len).ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Backend, StreamBackend};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_stream_backend() {
+        let mut backend = StreamBackend::new(Cursor::new(b"old data".to_vec()));
+        assert_eq!(backend.get_data().expect("could not get data"), b"old data");
+
+        backend.put_data(b"new").expect("could not put data");
+        assert_eq!(backend.get_data().expect("could not get data"), b"new");
+        assert_eq!(backend.size_hint(), Some(3));
+
+        backend
+            .put_data(b"longer data")
+            .expect("could not put data");
+        assert_eq!(
+            backend.get_data().expect("could not get data"),
+            b"longer data"
+        );
+        assert_eq!(backend.into_inner().into_inner(), b"longer data");
+    }
+}
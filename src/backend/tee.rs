@@ -0,0 +1,172 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use super::{Backend, BackendLock, BackendMetadata, Fingerprint};
+use crate::error;
+
+/// A backend that replicates the data to several backends.
+///
+/// Every write goes to all backends, reads are served by the first one that
+/// does not fail. This gives simple redundancy, for example by keeping a
+/// copy of the database on another disk.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::backend::{Backend, MemoryBackend, TeeBackend};
+///
+/// # fn main() -> rustbreak::error::BackendResult<()> {
+/// let mut backend = TeeBackend::new(vec![
+///     Box::new(MemoryBackend::new()),
+///     Box::new(MemoryBackend::new()),
+/// ]);
+/// backend.put_data(b"Hello")?;
+/// assert_eq!(backend.get_data()?, b"Hello");
+/// assert_eq!(backend.backends_mut()[1].get_data()?, b"Hello");
+/// # Ok(())
+/// # }
+/// ```
+pub struct TeeBackend {
+    backends: Vec<Box<dyn Backend>>,
+}
+
+impl std::fmt::Debug for TeeBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TeeBackend")
+            .field("backends", &self.backends.len())
+            .finish()
+    }
+}
+
+impl TeeBackend {
+    /// Replicate the data to `backends`, reading from them in the given
+    /// order.
+    #[must_use]
+    pub fn new(backends: Vec<Box<dyn Backend>>) -> Self {
+        Self { backends }
+    }
+
+    /// Add another backend to replicate to, it is read from last.
+    pub fn push<B: Backend + 'static>(&mut self, backend: B) {
+        self.backends.push(Box::new(backend));
+    }
+
+    /// Get the backends.
+    #[must_use]
+    pub fn backends(&self) -> &[Box<dyn Backend>] {
+        &self.backends
+    }
+
+    /// Get mutable references to the backends.
+    pub fn backends_mut(&mut self) -> &mut [Box<dyn Backend>] {
+        &mut self.backends
+    }
+
+    /// Consume the `TeeBackend` and return the backends.
+    #[must_use]
+    pub fn into_inner(self) -> Vec<Box<dyn Backend>> {
+        self.backends
+    }
+}
+
+impl Backend for TeeBackend {
+    /// Read from the first backend that does not fail.
+    ///
+    /// If all of them fail, the error of the last one is returned. Without
+    /// any backends there is no data, so this returns an empty `Vec`.
+    fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        let mut last_error = None;
+        for backend in &mut self.backends {
+            match backend.get_data() {
+                Ok(data) => return Ok(data),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        last_error.map_or_else(|| Ok(Vec::new()), Err)
+    }
+
+    /// Write to all backends.
+    ///
+    /// A failing backend does not stop the others from being written, the
+    /// first error is returned once all of them were tried.
+    fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        let mut result = Ok(());
+        for backend in &mut self.backends {
+            let res = backend.put_data(data);
+            if result.is_ok() {
+                result = res;
+            }
+        }
+        result
+    }
+
+    /// Quarantine the data of all backends, as they all hold the same data.
+    fn quarantine(&mut self) -> error::BackendResult<()> {
+        let mut result = Ok(());
+        for backend in &mut self.backends {
+            let res = backend.quarantine();
+            if result.is_ok() {
+                result = res;
+            }
+        }
+        result
+    }
+
+    /// The fingerprint of the first backend.
+    fn fingerprint(&mut self) -> error::BackendResult<Option<Fingerprint>> {
+        match self.backends.first_mut() {
+            Some(backend) => backend.fingerprint(),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.backends.iter().find_map(Backend::size_hint)
+    }
+
+    /// Lock all backends, in order.
+    fn lock_exclusive(&mut self) -> error::BackendResult<Option<BackendLock>> {
+        let mut locks = Vec::new();
+        for backend in &mut self.backends {
+            locks.extend(backend.lock_exclusive()?);
+        }
+        Ok((!locks.is_empty()).then(|| BackendLock::new(locks)))
+    }
+
+    /// The metadata of the first backend.
+    fn metadata(&mut self) -> error::BackendResult<Option<BackendMetadata>> {
+        match self.backends.first_mut() {
+            Some(backend) => backend.metadata(),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Backend, TeeBackend};
+    use crate::backend::{MemoryBackend, PathBackend};
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_tee_backend() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let missing = PathBackend::new(dir.path().join("missing").join("data"));
+        let mut backend = TeeBackend::new(vec![Box::new(missing)]);
+        backend.push(MemoryBackend::new());
+
+        // The first backend can not be written, but the second one is.
+        backend
+            .put_data(b"Hello")
+            .expect_err("writing to a missing directory succeeded");
+        assert_eq!(backend.get_data().expect("could not get data"), b"Hello");
+
+        std::fs::create_dir(dir.path().join("missing")).expect("could not create dir");
+        backend.put_data(b"World").expect("could not put data");
+        for backend in backend.backends_mut() {
+            assert_eq!(backend.get_data().expect("could not get data"), b"World");
+        }
+    }
+}
@@ -0,0 +1,111 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The `.lck` sidecar file that [`PathBackend`] locks instead of the
+//! database file, since saving replaces that.
+//!
+//! Backends opened with [`PathBackend::from_path_locked`] or
+//! [`PathBackend::from_path_single_writer`] keep an advisory lock on it
+//! while they are open, and write their PID into it. The file itself is left
+//! behind, only the PID is removed again. Since the operating system releases
+//! the lock of a process that exits, a file left behind by a writer that
+//! crashed is simply locked again, it does not depend on the PID, which might
+//! have been reused by another process since.
+//!
+//! [`PathBackend`]: super::PathBackend
+//! [`PathBackend::from_path_locked`]: super::PathBackend::from_path_locked
+//! [`PathBackend::from_path_single_writer`]: super::PathBackend::from_path_single_writer
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::{self, BackendError};
+
+/// Held by the process that locked the sidecar file, removes its PID from the
+/// file and releases the lock when dropped.
+#[derive(Debug)]
+pub(crate) struct WriterLock {
+    /// The sidecar file, locked while it is open.
+    file: File,
+}
+
+impl WriterLock {
+    /// Take over the locked `file`, writing our PID into it.
+    fn claim(mut file: File) -> error::BackendResult<Self> {
+        file.set_len(0)?;
+        writeln!(file, "{}", std::process::id())?;
+        Ok(Self { file })
+    }
+}
+
+impl Drop for WriterLock {
+    fn drop(&mut self) {
+        // Cleared before the lock is released. If this fails, the PID stays
+        // until the next process locks the file.
+        let _ = self.file.set_len(0);
+    }
+}
+
+/// The path of the sidecar file of the database file at `path`.
+fn lock_path(path: &Path) -> PathBuf {
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lck");
+    PathBuf::from(lock_path)
+}
+
+/// Open the sidecar file of the database file at `path`, creating it if
+/// needed.
+pub(crate) fn open(path: &Path) -> error::BackendResult<File> {
+    Ok(OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(lock_path(path))?)
+}
+
+/// Lock the sidecar file of the database file at `path`, or return `None` if
+/// another process holds the lock.
+pub(crate) fn acquire(path: &Path) -> error::BackendResult<Option<WriterLock>> {
+    let file = open(path)?;
+    match super::try_lock(&file) {
+        Ok(()) => WriterLock::claim(file).map(Some),
+        Err(BackendError::Locked) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// The PID written into the sidecar file of the database file at `path`, if
+/// there is one.
+pub(crate) fn holder(path: &Path) -> Option<u32> {
+    let mut contents = String::new();
+    File::open(lock_path(path))
+        .ok()?
+        .read_to_string(&mut contents)
+        .ok()?;
+    contents.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{acquire, holder, lock_path};
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn stale_lock_files_are_taken_over() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let path = dir.path().join("db.ron");
+        // Left behind by a writer that crashed, nobody holds the lock.
+        std::fs::write(lock_path(&path), "4194305\n").expect("could not write");
+
+        let lock = acquire(&path).expect("could not lock").expect("not stale");
+        assert_eq!(holder(&path), Some(std::process::id()));
+        assert!(acquire(&path).expect("could not lock").is_none());
+
+        drop(lock);
+        assert!(lock_path(&path).exists());
+        assert_eq!(holder(&path), None);
+        assert!(acquire(&path).expect("could not lock").is_some());
+    }
+}
@@ -0,0 +1,227 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use super::{Backend, Fingerprint};
+use crate::error;
+
+/// A backend that stores the data as a single entry of a ZIP archive.
+///
+/// The other entries of the archive are left as they are, so several
+/// databases, or a database and other documents, can share one archive. A
+/// missing archive or entry reads as empty data.
+///
+/// Every save writes a new archive next to the old one, copying the other
+/// entries without recompressing them, and then replaces the old archive
+/// with it. Like with the [`PathBackend`](crate::backend::PathBackend), the
+/// archive is not corrupted if the program crashes during a save. The entry
+/// keeps its position in the archive, a new entry is appended.
+///
+/// **Important**: This backend is only available with the `zip` feature.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::backend::{Backend, ZipEntryBackend};
+///
+/// # fn main() -> rustbreak::error::BackendResult<()> {
+/// # let dir = tempfile::tempdir()?;
+/// # let path = dir.path().join("project.zip");
+/// let mut settings = ZipEntryBackend::new(&path, "settings.ron");
+/// let mut notes = ZipEntryBackend::new(&path, "notes.ron");
+/// settings.put_data(b"(theme: \"dark\")")?;
+/// notes.put_data(b"[]")?;
+/// assert_eq!(settings.get_data()?, b"(theme: \"dark\")");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ZipEntryBackend {
+    archive_path: PathBuf,
+    entry_name: String,
+    compression: CompressionMethod,
+}
+
+impl ZipEntryBackend {
+    /// Store the data in the entry `entry_name` of the archive at
+    /// `archive_path`.
+    pub fn new<P: AsRef<Path>, N: Into<String>>(archive_path: P, entry_name: N) -> Self {
+        Self {
+            archive_path: archive_path.as_ref().to_path_buf(),
+            entry_name: entry_name.into(),
+            compression: CompressionMethod::Deflated,
+        }
+    }
+
+    /// Compress the entry with `compression` instead of Deflate.
+    #[must_use]
+    pub fn with_compression(mut self, compression: CompressionMethod) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// The path of the archive.
+    #[must_use]
+    pub fn archive_path(&self) -> &Path {
+        &self.archive_path
+    }
+
+    /// The name of the entry the data is stored in.
+    #[must_use]
+    pub fn entry_name(&self) -> &str {
+        &self.entry_name
+    }
+
+    /// Open the archive, `None` if it does not exist.
+    fn open(&self) -> error::BackendResult<Option<ZipArchive<File>>> {
+        match File::open(&self.archive_path) {
+            Ok(file) => Ok(Some(ZipArchive::new(file)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write_entry<W: Write + std::io::Seek>(
+        &self,
+        writer: &mut ZipWriter<W>,
+        data: &[u8],
+    ) -> error::BackendResult<()> {
+        let options = SimpleFileOptions::default().compression_method(self.compression);
+        writer.start_file(self.entry_name.as_str(), options)?;
+        writer.write_all(data)?;
+        Ok(())
+    }
+}
+
+impl Backend for ZipEntryBackend {
+    fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        let Some(mut archive) = self.open()? else {
+            return Ok(Vec::new());
+        };
+        let mut entry = match archive.by_name(&self.entry_name) {
+            Ok(entry) => entry,
+            Err(ZipError::FileNotFound) => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut data = Vec::with_capacity(usize::try_from(entry.size()).unwrap_or(0));
+        entry.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        let tempf = tempfile::NamedTempFile::new_in(super::path::parent_dir(&self.archive_path))?;
+        let mut writer = ZipWriter::new(tempf);
+        let mut written = false;
+        if let Some(mut archive) = self.open()? {
+            for i in 0..archive.len() {
+                let entry = archive.by_index_raw(i)?;
+                if entry.name() == self.entry_name {
+                    drop(entry);
+                    self.write_entry(&mut writer, data)?;
+                    written = true;
+                } else {
+                    writer.raw_copy_file(entry)?;
+                }
+            }
+            writer.set_raw_comment(archive.comment().into());
+        }
+        if !written {
+            self.write_entry(&mut writer, data)?;
+        }
+        let tempf = writer.finish()?;
+        tempf.as_file().sync_all()?;
+        tempf.persist(&self.archive_path)?;
+        Ok(())
+    }
+
+    /// The CRC32 and size of the entry, read from the central directory of
+    /// the archive.
+    fn fingerprint(&mut self) -> error::BackendResult<Option<Fingerprint>> {
+        let Some(mut archive) = self.open()? else {
+            return Ok(Some(Fingerprint::from_contents(&[])));
+        };
+        let Some(index) = archive.index_for_name(&self.entry_name) else {
+            return Ok(Some(Fingerprint::from_contents(&[])));
+        };
+        let entry = archive.by_index_raw(index)?;
+        let mut contents = entry.crc32().to_le_bytes().to_vec();
+        contents.extend_from_slice(&entry.size().to_le_bytes());
+        Ok(Some(Fingerprint::from_contents(&contents)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ZipEntryBackend;
+    use crate::backend::Backend;
+    use std::io::{Read, Write};
+    use zip::write::SimpleFileOptions;
+    use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn zip_entry_keeps_other_entries() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let path = dir.path().join("bundle.zip");
+        let mut writer = ZipWriter::new(std::fs::File::create(&path).unwrap());
+        for name in &["first.txt", "db.ron", "last.txt"] {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(name.as_bytes()).unwrap();
+        }
+        writer.set_raw_comment(b"bundle"[..].into());
+        writer.finish().unwrap();
+
+        let mut backend = ZipEntryBackend::new(&path, "db.ron");
+        assert_eq!(backend.get_data().expect("could not get"), b"db.ron");
+        let fingerprint = backend.fingerprint().expect("could not get fingerprint");
+        backend.put_data(b"(a: 1)").expect("could not put");
+        assert_eq!(backend.get_data().expect("could not get"), b"(a: 1)");
+        assert_ne!(
+            backend.fingerprint().expect("could not get fingerprint"),
+            fingerprint
+        );
+
+        let mut other =
+            ZipEntryBackend::new(&path, "new.ron").with_compression(CompressionMethod::Stored);
+        assert_eq!(other.get_data().expect("could not get"), b"");
+        other.put_data(b"new").expect("could not put");
+
+        let mut archive = ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let names: Vec<_> = archive.file_names().collect();
+        assert_eq!(names, ["first.txt", "db.ron", "last.txt", "new.ron"]);
+        assert_eq!(archive.comment(), b"bundle");
+        let mut text = String::new();
+        archive
+            .by_name("last.txt")
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "last.txt");
+        assert_eq!(
+            archive.by_name("new.ron").unwrap().compression(),
+            CompressionMethod::Stored
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn zip_entry_creates_the_archive() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let mut backend = ZipEntryBackend::new(dir.path().join("new.zip"), "db");
+        assert_eq!(backend.get_data().expect("could not get"), b"");
+        backend.put_data(b"data").expect("could not put");
+        assert_eq!(backend.get_data().expect("could not get"), b"data");
+    }
+}
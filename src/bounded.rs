@@ -0,0 +1,394 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Data that stays within a size limit by evicting entries.
+//!
+//! A [`BoundedMap`] holds at most a fixed number of entries, evicting the
+//! least recently used or the oldest one to make room. Data implementing
+//! [`Evict`] can also be kept below a limit on its serialized size with
+//! [`Database::save_evicting`](crate::Database::save_evicting).
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::de::{Deserialize, Deserializer, Error as _};
+use serde::ser::{Serialize, SerializeTuple, Serializer};
+
+/// Data that can give up parts of itself to get smaller.
+pub trait Evict {
+    /// Remove one part of the data, the one that is least needed.
+    ///
+    /// Returns `false` if there is nothing left to remove.
+    fn evict(&mut self) -> bool;
+}
+
+/// Which entry a [`BoundedMap`] evicts first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// The entry that was least recently inserted or read.
+    Lru,
+    /// The entry that was inserted first.
+    Fifo,
+}
+
+impl EvictionPolicy {
+    fn name(self) -> &'static str {
+        match self {
+            EvictionPolicy::Lru => "lru",
+            EvictionPolicy::Fifo => "fifo",
+        }
+    }
+}
+
+struct Slot<V> {
+    value: V,
+    inserted: u64,
+    /// Updated by reads, which only borrow the map.
+    used: AtomicU64,
+}
+
+/// A `HashMap` with a maximum number of entries.
+///
+/// Inserting a new key into a full map evicts an entry first, chosen by the
+/// [`EvictionPolicy`]. Finding that entry looks at all entries, so this is
+/// meant for maps of up to some thousand entries, like caches on small
+/// devices.
+///
+/// The capacity and the policy are saved with the entries, so a loaded map
+/// keeps them, and the entries keep their order.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::bounded::{BoundedMap, EvictionPolicy};
+/// use rustbreak::{deser::Ron, MemoryDatabase};
+///
+/// # fn main() -> rustbreak::error::Result<()> {
+/// let db = MemoryDatabase::<BoundedMap<u32, String>, Ron>::memory(BoundedMap::with_capacity(
+///     2,
+///     EvictionPolicy::Lru,
+/// ))?;
+/// db.write(|cache| {
+///     cache.insert(1, "one".to_owned());
+///     cache.insert(2, "two".to_owned());
+///     cache.get(&1);
+///     // Evicts 2, which was used least recently
+///     cache.insert(3, "three".to_owned());
+/// })?;
+/// assert!(!db.read(|cache| cache.contains_key(&2))?);
+/// # Ok(())
+/// # }
+/// ```
+pub struct BoundedMap<K, V> {
+    entries: HashMap<K, Slot<V>>,
+    capacity: Option<usize>,
+    policy: EvictionPolicy,
+    clock: AtomicU64,
+}
+
+impl<K, V> BoundedMap<K, V> {
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+impl<K: Eq + Hash, V> BoundedMap<K, V> {
+    /// An empty map without a capacity, which only evicts through
+    /// [`Evict::evict`].
+    #[must_use]
+    pub fn new(policy: EvictionPolicy) -> Self {
+        Self {
+            entries: HashMap::new(),
+            capacity: None,
+            policy,
+            clock: AtomicU64::new(0),
+        }
+    }
+
+    /// An empty map holding at most `capacity` entries.
+    #[must_use]
+    pub fn with_capacity(capacity: usize, policy: EvictionPolicy) -> Self {
+        Self {
+            capacity: Some(capacity),
+            ..Self::new(policy)
+        }
+    }
+
+    /// The maximum number of entries.
+    #[must_use]
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    /// Change the maximum number of entries, evicting entries until the map
+    /// fits.
+    pub fn set_capacity(&mut self, capacity: Option<usize>) {
+        self.capacity = capacity;
+        if let Some(capacity) = capacity {
+            while self.entries.len() > capacity && self.evict() {}
+        }
+    }
+
+    /// The eviction policy.
+    #[must_use]
+    pub fn policy(&self) -> EvictionPolicy {
+        self.policy
+    }
+
+    /// Insert an entry, returning the value it replaced.
+    ///
+    /// If the key is new and the map is full, an entry is evicted first. A
+    /// map with a capacity of zero stays empty.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if !self.entries.contains_key(&key) {
+            match self.capacity {
+                Some(0) => return None,
+                Some(capacity) if self.entries.len() >= capacity => {
+                    self.evict();
+                }
+                _ => {}
+            }
+        }
+        let tick = self.tick();
+        let slot = Slot {
+            value,
+            inserted: tick,
+            used: AtomicU64::new(tick),
+        };
+        self.entries.insert(key, slot).map(|old| old.value)
+    }
+
+    /// Get the value of `key`, counting as a use for
+    /// [`EvictionPolicy::Lru`].
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let slot = self.entries.get(key)?;
+        slot.used.store(self.tick(), Ordering::Relaxed);
+        Some(&slot.value)
+    }
+
+    /// Get the value of `key` mutably, counting as a use for
+    /// [`EvictionPolicy::Lru`].
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let tick = self.tick();
+        let slot = self.entries.get_mut(key)?;
+        *slot.used.get_mut() = tick;
+        Some(&mut slot.value)
+    }
+
+    /// Get the value of `key`, without counting as a use.
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.entries.get(key).map(|slot| &slot.value)
+    }
+
+    /// Whether there is an entry for `key`, which does not count as a use.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.entries.contains_key(key)
+    }
+
+    /// Remove the entry of `key`, returning its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.entries.remove(key).map(|slot| slot.value)
+    }
+
+    /// The number of entries.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether there are no entries.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Remove all entries.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Iterate over the entries, in arbitrary order, without counting as a
+    /// use.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter().map(|(key, slot)| (key, &slot.value))
+    }
+
+    /// The position of a slot in the eviction order, lowest first.
+    fn rank(&self, slot: &Slot<V>) -> u64 {
+        match self.policy {
+            EvictionPolicy::Lru => slot.used.load(Ordering::Relaxed),
+            EvictionPolicy::Fifo => slot.inserted,
+        }
+    }
+}
+
+/// Evicts the entry the [`EvictionPolicy`] picks.
+impl<K: Eq + Hash, V> Evict for BoundedMap<K, V> {
+    fn evict(&mut self) -> bool {
+        let Some(lowest) = self.entries.values().map(|slot| self.rank(slot)).min() else {
+            return false;
+        };
+        // Every tick is handed out once, so only one entry has the lowest
+        // rank. Finding it by rank avoids requiring `K: Clone`.
+        let policy = self.policy;
+        self.entries.retain(|_, slot| {
+            let rank = match policy {
+                EvictionPolicy::Lru => *slot.used.get_mut(),
+                EvictionPolicy::Fifo => slot.inserted,
+            };
+            rank != lowest
+        });
+        true
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> Clone for BoundedMap<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            entries: self
+                .entries
+                .iter()
+                .map(|(key, slot)| {
+                    let slot = Slot {
+                        value: slot.value.clone(),
+                        inserted: slot.inserted,
+                        used: AtomicU64::new(slot.used.load(Ordering::Relaxed)),
+                    };
+                    (key.clone(), slot)
+                })
+                .collect(),
+            capacity: self.capacity,
+            policy: self.policy,
+            clock: AtomicU64::new(self.clock.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for BoundedMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoundedMap")
+            .field("capacity", &self.capacity)
+            .field("policy", &self.policy)
+            .field(
+                "entries",
+                &self
+                    .entries
+                    .iter()
+                    .map(|(key, slot)| (key, &slot.value))
+                    .collect::<Vec<_>>(),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+/// Serializes a tuple of the capacity, the name of the policy, and the
+/// entries as key-value tuples, the one to be evicted first coming first.
+impl<K: Serialize + Eq + Hash, V: Serialize> Serialize for BoundedMap<K, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut entries: Vec<_> = self.entries.iter().collect();
+        entries.sort_by_key(|(_, slot)| self.rank(slot));
+        let entries: Vec<_> = entries
+            .into_iter()
+            .map(|(key, slot)| (key, &slot.value))
+            .collect();
+        let mut tuple = serializer.serialize_tuple(3)?;
+        tuple.serialize_element(&self.capacity)?;
+        tuple.serialize_element(self.policy.name())?;
+        tuple.serialize_element(&entries)?;
+        tuple.end()
+    }
+}
+
+impl<'de, K, V> Deserialize<'de> for BoundedMap<K, V>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    V: Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (capacity, policy, entries) =
+            <(Option<usize>, String, Vec<(K, V)>)>::deserialize(deserializer)?;
+        let policy = match policy.as_str() {
+            "lru" => EvictionPolicy::Lru,
+            "fifo" => EvictionPolicy::Fifo,
+            other => {
+                return Err(D::Error::unknown_variant(other, &["lru", "fifo"]));
+            }
+        };
+        let mut map = Self::new(policy);
+        for (key, value) in entries {
+            map.insert(key, value);
+        }
+        map.set_capacity(capacity);
+        Ok(map)
+    }
+}
+
+#[cfg(all(test, feature = "ron_enc"))]
+mod tests {
+    use super::{BoundedMap, Evict, EvictionPolicy};
+    use crate::deser::{DeSerializer, Ron};
+
+    #[test]
+    fn policies_pick_the_right_entry() {
+        let mut lru = BoundedMap::with_capacity(2, EvictionPolicy::Lru);
+        let mut fifo = BoundedMap::with_capacity(2, EvictionPolicy::Fifo);
+        for map in [&mut lru, &mut fifo] {
+            map.insert(1, 'a');
+            map.insert(2, 'b');
+            assert_eq!(map.get(&1), Some(&'a'));
+            map.insert(3, 'c');
+            assert_eq!(map.len(), 2);
+        }
+        assert!(lru.contains_key(&1) && !lru.contains_key(&2));
+        assert!(!fifo.contains_key(&1) && fifo.contains_key(&2));
+
+        assert!(fifo.evict());
+        assert!(fifo.evict());
+        assert!(!fifo.evict());
+        assert!(fifo.is_empty());
+    }
+
+    #[test]
+    fn order_and_capacity_survive_a_save() {
+        let mut map = BoundedMap::with_capacity(3, EvictionPolicy::Lru);
+        map.insert("a".to_owned(), 1);
+        map.insert("b".to_owned(), 2);
+        map.insert("c".to_owned(), 3);
+        map.get("a");
+
+        let bytes = Ron::default().serialize(&map).expect("could not serialize");
+        let mut loaded: BoundedMap<String, u32> = Ron::default()
+            .deserialize(&bytes[..])
+            .expect("could not deserialize");
+        assert_eq!(loaded.capacity(), Some(3));
+        assert_eq!(loaded.policy(), EvictionPolicy::Lru);
+        loaded.insert("d".to_owned(), 4);
+        assert!(!loaded.contains_key("b"), "b was used least recently");
+        assert!(loaded.contains_key("a"));
+    }
+}
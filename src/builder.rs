@@ -0,0 +1,423 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A builder for databases.
+//!
+//! Instead of picking the right one of the many constructors of
+//! [`Database`], a [`DatabaseBuilder`] puts the backend, what to do if there
+//! is no data yet, the `DeSer` and the other options together in one chain.
+//!
+//! # Example
+//!
+//! ```rust
+//! # extern crate rustbreak;
+//! # extern crate tempfile;
+//! use rustbreak::autosave::AutoSavePolicy;
+//! use rustbreak::backend::SyncPolicy;
+//! use rustbreak::deser::Ron;
+//! use rustbreak::{DatabaseBuilder, PathDatabase};
+//!
+//! # fn main() -> rustbreak::error::Result<()> {
+//! # let dir = tempfile::tempdir().unwrap();
+//! # let path = dir.path().join("state").join("db.ron");
+//! let db: PathDatabase<Vec<String>, Ron> = DatabaseBuilder::new()
+//!     .path(path)
+//!     .create_dirs()
+//!     .locked()
+//!     .sync_policy(SyncPolicy::Never)
+//!     .or_default()
+//!     .autosave(AutoSavePolicy::AfterEveryWrite)
+//!     .build()?;
+//!
+//! db.write(|names| names.push(String::from("Ferris")))?; // Saved right away
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::autosave::AutoSavePolicy;
+use crate::backend::{self, Backend, FileBackend, MemoryBackend, PathBackend, SyncPolicy};
+use crate::error::{self, BackendError};
+use crate::{Database, DeSerializer};
+
+/// The options of a [`DatabaseBuilder`] that backends use.
+#[derive(Debug, Clone, Default)]
+pub struct BackendOptions {
+    /// Fail instead of creating the file, since there is no initial data.
+    must_exist: bool,
+    locked: bool,
+    create_dirs: bool,
+    sync: Option<SyncPolicy>,
+}
+
+impl BackendOptions {
+    /// Whether the backend has to fail if there is no data yet, instead of
+    /// creating it.
+    #[must_use]
+    pub fn must_exist(&self) -> bool {
+        self.must_exist
+    }
+
+    /// Whether a lock should be taken, see [`DatabaseBuilder::locked`].
+    #[must_use]
+    pub fn locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Whether missing directories should be created, see
+    /// [`DatabaseBuilder::create_dirs`].
+    #[must_use]
+    pub fn create_dirs(&self) -> bool {
+        self.create_dirs
+    }
+
+    /// The sync policy, if one was chosen with
+    /// [`DatabaseBuilder::sync_policy`].
+    #[must_use]
+    pub fn sync_policy(&self) -> Option<SyncPolicy> {
+        self.sync
+    }
+}
+
+/// Where a [`DatabaseBuilder`] stores the data.
+///
+/// This is implemented by the targets chosen with
+/// [`DatabaseBuilder::path`], [`DatabaseBuilder::file`] and
+/// [`DatabaseBuilder::memory`].
+pub trait BuildBackend {
+    /// The backend this target opens.
+    type Backend: Backend;
+
+    /// Open the backend, returns it and whether it already contains data.
+    fn open(self, options: &BackendOptions) -> error::BackendResult<(Self::Backend, bool)>;
+}
+
+/// Store the data in a [`PathBackend`], see [`DatabaseBuilder::path`].
+#[derive(Debug)]
+pub struct PathTarget(PathBuf);
+
+impl BuildBackend for PathTarget {
+    type Backend = PathBackend;
+
+    fn open(self, options: &BackendOptions) -> error::BackendResult<(PathBackend, bool)> {
+        if options.must_exist && !self.0.is_file() {
+            return Err(not_found());
+        }
+        if options.create_dirs {
+            backend::create_parent_dirs(&self.0)?;
+        }
+        let (backend, exists) = if options.locked {
+            PathBackend::from_path_locked(self.0)?
+        } else {
+            PathBackend::from_path_or_create(self.0)?
+        };
+        let backend = match options.sync {
+            Some(policy) => backend.with_sync_policy(policy),
+            None => backend,
+        };
+        Ok((backend, exists))
+    }
+}
+
+/// Store the data in a [`FileBackend`], see [`DatabaseBuilder::file`].
+#[derive(Debug)]
+pub struct FileTarget(PathBuf);
+
+impl BuildBackend for FileTarget {
+    type Backend = FileBackend;
+
+    fn open(self, options: &BackendOptions) -> error::BackendResult<(FileBackend, bool)> {
+        if options.must_exist && !self.0.is_file() {
+            return Err(not_found());
+        }
+        if options.create_dirs {
+            backend::create_parent_dirs(&self.0)?;
+        }
+        let (backend, exists) = if options.locked {
+            FileBackend::from_path_locked(self.0)?
+        } else {
+            FileBackend::from_path_or_create(self.0)?
+        };
+        let backend = match options.sync {
+            Some(policy) => backend.with_sync_policy(policy),
+            None => backend,
+        };
+        Ok((backend, exists))
+    }
+}
+
+/// Store the data in a [`MemoryBackend`], see [`DatabaseBuilder::memory`].
+#[derive(Debug)]
+pub struct MemoryTarget;
+
+impl BuildBackend for MemoryTarget {
+    type Backend = MemoryBackend;
+
+    fn open(self, _options: &BackendOptions) -> error::BackendResult<(MemoryBackend, bool)> {
+        Ok((MemoryBackend::new(), false))
+    }
+}
+
+fn not_found() -> BackendError {
+    BackendError::Io(std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        "there is no data yet and no initial data was given",
+    ))
+}
+
+/// No backend was chosen yet.
+#[derive(Debug)]
+pub struct NoTarget;
+
+/// What to do if there is no data yet.
+enum Init<Data> {
+    Fail,
+    With(Box<dyn FnOnce() -> Data>),
+}
+
+/// Builds a [`Database`], see the [module documentation](self).
+///
+/// A backend has to be chosen before the database can be built. If there is
+/// no data yet, building fails unless [`DatabaseBuilder::or`],
+/// [`DatabaseBuilder::or_else`] or [`DatabaseBuilder::or_default`] was
+/// used, in which case the data is initialised and saved.
+pub struct DatabaseBuilder<Data, DeSer, Target = NoTarget> {
+    target: Target,
+    deser: Option<DeSer>,
+    init: Init<Data>,
+    options: BackendOptions,
+    autosave: AutoSavePolicy,
+    _data: PhantomData<fn() -> Data>,
+}
+
+impl<Data, DeSer> DatabaseBuilder<Data, DeSer, NoTarget> {
+    /// Start building a database.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            target: NoTarget,
+            deser: None,
+            init: Init::Fail,
+            options: BackendOptions::default(),
+            autosave: AutoSavePolicy::default(),
+            _data: PhantomData,
+        }
+    }
+}
+
+impl<Data, DeSer> Default for DatabaseBuilder<Data, DeSer, NoTarget> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Data, DeSer, Target> DatabaseBuilder<Data, DeSer, Target> {
+    fn with_target<T>(self, target: T) -> DatabaseBuilder<Data, DeSer, T> {
+        DatabaseBuilder {
+            target,
+            deser: self.deser,
+            init: self.init,
+            options: self.options,
+            autosave: self.autosave,
+            _data: PhantomData,
+        }
+    }
+
+    /// Store the data at `path` with a [`PathBackend`], building a
+    /// [`PathDatabase`](crate::PathDatabase).
+    #[must_use]
+    pub fn path<P: Into<PathBuf>>(self, path: P) -> DatabaseBuilder<Data, DeSer, PathTarget> {
+        self.with_target(PathTarget(path.into()))
+    }
+
+    /// Store the data at `path` with a [`FileBackend`], building a
+    /// [`FileDatabase`](crate::FileDatabase).
+    #[must_use]
+    pub fn file<P: Into<PathBuf>>(self, path: P) -> DatabaseBuilder<Data, DeSer, FileTarget> {
+        self.with_target(FileTarget(path.into()))
+    }
+
+    /// Store the data in memory, building a
+    /// [`MemoryDatabase`](crate::MemoryDatabase).
+    ///
+    /// There is never any data yet, so the initial data has to be given.
+    #[must_use]
+    pub fn memory(self) -> DatabaseBuilder<Data, DeSer, MemoryTarget> {
+        self.with_target(MemoryTarget)
+    }
+
+    /// Use this `DeSer`, instead of its default value.
+    ///
+    /// This is needed for `DeSer`s that can not be used with their default,
+    /// like [`Encrypted`](crate::deser::Encrypted).
+    #[must_use]
+    pub fn deser<T>(self, deser: T) -> DatabaseBuilder<Data, T, Target> {
+        DatabaseBuilder {
+            target: self.target,
+            deser: Some(deser),
+            init: self.init,
+            options: self.options,
+            autosave: self.autosave,
+            _data: PhantomData,
+        }
+    }
+
+    /// Initialise with `data` if there is no data yet.
+    #[must_use]
+    pub fn or(self, data: Data) -> Self
+    where
+        Data: 'static,
+    {
+        self.or_else(move || data)
+    }
+
+    /// Initialise with the result of `closure` if there is no data yet.
+    #[must_use]
+    pub fn or_else<C>(mut self, closure: C) -> Self
+    where
+        C: FnOnce() -> Data + 'static,
+    {
+        self.init = Init::With(Box::new(closure));
+        self
+    }
+
+    /// Initialise with `Data::default()` if there is no data yet.
+    #[must_use]
+    pub fn or_default(self) -> Self
+    where
+        Data: Default + 'static,
+    {
+        self.or_else(Data::default)
+    }
+
+    /// Take an exclusive advisory lock on the file, see
+    /// [`PathBackend::from_path_locked`] and
+    /// [`FileBackend::from_path_locked`].
+    #[must_use]
+    pub fn locked(mut self) -> Self {
+        self.options.locked = true;
+        self
+    }
+
+    /// Create the directories leading to the file if they don't yet exist.
+    #[must_use]
+    pub fn create_dirs(mut self) -> Self {
+        self.options.create_dirs = true;
+        self
+    }
+
+    /// Choose when saves are synced to disk.
+    #[must_use]
+    pub fn sync_policy(mut self, policy: SyncPolicy) -> Self {
+        self.options.sync = Some(policy);
+        self
+    }
+
+    /// Set the auto-save policy of the database.
+    ///
+    /// Policies that need a background thread still need
+    /// [`Database::start_autosave`] to be called.
+    #[must_use]
+    pub fn autosave(mut self, policy: AutoSavePolicy) -> Self {
+        self.autosave = policy;
+        self
+    }
+}
+
+impl<Data, DeSer, Target> DatabaseBuilder<Data, DeSer, Target>
+where
+    Data: Serialize + DeserializeOwned + Send,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+    Target: BuildBackend,
+{
+    /// Open the backend, load or initialise the data, and build the
+    /// database.
+    ///
+    /// # Errors
+    ///
+    /// Besides the errors of the backend and the `DeSer`, this fails with a
+    /// [`BackendError::Io`] of kind
+    /// [`NotFound`](std::io::ErrorKind::NotFound) if there is no data yet and
+    /// no initial data was given.
+    pub fn build(mut self) -> error::Result<Database<Data, Target::Backend, DeSer>> {
+        self.options.must_exist = matches!(self.init, Init::Fail);
+        let (mut backend, exists) = self.target.open(&self.options)?;
+        let deser = self.deser.unwrap_or_default();
+        let data = match (exists, self.init) {
+            (true, _) => Database::load_from_backend(&mut backend, &deser)?,
+            (false, Init::With(init)) => {
+                let data = init();
+                backend.put_data(&deser.serialize(&data)?)?;
+                data
+            }
+            (false, Init::Fail) => return Err(not_found().into()),
+        };
+
+        let db = Database::from_parts(data, backend, deser);
+        db.set_autosave_policy(self.autosave)?;
+        Ok(db)
+    }
+}
+
+impl<Data, DeSer: fmt::Debug, Target: fmt::Debug> fmt::Debug
+    for DatabaseBuilder<Data, DeSer, Target>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DatabaseBuilder")
+            .field("target", &self.target)
+            .field("deser", &self.deser)
+            .field("options", &self.options)
+            .field("autosave", &self.autosave)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, feature = "ron_enc"))]
+mod tests {
+    use super::DatabaseBuilder;
+    use crate::deser::Ron;
+    use crate::error::{BackendError, RustbreakError};
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn path_without_initial_data_fails() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let path = dir.path().join("db.ron");
+        let err = DatabaseBuilder::<Vec<u32>, Ron>::new().path(&path).build();
+        assert!(matches!(
+            err,
+            Err(RustbreakError::Backend(BackendError::Io(_)))
+        ));
+        assert!(!path.exists());
+
+        let db = DatabaseBuilder::<Vec<u32>, Ron>::new()
+            .path(&path)
+            .or(vec![1, 2])
+            .build()
+            .expect("could not build database");
+        drop(db);
+        let db = DatabaseBuilder::<Vec<u32>, Ron>::new()
+            .file(&path)
+            .build()
+            .expect("could not build database");
+        assert_eq!(db.get_data(false).unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn memory_with_deser() {
+        let db = DatabaseBuilder::<Vec<u32>, Ron>::new()
+            .memory()
+            .deser(Ron)
+            .or_else(|| vec![3])
+            .build()
+            .expect("could not build database");
+        assert_eq!(db.get_data(true).unwrap(), vec![3]);
+    }
+}
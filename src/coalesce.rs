@@ -0,0 +1,298 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Coalescing of concurrent saves.
+//!
+//! Every call to [`SaveCoalescer::run`] takes a ticket. A save that starts
+//! after a ticket was taken reads data that is at least as new as the data
+//! the caller saw, so once it finishes, every caller holding such a ticket
+//! can return without writing the same bytes again.
+//!
+//! [`SaveCoalescer::queue`] takes a ticket without saving, for the background
+//! writer of [`Database::queue_save`](crate::Database::queue_save). There is
+//! at most one writer, and it always saves for the newest ticket, so tickets
+//! queued while it is busy share one save.
+
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::backend::Backend;
+use crate::error::{self, RustbreakError};
+use crate::{Database, DeSerializer};
+
+#[derive(Debug, Default)]
+struct State {
+    /// The last ticket that was handed out.
+    requested: u64,
+    /// All tickets up to this one have been persisted.
+    completed: u64,
+    /// Whether a thread is currently saving.
+    in_progress: bool,
+    /// Whether the background writer is running.
+    writer: bool,
+}
+
+/// Lets concurrent callers piggyback on a save that is already running.
+#[derive(Debug, Default)]
+pub(crate) struct SaveCoalescer {
+    state: Mutex<State>,
+    done: Condvar,
+}
+
+/// Marks the running save as finished, even if it panicked.
+struct InProgress<'a> {
+    coalescer: &'a SaveCoalescer,
+    covers: u64,
+    succeeded: bool,
+}
+
+impl Drop for InProgress<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.coalescer.state.lock() {
+            state.in_progress = false;
+            if self.succeeded && state.completed < self.covers {
+                state.completed = self.covers;
+            }
+        }
+        self.coalescer.done.notify_all();
+    }
+}
+
+impl SaveCoalescer {
+    fn lock(&self) -> error::Result<MutexGuard<'_, State>> {
+        self.state.lock().map_err(|_| RustbreakError::Poison)
+    }
+
+    /// Run `save`, unless a save that started after this call has already
+    /// persisted the data.
+    ///
+    /// If a save is running, this waits for it. If it fails, the waiting
+    /// callers retry on their own so that every caller sees the outcome of a
+    /// save covering its changes.
+    pub(crate) fn run<F>(&self, save: F) -> error::Result<()>
+    where
+        F: FnOnce() -> error::Result<()>,
+    {
+        let mut state = self.lock()?;
+        state.requested += 1;
+        let ticket = state.requested;
+        self.run_locked(state, ticket, save)
+    }
+
+    /// Take a ticket for the background writer, and return whether the
+    /// caller has to start it.
+    pub(crate) fn queue(&self) -> error::Result<(u64, bool)> {
+        let mut state = self.lock()?;
+        state.requested += 1;
+        let start = !state.writer;
+        state.writer = true;
+        Ok((state.requested, start))
+    }
+
+    /// The ticket the background writer should save for next, or `None` if
+    /// every ticket is persisted and the writer should stop.
+    pub(crate) fn next_queued(&self) -> error::Result<Option<u64>> {
+        let mut state = self.lock()?;
+        if state.completed >= state.requested {
+            state.writer = false;
+            drop(state);
+            self.done.notify_all();
+            return Ok(None);
+        }
+        Ok(Some(state.requested))
+    }
+
+    /// Run `save` for a ticket taken with [`SaveCoalescer::queue`], unless a
+    /// save covering it already succeeded.
+    pub(crate) fn run_for<F>(&self, ticket: u64, save: F) -> error::Result<()>
+    where
+        F: FnOnce() -> error::Result<()>,
+    {
+        let state = self.lock()?;
+        self.run_locked(state, ticket, save)
+    }
+
+    /// Stop the background writer after a failed save.
+    pub(crate) fn stop_writer(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.writer = false;
+        }
+        self.done.notify_all();
+    }
+
+    /// Whether a save covering `ticket` succeeded.
+    pub(crate) fn is_completed(&self, ticket: u64) -> error::Result<bool> {
+        Ok(self.lock()?.completed >= ticket)
+    }
+
+    /// Wait until a save covering `ticket` succeeded.
+    ///
+    /// While the background writer runs, it is left to save. If it stops
+    /// before covering `ticket`, because a save failed, this runs `save`
+    /// itself, to report the error of a save that covers the ticket.
+    pub(crate) fn wait_for<F>(&self, ticket: u64, save: F) -> error::Result<()>
+    where
+        F: FnOnce() -> error::Result<()>,
+    {
+        let mut state = self.lock()?;
+        while state.completed < ticket && state.writer {
+            state = self.done.wait(state).map_err(|_| RustbreakError::Poison)?;
+        }
+        self.run_locked(state, ticket, save)
+    }
+
+    fn run_locked<F>(
+        &self,
+        mut state: MutexGuard<'_, State>,
+        ticket: u64,
+        save: F,
+    ) -> error::Result<()>
+    where
+        F: FnOnce() -> error::Result<()>,
+    {
+        loop {
+            if state.completed >= ticket {
+                return Ok(());
+            }
+            if !state.in_progress {
+                break;
+            }
+            state = self.done.wait(state).map_err(|_| RustbreakError::Poison)?;
+        }
+        state.in_progress = true;
+        let mut guard = InProgress {
+            coalescer: self,
+            covers: state.requested,
+            succeeded: false,
+        };
+        drop(state);
+
+        let result = save();
+        guard.succeeded = result.is_ok();
+        result
+    }
+}
+
+/// A database that can wait for the saves of its background writer, see
+/// [`SaveTicket`](crate::autosave::SaveTicket).
+pub(crate) trait AwaitSave: Send + Sync {
+    /// Whether a save covering `ticket` succeeded.
+    fn is_saved(&self, ticket: u64) -> error::Result<bool>;
+
+    /// Wait until a save covering `ticket` succeeded.
+    fn wait_for_save(&self, ticket: u64) -> error::Result<()>;
+}
+
+impl<Data, Back, DeSer> AwaitSave for Database<Data, Back, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send + Sync,
+    Back: Backend + Send,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    fn is_saved(&self, ticket: u64) -> error::Result<bool> {
+        self.saves.is_completed(ticket)
+    }
+
+    fn wait_for_save(&self, ticket: u64) -> error::Result<()> {
+        self.saves.wait_for(ticket, || self.save_now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SaveCoalescer;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Barrier};
+    use std::time::Duration;
+
+    #[test]
+    fn sequential_saves_all_run() {
+        let coalescer = SaveCoalescer::default();
+        let runs = AtomicUsize::new(0);
+        for _ in 0..3 {
+            coalescer
+                .run(|| {
+                    runs.fetch_add(1, Ordering::SeqCst);
+                    Ok(())
+                })
+                .expect("save failed");
+        }
+        assert_eq!(3, runs.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn concurrent_saves_are_coalesced() {
+        let coalescer = Arc::new(SaveCoalescer::default());
+        let runs = Arc::new(AtomicUsize::new(0));
+        let threads = 8;
+        let barrier = Arc::new(Barrier::new(threads));
+
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                let coalescer = Arc::clone(&coalescer);
+                let runs = Arc::clone(&runs);
+                let barrier = Arc::clone(&barrier);
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    coalescer.run(|| {
+                        runs.fetch_add(1, Ordering::SeqCst);
+                        std::thread::sleep(Duration::from_millis(50));
+                        Ok(())
+                    })
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle
+                .join()
+                .expect("thread panicked")
+                .expect("save failed");
+        }
+
+        let runs = runs.load(Ordering::SeqCst);
+        assert!(runs >= 1 && runs < threads, "{} saves ran", runs);
+    }
+
+    #[test]
+    fn failed_save_is_not_counted() {
+        let coalescer = SaveCoalescer::default();
+        coalescer
+            .run(|| Err(crate::error::RustbreakError::WritePanic))
+            .expect_err("save should fail");
+        let runs = AtomicUsize::new(0);
+        coalescer
+            .run(|| {
+                runs.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            })
+            .expect("save failed");
+        assert_eq!(1, runs.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn queued_tickets_share_a_save() {
+        let coalescer = SaveCoalescer::default();
+        let (first, start) = coalescer.queue().expect("could not queue");
+        assert!(start);
+        let (second, start) = coalescer.queue().expect("could not queue");
+        assert!(!start, "the writer is already running");
+
+        let runs = AtomicUsize::new(0);
+        let save = || {
+            runs.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        };
+        while let Some(ticket) = coalescer.next_queued().expect("could not get ticket") {
+            coalescer.run_for(ticket, save).expect("save failed");
+        }
+        assert_eq!(1, runs.load(Ordering::SeqCst));
+        assert!(coalescer.is_completed(first).expect("poisoned"));
+        coalescer
+            .wait_for(second, || unreachable!("the ticket is saved"))
+            .expect("save failed");
+    }
+}
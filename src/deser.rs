@@ -2,19 +2,80 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 use crate::error;
-use std::io::Read;
+use std::io::{Read, Write};
 
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+mod any;
+mod boxed;
+mod canonical;
+#[cfg(any(
+    feature = "ron_enc",
+    feature = "json_enc",
+    feature = "yaml_enc",
+    feature = "safe_yaml_enc",
+    feature = "bin_enc",
+    feature = "toml_enc",
+    feature = "cbor_enc",
+    feature = "postcard_enc"
+))]
+mod format;
+
+pub use self::any::AnyDeSer;
+pub use self::boxed::{BoxedDeSer, DynDeSerializer};
+pub use self::canonical::{sorted_map, sorted_set, Canonical, CanonicalValue};
+#[cfg(any(
+    feature = "ron_enc",
+    feature = "json_enc",
+    feature = "yaml_enc",
+    feature = "safe_yaml_enc",
+    feature = "bin_enc",
+    feature = "toml_enc",
+    feature = "cbor_enc",
+    feature = "postcard_enc"
+))]
+pub use self::format::AnyFormat;
+
 #[cfg(feature = "ron_enc")]
-pub use self::ron::Ron;
+pub use self::ron::{PrettyConfig, Ron};
 
 #[cfg(feature = "yaml_enc")]
+#[allow(deprecated)]
 pub use self::yaml::Yaml;
 
+#[cfg(feature = "safe_yaml_enc")]
+pub use self::safe_yaml::SafeYaml;
+
 #[cfg(feature = "bin_enc")]
-pub use self::bincode::Bincode;
+pub use self::bincode::{Bincode, BincodeOptions};
+
+#[cfg(feature = "json_enc")]
+pub use self::json::Json;
+
+#[cfg(feature = "toml_enc")]
+pub use self::toml::Toml;
+
+#[cfg(feature = "cbor_enc")]
+pub use self::cbor::Cbor;
+
+#[cfg(feature = "postcard_enc")]
+pub use self::postcard::Postcard;
+
+#[cfg(feature = "zstd_enc")]
+pub use self::zstd::Zstd;
+
+#[cfg(feature = "gzip_enc")]
+pub use self::gzip::Gzip;
+
+#[cfg(feature = "armor")]
+pub use self::armored::Armored;
+
+#[cfg(feature = "encryption")]
+pub use self::encrypted::Encrypted;
+
+#[cfg(feature = "signing")]
+pub use self::signed::Signed;
 
 /// A trait to bundle serializer and deserializer in a simple struct
 ///
@@ -75,68 +136,246 @@ pub trait DeSerializer<T: Serialize + DeserializeOwned>:
 {
     /// Serializes a given value to a [`String`].
     fn serialize(&self, val: &T) -> error::DeSerResult<Vec<u8>>;
+    /// Serializes a given value into `writer`.
+    ///
+    /// The default implementation writes the output of
+    /// [`DeSerializer::serialize`]. Encodings that can write incrementally
+    /// should override it, so that saving does not need to hold the whole
+    /// serialized data in memory.
+    fn serialize_into<W: Write>(&self, val: &T, mut writer: W) -> error::DeSerResult<()> {
+        writer.write_all(&self.serialize(val)?)?;
+        Ok(())
+    }
     /// Deserializes a [`String`] to a value.
     fn deserialize<R: Read>(&self, s: R) -> error::DeSerResult<T>;
 }
 
 #[cfg(feature = "ron_enc")]
 mod ron {
-    use std::io::Read;
+    use std::io::{Read, Write};
 
     use serde::de::DeserializeOwned;
     use serde::Serialize;
 
     use ron::de::from_reader as from_ron_string;
-    use ron::ser::to_string_pretty as to_ron_string;
-    use ron::ser::PrettyConfig;
+    use ron::ser::Serializer;
+
+    pub use ron::ser::PrettyConfig;
 
     use crate::deser::DeSerializer;
     use crate::error;
 
+    /// How [`Ron`] formats its output.
+    #[derive(Debug, Clone)]
+    enum Style {
+        /// Pretty printed with `PrettyConfig::default()`.
+        DefaultPretty,
+        Pretty(PrettyConfig),
+        Compact,
+    }
+
     /// The Struct that allows you to use `ron` the Rusty Object Notation.
-    #[derive(Debug, Default, Clone)]
-    pub struct Ron;
+    ///
+    /// By default the output is pretty printed with the default
+    /// [`PrettyConfig`]. Use [`Ron::with_pretty_config`] to change how, or
+    /// [`Ron::compact`] to get the smallest possible output instead, which
+    /// is about half the size for deeply nested data.
+    ///
+    /// `Ron` is also a constant holding the default, so it can be passed as
+    /// `Ron`:
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// use rustbreak::deser::{PrettyConfig, Ron};
+    /// use rustbreak::MemoryDatabase;
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// let db = MemoryDatabase::<Vec<u32>, Ron>::memory(vec![1, 2])?;
+    /// let db = db.with_deser(Ron::compact());
+    /// db.save()?;
+    ///
+    /// let config = PrettyConfig::new().with_indentor("\t".to_string());
+    /// let db = db.with_deser(Ron::with_pretty_config(config).with_struct_names(true));
+    /// db.save()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[derive(Debug, Clone)]
+    pub struct Ron {
+        style: Style,
+        struct_names: bool,
+    }
+
+    /// The default [`Ron`](struct@Ron), pretty printed with the default [`PrettyConfig`].
+    #[allow(non_upper_case_globals)]
+    pub const Ron: Ron = Ron {
+        style: Style::DefaultPretty,
+        struct_names: false,
+    };
+
+    impl Ron {
+        /// Pretty print the output with `config`.
+        #[must_use]
+        pub fn with_pretty_config(config: PrettyConfig) -> Self {
+            Self {
+                style: Style::Pretty(config),
+                ..Ron
+            }
+        }
+
+        /// Write the output without any whitespace.
+        #[must_use]
+        pub fn compact() -> Self {
+            Self {
+                style: Style::Compact,
+                ..Ron
+            }
+        }
+
+        /// Write the names of structs in front of them, like `Point(x: 1)`
+        /// instead of `(x: 1)`. Disabled by default.
+        #[must_use]
+        pub fn with_struct_names(mut self, struct_names: bool) -> Self {
+            self.struct_names = struct_names;
+            self
+        }
+
+        fn pretty_config(&self) -> Option<PrettyConfig> {
+            match &self.style {
+                Style::DefaultPretty => Some(PrettyConfig::default()),
+                Style::Pretty(config) => Some(config.clone()),
+                Style::Compact => None,
+            }
+        }
+    }
+
+    impl Default for Ron {
+        fn default() -> Self {
+            Ron
+        }
+    }
 
     impl<T: Serialize + DeserializeOwned> DeSerializer<T> for Ron {
         fn serialize(&self, val: &T) -> error::DeSerResult<Vec<u8>> {
-            Ok(to_ron_string(val, PrettyConfig::default()).map(String::into_bytes)?)
+            let mut buf = Vec::new();
+            self.serialize_into(val, &mut buf)?;
+            Ok(buf)
+        }
+        fn serialize_into<W: Write>(&self, val: &T, writer: W) -> error::DeSerResult<()> {
+            let mut serializer = Serializer::new(writer, self.pretty_config(), self.struct_names)?;
+            Ok(val.serialize(&mut serializer)?)
         }
         fn deserialize<R: Read>(&self, s: R) -> error::DeSerResult<T> {
             Ok(from_ron_string(s)?)
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{PrettyConfig, Ron};
+        use crate::deser::DeSerializer;
+
+        #[test]
+        fn compact_and_pretty_config() {
+            let data = vec![(1_u32, "one".to_string())];
+            let compact = Ron::compact().serialize(&data).unwrap();
+            assert_eq!(compact, br#"[(1,"one")]"#);
+
+            let config = PrettyConfig::new().with_indentor("\t".to_string());
+            let pretty = Ron::with_pretty_config(config).serialize(&data).unwrap();
+            assert!(pretty.starts_with(b"[\n\t("));
+            assert_ne!(pretty, Ron.serialize(&data).unwrap());
+
+            let back: Vec<(u32, String)> = Ron::compact().deserialize(pretty.as_slice()).unwrap();
+            assert_eq!(back, data);
+
+            let named = Ron::compact().with_struct_names(true);
+            let point = named.serialize(&Point { x: 1 }).unwrap();
+            assert_eq!(point, b"Point(x:1)");
+        }
+
+        #[derive(Debug, serde::Serialize, serde::Deserialize)]
+        struct Point {
+            x: u32,
+        }
+    }
 }
 
 #[cfg(feature = "yaml_enc")]
 mod yaml {
-    use std::io::Read;
+    use std::io::{Read, Write};
 
     use serde::de::DeserializeOwned;
     use serde::Serialize;
-    use serde_yaml::{from_reader as from_yaml_string, to_string as to_yaml_string};
+    use serde_yaml::{from_reader as from_yaml_string, to_string as to_yaml_string, to_writer};
 
     use crate::deser::DeSerializer;
     use crate::error;
 
     /// The struct that allows you to use yaml.
+    ///
+    /// It uses `serde_yaml` 0.8, which is no longer maintained and depends on
+    /// crates with known soundness issues. Use [`SafeYaml`] instead, which
+    /// reads and writes the same files.
+    ///
+    /// [`SafeYaml`]: crate::deser::SafeYaml
+    #[deprecated(
+        note = "serde_yaml 0.8 is unmaintained, use `SafeYaml` with the `safe_yaml_enc` feature"
+    )]
     #[derive(Debug, Default, Clone)]
     pub struct Yaml;
 
+    #[allow(deprecated)]
     impl<T: Serialize + DeserializeOwned> DeSerializer<T> for Yaml {
         fn serialize(&self, val: &T) -> error::DeSerResult<Vec<u8>> {
             Ok(to_yaml_string(val).map(String::into_bytes)?)
         }
+        fn serialize_into<W: Write>(&self, val: &T, writer: W) -> error::DeSerResult<()> {
+            Ok(to_writer(writer, val)?)
+        }
         fn deserialize<R: Read>(&self, s: R) -> error::DeSerResult<T> {
             Ok(from_yaml_string(s)?)
         }
     }
 }
 
+#[cfg(feature = "safe_yaml_enc")]
+mod safe_yaml {
+    use std::io::{Read, Write};
+
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use serde_yaml_ng::{from_reader, to_string, to_writer};
+
+    use crate::deser::DeSerializer;
+    use crate::error;
+
+    /// The struct that allows you to use yaml, with the maintained
+    /// `serde_yaml_ng`.
+    ///
+    /// The output is the same as that of the deprecated `Yaml`, so switching
+    /// to it keeps existing files readable.
+    #[derive(Debug, Default, Clone)]
+    pub struct SafeYaml;
+
+    impl<T: Serialize + DeserializeOwned> DeSerializer<T> for SafeYaml {
+        fn serialize(&self, val: &T) -> error::DeSerResult<Vec<u8>> {
+            Ok(to_string(val).map(String::into_bytes)?)
+        }
+        fn serialize_into<W: Write>(&self, val: &T, writer: W) -> error::DeSerResult<()> {
+            Ok(to_writer(writer, val)?)
+        }
+        fn deserialize<R: Read>(&self, s: R) -> error::DeSerResult<T> {
+            Ok(from_reader(s)?)
+        }
+    }
+}
+
 #[cfg(feature = "bin_enc")]
 mod bincode {
-    use std::io::Read;
+    use std::io::{Read, Write};
 
-    use bincode::{deserialize_from, serialize};
+    use bincode::{DefaultOptions, Options};
     use serde::de::DeserializeOwned;
     use serde::Serialize;
 
@@ -144,15 +383,958 @@ mod bincode {
     use crate::error;
 
     /// The struct that allows you to use bincode
+    ///
+    /// By default it uses the options of `bincode::serialize`, use
+    /// [`Bincode::with_options`] to pick others, or [`Bincode::with_limit`]
+    /// to limit how large the data may be.
     #[derive(Debug, Default, Clone)]
-    pub struct Bincode;
+    pub struct Bincode {
+        options: BincodeOptions,
+    }
+
+    impl Bincode {
+        /// Fail to serialize or deserialize data that is larger than `bytes`.
+        ///
+        /// The limit is checked while deserializing, before anything is
+        /// allocated, so a corrupted or hostile length prefix can not make
+        /// the database allocate more than `bytes`. Fails with
+        /// [`bincode::ErrorKind::SizeLimit`].
+        #[must_use]
+        pub fn with_limit(bytes: u64) -> Self {
+            Self::with_options(BincodeOptions::new().with_limit(bytes))
+        }
+
+        /// Use the given options.
+        ///
+        /// Data has to be loaded with the options it was saved with.
+        ///
+        /// ```rust
+        /// # extern crate rustbreak;
+        /// use rustbreak::deser::{Bincode, BincodeOptions};
+        ///
+        /// let deser = Bincode::with_options(
+        ///     BincodeOptions::new()
+        ///         .with_varint_encoding()
+        ///         .with_big_endian()
+        ///         .with_limit(1 << 20),
+        /// );
+        /// ```
+        #[must_use]
+        pub fn with_options(options: BincodeOptions) -> Self {
+            Self { options }
+        }
+
+        /// The options in use.
+        #[must_use]
+        pub fn options(&self) -> BincodeOptions {
+            self.options
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum IntEncoding {
+        Fixint,
+        Varint,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Endian {
+        Little,
+        Big,
+        Native,
+    }
+
+    /// The options of [`Bincode`], mirroring [`bincode::Options`].
+    ///
+    /// The defaults are those of `bincode::serialize`: integers are encoded
+    /// with a fixed size in little endian, there is no size limit, and
+    /// trailing bytes are allowed. Note that this differs from
+    /// [`bincode::DefaultOptions`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct BincodeOptions {
+        int_encoding: IntEncoding,
+        endian: Endian,
+        limit: Option<u64>,
+        allow_trailing_bytes: bool,
+    }
+
+    impl BincodeOptions {
+        /// The options of `bincode::serialize`.
+        #[must_use]
+        pub fn new() -> Self {
+            Self {
+                int_encoding: IntEncoding::Fixint,
+                endian: Endian::Little,
+                limit: None,
+                allow_trailing_bytes: true,
+            }
+        }
+
+        /// Encode integers with a variable length, small values take fewer
+        /// bytes.
+        #[must_use]
+        pub fn with_varint_encoding(mut self) -> Self {
+            self.int_encoding = IntEncoding::Varint;
+            self
+        }
+
+        /// Encode integers with their full size, this is the default. The
+        /// layout then only depends on the types, which keeps it stable
+        /// across versions of your data that only append fields.
+        #[must_use]
+        pub fn with_fixint_encoding(mut self) -> Self {
+            self.int_encoding = IntEncoding::Fixint;
+            self
+        }
+
+        /// Use little endian byte order, this is the default.
+        #[must_use]
+        pub fn with_little_endian(mut self) -> Self {
+            self.endian = Endian::Little;
+            self
+        }
+
+        /// Use big endian byte order.
+        #[must_use]
+        pub fn with_big_endian(mut self) -> Self {
+            self.endian = Endian::Big;
+            self
+        }
+
+        /// Use the byte order of the machine, the data can not be moved to
+        /// machines with another one.
+        #[must_use]
+        pub fn with_native_endian(mut self) -> Self {
+            self.endian = Endian::Native;
+            self
+        }
+
+        /// Fail to serialize or deserialize data larger than `bytes`, see
+        /// [`Bincode::with_limit`].
+        #[must_use]
+        pub fn with_limit(mut self, bytes: u64) -> Self {
+            self.limit = Some(bytes);
+            self
+        }
+
+        /// Do not limit the size of the data, this is the default.
+        #[must_use]
+        pub fn with_no_limit(mut self) -> Self {
+            self.limit = None;
+            self
+        }
+
+        /// Allow bytes after the data when deserializing, this is the
+        /// default.
+        #[must_use]
+        pub fn allow_trailing_bytes(mut self) -> Self {
+            self.allow_trailing_bytes = true;
+            self
+        }
+
+        /// Fail to deserialize if there are bytes after the data.
+        ///
+        /// This can only be checked on a slice, so the whole data is read
+        /// into memory before deserializing it.
+        #[must_use]
+        pub fn reject_trailing_bytes(mut self) -> Self {
+            self.allow_trailing_bytes = false;
+            self
+        }
+
+        /// Run `task` with the matching [`bincode::Options`].
+        fn apply<V: WithOptions>(self, task: V) -> V::Output {
+            let options = DefaultOptions::new();
+            match self.int_encoding {
+                IntEncoding::Fixint => self.endian(options.with_fixint_encoding(), task),
+                IntEncoding::Varint => self.endian(options.with_varint_encoding(), task),
+            }
+        }
+
+        fn endian<O: Options, V: WithOptions>(self, options: O, task: V) -> V::Output {
+            match self.endian {
+                Endian::Little => self.trailing(options.with_little_endian(), task),
+                Endian::Big => self.trailing(options.with_big_endian(), task),
+                Endian::Native => self.trailing(options.with_native_endian(), task),
+            }
+        }
+
+        fn trailing<O: Options, V: WithOptions>(self, options: O, task: V) -> V::Output {
+            if self.allow_trailing_bytes {
+                self.limit(options.allow_trailing_bytes(), task)
+            } else {
+                self.limit(options.reject_trailing_bytes(), task)
+            }
+        }
+
+        fn limit<O: Options, V: WithOptions>(self, options: O, task: V) -> V::Output {
+            match self.limit {
+                Some(limit) => task.run(options.with_limit(limit)),
+                None => task.run(options.with_no_limit()),
+            }
+        }
+    }
+
+    impl Default for BincodeOptions {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    /// Something to do with [`bincode::Options`], whose type depends on
+    /// every option.
+    trait WithOptions {
+        type Output;
+        fn run<O: Options>(self, options: O) -> Self::Output;
+    }
+
+    struct SerializeTo<'a, T, W>(&'a T, W);
+
+    impl<T: Serialize, W: Write> WithOptions for SerializeTo<'_, T, W> {
+        type Output = bincode::Result<()>;
+        fn run<O: Options>(self, options: O) -> Self::Output {
+            options.serialize_into(self.1, self.0)
+        }
+    }
+
+    struct DeserializeFrom<T, R>(R, std::marker::PhantomData<T>);
+
+    impl<T: DeserializeOwned, R: Read> WithOptions for DeserializeFrom<T, R> {
+        type Output = bincode::Result<T>;
+        fn run<O: Options>(self, options: O) -> Self::Output {
+            options.deserialize_from(self.0)
+        }
+    }
+
+    struct DeserializeSlice<'a, T>(&'a [u8], std::marker::PhantomData<T>);
+
+    impl<T: DeserializeOwned> WithOptions for DeserializeSlice<'_, T> {
+        type Output = bincode::Result<T>;
+        fn run<O: Options>(self, options: O) -> Self::Output {
+            options.deserialize(self.0)
+        }
+    }
 
     impl<T: Serialize + DeserializeOwned> DeSerializer<T> for Bincode {
         fn serialize(&self, val: &T) -> error::DeSerResult<Vec<u8>> {
-            Ok(serialize(val)?)
+            let mut buf = Vec::new();
+            self.serialize_into(val, &mut buf)?;
+            Ok(buf)
+        }
+        fn serialize_into<W: Write>(&self, val: &T, writer: W) -> error::DeSerResult<()> {
+            Ok(self.options.apply(SerializeTo(val, writer))?)
+        }
+        /// Trailing bytes can only be detected in a slice, so the data is
+        /// read into memory first if they are rejected.
+        fn deserialize<R: Read>(&self, mut s: R) -> error::DeSerResult<T> {
+            if self.options.allow_trailing_bytes {
+                return Ok(self
+                    .options
+                    .apply(DeserializeFrom(s, std::marker::PhantomData))?);
+            }
+            let mut buf = Vec::new();
+            s.read_to_end(&mut buf)?;
+            Ok(self
+                .options
+                .apply(DeserializeSlice(&buf, std::marker::PhantomData))?)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{Bincode, BincodeOptions};
+        use crate::deser::DeSerializer;
+        use crate::error::DeSerError;
+
+        #[test]
+        fn limit_rejects_large_lengths() {
+            let data = Bincode::default().serialize(&vec![1_u64; 16]).unwrap();
+            assert_eq!(data, bincode::serialize(&vec![1_u64; 16]).unwrap());
+
+            let limited = Bincode::with_limit(64);
+            let err = DeSerializer::<Vec<u64>>::deserialize(&limited, data.as_slice());
+            assert!(matches!(err, Err(DeSerError::Bincode(_))));
+            let small = limited.serialize(&vec![1_u64; 4]).unwrap();
+            let back: Vec<u64> = limited.deserialize(small.as_slice()).unwrap();
+            assert_eq!(back, vec![1; 4]);
+        }
+
+        #[test]
+        fn options_change_the_encoding() {
+            let varint = Bincode::with_options(BincodeOptions::new().with_varint_encoding());
+            assert_eq!(varint.serialize(&7_u64).unwrap(), [7]);
+            let big = Bincode::with_options(BincodeOptions::new().with_big_endian());
+            assert_eq!(big.serialize(&7_u32).unwrap(), [0, 0, 0, 7]);
+
+            let strict = Bincode::with_options(BincodeOptions::new().reject_trailing_bytes());
+            let err = DeSerializer::<u8>::deserialize(&strict, &[1_u8, 2][..]);
+            assert!(matches!(err, Err(DeSerError::Bincode(_))));
+            let lenient: u8 = Bincode::default().deserialize(&[1_u8, 2][..]).unwrap();
+            assert_eq!(lenient, 1);
+        }
+    }
+}
+
+#[cfg(feature = "json_enc")]
+mod json {
+    use std::io::{Read, Write};
+
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use serde_json::{
+        from_reader as from_json_reader, to_vec, to_vec_pretty, to_writer, to_writer_pretty,
+    };
+
+    use crate::deser::DeSerializer;
+    use crate::error;
+
+    /// The struct that allows you to use JSON.
+    ///
+    /// By default the output is pretty printed, use [`Json::compact`] to get
+    /// the smallest possible output instead.
+    #[derive(Debug, Clone)]
+    pub struct Json {
+        pretty: bool,
+    }
+
+    impl Json {
+        /// Pretty print the JSON output, this is the default.
+        #[must_use]
+        pub fn pretty() -> Self {
+            Self { pretty: true }
+        }
+
+        /// Write the JSON output without any whitespace.
+        #[must_use]
+        pub fn compact() -> Self {
+            Self { pretty: false }
+        }
+    }
+
+    impl Default for Json {
+        fn default() -> Self {
+            Self::pretty()
+        }
+    }
+
+    impl<T: Serialize + DeserializeOwned> DeSerializer<T> for Json {
+        fn serialize(&self, val: &T) -> error::DeSerResult<Vec<u8>> {
+            if self.pretty {
+                Ok(to_vec_pretty(val)?)
+            } else {
+                Ok(to_vec(val)?)
+            }
+        }
+        fn serialize_into<W: Write>(&self, val: &T, writer: W) -> error::DeSerResult<()> {
+            if self.pretty {
+                Ok(to_writer_pretty(writer, val)?)
+            } else {
+                Ok(to_writer(writer, val)?)
+            }
         }
         fn deserialize<R: Read>(&self, s: R) -> error::DeSerResult<T> {
-            Ok(deserialize_from(s)?)
+            Ok(from_json_reader(s)?)
+        }
+    }
+}
+
+#[cfg(feature = "toml_enc")]
+mod toml {
+    use std::io::Read;
+
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use toml::{from_str as from_toml_str, to_string_pretty};
+
+    use crate::deser::DeSerializer;
+    use crate::error;
+
+    /// The struct that allows you to use TOML.
+    ///
+    /// TOML documents always have a table at the top level, so the data has to
+    /// serialize as a struct or a map with string keys. This makes it a good
+    /// fit for configuration files:
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate serde_derive;
+    /// use rustbreak::{deser::Toml, MemoryDatabase};
+    ///
+    /// #[derive(Debug, Default, Clone, Serialize, Deserialize)]
+    /// struct Config {
+    ///     name: String,
+    ///     retries: u32,
+    /// }
+    ///
+    /// # fn main() -> rustbreak::Result<()> {
+    /// let db = MemoryDatabase::<Config, Toml>::memory(Config::default())?;
+    /// db.write(|config| config.retries = 3)?;
+    /// db.save()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[derive(Debug, Default, Clone)]
+    pub struct Toml;
+
+    impl<T: Serialize + DeserializeOwned> DeSerializer<T> for Toml {
+        fn serialize(&self, val: &T) -> error::DeSerResult<Vec<u8>> {
+            Ok(to_string_pretty(val)?.into_bytes())
+        }
+        fn deserialize<R: Read>(&self, mut s: R) -> error::DeSerResult<T> {
+            let mut input = String::new();
+            s.read_to_string(&mut input)?;
+            Ok(from_toml_str(&input)?)
+        }
+    }
+}
+
+#[cfg(feature = "cbor_enc")]
+mod cbor {
+    use std::io::{Read, Write};
+
+    use ciborium::{de::from_reader as from_cbor_reader, ser::into_writer};
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+
+    use crate::deser::DeSerializer;
+    use crate::error;
+
+    /// The struct that allows you to use CBOR.
+    #[derive(Debug, Default, Clone)]
+    pub struct Cbor;
+
+    impl<T: Serialize + DeserializeOwned> DeSerializer<T> for Cbor {
+        fn serialize(&self, val: &T) -> error::DeSerResult<Vec<u8>> {
+            let mut buf = Vec::new();
+            into_writer(val, &mut buf)?;
+            Ok(buf)
+        }
+        fn serialize_into<W: Write>(&self, val: &T, writer: W) -> error::DeSerResult<()> {
+            Ok(into_writer(val, writer)?)
+        }
+        fn deserialize<R: Read>(&self, s: R) -> error::DeSerResult<T> {
+            Ok(from_cbor_reader(s)?)
+        }
+    }
+}
+
+#[cfg(feature = "postcard_enc")]
+mod postcard {
+    use std::io::Read;
+
+    use postcard::{from_bytes, to_allocvec};
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+
+    use crate::deser::DeSerializer;
+    use crate::error;
+
+    /// The struct that allows you to use Postcard.
+    ///
+    /// Postcard is not self-describing, so changing the layout of your data
+    /// makes previously saved databases unreadable.
+    #[derive(Debug, Default, Clone)]
+    pub struct Postcard;
+
+    impl<T: Serialize + DeserializeOwned> DeSerializer<T> for Postcard {
+        fn serialize(&self, val: &T) -> error::DeSerResult<Vec<u8>> {
+            Ok(to_allocvec(val)?)
+        }
+        fn deserialize<R: Read>(&self, mut s: R) -> error::DeSerResult<T> {
+            let mut bytes = Vec::new();
+            s.read_to_end(&mut bytes)?;
+            Ok(from_bytes(&bytes)?)
+        }
+    }
+}
+
+#[cfg(feature = "zstd_enc")]
+mod zstd {
+    use std::io::{Read, Write};
+
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use zstd::stream::{Decoder, Encoder};
+    use zstd::DEFAULT_COMPRESSION_LEVEL;
+
+    use crate::deser::DeSerializer;
+    use crate::error;
+
+    /// Compresses the output of another `DeSer` with Zstandard.
+    ///
+    /// For example `Zstd<Ron>` stores Ron, compressed with the default
+    /// compression level. Use [`Zstd::with_level`] to pick another one.
+    #[derive(Debug, Clone)]
+    pub struct Zstd<D> {
+        inner: D,
+        level: i32,
+    }
+
+    impl<D> Zstd<D> {
+        /// Compress the output of `inner` with the default compression level.
+        pub fn new(inner: D) -> Self {
+            Self {
+                inner,
+                level: DEFAULT_COMPRESSION_LEVEL,
+            }
+        }
+
+        /// Use the given compression level, see the `zstd` crate for the
+        /// valid range.
+        #[must_use]
+        pub fn with_level(mut self, level: i32) -> Self {
+            self.level = level;
+            self
+        }
+    }
+
+    impl<D: Default> Default for Zstd<D> {
+        fn default() -> Self {
+            Self::new(D::default())
+        }
+    }
+
+    impl<T: Serialize + DeserializeOwned, D: DeSerializer<T>> DeSerializer<T> for Zstd<D> {
+        fn serialize(&self, val: &T) -> error::DeSerResult<Vec<u8>> {
+            let mut buf = Vec::new();
+            self.serialize_into(val, &mut buf)?;
+            Ok(buf)
+        }
+        fn serialize_into<W: Write>(&self, val: &T, writer: W) -> error::DeSerResult<()> {
+            let mut encoder = Encoder::new(writer, self.level)?;
+            self.inner.serialize_into(val, &mut encoder)?;
+            encoder.finish()?;
+            Ok(())
+        }
+        fn deserialize<R: Read>(&self, s: R) -> error::DeSerResult<T> {
+            self.inner.deserialize(Decoder::new(s)?)
+        }
+    }
+}
+
+#[cfg(feature = "gzip_enc")]
+mod gzip {
+    use std::io::{Read, Write};
+
+    use flate2::read::GzDecoder;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+
+    use crate::deser::DeSerializer;
+    use crate::error;
+
+    /// Compresses the output of another `DeSer` with gzip.
+    ///
+    /// For example `Gzip<Bincode>` stores Bincode, compressed with the
+    /// default compression level. Use [`Gzip::with_level`] to pick another
+    /// one.
+    #[derive(Debug, Clone)]
+    pub struct Gzip<D> {
+        inner: D,
+        level: Compression,
+    }
+
+    impl<D> Gzip<D> {
+        /// Compress the output of `inner` with the default compression level.
+        pub fn new(inner: D) -> Self {
+            Self {
+                inner,
+                level: Compression::default(),
+            }
+        }
+
+        /// Use the given compression level, from 0 (none) to 9 (best).
+        #[must_use]
+        pub fn with_level(mut self, level: u32) -> Self {
+            self.level = Compression::new(level);
+            self
+        }
+    }
+
+    impl<D: Default> Default for Gzip<D> {
+        fn default() -> Self {
+            Self::new(D::default())
+        }
+    }
+
+    impl<T: Serialize + DeserializeOwned, D: DeSerializer<T>> DeSerializer<T> for Gzip<D> {
+        fn serialize(&self, val: &T) -> error::DeSerResult<Vec<u8>> {
+            let mut buf = Vec::new();
+            self.serialize_into(val, &mut buf)?;
+            Ok(buf)
+        }
+        fn serialize_into<W: Write>(&self, val: &T, writer: W) -> error::DeSerResult<()> {
+            let mut encoder = GzEncoder::new(writer, self.level);
+            self.inner.serialize_into(val, &mut encoder)?;
+            encoder.finish()?;
+            Ok(())
+        }
+        fn deserialize<R: Read>(&self, s: R) -> error::DeSerResult<T> {
+            self.inner.deserialize(GzDecoder::new(s))
+        }
+    }
+}
+
+#[cfg(feature = "armor")]
+mod armored {
+    use std::io::{Read, Write};
+
+    use base64::write::EncoderWriter;
+    use base64::Config;
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+
+    use crate::deser::DeSerializer;
+    use crate::error;
+
+    /// Encodes the output of another `DeSer` with base64.
+    ///
+    /// For example `Armored<Bincode>` stores Bincode as base64 text, so that
+    /// it can be kept where only text is allowed, like environment
+    /// variables, configuration files or version control. Whitespace, such
+    /// as a trailing newline added by an editor or line breaks, is ignored
+    /// on load.
+    ///
+    /// The standard alphabet with padding is used, see
+    /// [`Armored::url_safe`] for the URL safe one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// use rustbreak::deser::{Armored, Bincode, DeSerializer};
+    ///
+    /// # fn main() -> rustbreak::error::DeSerResult<()> {
+    /// let deser = Armored::new(Bincode::default());
+    /// let text = deser.serialize(&vec![1u32, 2, 3])?;
+    /// assert!(text.iter().all(u8::is_ascii_graphic));
+    ///
+    /// let data: Vec<u32> = deser.deserialize(&b"AwAAAAAAAAABAAAAAgAAAAMAAAA=\n"[..])?;
+    /// assert_eq!(data, vec![1, 2, 3]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[derive(Debug, Clone)]
+    pub struct Armored<D> {
+        inner: D,
+        config: Config,
+    }
+
+    impl<D> Armored<D> {
+        /// Encode the output of `inner` with the standard base64 alphabet.
+        pub fn new(inner: D) -> Self {
+            Self {
+                inner,
+                config: base64::STANDARD,
+            }
+        }
+
+        /// Use the URL and filename safe alphabet instead, which replaces
+        /// `+` and `/` with `-` and `_`.
+        #[must_use]
+        pub fn url_safe(mut self) -> Self {
+            self.config = base64::URL_SAFE;
+            self
+        }
+    }
+
+    impl<D: Default> Default for Armored<D> {
+        fn default() -> Self {
+            Self::new(D::default())
+        }
+    }
+
+    impl<T: Serialize + DeserializeOwned, D: DeSerializer<T>> DeSerializer<T> for Armored<D> {
+        fn serialize(&self, val: &T) -> error::DeSerResult<Vec<u8>> {
+            let mut buf = Vec::new();
+            self.serialize_into(val, &mut buf)?;
+            Ok(buf)
+        }
+        fn serialize_into<W: Write>(&self, val: &T, mut writer: W) -> error::DeSerResult<()> {
+            let mut encoder = EncoderWriter::new(&mut writer, self.config);
+            self.inner.serialize_into(val, &mut encoder)?;
+            encoder.finish()?;
+            Ok(())
+        }
+        fn deserialize<R: Read>(&self, mut s: R) -> error::DeSerResult<T> {
+            let mut text = Vec::new();
+            s.read_to_end(&mut text)?;
+            text.retain(|b| !b.is_ascii_whitespace());
+            let data = base64::decode_config(&text, self.config)?;
+            self.inner.deserialize(data.as_slice())
+        }
+    }
+
+    #[cfg(all(test, feature = "bin_enc"))]
+    mod tests {
+        use super::Armored;
+        use crate::deser::{Bincode, DeSerializer};
+
+        #[test]
+        fn armored_round_trips_as_text() {
+            let data: Vec<u8> = (0..=255).collect();
+            for deser in [
+                Armored::new(Bincode::default()),
+                Armored::new(Bincode::default()).url_safe(),
+            ] {
+                let text = deser.serialize(&data).expect("could not serialize");
+                assert!(text.iter().all(u8::is_ascii_graphic));
+
+                let mut wrapped = Vec::new();
+                for line in text.chunks(64) {
+                    wrapped.extend_from_slice(line);
+                    wrapped.extend_from_slice(b"\r\n");
+                }
+                let back: Vec<u8> = deser
+                    .deserialize(wrapped.as_slice())
+                    .expect("could not deserialize");
+                assert_eq!(back, data);
+            }
+
+            let res: Result<Vec<u8>, _> =
+                Armored::new(Bincode::default()).deserialize(&b"not!"[..]);
+            assert!(res.is_err());
+        }
+    }
+}
+
+#[cfg(feature = "encryption")]
+mod encrypted {
+    use std::io::Read;
+
+    use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+    use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+
+    use crate::deser::DeSerializer;
+    use crate::error;
+
+    /// The length of the random nonce stored in front of the ciphertext.
+    const NONCE_LEN: usize = 24;
+
+    /// Encrypts the output of another `DeSer` with XChaCha20-Poly1305.
+    ///
+    /// Every save uses a fresh random nonce, which is stored in front of the
+    /// ciphertext. Loading data that was tampered with or encrypted with
+    /// another key fails with [`DeSerError::Decryption`].
+    ///
+    /// The default value has no key and fails to save or load with
+    /// [`DeSerError::MissingKey`], create it with [`Encrypted::new`] and hand
+    /// it to [`Database::from_parts`] or [`Database::with_deser`] instead.
+    ///
+    /// [`DeSerError::Decryption`]: crate::error::DeSerError::Decryption
+    /// [`DeSerError::MissingKey`]: crate::error::DeSerError::MissingKey
+    /// [`Database::from_parts`]: crate::Database::from_parts
+    /// [`Database::with_deser`]: crate::Database::with_deser
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// use rustbreak::deser::{Encrypted, Ron};
+    /// use rustbreak::{backend::MemoryBackend, Database};
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// let key = [42; 32]; // Load this from somewhere safe
+    /// let db = Database::from_parts(
+    ///     String::from("secret token"),
+    ///     MemoryBackend::new(),
+    ///     Encrypted::new(Ron, key),
+    /// );
+    /// db.save()?;
+    /// db.load()?;
+    /// assert_eq!(*db.borrow_data()?, "secret token");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[derive(Clone, Default)]
+    pub struct Encrypted<D> {
+        inner: D,
+        cipher: Option<XChaCha20Poly1305>,
+    }
+
+    impl<D> Encrypted<D> {
+        /// Encrypt the output of `inner` with the given 256 bit key.
+        pub fn new(inner: D, key: [u8; 32]) -> Self {
+            Self {
+                inner,
+                cipher: Some(XChaCha20Poly1305::new(&key.into())),
+            }
+        }
+
+        fn cipher(&self) -> error::DeSerResult<&XChaCha20Poly1305> {
+            self.cipher.as_ref().ok_or(error::DeSerError::MissingKey)
+        }
+    }
+
+    impl<D: std::fmt::Debug> std::fmt::Debug for Encrypted<D> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("Encrypted")
+                .field("inner", &self.inner)
+                .field("has_key", &self.cipher.is_some())
+                .finish()
+        }
+    }
+
+    impl<T: Serialize + DeserializeOwned, D: DeSerializer<T>> DeSerializer<T> for Encrypted<D> {
+        fn serialize(&self, val: &T) -> error::DeSerResult<Vec<u8>> {
+            let cipher = self.cipher()?;
+            let plain = self.inner.serialize(val)?;
+            let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+            let ciphertext = cipher
+                .encrypt(&nonce, plain.as_slice())
+                .map_err(|_| error::DeSerError::Internal("Encryption failed".to_string()))?;
+            let mut buf = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+            buf.extend_from_slice(&nonce);
+            buf.extend_from_slice(&ciphertext);
+            Ok(buf)
+        }
+        fn deserialize<R: Read>(&self, mut s: R) -> error::DeSerResult<T> {
+            let cipher = self.cipher()?;
+            let mut buf = Vec::new();
+            s.read_to_end(&mut buf)?;
+            if buf.len() < NONCE_LEN {
+                return Err(error::DeSerError::Decryption);
+            }
+            let (nonce, ciphertext) = buf.split_at(NONCE_LEN);
+            let plain = cipher
+                .decrypt(XNonce::from_slice(nonce), ciphertext)
+                .map_err(|_| error::DeSerError::Decryption)?;
+            self.inner.deserialize(plain.as_slice())
+        }
+    }
+
+    #[cfg(all(test, feature = "ron_enc"))]
+    mod tests {
+        use super::Encrypted;
+        use crate::deser::{DeSerializer, Ron};
+        use crate::error::DeSerError;
+
+        #[test]
+        fn roundtrip_and_wrong_key() {
+            let deser = Encrypted::new(Ron, [1; 32]);
+            let data = deser.serialize(&vec![1_u32, 2, 3]).unwrap();
+            assert!(!data.windows(3).any(|w| w == b"1, "));
+            let back: Vec<u32> = deser.deserialize(data.as_slice()).unwrap();
+            assert_eq!(back, vec![1, 2, 3]);
+
+            let other = Encrypted::new(Ron, [2; 32]);
+            let err = DeSerializer::<Vec<u32>>::deserialize(&other, data.as_slice());
+            assert!(matches!(err, Err(DeSerError::Decryption)));
+        }
+
+        #[test]
+        fn default_has_no_key() {
+            let deser = Encrypted::<Ron>::default();
+            let err = deser.serialize(&1_u32);
+            assert!(matches!(err, Err(DeSerError::MissingKey)));
+        }
+    }
+}
+
+#[cfg(feature = "signing")]
+mod signed {
+    use std::io::Read;
+
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+
+    use crate::deser::DeSerializer;
+    use crate::error;
+
+    /// The length of the keyed hash appended to the data.
+    const TAG_LEN: usize = blake3::OUT_LEN;
+
+    /// Appends a keyed BLAKE3 hash to the output of another `DeSer`.
+    ///
+    /// The hash is checked before the data is handed to the inner `DeSer`,
+    /// so tampered or truncated data fails with
+    /// [`DeSerError::IntegrityFailure`]. The data itself is stored as is,
+    /// use [`Encrypted`](crate::deser::Encrypted) to also hide it.
+    ///
+    /// The default value has no key and fails to save or load with
+    /// [`DeSerError::MissingKey`], create it with [`Signed::new`] and hand it
+    /// to [`Database::from_parts`] or [`Database::with_deser`] instead.
+    ///
+    /// [`DeSerError::IntegrityFailure`]: crate::error::DeSerError::IntegrityFailure
+    /// [`DeSerError::MissingKey`]: crate::error::DeSerError::MissingKey
+    /// [`Database::from_parts`]: crate::Database::from_parts
+    /// [`Database::with_deser`]: crate::Database::with_deser
+    #[derive(Clone, Default)]
+    pub struct Signed<D> {
+        inner: D,
+        key: Option<[u8; 32]>,
+    }
+
+    impl<D> Signed<D> {
+        /// Sign the output of `inner` with the given 256 bit key.
+        pub fn new(inner: D, key: [u8; 32]) -> Self {
+            Self {
+                inner,
+                key: Some(key),
+            }
+        }
+
+        fn tag(&self, data: &[u8]) -> error::DeSerResult<blake3::Hash> {
+            let key = self.key.as_ref().ok_or(error::DeSerError::MissingKey)?;
+            Ok(blake3::keyed_hash(key, data))
+        }
+    }
+
+    impl<D: std::fmt::Debug> std::fmt::Debug for Signed<D> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("Signed")
+                .field("inner", &self.inner)
+                .field("has_key", &self.key.is_some())
+                .finish()
+        }
+    }
+
+    impl<T: Serialize + DeserializeOwned, D: DeSerializer<T>> DeSerializer<T> for Signed<D> {
+        fn serialize(&self, val: &T) -> error::DeSerResult<Vec<u8>> {
+            let mut buf = self.inner.serialize(val)?;
+            let tag = self.tag(&buf)?;
+            buf.extend_from_slice(tag.as_bytes());
+            Ok(buf)
+        }
+        fn deserialize<R: Read>(&self, mut s: R) -> error::DeSerResult<T> {
+            let mut buf = Vec::new();
+            s.read_to_end(&mut buf)?;
+            if buf.len() < TAG_LEN {
+                return Err(error::DeSerError::IntegrityFailure);
+            }
+            let (data, tag) = buf.split_at(buf.len() - TAG_LEN);
+            let mut expected = [0; TAG_LEN];
+            expected.copy_from_slice(tag);
+            // `Hash` compares in constant time
+            if self.tag(data)? != blake3::Hash::from(expected) {
+                return Err(error::DeSerError::IntegrityFailure);
+            }
+            self.inner.deserialize(data)
+        }
+    }
+
+    #[cfg(all(test, feature = "ron_enc"))]
+    mod tests {
+        use super::Signed;
+        use crate::deser::{DeSerializer, Ron};
+        use crate::error::DeSerError;
+
+        #[test]
+        fn detects_tampering_and_truncation() {
+            let deser = Signed::new(Ron, [7; 32]);
+            let mut data = deser.serialize(&vec![1_u32, 2, 3]).unwrap();
+            let back: Vec<u32> = deser.deserialize(data.as_slice()).unwrap();
+            assert_eq!(back, vec![1, 2, 3]);
+
+            let truncated = DeSerializer::<Vec<u32>>::deserialize(&deser, &data[1..]);
+            assert!(matches!(truncated, Err(DeSerError::IntegrityFailure)));
+
+            data[1] ^= 1;
+            let tampered = DeSerializer::<Vec<u32>>::deserialize(&deser, data.as_slice());
+            assert!(matches!(tampered, Err(DeSerError::IntegrityFailure)));
         }
     }
 }
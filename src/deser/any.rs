@@ -0,0 +1,212 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::io::{Read, Write};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::deser::DeSerializer;
+use crate::error;
+
+/// Loads data in any of the enabled formats, and saves it in a primary one.
+///
+/// On load, the data is sniffed to guess whether it is Ron, JSON, Yaml or
+/// Bincode, and deserialized with that format, if its feature is enabled.
+/// Saving always uses the primary `DeSer`, so a database that switched
+/// formats can still load the files written in the old one, and converts
+/// them on the next save.
+///
+/// Guessing is done from the first characters: binary data is Bincode, `(`
+/// or a struct name followed by `(` is Ron, `{`, `[` or a scalar is JSON,
+/// and `---`, `#` or a `key:` is Yaml. Since some data is valid in several
+/// formats, the likely ones are tried in turn, and finally the primary
+/// `DeSer`. If all of them fail, the error of the first one is returned.
+///
+/// Bincode is loaded with the default [`BincodeOptions`], Json with
+/// [`Json::default`], and Yaml with `SafeYaml` if both Yaml features are
+/// enabled.
+///
+/// [`BincodeOptions`]: crate::deser::BincodeOptions
+/// [`Json::default`]: crate::deser::Json
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::deser::{AnyDeSer, DeSerializer, Json, Ron};
+///
+/// # fn main() -> rustbreak::error::DeSerResult<()> {
+/// let deser = AnyDeSer::new(Ron);
+/// let old = Json::default().serialize(&vec![1, 2, 3])?;
+/// let data: Vec<u32> = deser.deserialize(old.as_slice())?;
+/// assert_eq!(data, vec![1, 2, 3]);
+/// assert_eq!(deser.serialize(&data)?, Ron.serialize(&data)?);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct AnyDeSer<D> {
+    primary: D,
+}
+
+impl<D> AnyDeSer<D> {
+    /// Save with `primary`, and load any format.
+    pub fn new(primary: D) -> Self {
+        Self { primary }
+    }
+}
+
+impl<T, D> DeSerializer<T> for AnyDeSer<D>
+where
+    T: Serialize + DeserializeOwned,
+    D: DeSerializer<T>,
+{
+    fn serialize(&self, val: &T) -> error::DeSerResult<Vec<u8>> {
+        self.primary.serialize(val)
+    }
+    fn serialize_into<W: Write>(&self, val: &T, writer: W) -> error::DeSerResult<()> {
+        self.primary.serialize_into(val, writer)
+    }
+    fn deserialize<R: Read>(&self, mut s: R) -> error::DeSerResult<T> {
+        let mut data = Vec::new();
+        s.read_to_end(&mut data)?;
+
+        let mut first_err = None;
+        for format in sniff(&data) {
+            match format.deserialize(&data) {
+                Some(Ok(val)) => return Ok(val),
+                Some(Err(e)) => {
+                    first_err.get_or_insert(e);
+                }
+                None => {}
+            }
+        }
+        match self.primary.deserialize(data.as_slice()) {
+            Ok(val) => Ok(val),
+            Err(e) => Err(first_err.unwrap_or(e)),
+        }
+    }
+}
+
+/// A format that [`sniff`] can detect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Ron,
+    Json,
+    Yaml,
+    Bincode,
+}
+
+impl Format {
+    /// Deserialize `data` in this format, `None` if its feature is disabled.
+    #[allow(unused_variables, clippy::unnecessary_wraps)]
+    fn deserialize<T: Serialize + DeserializeOwned>(
+        self,
+        data: &[u8],
+    ) -> Option<error::DeSerResult<T>> {
+        match self {
+            #[cfg(feature = "ron_enc")]
+            Format::Ron => Some(crate::deser::Ron.deserialize(data)),
+            #[cfg(feature = "json_enc")]
+            Format::Json => Some(crate::deser::Json::default().deserialize(data)),
+            #[cfg(feature = "safe_yaml_enc")]
+            Format::Yaml => Some(crate::deser::SafeYaml.deserialize(data)),
+            #[cfg(all(feature = "yaml_enc", not(feature = "safe_yaml_enc")))]
+            #[allow(deprecated)]
+            Format::Yaml => Some(crate::deser::Yaml.deserialize(data)),
+            #[cfg(feature = "bin_enc")]
+            Format::Bincode => Some(crate::deser::Bincode::default().deserialize(data)),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+}
+
+/// The formats `data` is likely in, the most likely first.
+fn sniff(data: &[u8]) -> &'static [Format] {
+    let text = match std::str::from_utf8(data) {
+        Ok(text) if !text.contains(|c: char| c.is_control() && !c.is_whitespace()) => text,
+        _ => return &[Format::Bincode],
+    };
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    if text.starts_with("#![enable") || text.starts_with('(') || text.starts_with("//") {
+        return &[Format::Ron];
+    }
+    if text.starts_with("---") || text.starts_with('#') {
+        return &[Format::Yaml];
+    }
+    match text.chars().next() {
+        None => &[],
+        Some('{' | '[' | '"' | '-' | '0'..='9') => &[Format::Json, Format::Ron, Format::Yaml],
+        Some(_) => {
+            // A Ron struct name or enum variant, or a Yaml key.
+            let rest = text.trim_start_matches(|c: char| c.is_alphanumeric() || c == '_');
+            match rest.trim_start().chars().next() {
+                Some('(' | '{') => &[Format::Ron],
+                Some(':') => &[Format::Yaml],
+                _ => &[Format::Json, Format::Ron, Format::Yaml],
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sniff, Format};
+
+    #[test]
+    fn sniff_formats() {
+        assert_eq!(sniff(b"(a: 1)"), &[Format::Ron]);
+        assert_eq!(sniff(b"  Point(x: 1, y: 2)"), &[Format::Ron]);
+        assert_eq!(sniff(b"#![enable(implicit_some)]\n(a: 1)"), &[Format::Ron]);
+        assert_eq!(sniff(b"---\na: 1"), &[Format::Yaml]);
+        assert_eq!(sniff(b"name: value\n"), &[Format::Yaml]);
+        assert_eq!(sniff(b"\xef\xbb\xbf{\"a\": 1}")[0], Format::Json);
+        assert_eq!(sniff(b"[1, 2]")[0], Format::Json);
+        assert_eq!(sniff(b"\x03\x00\x00\x00"), &[Format::Bincode]);
+        assert_eq!(sniff(b"\xff\xfe"), &[Format::Bincode]);
+        assert!(sniff(b"  \n").is_empty());
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "ron_enc",
+        feature = "json_enc",
+        feature = "safe_yaml_enc",
+        feature = "bin_enc"
+    ))]
+    fn loads_every_format() {
+        use super::AnyDeSer;
+        use crate::deser::{Bincode, DeSerializer, Json, Ron, SafeYaml};
+        use std::collections::BTreeMap;
+
+        let data: BTreeMap<String, Vec<i32>> = vec![
+            ("one".to_string(), vec![1]),
+            ("two".to_string(), vec![-2, 2]),
+        ]
+        .into_iter()
+        .collect();
+        let files = vec![
+            Ron.serialize(&data).unwrap(),
+            Json::default().serialize(&data).unwrap(),
+            Json::compact().serialize(&data).unwrap(),
+            DeSerializer::<BTreeMap<String, Vec<i32>>>::serialize(&SafeYaml, &data).unwrap(),
+            Bincode::default().serialize(&data).unwrap(),
+        ];
+        let deser = AnyDeSer::new(Ron);
+        for file in files {
+            let back: BTreeMap<String, Vec<i32>> = deser.deserialize(file.as_slice()).unwrap();
+            assert_eq!(back, data);
+        }
+        assert_eq!(
+            deser.serialize(&data).unwrap(),
+            Ron.serialize(&data).unwrap()
+        );
+
+        let res: crate::error::DeSerResult<BTreeMap<String, Vec<i32>>> =
+            deser.deserialize(&b"(not valid"[..]);
+        assert!(res.is_err());
+    }
+}
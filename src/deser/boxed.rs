@@ -0,0 +1,175 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::fmt;
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::deser::DeSerializer;
+use crate::error;
+
+/// The object safe part of [`DeSerializer`], implemented for every
+/// `DeSerializer`.
+///
+/// `DeSerializer` has generic methods and requires `Default` and `Clone`, so
+/// it can not be made into a trait object. This trait can, and
+/// [`BoxedDeSer`] turns it back into a `DeSerializer`.
+pub trait DynDeSerializer<T>: Send + Sync {
+    /// Like [`DeSerializer::serialize`].
+    fn serialize(&self, val: &T) -> error::DeSerResult<Vec<u8>>;
+    /// Like [`DeSerializer::serialize_into`].
+    fn serialize_into(&self, val: &T, writer: &mut dyn Write) -> error::DeSerResult<()>;
+    /// Like [`DeSerializer::deserialize`].
+    fn deserialize(&self, reader: &mut dyn Read) -> error::DeSerResult<T>;
+}
+
+impl<T, D> DynDeSerializer<T> for D
+where
+    T: Serialize + DeserializeOwned,
+    D: DeSerializer<T>,
+{
+    fn serialize(&self, val: &T) -> error::DeSerResult<Vec<u8>> {
+        DeSerializer::serialize(self, val)
+    }
+    fn serialize_into(&self, val: &T, writer: &mut dyn Write) -> error::DeSerResult<()> {
+        DeSerializer::serialize_into(self, val, writer)
+    }
+    fn deserialize(&self, reader: &mut dyn Read) -> error::DeSerResult<T> {
+        DeSerializer::deserialize(self, reader)
+    }
+}
+
+/// A `DeSer` chosen at runtime, for example from a configuration value.
+///
+/// It wraps any [`DeSerializer`] behind a [`DynDeSerializer`] trait object,
+/// so all databases of the same data have the same type, whatever their
+/// format. Clones share the wrapped `DeSer`.
+///
+/// The default value has no format and fails to save or load with
+/// [`DeSerError::MissingFormat`], create it with [`BoxedDeSer::new`] and hand
+/// it to [`Database::from_parts`] or [`Database::with_deser`] instead.
+///
+/// [`DeSerError::MissingFormat`]: crate::error::DeSerError::MissingFormat
+/// [`Database::from_parts`]: crate::Database::from_parts
+/// [`Database::with_deser`]: crate::Database::with_deser
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::deser::{BoxedDeSer, DeSerializer, Json, Ron};
+/// use rustbreak::backend::{Backend, MemoryBackend};
+/// use rustbreak::Database;
+///
+/// fn deser_for(format: &str) -> BoxedDeSer<Vec<u32>> {
+///     match format {
+///         "json" => BoxedDeSer::new(Json::default()),
+///         _ => BoxedDeSer::new(Ron),
+///     }
+/// }
+///
+/// # fn main() -> rustbreak::error::Result<()> {
+/// let db = Database::from_parts(vec![1, 2], MemoryBackend::new(), deser_for("json"));
+/// db.save()?;
+/// let (_, mut backend, _) = db.into_inner()?;
+/// assert_eq!(backend.get_data()?, Json::default().serialize(&vec![1, 2])?);
+/// # Ok(())
+/// # }
+/// ```
+pub struct BoxedDeSer<T> {
+    inner: Option<Arc<dyn DynDeSerializer<T>>>,
+}
+
+impl<T> BoxedDeSer<T> {
+    /// Erase the type of `deser`.
+    pub fn new<D: DynDeSerializer<T> + 'static>(deser: D) -> Self {
+        Self::from_arc(Arc::new(deser))
+    }
+
+    /// Use a `DeSer` that is already shared.
+    pub fn from_arc(deser: Arc<dyn DynDeSerializer<T>>) -> Self {
+        Self { inner: Some(deser) }
+    }
+
+    fn inner(&self) -> error::DeSerResult<&dyn DynDeSerializer<T>> {
+        self.inner
+            .as_deref()
+            .ok_or(error::DeSerError::MissingFormat)
+    }
+}
+
+impl<T> DeSerializer<T> for BoxedDeSer<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    fn serialize(&self, val: &T) -> error::DeSerResult<Vec<u8>> {
+        self.inner()?.serialize(val)
+    }
+    fn serialize_into<W: Write>(&self, val: &T, mut writer: W) -> error::DeSerResult<()> {
+        self.inner()?.serialize_into(val, &mut writer)
+    }
+    fn deserialize<R: Read>(&self, mut s: R) -> error::DeSerResult<T> {
+        self.inner()?.deserialize(&mut s)
+    }
+}
+
+impl<T> Default for BoxedDeSer<T> {
+    fn default() -> Self {
+        Self { inner: None }
+    }
+}
+
+impl<T> Clone for BoxedDeSer<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for BoxedDeSer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxedDeSer")
+            .field("format", &self.inner.is_some())
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "ron_enc", feature = "json_enc"))]
+mod tests {
+    use super::BoxedDeSer;
+    use crate::deser::{DeSerializer, Json, Ron};
+    use crate::error::DeSerError;
+
+    #[test]
+    fn dispatches_to_the_boxed_deser() {
+        let data = vec![1_u32, 2];
+        for (deser, expected) in [
+            (BoxedDeSer::new(Ron), Ron.serialize(&data)),
+            (
+                BoxedDeSer::new(Json::default()),
+                Json::default().serialize(&data),
+            ),
+        ] {
+            let expected = expected.expect("could not serialize");
+            let mut written = Vec::new();
+            deser
+                .serialize_into(&data, &mut written)
+                .expect("could not serialize");
+            assert_eq!(written, expected);
+            let read: Vec<u32> = deser
+                .clone()
+                .deserialize(written.as_slice())
+                .expect("could not deserialize");
+            assert_eq!(read, data);
+        }
+        assert!(matches!(
+            BoxedDeSer::<Vec<u32>>::default().serialize(&data),
+            Err(DeSerError::MissingFormat)
+        ));
+    }
+}
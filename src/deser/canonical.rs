@@ -0,0 +1,849 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::hash::BuildHasher;
+use std::io::{Read, Write};
+
+use serde::de::{self, DeserializeOwned, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, Serialize, Serializer};
+
+use crate::deser::DeSerializer;
+use crate::error;
+
+/// Makes the output of another `DeSer` deterministic, by sorting the entries
+/// of every map.
+///
+/// A `HashMap` serializes its entries in a random order, so saving the same
+/// data twice produces different files. That makes for useless diffs when
+/// the database is kept in version control. `Canonical<Ron>` stores the same
+/// Ron as `Ron`, but with the entries of maps sorted by their keys, so that
+/// logically identical data is always stored byte for byte the same.
+///
+/// The data is first serialized into a [`CanonicalValue`], which is then
+/// sorted and serialized with the inner `DeSer`, so saving takes a bit more
+/// time and memory. Loading is unaffected.
+///
+/// Sets serialize like sequences, so their order can not be fixed. Use
+/// [`sorted_set`] on `HashSet` fields, or store a `BTreeSet` instead. The
+/// same goes for `BTreeMap` and [`sorted_map`], if you only need some maps
+/// to be sorted.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::deser::{Canonical, DeSerializer, Ron};
+/// use std::collections::HashMap;
+///
+/// # fn main() -> rustbreak::error::DeSerResult<()> {
+/// let map: HashMap<u32, String> = (0..100).map(|i| (i, i.to_string())).collect();
+/// let other: HashMap<u32, String> = (0..100).rev().map(|i| (i, i.to_string())).collect();
+///
+/// let deser = Canonical::new(Ron::compact());
+/// assert_eq!(deser.serialize(&map)?, deser.serialize(&other)?);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Canonical<D> {
+    inner: D,
+}
+
+impl<D> Canonical<D> {
+    /// Sort the maps in the output of `inner`.
+    pub fn new(inner: D) -> Self {
+        Self { inner }
+    }
+}
+
+impl<T, D> DeSerializer<T> for Canonical<D>
+where
+    T: Serialize + DeserializeOwned,
+    D: DeSerializer<T> + DeSerializer<CanonicalValue>,
+{
+    fn serialize(&self, val: &T) -> error::DeSerResult<Vec<u8>> {
+        DeSerializer::<CanonicalValue>::serialize(
+            &self.inner,
+            &CanonicalValue::from_serialize(val)?,
+        )
+    }
+    fn serialize_into<W: Write>(&self, val: &T, writer: W) -> error::DeSerResult<()> {
+        let value = CanonicalValue::from_serialize(val)?;
+        DeSerializer::<CanonicalValue>::serialize_into(&self.inner, &value, writer)
+    }
+    fn deserialize<R: Read>(&self, s: R) -> error::DeSerResult<T> {
+        DeSerializer::<T>::deserialize(&self.inner, s)
+    }
+}
+
+/// Serialize a `HashMap` with its entries sorted by key, for
+/// `#[serde(serialize_with = "rustbreak::deser::sorted_map")]`.
+///
+/// It serializes like a `BTreeMap` holding the same entries, so the field
+/// still deserializes as a `HashMap`.
+///
+/// ```rust
+/// # #[macro_use] extern crate serde_derive;
+/// use std::collections::HashMap;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Data {
+///     #[serde(serialize_with = "rustbreak::deser::sorted_map")]
+///     users: HashMap<String, u32>,
+/// }
+/// # fn main() {}
+/// ```
+pub fn sorted_map<K, V, H, S>(map: &HashMap<K, V, H>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    K: Serialize + Ord,
+    V: Serialize,
+    H: BuildHasher,
+    S: Serializer,
+{
+    serializer.collect_map(map.iter().collect::<BTreeMap<_, _>>())
+}
+
+/// Serialize a `HashSet` with its elements sorted, for
+/// `#[serde(serialize_with = "rustbreak::deser::sorted_set")]`, like
+/// [`sorted_map`].
+pub fn sorted_set<T, H, S>(set: &HashSet<T, H>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize + Ord,
+    H: BuildHasher,
+    S: Serializer,
+{
+    serializer.collect_seq(set.iter().collect::<BTreeSet<_>>())
+}
+
+/// Data as it is serialized, with the entries of maps sorted, see
+/// [`Canonical`].
+///
+/// It serializes exactly like the data it was made from, except for the
+/// order of map entries. Deserializing it is only supported for
+/// self-describing formats, and loses the names of structs and enum
+/// variants, it is only implemented so that any `DeSer` can serialize it.
+#[derive(Debug, Clone)]
+pub struct CanonicalValue(Value);
+
+impl CanonicalValue {
+    fn from_serialize<T: Serialize + ?Sized>(val: &T) -> error::DeSerResult<Self> {
+        val.serialize(ValueSerializer)
+            .map(Self)
+            .map_err(|e| error::DeSerError::Canonical(e.0))
+    }
+}
+
+impl Serialize for CanonicalValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> de::Deserialize<'de> for CanonicalValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor).map(Self)
+    }
+}
+
+/// The serde data model.
+#[derive(Debug, Clone)]
+enum Value {
+    Bool(bool),
+    I64(i64),
+    I128(i128),
+    U64(u64),
+    U128(u128),
+    F32(f32),
+    F64(f64),
+    Char(char),
+    Str(String),
+    Bytes(Vec<u8>),
+    None,
+    Some(Box<Value>),
+    Unit,
+    UnitStruct(&'static str),
+    UnitVariant(&'static str, u32, &'static str),
+    NewtypeStruct(&'static str, Box<Value>),
+    NewtypeVariant(&'static str, u32, &'static str, Box<Value>),
+    Seq(Vec<Value>),
+    Tuple(Vec<Value>),
+    TupleStruct(&'static str, Vec<Value>),
+    TupleVariant(&'static str, u32, &'static str, Vec<Value>),
+    /// Sorted by key.
+    Map(Vec<(Value, Value)>),
+    Struct(&'static str, Vec<(&'static str, Value)>),
+    StructVariant(&'static str, u32, &'static str, Vec<(&'static str, Value)>),
+}
+
+impl Value {
+    /// The order of the kinds of values, for sorting map keys.
+    fn rank(&self) -> u8 {
+        match self {
+            Value::Bool(_) => 0,
+            Value::I64(_) | Value::I128(_) | Value::U64(_) | Value::U128(_) => 1,
+            Value::F32(_) | Value::F64(_) => 2,
+            Value::Char(_) | Value::Str(_) => 3,
+            Value::Bytes(_) => 4,
+            Value::None => 5,
+            Value::Some(_) => 6,
+            Value::Unit | Value::UnitStruct(_) => 7,
+            Value::UnitVariant(..) => 8,
+            Value::NewtypeVariant(..) => 9,
+            Value::TupleVariant(..) => 10,
+            Value::StructVariant(..) => 11,
+            Value::NewtypeStruct(..) => 12,
+            Value::Seq(_) | Value::Tuple(_) | Value::TupleStruct(..) => 13,
+            Value::Map(_) => 14,
+            Value::Struct(..) => 15,
+        }
+    }
+
+    /// A total order, used to sort map keys.
+    fn compare(&self, other: &Self) -> Ordering {
+        self.rank()
+            .cmp(&other.rank())
+            .then_with(|| match (self, other) {
+                (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+                (Value::F32(a), Value::F32(b)) => a.total_cmp(b),
+                (Value::F64(a), Value::F64(b)) => a.total_cmp(b),
+                (Value::F32(a), Value::F64(b)) => f64::from(*a).total_cmp(b),
+                (Value::F64(a), Value::F32(b)) => a.total_cmp(&f64::from(*b)),
+                (Value::Bytes(a), Value::Bytes(b)) => a.cmp(b),
+                (Value::Some(a), Value::Some(b))
+                | (Value::NewtypeStruct(_, a), Value::NewtypeStruct(_, b)) => a.compare(b),
+                (Value::UnitVariant(_, a, _), Value::UnitVariant(_, b, _)) => a.cmp(b),
+                (Value::NewtypeVariant(_, a, _, x), Value::NewtypeVariant(_, b, _, y)) => {
+                    a.cmp(b).then_with(|| x.compare(y))
+                }
+                (Value::TupleVariant(_, a, _, x), Value::TupleVariant(_, b, _, y)) => {
+                    a.cmp(b).then_with(|| compare_seqs(x, y))
+                }
+                (Value::StructVariant(_, a, _, x), Value::StructVariant(_, b, _, y)) => {
+                    a.cmp(b).then_with(|| compare_fields(x, y))
+                }
+                (Value::Map(a), Value::Map(b)) => compare_entries(a, b),
+                (Value::Struct(_, a), Value::Struct(_, b)) => compare_fields(a, b),
+                (a, b) => match (a.as_integer(), b.as_integer(), a.as_str(), b.as_str()) {
+                    (Some(a), Some(b), _, _) => a.cmp(&b),
+                    (_, _, Some(a), Some(b)) => a.cmp(&b),
+                    _ => compare_seqs(a.as_seq(), b.as_seq()),
+                },
+            })
+    }
+
+    /// Integers of any size, the signed ones first.
+    fn as_integer(&self) -> Option<(bool, u128)> {
+        match *self {
+            Value::I64(i) => Some(signed(i128::from(i))),
+            Value::I128(i) => Some(signed(i)),
+            Value::U64(u) => Some((true, u128::from(u))),
+            Value::U128(u) => Some((true, u)),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<std::borrow::Cow<'_, str>> {
+        match self {
+            Value::Char(c) => Some(c.to_string().into()),
+            Value::Str(s) => Some(s.as_str().into()),
+            _ => None,
+        }
+    }
+
+    fn as_seq(&self) -> &[Value] {
+        match self {
+            Value::Seq(values) | Value::Tuple(values) | Value::TupleStruct(_, values) => values,
+            _ => &[],
+        }
+    }
+}
+
+/// Map a signed integer onto one that sorts the same, with the sign first.
+fn signed(i: i128) -> (bool, u128) {
+    if i < 0 {
+        // Larger magnitudes are smaller, so invert them.
+        (false, u128::MAX - i.unsigned_abs())
+    } else {
+        (true, i.unsigned_abs())
+    }
+}
+
+fn compare_seqs(a: &[Value], b: &[Value]) -> Ordering {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| a.compare(b))
+        .find(|o| o.is_ne())
+        .unwrap_or_else(|| a.len().cmp(&b.len()))
+}
+
+fn compare_entries(a: &[(Value, Value)], b: &[(Value, Value)]) -> Ordering {
+    a.iter()
+        .zip(b)
+        .map(|((ak, av), (bk, bv))| ak.compare(bk).then_with(|| av.compare(bv)))
+        .find(|o| o.is_ne())
+        .unwrap_or_else(|| a.len().cmp(&b.len()))
+}
+
+fn compare_fields(a: &[(&str, Value)], b: &[(&str, Value)]) -> Ordering {
+    a.iter()
+        .zip(b)
+        .map(|((ak, av), (bk, bv))| ak.cmp(bk).then_with(|| av.compare(bv)))
+        .find(|o| o.is_ne())
+        .unwrap_or_else(|| a.len().cmp(&b.len()))
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{
+            SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
+            SerializeTupleStruct, SerializeTupleVariant,
+        };
+
+        match self {
+            Value::Bool(v) => serializer.serialize_bool(*v),
+            Value::I64(v) => serializer.serialize_i64(*v),
+            Value::I128(v) => serializer.serialize_i128(*v),
+            Value::U64(v) => serializer.serialize_u64(*v),
+            Value::U128(v) => serializer.serialize_u128(*v),
+            Value::F32(v) => serializer.serialize_f32(*v),
+            Value::F64(v) => serializer.serialize_f64(*v),
+            Value::Char(v) => serializer.serialize_char(*v),
+            Value::Str(v) => serializer.serialize_str(v),
+            Value::Bytes(v) => serializer.serialize_bytes(v),
+            Value::None => serializer.serialize_none(),
+            Value::Some(v) => serializer.serialize_some(v),
+            Value::Unit => serializer.serialize_unit(),
+            Value::UnitStruct(name) => serializer.serialize_unit_struct(name),
+            Value::UnitVariant(name, index, variant) => {
+                serializer.serialize_unit_variant(name, *index, variant)
+            }
+            Value::NewtypeStruct(name, v) => serializer.serialize_newtype_struct(name, v),
+            Value::NewtypeVariant(name, index, variant, v) => {
+                serializer.serialize_newtype_variant(name, *index, variant, v)
+            }
+            Value::Seq(values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for v in values {
+                    seq.serialize_element(v)?;
+                }
+                seq.end()
+            }
+            Value::Tuple(values) => {
+                let mut tuple = serializer.serialize_tuple(values.len())?;
+                for v in values {
+                    tuple.serialize_element(v)?;
+                }
+                tuple.end()
+            }
+            Value::TupleStruct(name, values) => {
+                let mut tuple = serializer.serialize_tuple_struct(name, values.len())?;
+                for v in values {
+                    tuple.serialize_field(v)?;
+                }
+                tuple.end()
+            }
+            Value::TupleVariant(name, index, variant, values) => {
+                let mut tuple =
+                    serializer.serialize_tuple_variant(name, *index, variant, values.len())?;
+                for v in values {
+                    tuple.serialize_field(v)?;
+                }
+                tuple.end()
+            }
+            Value::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (k, v) in entries {
+                    map.serialize_entry(k, v)?;
+                }
+                map.end()
+            }
+            Value::Struct(name, fields) => {
+                let mut s = serializer.serialize_struct(name, fields.len())?;
+                for (k, v) in fields {
+                    s.serialize_field(k, v)?;
+                }
+                s.end()
+            }
+            Value::StructVariant(name, index, variant, fields) => {
+                let mut s =
+                    serializer.serialize_struct_variant(name, *index, variant, fields.len())?;
+                for (k, v) in fields {
+                    s.serialize_field(k, v)?;
+                }
+                s.end()
+            }
+        }
+    }
+}
+
+/// The error of the [`ValueSerializer`].
+#[derive(Debug)]
+struct ValueError(String);
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ValueError {}
+
+impl ser::Error for ValueError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+/// Serializes data into a [`Value`], sorting maps.
+struct ValueSerializer;
+
+fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, ValueError> {
+    value.serialize(ValueSerializer)
+}
+
+impl Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = ValueError;
+    type SerializeSeq = SeqBuilder;
+    type SerializeTuple = SeqBuilder;
+    type SerializeTupleStruct = SeqBuilder;
+    type SerializeTupleVariant = SeqBuilder;
+    type SerializeMap = MapBuilder;
+    type SerializeStruct = StructBuilder;
+    type SerializeStructVariant = StructBuilder;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, ValueError> {
+        Ok(Value::Bool(v))
+    }
+    fn serialize_i8(self, v: i8) -> Result<Value, ValueError> {
+        Ok(Value::I64(v.into()))
+    }
+    fn serialize_i16(self, v: i16) -> Result<Value, ValueError> {
+        Ok(Value::I64(v.into()))
+    }
+    fn serialize_i32(self, v: i32) -> Result<Value, ValueError> {
+        Ok(Value::I64(v.into()))
+    }
+    fn serialize_i64(self, v: i64) -> Result<Value, ValueError> {
+        Ok(Value::I64(v))
+    }
+    fn serialize_i128(self, v: i128) -> Result<Value, ValueError> {
+        Ok(Value::I128(v))
+    }
+    fn serialize_u8(self, v: u8) -> Result<Value, ValueError> {
+        Ok(Value::U64(v.into()))
+    }
+    fn serialize_u16(self, v: u16) -> Result<Value, ValueError> {
+        Ok(Value::U64(v.into()))
+    }
+    fn serialize_u32(self, v: u32) -> Result<Value, ValueError> {
+        Ok(Value::U64(v.into()))
+    }
+    fn serialize_u64(self, v: u64) -> Result<Value, ValueError> {
+        Ok(Value::U64(v))
+    }
+    fn serialize_u128(self, v: u128) -> Result<Value, ValueError> {
+        Ok(Value::U128(v))
+    }
+    fn serialize_f32(self, v: f32) -> Result<Value, ValueError> {
+        Ok(Value::F32(v))
+    }
+    fn serialize_f64(self, v: f64) -> Result<Value, ValueError> {
+        Ok(Value::F64(v))
+    }
+    fn serialize_char(self, v: char) -> Result<Value, ValueError> {
+        Ok(Value::Char(v))
+    }
+    fn serialize_str(self, v: &str) -> Result<Value, ValueError> {
+        Ok(Value::Str(v.to_string()))
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, ValueError> {
+        Ok(Value::Bytes(v.to_vec()))
+    }
+    fn serialize_none(self) -> Result<Value, ValueError> {
+        Ok(Value::None)
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, ValueError> {
+        Ok(Value::Some(Box::new(to_value(value)?)))
+    }
+    fn serialize_unit(self) -> Result<Value, ValueError> {
+        Ok(Value::Unit)
+    }
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Value, ValueError> {
+        Ok(Value::UnitStruct(name))
+    }
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+    ) -> Result<Value, ValueError> {
+        Ok(Value::UnitVariant(name, index, variant))
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Value, ValueError> {
+        Ok(Value::NewtypeStruct(name, Box::new(to_value(value)?)))
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, ValueError> {
+        let value = Box::new(to_value(value)?);
+        Ok(Value::NewtypeVariant(name, index, variant, value))
+    }
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqBuilder, ValueError> {
+        Ok(SeqBuilder::new(SeqKind::Seq, len.unwrap_or(0)))
+    }
+    fn serialize_tuple(self, len: usize) -> Result<SeqBuilder, ValueError> {
+        Ok(SeqBuilder::new(SeqKind::Tuple, len))
+    }
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<SeqBuilder, ValueError> {
+        Ok(SeqBuilder::new(SeqKind::TupleStruct(name), len))
+    }
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SeqBuilder, ValueError> {
+        Ok(SeqBuilder::new(
+            SeqKind::TupleVariant(name, index, variant),
+            len,
+        ))
+    }
+    fn serialize_map(self, len: Option<usize>) -> Result<MapBuilder, ValueError> {
+        Ok(MapBuilder {
+            entries: Vec::with_capacity(len.unwrap_or(0)),
+            key: None,
+        })
+    }
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<StructBuilder, ValueError> {
+        Ok(StructBuilder {
+            variant: None,
+            name,
+            fields: Vec::with_capacity(len),
+        })
+    }
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<StructBuilder, ValueError> {
+        Ok(StructBuilder {
+            variant: Some((index, variant)),
+            name,
+            fields: Vec::with_capacity(len),
+        })
+    }
+}
+
+enum SeqKind {
+    Seq,
+    Tuple,
+    TupleStruct(&'static str),
+    TupleVariant(&'static str, u32, &'static str),
+}
+
+struct SeqBuilder {
+    kind: SeqKind,
+    values: Vec<Value>,
+}
+
+impl SeqBuilder {
+    fn new(kind: SeqKind, len: usize) -> Self {
+        Self {
+            kind,
+            values: Vec::with_capacity(len),
+        }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        self.values.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Value {
+        match self.kind {
+            SeqKind::Seq => Value::Seq(self.values),
+            SeqKind::Tuple => Value::Tuple(self.values),
+            SeqKind::TupleStruct(name) => Value::TupleStruct(name, self.values),
+            SeqKind::TupleVariant(name, index, variant) => {
+                Value::TupleVariant(name, index, variant, self.values)
+            }
+        }
+    }
+}
+
+impl ser::SerializeSeq for SeqBuilder {
+    type Ok = Value;
+    type Error = ValueError;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Value, ValueError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTuple for SeqBuilder {
+    type Ok = Value;
+    type Error = ValueError;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Value, ValueError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqBuilder {
+    type Ok = Value;
+    type Error = ValueError;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Value, ValueError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqBuilder {
+    type Ok = Value;
+    type Error = ValueError;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Value, ValueError> {
+        Ok(self.finish())
+    }
+}
+
+struct MapBuilder {
+    entries: Vec<(Value, Value)>,
+    /// The key passed to `serialize_key`, waiting for its value.
+    key: Option<Value>,
+}
+
+impl ser::SerializeMap for MapBuilder {
+    type Ok = Value;
+    type Error = ValueError;
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), ValueError> {
+        self.key = Some(to_value(key)?);
+        Ok(())
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| ValueError("serialize_value called before serialize_key".into()))?;
+        self.entries.push((key, to_value(value)?));
+        Ok(())
+    }
+    fn end(mut self) -> Result<Value, ValueError> {
+        self.entries.sort_by(|(a, _), (b, _)| a.compare(b));
+        Ok(Value::Map(self.entries))
+    }
+}
+
+struct StructBuilder {
+    variant: Option<(u32, &'static str)>,
+    name: &'static str,
+    fields: Vec<(&'static str, Value)>,
+}
+
+impl ser::SerializeStruct for StructBuilder {
+    type Ok = Value;
+    type Error = ValueError;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ValueError> {
+        self.fields.push((key, to_value(value)?));
+        Ok(())
+    }
+    fn end(self) -> Result<Value, ValueError> {
+        Ok(Value::Struct(self.name, self.fields))
+    }
+}
+
+impl ser::SerializeStructVariant for StructBuilder {
+    type Ok = Value;
+    type Error = ValueError;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ValueError> {
+        self.fields.push((key, to_value(value)?));
+        Ok(())
+    }
+    fn end(self) -> Result<Value, ValueError> {
+        let (index, variant) = self.variant.unwrap_or((0, ""));
+        Ok(Value::StructVariant(self.name, index, variant, self.fields))
+    }
+}
+
+/// Deserializes any self-describing data into a [`Value`].
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any value")
+    }
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::I64(v))
+    }
+    fn visit_i128<E>(self, v: i128) -> Result<Value, E> {
+        Ok(Value::I128(v))
+    }
+    fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::U64(v))
+    }
+    fn visit_u128<E>(self, v: u128) -> Result<Value, E> {
+        Ok(Value::U128(v))
+    }
+    fn visit_f32<E>(self, v: f32) -> Result<Value, E> {
+        Ok(Value::F32(v))
+    }
+    fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::F64(v))
+    }
+    fn visit_char<E>(self, v: char) -> Result<Value, E> {
+        Ok(Value::Char(v))
+    }
+    fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::Str(v.to_string()))
+    }
+    fn visit_string<E>(self, v: String) -> Result<Value, E> {
+        Ok(Value::Str(v))
+    }
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Value, E> {
+        Ok(Value::Bytes(v.to_vec()))
+    }
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Value, E> {
+        Ok(Value::Bytes(v))
+    }
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::None)
+    }
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        let value = deserializer.deserialize_any(ValueVisitor)?;
+        Ok(Value::Some(Box::new(value)))
+    }
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Unit)
+    }
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(CanonicalValue(value)) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Value::Seq(values))
+    }
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0).min(4096));
+        while let Some((CanonicalValue(k), CanonicalValue(v))) = map.next_entry()? {
+            entries.push((k, v));
+        }
+        entries.sort_by(|(a, _), (b, _)| a.compare(b));
+        Ok(Value::Map(entries))
+    }
+}
+
+#[cfg(all(test, feature = "ron_enc"))]
+mod tests {
+    use super::{sorted_map, Canonical};
+    use crate::deser::{DeSerializer, Ron};
+    use std::collections::HashMap;
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    enum Kind {
+        Plain,
+        Tagged(i32),
+        Named { id: u8 },
+    }
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Data {
+        name: String,
+        kinds: HashMap<i64, Kind>,
+        #[serde(serialize_with = "sorted_map")]
+        labels: HashMap<String, Option<(f32, char)>>,
+        nested: Vec<HashMap<String, bool>>,
+    }
+
+    fn data(reverse: bool) -> Data {
+        let mut keys: Vec<i64> = (-50..50).collect();
+        if reverse {
+            keys.reverse();
+        }
+        Data {
+            name: "data".to_string(),
+            kinds: keys
+                .iter()
+                .map(|&k| {
+                    let kind = match k % 3 {
+                        0 => Kind::Plain,
+                        1 => Kind::Tagged(7),
+                        _ => Kind::Named { id: 1 },
+                    };
+                    (k, kind)
+                })
+                .collect(),
+            labels: keys
+                .iter()
+                .map(|k| (k.to_string(), Some((0.5, 'x'))))
+                .collect(),
+            nested: vec![keys.iter().map(|k| (format!("{k}"), k % 2 == 0)).collect()],
+        }
+    }
+
+    #[test]
+    fn canonical_output_is_sorted_and_identical() {
+        let deser = Canonical::new(Ron);
+        let a = deser.serialize(&data(false)).unwrap();
+        let b = deser.serialize(&data(true)).unwrap();
+        assert_eq!(a, b);
+        let text = String::from_utf8(a.clone()).unwrap();
+        let first = text.find("-50:").unwrap();
+        assert!(first < text.find("-49:").unwrap());
+        assert!(text.find("-1:").unwrap() < text.find(" 0:").unwrap());
+
+        let back: Data = deser.deserialize(a.as_slice()).unwrap();
+        assert_eq!(back, data(false));
+        // The output is the same as that of the inner `DeSer`, up to the order.
+        let plain: Data = Ron
+            .deserialize(Ron.serialize(&back).unwrap().as_slice())
+            .unwrap();
+        assert_eq!(plain, back);
+    }
+}
@@ -0,0 +1,235 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::fmt;
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::deser::DeSerializer;
+use crate::error;
+
+/// One of the enabled formats, chosen at runtime.
+///
+/// Every variant uses the default configuration of its `DeSer`, and only
+/// exists if the feature of the format is enabled. `Yaml` uses `SafeYaml`
+/// if the `safe_yaml_enc` feature is enabled, and the deprecated `Yaml`
+/// otherwise.
+///
+/// The names of [`AnyFormat::name`] parse back into the format, which is
+/// what a `--format` flag needs. The default is the first of
+/// [`AnyFormat::ALL`].
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::deser::AnyFormat;
+/// use rustbreak::MemoryDatabase;
+///
+/// # fn main() -> rustbreak::error::Result<()> {
+/// let format: AnyFormat = "json".parse()?;
+/// let db = MemoryDatabase::<Vec<u32>, AnyFormat>::memory(vec![1, 2])?.with_deser(format);
+/// db.save()?;
+/// assert!(AnyFormat::ALL.contains(&AnyFormat::Json));
+/// assert!("xml".parse::<AnyFormat>().is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AnyFormat {
+    /// [`Ron`](struct@crate::deser::Ron)
+    #[cfg(feature = "ron_enc")]
+    Ron,
+    /// [`Json`](crate::deser::Json)
+    #[cfg(feature = "json_enc")]
+    Json,
+    /// `SafeYaml`, or `Yaml` without the `safe_yaml_enc` feature
+    #[cfg(any(feature = "yaml_enc", feature = "safe_yaml_enc"))]
+    Yaml,
+    /// [`Bincode`](crate::deser::Bincode)
+    #[cfg(feature = "bin_enc")]
+    Bincode,
+    /// [`Toml`](crate::deser::Toml)
+    #[cfg(feature = "toml_enc")]
+    Toml,
+    /// [`Cbor`](crate::deser::Cbor)
+    #[cfg(feature = "cbor_enc")]
+    Cbor,
+    /// [`Postcard`](crate::deser::Postcard)
+    #[cfg(feature = "postcard_enc")]
+    Postcard,
+}
+
+impl AnyFormat {
+    /// All enabled formats.
+    pub const ALL: &'static [Self] = &[
+        #[cfg(feature = "ron_enc")]
+        Self::Ron,
+        #[cfg(feature = "json_enc")]
+        Self::Json,
+        #[cfg(any(feature = "yaml_enc", feature = "safe_yaml_enc"))]
+        Self::Yaml,
+        #[cfg(feature = "bin_enc")]
+        Self::Bincode,
+        #[cfg(feature = "toml_enc")]
+        Self::Toml,
+        #[cfg(feature = "cbor_enc")]
+        Self::Cbor,
+        #[cfg(feature = "postcard_enc")]
+        Self::Postcard,
+    ];
+
+    /// The lowercase name of the format, like `"ron"`.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            #[cfg(feature = "ron_enc")]
+            Self::Ron => "ron",
+            #[cfg(feature = "json_enc")]
+            Self::Json => "json",
+            #[cfg(any(feature = "yaml_enc", feature = "safe_yaml_enc"))]
+            Self::Yaml => "yaml",
+            #[cfg(feature = "bin_enc")]
+            Self::Bincode => "bincode",
+            #[cfg(feature = "toml_enc")]
+            Self::Toml => "toml",
+            #[cfg(feature = "cbor_enc")]
+            Self::Cbor => "cbor",
+            #[cfg(feature = "postcard_enc")]
+            Self::Postcard => "postcard",
+        }
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> DeSerializer<T> for AnyFormat {
+    fn serialize(&self, val: &T) -> error::DeSerResult<Vec<u8>> {
+        match self {
+            #[cfg(feature = "ron_enc")]
+            Self::Ron => crate::deser::Ron.serialize(val),
+            #[cfg(feature = "json_enc")]
+            Self::Json => crate::deser::Json::default().serialize(val),
+            #[cfg(feature = "safe_yaml_enc")]
+            Self::Yaml => crate::deser::SafeYaml.serialize(val),
+            #[cfg(all(feature = "yaml_enc", not(feature = "safe_yaml_enc")))]
+            #[allow(deprecated)]
+            Self::Yaml => crate::deser::Yaml.serialize(val),
+            #[cfg(feature = "bin_enc")]
+            Self::Bincode => crate::deser::Bincode::default().serialize(val),
+            #[cfg(feature = "toml_enc")]
+            Self::Toml => crate::deser::Toml.serialize(val),
+            #[cfg(feature = "cbor_enc")]
+            Self::Cbor => crate::deser::Cbor.serialize(val),
+            #[cfg(feature = "postcard_enc")]
+            Self::Postcard => crate::deser::Postcard.serialize(val),
+        }
+    }
+    fn serialize_into<W: Write>(&self, val: &T, writer: W) -> error::DeSerResult<()> {
+        match self {
+            #[cfg(feature = "ron_enc")]
+            Self::Ron => crate::deser::Ron.serialize_into(val, writer),
+            #[cfg(feature = "json_enc")]
+            Self::Json => crate::deser::Json::default().serialize_into(val, writer),
+            #[cfg(feature = "safe_yaml_enc")]
+            Self::Yaml => crate::deser::SafeYaml.serialize_into(val, writer),
+            #[cfg(all(feature = "yaml_enc", not(feature = "safe_yaml_enc")))]
+            #[allow(deprecated)]
+            Self::Yaml => crate::deser::Yaml.serialize_into(val, writer),
+            #[cfg(feature = "bin_enc")]
+            Self::Bincode => crate::deser::Bincode::default().serialize_into(val, writer),
+            #[cfg(feature = "toml_enc")]
+            Self::Toml => crate::deser::Toml.serialize_into(val, writer),
+            #[cfg(feature = "cbor_enc")]
+            Self::Cbor => crate::deser::Cbor.serialize_into(val, writer),
+            #[cfg(feature = "postcard_enc")]
+            Self::Postcard => crate::deser::Postcard.serialize_into(val, writer),
+        }
+    }
+    fn deserialize<R: Read>(&self, s: R) -> error::DeSerResult<T> {
+        match self {
+            #[cfg(feature = "ron_enc")]
+            Self::Ron => crate::deser::Ron.deserialize(s),
+            #[cfg(feature = "json_enc")]
+            Self::Json => crate::deser::Json::default().deserialize(s),
+            #[cfg(feature = "safe_yaml_enc")]
+            Self::Yaml => crate::deser::SafeYaml.deserialize(s),
+            #[cfg(all(feature = "yaml_enc", not(feature = "safe_yaml_enc")))]
+            #[allow(deprecated)]
+            Self::Yaml => crate::deser::Yaml.deserialize(s),
+            #[cfg(feature = "bin_enc")]
+            Self::Bincode => crate::deser::Bincode::default().deserialize(s),
+            #[cfg(feature = "toml_enc")]
+            Self::Toml => crate::deser::Toml.deserialize(s),
+            #[cfg(feature = "cbor_enc")]
+            Self::Cbor => crate::deser::Cbor.deserialize(s),
+            #[cfg(feature = "postcard_enc")]
+            Self::Postcard => crate::deser::Postcard.deserialize(s),
+        }
+    }
+}
+
+impl Default for AnyFormat {
+    fn default() -> Self {
+        Self::ALL[0]
+    }
+}
+
+impl fmt::Display for AnyFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for AnyFormat {
+    type Err = error::DeSerError;
+
+    /// Parse the name of a format, ignoring case. `"yml"` and `"bin"` are
+    /// accepted as well.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_ascii_lowercase();
+        let name = match name.as_str() {
+            "yml" => "yaml",
+            "bin" => "bincode",
+            name => name,
+        };
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|format| format.name() == name)
+            .ok_or_else(|| error::DeSerError::UnknownFormat(s.to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AnyFormat;
+    use crate::deser::DeSerializer;
+
+    #[test]
+    fn every_format_roundtrips_by_name() {
+        let data = vec![(String::from("a"), 1_u32), (String::from("b"), 2)];
+        for format in AnyFormat::ALL {
+            let parsed: AnyFormat = format
+                .to_string()
+                .to_uppercase()
+                .parse()
+                .expect("could not parse the name");
+            assert_eq!(parsed, *format);
+            // TOML can only store tables at the top level.
+            #[cfg(feature = "toml_enc")]
+            if *format == AnyFormat::Toml {
+                continue;
+            }
+            let bytes = format.serialize(&data).expect("could not serialize");
+            let read: Vec<(String, u32)> = format
+                .deserialize(bytes.as_slice())
+                .expect("could not deserialize");
+            assert_eq!(read, data);
+        }
+        assert!("xml".parse::<AnyFormat>().is_err());
+    }
+}
@@ -0,0 +1,373 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A database for documents whose schema is not known at compile time.
+//!
+//! See [`DynDatabase`] for details.
+
+use std::fmt::Write;
+
+use serde::de::DeserializeOwned;
+pub use serde_json::Value;
+
+use crate::backend::Backend;
+use crate::error::{self, DeSerError, RustbreakError};
+use crate::{Database, DeSerializer};
+
+/// A [`Database`] holding a [`Value`], with methods to get and set the
+/// values at paths into it.
+///
+/// A path is made of object keys separated by `.`, and array indices in
+/// brackets, like `users[2].name`. The empty path is the whole document.
+/// Keys can not contain `.` or `[`.
+///
+/// Every method locks the database once, like [`Database::read`] or
+/// [`Database::write`] would. Values are cloned out of the document. With
+/// [`DynDatabase::with_auto_save`], every change is saved right away.
+///
+/// Any self describing `DeSer` can be used, for example to edit Ron, Yaml or
+/// JSON files without knowing what they hold.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::dynamic::{DynDatabase, Value};
+/// use rustbreak::{deser::Json, MemoryDatabase};
+///
+/// # fn main() -> rustbreak::error::Result<()> {
+/// let db = DynDatabase::new(MemoryDatabase::<Value, Json>::memory(Value::Null)?);
+///
+/// db.set_path("servers[0].port", 8080.into())?;
+/// db.set_path("servers[0].name", "alpha".into())?;
+/// assert_eq!(db.get_path("servers[0].port")?, Some(8080.into()));
+/// assert_eq!(db.get_path_as::<String>("servers[0].name")?, Some("alpha".to_string()));
+/// assert_eq!(db.get_path("servers[1]")?, None);
+///
+/// assert_eq!(db.remove_path("servers[0].port")?, Some(8080.into()));
+/// assert_eq!(db.get_path("")?, Some(serde_json::json!({"servers": [{"name": "alpha"}]})));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct DynDatabase<Back, DeSer> {
+    db: Database<Value, Back, DeSer>,
+    auto_save: bool,
+}
+
+impl<Back, DeSer> DynDatabase<Back, DeSer>
+where
+    Back: Backend,
+    DeSer: DeSerializer<Value> + Send + Sync + Clone,
+{
+    /// Use the document in `db`.
+    pub fn new(db: Database<Value, Back, DeSer>) -> Self {
+        Self {
+            db,
+            auto_save: false,
+        }
+    }
+
+    /// Save after every change if `auto_save` is set, like
+    /// [`Database::write_and_save`]. Disabled by default.
+    #[must_use]
+    pub fn with_auto_save(mut self, auto_save: bool) -> Self {
+        self.auto_save = auto_save;
+        self
+    }
+
+    /// Get the underlying database, for example to [`Database::save`] it.
+    pub fn database(&self) -> &Database<Value, Back, DeSer> {
+        &self.db
+    }
+
+    /// Consume the `DynDatabase` and return the underlying database.
+    pub fn into_inner(self) -> Database<Value, Back, DeSer> {
+        self.db
+    }
+
+    /// Change the document, and save it if auto-save is enabled.
+    fn change<T, R>(&self, task: T) -> error::Result<R>
+    where
+        T: FnOnce(&mut Value) -> R,
+    {
+        if self.auto_save {
+            self.db.write_and_save(task)
+        } else {
+            self.db.write(task)
+        }
+    }
+
+    /// Get a clone of the value at `path`, `None` if there is none.
+    ///
+    /// Fails with [`RustbreakError::InvalidPath`] if `path` can not be
+    /// parsed.
+    pub fn get_path(&self, path: &str) -> error::Result<Option<Value>> {
+        let path = parse(path)?;
+        self.db.read(|doc| get(doc, &path).cloned())
+    }
+
+    /// Get the value at `path`, deserialized into a `T`.
+    ///
+    /// Fails with [`DeSerError::Json`] if it is not a `T`.
+    pub fn get_path_as<T: DeserializeOwned>(&self, path: &str) -> error::Result<Option<T>> {
+        self.get_path(path)?
+            .map(|value| serde_json::from_value(value).map_err(DeSerError::from))
+            .transpose()
+            .map_err(RustbreakError::from)
+    }
+
+    /// Set the value at `path`, returning the previous one.
+    ///
+    /// Missing objects and arrays on the way, or `null`s in their place, are
+    /// created. An index may be one past the end of an array, to append to
+    /// it.
+    ///
+    /// Fails with [`RustbreakError::InvalidPath`] if `path` can not be
+    /// parsed, leads through a value that is not an object or array, or
+    /// indexes past the end of an array. The document is not changed then.
+    pub fn set_path(&self, path: &str, value: Value) -> error::Result<Option<Value>> {
+        let path = parse(path)?;
+        // Fail before locking for writing, which would mark the database dirty.
+        self.db.read(|doc| check_set(doc, &path))??;
+        self.change(|doc| set(doc, &path, value))?
+    }
+
+    /// Remove the value at `path`, returning it.
+    ///
+    /// Removing an array element moves the ones after it down. Removing the
+    /// whole document, with the empty path, leaves `null`.
+    pub fn remove_path(&self, path: &str) -> error::Result<Option<Value>> {
+        let path = parse(path)?;
+        // Only lock for writing if there is something to remove.
+        if self.db.read(|doc| get(doc, &path).is_none())? {
+            return Ok(None);
+        }
+        self.change(|doc| remove(doc, &path))
+    }
+}
+
+impl<Back, DeSer> From<Database<Value, Back, DeSer>> for DynDatabase<Back, DeSer>
+where
+    Back: Backend,
+    DeSer: DeSerializer<Value> + Send + Sync + Clone,
+{
+    fn from(db: Database<Value, Back, DeSer>) -> Self {
+        Self::new(db)
+    }
+}
+
+/// A part of a path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+fn invalid(path: &str, reason: &str) -> RustbreakError {
+    RustbreakError::InvalidPath(format!("`{path}`: {reason}"))
+}
+
+/// Split `path` into its segments.
+fn parse(path: &str) -> error::Result<Vec<Segment>> {
+    let mut segments = Vec::new();
+    let mut rest = path;
+    let mut first = true;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('[') {
+            let end = after
+                .find(']')
+                .ok_or_else(|| invalid(path, "missing `]`"))?;
+            let index = after[..end]
+                .parse()
+                .map_err(|_| invalid(path, "an index has to be a number"))?;
+            segments.push(Segment::Index(index));
+            rest = &after[end + 1..];
+        } else {
+            let key = match rest.strip_prefix('.') {
+                Some(key) if !first => key,
+                _ if first => rest,
+                _ => return Err(invalid(path, "expected `.` or `[`")),
+            };
+            let end = key.find(['.', '[']).unwrap_or(key.len());
+            if end == 0 {
+                return Err(invalid(path, "empty key"));
+            }
+            segments.push(Segment::Key(key[..end].to_string()));
+            rest = &key[end..];
+        }
+        first = false;
+    }
+    Ok(segments)
+}
+
+fn get<'a>(doc: &'a Value, path: &[Segment]) -> Option<&'a Value> {
+    path.iter().try_fold(doc, |value, segment| match segment {
+        Segment::Key(key) => value.get(key),
+        Segment::Index(index) => value.get(index),
+    })
+}
+
+/// Check that [`set`] will succeed, without changing the document.
+fn check_set(doc: &Value, path: &[Segment]) -> error::Result<()> {
+    let mut current = Some(doc);
+    for (i, segment) in path.iter().enumerate() {
+        let shown = || show(&path[..=i]);
+        current = match (current, segment) {
+            (None | Some(Value::Null), Segment::Key(_) | Segment::Index(0)) => None,
+            (None | Some(Value::Null), Segment::Index(_)) => {
+                return Err(invalid(&shown(), "index past the end of a new array"))
+            }
+            (Some(Value::Object(map)), Segment::Key(key)) => map.get(key),
+            (Some(Value::Array(array)), Segment::Index(index)) => {
+                if *index > array.len() {
+                    return Err(invalid(&shown(), "index past the end of the array"));
+                }
+                array.get(*index)
+            }
+            (Some(_), Segment::Key(_)) => return Err(invalid(&shown(), "not in an object")),
+            (Some(_), Segment::Index(_)) => return Err(invalid(&shown(), "not in an array")),
+        };
+    }
+    Ok(())
+}
+
+/// Set the value at `path`, see [`DynDatabase::set_path`].
+fn set(doc: &mut Value, path: &[Segment], value: Value) -> error::Result<Option<Value>> {
+    check_set(doc, path)?;
+    let existed = get(doc, path).is_some();
+    let mut current = doc;
+    for (i, segment) in path.iter().enumerate() {
+        if current.is_null() {
+            *current = match segment {
+                Segment::Key(_) => Value::Object(serde_json::Map::new()),
+                Segment::Index(_) => Value::Array(Vec::new()),
+            };
+        }
+        current = match (current, segment) {
+            (Value::Object(map), Segment::Key(key)) => {
+                map.entry(key.clone()).or_insert(Value::Null)
+            }
+            (Value::Array(array), Segment::Index(index)) if *index <= array.len() => {
+                if *index == array.len() {
+                    array.push(Value::Null);
+                }
+                &mut array[*index]
+            }
+            _ => return Err(invalid(&show(&path[..=i]), "can not be set")),
+        };
+    }
+    let previous = std::mem::replace(current, value);
+    Ok(existed.then_some(previous))
+}
+
+/// Remove the value at `path`, see [`DynDatabase::remove_path`].
+fn remove(doc: &mut Value, path: &[Segment]) -> Option<Value> {
+    let Some((last, parent)) = path.split_last() else {
+        return Some(doc.take());
+    };
+    let parent = parent
+        .iter()
+        .try_fold(doc, |value, segment| match segment {
+            Segment::Key(key) => value.get_mut(key),
+            Segment::Index(index) => value.get_mut(index),
+        })?;
+    match (parent, last) {
+        (Value::Object(map), Segment::Key(key)) => map.remove(key),
+        (Value::Array(array), Segment::Index(index)) if *index < array.len() => {
+            Some(array.remove(*index))
+        }
+        _ => None,
+    }
+}
+
+/// Turn segments back into a path, for errors.
+fn show(path: &[Segment]) -> String {
+    let mut shown = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(key) if shown.is_empty() => shown.push_str(key),
+            Segment::Key(key) => {
+                shown.push('.');
+                shown.push_str(key);
+            }
+            Segment::Index(index) => {
+                let _ = write!(shown, "[{index}]");
+            }
+        }
+    }
+    shown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, DynDatabase, Segment, Value};
+    use crate::deser::Ron;
+    use crate::{MemoryDatabase, RustbreakError};
+    use serde_json::json;
+
+    #[test]
+    fn parse_paths() {
+        assert_eq!(parse("").unwrap(), []);
+        assert_eq!(
+            parse("a.b[2][0].c").unwrap(),
+            [
+                Segment::Key("a".into()),
+                Segment::Key("b".into()),
+                Segment::Index(2),
+                Segment::Index(0),
+                Segment::Key("c".into()),
+            ]
+        );
+        assert_eq!(parse("[1]").unwrap(), [Segment::Index(1)]);
+        for bad in ["a..b", ".a", "a[", "a[x]", "a[0]b", "a."] {
+            assert!(
+                matches!(parse(bad), Err(RustbreakError::InvalidPath(_))),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn set_get_and_remove_paths() {
+        let db = DynDatabase::new(
+            MemoryDatabase::<Value, Ron>::memory(json!({"name": "db", "tags": ["a"]}))
+                .expect("could not create database"),
+        )
+        .with_auto_save(true);
+
+        assert_eq!(db.set_path("tags[1]", "b".into()).unwrap(), None);
+        assert_eq!(
+            db.set_path("name", "renamed".into()).unwrap(),
+            Some("db".into())
+        );
+        db.set_path("nested.list[0].x", 1.into()).unwrap();
+        assert!(!db.database().is_dirty());
+        assert_eq!(
+            db.get_path("").unwrap(),
+            Some(json!({
+                "name": "renamed",
+                "tags": ["a", "b"],
+                "nested": {"list": [{"x": 1}]},
+            }))
+        );
+
+        // Failed sets leave the document alone.
+        for bad in ["tags[5]", "name.first", "tags.first", "new[1]"] {
+            assert!(db.set_path(bad, Value::Null).is_err(), "{}", bad);
+        }
+        assert_eq!(db.get_path("new").unwrap(), None);
+
+        assert_eq!(db.remove_path("tags[0]").unwrap(), Some("a".into()));
+        assert_eq!(
+            db.get_path_as::<Vec<String>>("tags").unwrap(),
+            Some(vec!["b".to_string()])
+        );
+        assert!(db.get_path_as::<u32>("tags").is_err());
+        assert_eq!(db.remove_path("missing.path").unwrap(), None);
+        db.database().load().expect("could not load");
+        assert_eq!(db.get_path("nested.list[0].x").unwrap(), Some(1.into()));
+    }
+}
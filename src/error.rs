@@ -5,12 +5,16 @@
 /// An error returned by a `DeSer` implementor
 #[derive(Debug, thiserror::Error)]
 #[non_exhaustive]
-#[allow(clippy::empty_enum)] // This can occur when no desers have beeen enabled
+#[allow(clippy::empty_enums)] // This can occur when no desers have beeen enabled
 pub enum DeSerError {
     #[cfg(feature = "yaml_enc")]
     /// An error occured with Yaml
     #[error("An error with yaml occured")]
     Yaml(#[from] serde_yaml::Error),
+    #[cfg(feature = "safe_yaml_enc")]
+    /// An error occured with `SafeYaml`
+    #[error("An error with yaml occured")]
+    SafeYaml(#[from] serde_yaml_ng::Error),
     #[cfg(feature = "ron_enc")]
     /// An error occured with Ron
     #[error("An error with Ron occured")]
@@ -19,6 +23,64 @@ pub enum DeSerError {
     /// An error occured with Bincode
     #[error("An error with Bincode occured")]
     Bincode(#[from] std::boxed::Box<bincode::ErrorKind>),
+    #[cfg(any(feature = "json_enc", feature = "migrations", feature = "dynamic"))]
+    /// An error occured with JSON
+    #[error("An error with JSON occured")]
+    Json(#[from] serde_json::Error),
+    #[cfg(feature = "toml_enc")]
+    /// An error occured while serializing TOML
+    #[error("An error while serializing TOML occured")]
+    TomlSer(#[from] toml::ser::Error),
+    #[cfg(feature = "toml_enc")]
+    /// An error occured while deserializing TOML
+    #[error("An error while deserializing TOML occured")]
+    TomlDe(#[from] toml::de::Error),
+    #[cfg(feature = "cbor_enc")]
+    /// An error occured while serializing CBOR
+    #[error("An error while serializing CBOR occured")]
+    CborSer(#[from] ciborium::ser::Error<std::io::Error>),
+    #[cfg(feature = "cbor_enc")]
+    /// An error occured while deserializing CBOR
+    #[error("An error while deserializing CBOR occured")]
+    CborDe(#[from] ciborium::de::Error<std::io::Error>),
+    #[cfg(feature = "postcard_enc")]
+    /// An error occured with Postcard
+    #[error("An error with Postcard occured")]
+    Postcard(#[from] postcard::Error),
+    #[cfg(feature = "armor")]
+    /// The data of an `Armored` was not valid base64
+    #[error("The data was not valid base64")]
+    Base64(#[from] base64::DecodeError),
+    #[cfg(feature = "encryption")]
+    /// The data could not be decrypted, it was either tampered with or
+    /// encrypted with another key
+    #[error("The data could not be decrypted")]
+    Decryption,
+    #[cfg(feature = "signing")]
+    /// The keyed hash of the data did not match, it was either tampered
+    /// with, truncated or signed with another key
+    #[error("The integrity check of the data failed")]
+    IntegrityFailure,
+    #[cfg(any(feature = "encryption", feature = "signing"))]
+    /// An `Encrypted` or `Signed` was used without a key
+    #[error("No encryption key was given")]
+    MissingKey,
+    #[cfg(feature = "migrations")]
+    /// The data could not be migrated to the current schema version
+    #[error("The data could not be migrated: {0}")]
+    Migration(String),
+    /// The data could not be serialized by `Canonical`
+    #[error("The data could not be serialized canonically: {0}")]
+    Canonical(String),
+    /// A `BoxedDeSer` was used without a format
+    #[error("No format was chosen for the data")]
+    MissingFormat,
+    /// An `AnyFormat` was parsed from an unknown or disabled format name
+    #[error("The format {0:?} is unknown or not enabled")]
+    UnknownFormat(String),
+    /// An I/O Error occured while reading the serialized data
+    #[error("An I/O Error occured")]
+    Io(#[from] std::io::Error),
     /// An internal error to Rustbreak occured
     #[error("An internal error to rustbreak occured, please report it to the maintainers")]
     Internal(String),
@@ -43,6 +105,62 @@ pub enum BackendError {
     /// An I/O Error occured
     #[error("An I/O Error occured")]
     Io(#[from] std::io::Error),
+    /// The stored data is not a valid envelope, see
+    /// [`Enveloped`](crate::backend::Enveloped)
+    #[error("The stored data is corrupt: {0}")]
+    Corrupt(String),
+    #[cfg(feature = "watch")]
+    /// The file could not be watched for changes
+    #[error("The file could not be watched")]
+    Watch(#[from] notify::Error),
+    /// The file is locked by another process
+    #[error("The database file is locked by another process")]
+    Locked,
+    /// The database was opened read-only, because another process writes it
+    #[error("The database is read-only, another process writes it")]
+    ReadOnly,
+    /// A conditional save failed, because the stored data was changed by
+    /// someone else since it was last read or written
+    #[error("The stored data was changed by someone else")]
+    Conflict,
+    /// The serialized data is larger than the quota set with
+    /// `Database::set_max_save_size`, and could not be made to fit by
+    /// `Database::save_evicting`. It was not saved
+    #[error("The data is {size} bytes, more than the quota of {limit} bytes")]
+    QuotaExceeded {
+        /// The size of the serialized data in bytes
+        size: u64,
+        /// The quota in bytes
+        limit: u64,
+    },
+    #[cfg(feature = "http")]
+    /// An HTTP request of the `HttpBackend` failed
+    #[error("An HTTP request failed")]
+    Http(#[from] Box<ureq::Error>),
+    #[cfg(feature = "object_store")]
+    /// A request of the `ObjectStoreBackend` failed
+    #[error("A request to the object store failed")]
+    ObjectStore(#[from] object_store::Error),
+    #[cfg(feature = "redis")]
+    /// A request of the `RedisBackend` failed
+    #[error("A request to Redis failed")]
+    Redis(#[from] redis::RedisError),
+    #[cfg(feature = "sqlite")]
+    /// A query of the `SqliteBackend` failed
+    #[error("A SQLite query failed")]
+    Sqlite(#[from] rusqlite::Error),
+    #[cfg(feature = "wasm")]
+    /// The browser storage of the `LocalStorageBackend` failed
+    #[error("The browser storage failed: {0}")]
+    WebStorage(String),
+    #[cfg(feature = "embedded")]
+    /// The flash of the `FlashBackend` failed
+    #[error("The flash storage failed: {0}")]
+    Flash(embedded_storage::nor_flash::NorFlashErrorKind),
+    #[cfg(feature = "zip")]
+    /// The archive of the `ZipEntryBackend` could not be read or written
+    #[error("The ZIP archive could not be read or written")]
+    Zip(#[from] zip::result::ZipError),
     /// An internal error to Rustbreak occured
     #[error("An internal error to rustbreak occured, please report it to the maintainers")]
     Internal(String),
@@ -61,13 +179,21 @@ pub enum BackendError {
 #[derive(Debug, thiserror::Error)]
 #[non_exhaustive]
 pub enum RustbreakError {
-    /// A context error when a DeSerialization failed
+    /// A context error when a `DeSerialization` failed
     #[error("Could not deserialize the value")]
     DeSerialization(#[from] DeSerError),
     /// This error is returned if the `Database` is poisoned. See
     /// `Database::write` for details
     #[error("The database has been poisoned")]
     Poison,
+    /// The database is locked, and `Database::try_read` or
+    /// `Database::try_write` did not wait for it
+    #[error("The database is locked")]
+    WouldBlock,
+    /// The database stayed locked for longer than the timeout given to
+    /// `Database::read_timeout` or `Database::write_timeout`
+    #[error("Timed out waiting for the database lock")]
+    Timeout,
     /// An error in the backend happened
     #[error("The backend has encountered an error")]
     Backend(#[from] BackendError),
@@ -75,6 +201,46 @@ pub enum RustbreakError {
     /// returned
     #[error("The write operation paniced but got caught")]
     WritePanic,
+    /// If `Database::read_safe` is used and the closure panics, this error is
+    /// returned
+    #[error("The read operation paniced but got caught")]
+    ReadPanic,
+    /// If external change detection is enabled, the data in the backend was
+    /// changed by someone else since it was last loaded or saved. See
+    /// `Database::set_external_change_detection`
+    #[error("The data in the backend was changed externally")]
+    ExternalChange,
+    /// The stored data is larger than the limit set with
+    /// `Database::set_max_load_size`, it was not loaded
+    #[error("The stored data is {size} bytes, more than the limit of {limit} bytes")]
+    TooLarge {
+        /// The size of the stored data in bytes
+        size: u64,
+        /// The limit in bytes
+        limit: u64,
+    },
+    /// The validator set with `Database::set_validator` rejected the data,
+    /// it was not saved or loaded
+    #[error("The data is invalid")]
+    Validation(#[source] Box<dyn std::error::Error + Send + Sync>),
+    #[cfg(feature = "dynamic")]
+    /// A path given to a `DynDatabase` could not be parsed, or does not fit
+    /// the document
+    #[error("Invalid document path {0}")]
+    InvalidPath(String),
+    /// If the closure given to `Database::write_fallible` returns an error,
+    /// it is returned wrapped in this variant
+    #[error("The write operation was aborted")]
+    Aborted(#[source] Box<dyn std::error::Error + Send + Sync>),
+    /// The directory of a `ShardedDatabase` holds another number of shards
+    /// than it was opened with
+    #[error("The directory holds {found} shards, not {expected}")]
+    ShardCount {
+        /// The number of shards in the directory
+        found: usize,
+        /// The number of shards the database was opened with
+        expected: usize,
+    },
 }
 
 /// A simple type alias for errors
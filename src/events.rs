@@ -0,0 +1,277 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A database that stores the events changing its state, instead of the
+//! state itself.
+//!
+//! See [`EventDatabase`] for details.
+
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::backend::RecordLog;
+use crate::error::{self, BackendError};
+use crate::sync::{Mutex, RwLock};
+use crate::DeSerializer;
+
+/// The bytes every event log starts with.
+const MAGIC: [u8; 4] = *b"RBEV";
+/// A record holding a snapshot of the state.
+const SNAPSHOT: u8 = 0;
+/// A record holding an event.
+const EVENT: u8 = 1;
+
+/// The log of an [`EventDatabase`].
+#[derive(Debug)]
+struct Log {
+    records: RecordLog,
+    /// The number of events after the last snapshot.
+    events: usize,
+}
+
+/// A database whose state is only changed by events, which are appended to
+/// a log.
+///
+/// You give it a function applying an event to the state. Emitting an event
+/// first appends it to the log, and then applies it. Loading replays the
+/// log, so the state is always the result of the events that were stored,
+/// even if the program crashed while writing one: a partially written event
+/// is ignored.
+///
+/// Since only the events are written, emitting one is cheap no matter how
+/// large the state is. To keep the log from growing forever, a snapshot of
+/// the state replaces it every [`EventDatabase::with_snapshot_every`]
+/// events, and whenever you call [`EventDatabase::snapshot`].
+///
+/// The `DeSer` is used for both the state and the events.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::{deser::Ron, events::EventDatabase};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// enum Event {
+///     Deposit(u64),
+///     Withdraw(u64),
+/// }
+///
+/// fn apply(balance: &mut u64, event: &Event) {
+///     match event {
+///         Event::Deposit(amount) => *balance += amount,
+///         Event::Withdraw(amount) => *balance -= amount,
+///     }
+/// }
+///
+/// # fn main() -> rustbreak::error::Result<()> {
+/// # let dir = tempfile::tempdir().unwrap();
+/// let path = dir.path().join("account");
+/// let db = EventDatabase::<u64, Event, Ron>::open(&path, apply)?;
+/// db.emit(Event::Deposit(100))?;
+/// db.emit(Event::Withdraw(30))?;
+/// assert_eq!(db.read(|balance| *balance)?, 70);
+///
+/// let db = EventDatabase::<u64, Event, Ron>::open(&path, apply)?;
+/// assert_eq!(db.read(|balance| *balance)?, 70);
+/// assert_eq!(db.events()?, 2);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct EventDatabase<State, Event, DeSer> {
+    state: RwLock<State>,
+    log: Mutex<Log>,
+    apply: fn(&mut State, &Event),
+    deser: DeSer,
+    snapshot_every: usize,
+}
+
+impl<State, Event, DeSer> EventDatabase<State, Event, DeSer>
+where
+    State: Serialize + DeserializeOwned + Default + Send,
+    Event: Serialize + DeserializeOwned,
+    DeSer: DeSerializer<State> + DeSerializer<Event>,
+{
+    /// Open the event log at `path`, creating it if it doesn't yet exist,
+    /// and replay it with `apply`, starting from the default state.
+    pub fn open<P: AsRef<Path>>(path: P, apply: fn(&mut State, &Event)) -> error::Result<Self> {
+        let db = Self {
+            state: RwLock::new(State::default()),
+            log: Mutex::new(Log {
+                records: RecordLog::open(path.as_ref().to_owned(), MAGIC)?,
+                events: 0,
+            }),
+            apply,
+            deser: DeSer::default(),
+            snapshot_every: 1000,
+        };
+        db.load()?;
+        Ok(db)
+    }
+
+    /// Replace the log with a snapshot once it holds `events` events after
+    /// the last snapshot, the default is `1000`. An `events` of `0` only
+    /// takes snapshots when [`EventDatabase::snapshot`] is called.
+    #[must_use]
+    pub fn with_snapshot_every(mut self, events: usize) -> Self {
+        self.snapshot_every = events;
+        self
+    }
+
+    /// Store `event` and apply it to the state.
+    ///
+    /// If a snapshot is due and taking it fails, the event is still stored
+    /// and applied, and the error is returned.
+    pub fn emit(&self, event: Event) -> error::Result<()> {
+        self.emit_all(std::iter::once(event))
+    }
+
+    /// Store `events` in a single write, and apply them to the state in
+    /// order.
+    ///
+    /// Either all or none of the events are stored.
+    pub fn emit_all<I>(&self, events: I) -> error::Result<()>
+    where
+        I: IntoIterator<Item = Event>,
+    {
+        let mut log = self.log.lock()?;
+        let events: Vec<Event> = events.into_iter().collect();
+        let serialized = events
+            .iter()
+            .map(|event| DeSerializer::<Event>::serialize(&self.deser, event))
+            .collect::<error::DeSerResult<Vec<_>>>()?;
+        let records: Vec<(u8, &[u8])> = serialized.iter().map(|ser| (EVENT, &ser[..])).collect();
+        log.records.append(&records)?;
+        log.events += events.len();
+
+        let mut state = self.state.write()?;
+        for event in &events {
+            (self.apply)(&mut state, event);
+        }
+        if self.snapshot_every > 0 && log.events >= self.snapshot_every {
+            self.write_snapshot(&mut log, &state)?;
+        }
+        Ok(())
+    }
+
+    /// Read lock the state, and run `task` on it.
+    pub fn read<T, R>(&self, task: T) -> error::Result<R>
+    where
+        T: FnOnce(&State) -> R,
+    {
+        let state = self.state.read()?;
+        Ok(task(&state))
+    }
+
+    /// Get a clone of the state.
+    pub fn get_data(&self) -> error::Result<State>
+    where
+        State: Clone,
+    {
+        self.read(State::clone)
+    }
+
+    /// The number of events stored after the last snapshot.
+    pub fn events(&self) -> error::Result<usize> {
+        Ok(self.log.lock()?.events)
+    }
+
+    /// Replace the log with a snapshot of the current state.
+    ///
+    /// The snapshot is written to a temporary file, which then replaces the
+    /// log, so no events are lost if this fails.
+    pub fn snapshot(&self) -> error::Result<()> {
+        let mut log = self.log.lock()?;
+        let state = self.state.read()?;
+        self.write_snapshot(&mut log, &state)
+    }
+
+    fn write_snapshot(&self, log: &mut Log, state: &State) -> error::Result<()> {
+        let ser = DeSerializer::<State>::serialize(&self.deser, state)?;
+        log.records.rewrite(SNAPSHOT, &ser)?;
+        log.events = 0;
+        Ok(())
+    }
+
+    /// Replay the log into the default state, replacing the current state.
+    pub fn load(&self) -> error::Result<()> {
+        let mut log = self.log.lock()?;
+        let mut state = State::default();
+        let mut events = 0;
+        log.records.replay(|kind, payload| {
+            match kind {
+                SNAPSHOT => {
+                    state = DeSerializer::<State>::deserialize(&self.deser, payload)?;
+                    events = 0;
+                }
+                EVENT => {
+                    let event = DeSerializer::<Event>::deserialize(&self.deser, payload)?;
+                    (self.apply)(&mut state, &event);
+                    events += 1;
+                }
+                _ => {
+                    return Err(
+                        BackendError::Corrupt("unknown event log record".to_string()).into(),
+                    )
+                }
+            }
+            Ok::<_, error::RustbreakError>(())
+        })?;
+        log.events = events;
+        *self.state.write()? = state;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EventDatabase;
+    use crate::deser::Ron;
+    use std::io::Write;
+
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    fn push(list: &mut Vec<u32>, event: &u32) {
+        list.push(*event);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn events_replay_and_snapshot() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let path = dir.path().join("events");
+        let db = EventDatabase::<Vec<u32>, u32, Ron>::open(&path, push)
+            .expect("could not open database")
+            .with_snapshot_every(5);
+        db.emit_all(0..3).expect("could not emit");
+        assert_eq!(db.events().expect("could not get events"), 3);
+        db.emit_all(3..6).expect("could not emit");
+        assert_eq!(db.events().expect("could not get events"), 0);
+        db.emit(6).expect("could not emit");
+
+        // A partially written event is ignored.
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .expect("could not open log");
+        file.write_all(&[1, 10]).expect("could not write");
+
+        let db = EventDatabase::<Vec<u32>, u32, Ron>::open(&path, push)
+            .expect("could not open database");
+        assert_eq!(db.events().expect("could not get events"), 1);
+        db.emit(7).expect("could not emit");
+        assert_eq!(
+            db.get_data().expect("could not get data"),
+            (0..8).collect::<Vec<_>>()
+        );
+
+        db.snapshot().expect("could not snapshot");
+        db.load().expect("could not load");
+        assert_eq!(db.events().expect("could not get events"), 0);
+        assert_eq!(db.read(Vec::len).expect("could not read"), 8);
+    }
+}
@@ -0,0 +1,316 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A map whose entries expire, to use Rustbreak as a cache or token store.
+//!
+//! See [`ExpiringMap`] for details.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::time::{Duration, SystemTime};
+
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{SerializeMap, Serializer};
+use serde::Serialize;
+
+/// A value and when it expires.
+#[derive(Clone)]
+struct Entry<V> {
+    value: V,
+    expires_at: Option<SystemTime>,
+}
+
+impl<V> Entry<V> {
+    fn is_live(&self, now: SystemTime) -> bool {
+        self.expires_at.is_none_or(|at| at > now)
+    }
+}
+
+/// A `HashMap` whose entries can have an expiration time.
+///
+/// Use it as the data of a [`Database`](crate::Database). Expired entries
+/// are skipped by every lookup, as if they had been removed, but stay in
+/// memory until [`ExpiringMap::purge_expired`] is called. They are never
+/// serialized, so saving the database drops them from the backend too.
+///
+/// Expiration uses the wall clock, and is stored as a [`SystemTime`], so it
+/// carries across restarts.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::{deser::Ron, expiring::ExpiringMap, MemoryDatabase};
+/// use std::time::Duration;
+///
+/// # fn main() -> rustbreak::error::Result<()> {
+/// let db = MemoryDatabase::<ExpiringMap<String, String>, Ron>::memory(ExpiringMap::new())?;
+///
+/// db.write(|tokens| {
+///     tokens.insert_with_ttl("session".to_owned(), "abc".to_owned(), Duration::from_hours(1));
+///     tokens.insert("api".to_owned(), "xyz".to_owned());
+/// })?;
+/// assert_eq!(db.read(|tokens| tokens.get("session").cloned())?.as_deref(), Some("abc"));
+///
+/// // Every now and then
+/// db.write(|tokens| tokens.purge_expired())?;
+/// db.save()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ExpiringMap<K, V> {
+    entries: HashMap<K, Entry<V>>,
+}
+
+impl<K, V> Default for ExpiringMap<K, V> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for ExpiringMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(
+                self.entries
+                    .iter()
+                    .map(|(key, entry)| (key, (&entry.value, entry.expires_at))),
+            )
+            .finish()
+    }
+}
+
+impl<K: Eq + Hash, V> ExpiringMap<K, V> {
+    /// An empty map.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert an entry that never expires, returning the live value it
+    /// replaced.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert_entry(key, value, None)
+    }
+
+    /// Insert an entry that expires after `ttl`, returning the live value it
+    /// replaced.
+    pub fn insert_with_ttl(&mut self, key: K, value: V, ttl: Duration) -> Option<V> {
+        self.insert_entry(key, value, Some(SystemTime::now() + ttl))
+    }
+
+    /// Insert an entry that expires at `expires_at`, returning the live value
+    /// it replaced.
+    pub fn insert_with_expiry(&mut self, key: K, value: V, expires_at: SystemTime) -> Option<V> {
+        self.insert_entry(key, value, Some(expires_at))
+    }
+
+    fn insert_entry(&mut self, key: K, value: V, expires_at: Option<SystemTime>) -> Option<V> {
+        let now = SystemTime::now();
+        self.entries
+            .insert(key, Entry { value, expires_at })
+            .filter(|old| old.is_live(now))
+            .map(|old| old.value)
+    }
+
+    /// Get the value of `key`, unless it expired.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.live(key).map(|entry| &entry.value)
+    }
+
+    /// Get the value of `key` mutably, unless it expired.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let now = SystemTime::now();
+        self.entries
+            .get_mut(key)
+            .filter(|entry| entry.is_live(now))
+            .map(|entry| &mut entry.value)
+    }
+
+    /// When the entry of `key` expires, `None` if it never does, or if there
+    /// is no live entry.
+    pub fn expires_at<Q>(&self, key: &Q) -> Option<SystemTime>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.live(key).and_then(|entry| entry.expires_at)
+    }
+
+    /// Set when the live entry of `key` expires, `None` for never.
+    ///
+    /// Returns whether there was a live entry.
+    pub fn set_expiry<Q>(&mut self, key: &Q, expires_at: Option<SystemTime>) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let now = SystemTime::now();
+        match self.entries.get_mut(key).filter(|entry| entry.is_live(now)) {
+            Some(entry) => {
+                entry.expires_at = expires_at;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether there is a live entry for `key`.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.live(key).is_some()
+    }
+
+    /// Remove the entry of `key`, returning its value unless it expired.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let now = SystemTime::now();
+        self.entries
+            .remove(key)
+            .filter(|entry| entry.is_live(now))
+            .map(|entry| entry.value)
+    }
+
+    /// Remove all expired entries, returning how many there were.
+    pub fn purge_expired(&mut self) -> usize {
+        let now = SystemTime::now();
+        let before = self.entries.len();
+        self.entries.retain(|_, entry| entry.is_live(now));
+        before - self.entries.len()
+    }
+
+    /// The number of live entries.
+    ///
+    /// This looks at every entry, since entries expire without being touched.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Whether there are no live entries.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    /// Remove all entries.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Iterate over the live entries, in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        let now = SystemTime::now();
+        self.entries
+            .iter()
+            .filter(move |(_, entry)| entry.is_live(now))
+            .map(|(key, entry)| (key, &entry.value))
+    }
+
+    fn live<Q>(&self, key: &Q) -> Option<&Entry<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.entries
+            .get(key)
+            .filter(|entry| entry.is_live(SystemTime::now()))
+    }
+}
+
+/// Serializes the live entries as a map from the key to a tuple of the value
+/// and the expiration time.
+impl<K: Serialize, V: Serialize> Serialize for ExpiringMap<K, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let now = SystemTime::now();
+        let live: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.is_live(now))
+            .collect();
+        let mut map = serializer.serialize_map(Some(live.len()))?;
+        for (key, entry) in live {
+            map.serialize_entry(key, &(&entry.value, entry.expires_at))?;
+        }
+        map.end()
+    }
+}
+
+impl<'de, K, V> Deserialize<'de> for ExpiringMap<K, V>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    V: Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let entries = HashMap::<K, (V, Option<SystemTime>)>::deserialize(deserializer)?;
+        Ok(Self {
+            entries: entries
+                .into_iter()
+                .map(|(key, (value, expires_at))| (key, Entry { value, expires_at }))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(all(test, feature = "ron_enc"))]
+mod tests {
+    use super::ExpiringMap;
+    use crate::deser::{DeSerializer, Ron};
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn expired_entries_are_skipped_and_not_saved() {
+        let mut map = ExpiringMap::<String, u32>::new();
+        let past = SystemTime::now() - Duration::from_secs(1);
+        map.insert("forever".to_owned(), 1);
+        map.insert_with_ttl("later".to_owned(), 2, Duration::from_hours(1));
+        map.insert_with_expiry("gone".to_owned(), 3, past);
+
+        assert_eq!(map.get("forever"), Some(&1));
+        assert_eq!(map.get("later"), Some(&2));
+        assert_eq!(map.get("gone"), None);
+        assert!(!map.contains_key("gone"));
+        assert_eq!(map.len(), 2);
+        assert_eq!(
+            map.insert("gone".to_owned(), 4),
+            None,
+            "the old value expired"
+        );
+        assert!(map.set_expiry("gone", Some(past)));
+
+        let bytes = Ron::default().serialize(&map).expect("could not serialize");
+        let loaded: ExpiringMap<String, u32> = Ron::default()
+            .deserialize(&bytes[..])
+            .expect("could not deserialize");
+        assert_eq!(loaded.entries.len(), 2);
+        assert!(loaded.expires_at("later").is_some());
+        assert_eq!(loaded.expires_at("forever"), None);
+
+        assert_eq!(map.purge_expired(), 1);
+        assert_eq!(map.remove("later"), Some(2));
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            [(&"forever".to_owned(), &1)]
+        );
+    }
+}
@@ -0,0 +1,175 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Databases in `static`s, opened on first use.
+//!
+//! See [`StaticDatabase`] for details.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::backend::{Backend, PathBackend};
+use crate::{error, registry, Database, DeSerializer};
+
+/// A database that can live in a `static`, and is opened on first use.
+///
+/// This covers the global configuration pattern without extra crates: the
+/// database is opened by the first call to [`StaticDatabase::get_or_init`],
+/// every later call returns the same database. Opening it runs at most
+/// once, even if several threads race for it.
+///
+/// Once opened, the database is [registered](crate::registry::register), so
+/// [`flush_all`](crate::flush_all), or the guard of
+/// [`registry::flush_on_shutdown`], saves it on shutdown. Values in `static`s
+/// are never dropped, so nothing is saved without one of them, or a call to
+/// [`StaticDatabase::flush`].
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// # extern crate tempfile;
+/// use rustbreak::global::StaticDatabase;
+/// use rustbreak::{backend::PathBackend, deser::Ron, registry};
+///
+/// static SETTINGS: StaticDatabase<Vec<String>, PathBackend, Ron> = StaticDatabase::new();
+///
+/// # fn main() -> rustbreak::error::Result<()> {
+/// # let dir = tempfile::tempdir().unwrap();
+/// # let path = dir.path().join("settings.ron");
+/// let _shutdown = registry::flush_on_shutdown()?;
+///
+/// let settings = SETTINGS.get_or_init(&path)?;
+/// settings.write(|settings| settings.push("verbose".to_owned()))?;
+///
+/// // Later, anywhere in the program
+/// let settings = SETTINGS.get().expect("settings were opened in main");
+/// assert_eq!(settings.read(|settings| settings.len())?, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub struct StaticDatabase<Data, Back, DeSer> {
+    db: OnceLock<Arc<Database<Data, Back, DeSer>>>,
+    /// Held while opening, so that only one thread opens the database.
+    init: Mutex<()>,
+}
+
+impl<Data, Back, DeSer> StaticDatabase<Data, Back, DeSer> {
+    /// A database that is not opened yet.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            db: OnceLock::new(),
+            init: Mutex::new(()),
+        }
+    }
+
+    /// The database, if it was opened already.
+    pub fn get(&self) -> Option<&Database<Data, Back, DeSer>> {
+        self.db.get().map(|db| &**db)
+    }
+}
+
+impl<Data, Back, DeSer> StaticDatabase<Data, Back, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send + Sync + 'static,
+    Back: Backend + Send + 'static,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone + 'static,
+{
+    /// The database, opening it with `open` if it is not opened yet.
+    ///
+    /// If `open` fails, the error is returned and the next call tries again.
+    pub fn get_or_try_init<F>(&self, open: F) -> error::Result<&Database<Data, Back, DeSer>>
+    where
+        F: FnOnce() -> error::Result<Database<Data, Back, DeSer>>,
+    {
+        if let Some(db) = self.get() {
+            return Ok(db);
+        }
+        // The lock only guards the call of `open`, which can not leave it in
+        // an inconsistent state.
+        let _init = self.init.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(db) = self.get() {
+            return Ok(db);
+        }
+        let db = Arc::new(open()?);
+        registry::register(&db);
+        Ok(&**self.db.get_or_init(|| db))
+    }
+
+    /// Save the database if it was opened and is dirty.
+    ///
+    /// Returns whether a save happened.
+    pub fn flush(&self) -> error::Result<bool> {
+        match self.get() {
+            Some(db) => db.save_if_dirty(),
+            None => Ok(false),
+        }
+    }
+}
+
+impl<Data, DeSer> StaticDatabase<Data, PathBackend, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Default + Send + Sync + 'static,
+    DeSer: DeSerializer<Data> + Default + Send + Sync + Clone + 'static,
+{
+    /// The database, opening the file at `path` if it is not opened yet, like
+    /// [`Database::load_from_path_or_default`] does.
+    ///
+    /// Once the database is opened, `path` is ignored, later calls return it
+    /// even if they pass another path.
+    pub fn get_or_init<P: Into<PathBuf>>(
+        &self,
+        path: P,
+    ) -> error::Result<&Database<Data, PathBackend, DeSer>> {
+        self.get_or_try_init(|| {
+            Database::<Data, PathBackend, DeSer>::load_from_path_or_default(path.into())
+        })
+    }
+}
+
+impl<Data, Back, DeSer> Default for StaticDatabase<Data, Back, DeSer> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Data, Back, DeSer> std::fmt::Debug for StaticDatabase<Data, Back, DeSer> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StaticDatabase")
+            .field("initialized", &self.db.get().is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, feature = "ron_enc"))]
+mod tests {
+    use super::StaticDatabase;
+    use crate::{backend::MemoryBackend, deser::Ron, error::RustbreakError, Database};
+
+    static NUMBERS: StaticDatabase<Vec<u32>, MemoryBackend, Ron> = StaticDatabase::new();
+
+    #[test]
+    fn opens_once() {
+        assert!(NUMBERS.get().is_none());
+        assert!(NUMBERS
+            .get_or_try_init(|| Err(RustbreakError::Poison))
+            .is_err());
+        assert!(!NUMBERS.flush().expect("could not flush"));
+
+        let db = NUMBERS
+            .get_or_try_init(|| Database::memory(vec![1]))
+            .expect("could not open");
+        db.write(|numbers| numbers.push(2))
+            .expect("could not write");
+        let again = NUMBERS
+            .get_or_try_init(|| panic!("opened twice"))
+            .expect("could not open");
+        assert_eq!(again.get_data(false).expect("could not read"), [1, 2]);
+        assert!(NUMBERS.flush().expect("could not flush"));
+    }
+}
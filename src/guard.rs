@@ -0,0 +1,170 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Guards holding the lock of a database.
+//!
+//! See [`Database::borrow_data`](crate::Database::borrow_data),
+//! [`Database::borrow_data_mut`](crate::Database::borrow_data_mut),
+//! [`Database::borrow_data_map`](crate::Database::borrow_data_map) and
+//! [`Database::borrow_data_mut_autosave`](crate::Database::borrow_data_mut_autosave).
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::backend::Backend;
+use crate::{error, Database, DeSerializer};
+
+pub use crate::sync::{ReadGuard, WriteGuard};
+
+/// A projection from the data to a part of it.
+type MapFn<'a, Data, U> = dyn Fn(&Data) -> &U + 'a;
+
+/// A read lock on a database that dereferences to a part of the data.
+///
+/// It is returned by
+/// [`Database::borrow_data_map`](crate::Database::borrow_data_map), and
+/// keeps the database read locked until it is dropped, like
+/// [`Database::borrow_data`](crate::Database::borrow_data) does.
+///
+/// The projection is applied on every dereference, so it should be cheap,
+/// like accessing a field.
+pub struct MappedReadGuard<'a, Data, U: ?Sized> {
+    guard: ReadGuard<'a, Data>,
+    map: Box<MapFn<'a, Data, U>>,
+}
+
+impl<'a, Data, U: ?Sized> MappedReadGuard<'a, Data, U> {
+    pub(crate) fn new<F>(guard: ReadGuard<'a, Data>, map: F) -> Self
+    where
+        F: Fn(&Data) -> &U + 'a,
+    {
+        Self {
+            guard,
+            map: Box::new(map),
+        }
+    }
+}
+
+impl<Data, U: ?Sized> Deref for MappedReadGuard<'_, Data, U> {
+    type Target = U;
+
+    fn deref(&self) -> &U {
+        (self.map)(&self.guard)
+    }
+}
+
+impl<Data, U: ?Sized + fmt::Debug> fmt::Debug for MappedReadGuard<'_, Data, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("MappedReadGuard").field(&&**self).finish()
+    }
+}
+
+/// A write lock on a database that saves the data when it is released.
+///
+/// It is returned by
+/// [`Database::borrow_data_mut_autosave`](crate::Database::borrow_data_mut_autosave),
+/// and dereferences to the data like
+/// [`Database::borrow_data_mut`](crate::Database::borrow_data_mut) does. When
+/// it is dropped, the watchers are notified and the data is saved before the
+/// lock is released, like with
+/// [`Database::write_and_save`](crate::Database::write_and_save). Errors can
+/// not be reported from a destructor, call [`SavingWriteGuard::commit`] to
+/// handle them.
+///
+/// Nothing is saved if the guard is dropped during a panic, as the data may
+/// be half changed. The database stays dirty though, so that
+/// [`Database::save_if_dirty`](crate::Database::save_if_dirty) and the
+/// autosave saves it later, unless the lock is poisoned (without the
+/// `parking_lot` feature).
+pub struct SavingWriteGuard<'a, Data, Back, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send,
+    Back: Backend,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    db: &'a Database<Data, Back, DeSer>,
+    lock: Option<WriteGuard<'a, Data>>,
+}
+
+impl<'a, Data, Back, DeSer> SavingWriteGuard<'a, Data, Back, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send,
+    Back: Backend,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    pub(crate) fn new(db: &'a Database<Data, Back, DeSer>, lock: WriteGuard<'a, Data>) -> Self {
+        Self {
+            db,
+            lock: Some(lock),
+        }
+    }
+
+    /// Save the data and release the lock, returning any error of the save.
+    pub fn commit(mut self) -> error::Result<()> {
+        match self.lock.take() {
+            Some(lock) => self.db.commit_write(lock),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<Data, Back, DeSer> Deref for SavingWriteGuard<'_, Data, Back, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send,
+    Back: Backend,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    type Target = Data;
+
+    fn deref(&self) -> &Data {
+        // `lock` is only ever taken by a method consuming `self`.
+        self.lock.as_ref().expect("lock was already released")
+    }
+}
+
+impl<Data, Back, DeSer> DerefMut for SavingWriteGuard<'_, Data, Back, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send,
+    Back: Backend,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    fn deref_mut(&mut self) -> &mut Data {
+        // `lock` is only ever taken by a method consuming `self`.
+        self.lock.as_mut().expect("lock was already released")
+    }
+}
+
+impl<Data, Back, DeSer> fmt::Debug for SavingWriteGuard<'_, Data, Back, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send + fmt::Debug,
+    Back: Backend,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SavingWriteGuard").field(&&**self).finish()
+    }
+}
+
+impl<Data, Back, DeSer> Drop for SavingWriteGuard<'_, Data, Back, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send,
+    Back: Backend,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    fn drop(&mut self) {
+        if let Some(lock) = self.lock.take() {
+            if std::thread::panicking() {
+                // Leave saving the changes made so far to whoever recovers.
+                self.db.mark_dirty();
+                drop(lock);
+            } else {
+                // Errors can not be reported from drop, `commit` exists for that.
+                let _ = self.db.commit_write(lock);
+            }
+        }
+    }
+}
@@ -0,0 +1,122 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Hooks that run when a database saves or loads.
+//!
+//! See [`Database::on_before_save`](crate::Database::on_before_save),
+//! [`Database::on_after_save`](crate::Database::on_after_save) and
+//! [`Database::on_after_load`](crate::Database::on_after_load), and
+//! [`Database::set_validator`](crate::Database::set_validator) for a hook
+//! that can reject the data.
+
+use std::fmt;
+
+/// Identifies a hook registered on a database.
+///
+/// Pass it to [`Database::remove_hook`](crate::Database::remove_hook) to
+/// remove the hook again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HookId(u64);
+
+/// When a hook runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Event {
+    BeforeSave,
+    AfterSave,
+    AfterLoad,
+}
+
+type Hook<Data> = dyn FnMut(&Data) + Send;
+
+/// The error returned by a validator.
+pub(crate) type ValidationError = Box<dyn std::error::Error + Send + Sync>;
+
+type Validate<Data> = dyn Fn(&Data) -> Result<(), ValidationError> + Send;
+
+/// The hooks registered on a database.
+pub(crate) struct Hooks<Data> {
+    next_id: u64,
+    entries: Vec<(HookId, Event, Box<Hook<Data>>)>,
+    /// The validator, and whether it also checks loaded data.
+    validator: Option<(Box<Validate<Data>>, bool)>,
+}
+
+impl<Data> Default for Hooks<Data> {
+    fn default() -> Self {
+        Self {
+            next_id: 0,
+            entries: Vec::new(),
+            validator: None,
+        }
+    }
+}
+
+impl<Data> fmt::Debug for Hooks<Data> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("len", &self.entries.len())
+            .field("validator", &self.validator.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Data> Hooks<Data> {
+    /// Register a hook to run on `event`.
+    pub(crate) fn add<F>(&mut self, event: Event, hook: F) -> HookId
+    where
+        F: FnMut(&Data) + Send + 'static,
+    {
+        let id = HookId(self.next_id);
+        self.next_id += 1;
+        self.entries.push((id, event, Box::new(hook)));
+        id
+    }
+
+    /// Remove a hook, returns whether it was registered.
+    pub(crate) fn remove(&mut self, id: HookId) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|(entry, _, _)| *entry != id);
+        len != self.entries.len()
+    }
+
+    /// Whether any hook runs on `event`.
+    pub(crate) fn has(&self, event: Event) -> bool {
+        self.entries.iter().any(|(_, e, _)| *e == event)
+    }
+
+    /// Run the hooks of `event`, in the order they were registered.
+    pub(crate) fn run(&mut self, event: Event, data: &Data) {
+        for (_, e, hook) in &mut self.entries {
+            if *e == event {
+                hook(data);
+            }
+        }
+    }
+
+    /// Replace the validator.
+    pub(crate) fn set_validator<F, E>(&mut self, on_load: bool, validator: F)
+    where
+        F: Fn(&Data) -> Result<(), E> + Send + 'static,
+        E: Into<ValidationError>,
+    {
+        let validate: Box<Validate<Data>> =
+            Box::new(move |data| validator(data).map_err(Into::into));
+        self.validator = Some((validate, on_load));
+    }
+
+    /// Remove the validator.
+    pub(crate) fn clear_validator(&mut self) {
+        self.validator = None;
+    }
+
+    /// Check `data` that is about to be saved, or was loaded if `loaded`.
+    pub(crate) fn validate(&self, data: &Data, loaded: bool) -> crate::error::Result<()> {
+        match &self.validator {
+            Some((validate, on_load)) if !loaded || *on_load => {
+                validate(data).map_err(crate::error::RustbreakError::Validation)
+            }
+            _ => Ok(()),
+        }
+    }
+}
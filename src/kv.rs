@@ -0,0 +1,191 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A key-value database, the most common way to use Rustbreak.
+//!
+//! See [`KvDatabase`] for details.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::backend::Backend;
+use crate::{error, Database, DeSerializer};
+
+/// A [`Database`] holding a `HashMap`, with methods to use the map directly.
+///
+/// Every method locks the database once, like [`Database::read`] or
+/// [`Database::write`] would. Values are cloned out of the map. With
+/// [`KvDatabase::with_auto_save`], every change is saved right away.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::{deser::Ron, kv::KvDatabase, MemoryDatabase};
+/// use std::collections::HashMap;
+///
+/// # fn main() -> rustbreak::error::Result<()> {
+/// let db = KvDatabase::new(MemoryDatabase::<HashMap<String, u32>, Ron>::memory(HashMap::new())?)
+///     .with_auto_save(true);
+///
+/// db.insert("apples".to_string(), 3)?;
+/// assert_eq!(db.get("apples")?, Some(3));
+/// assert!(db.contains_key("apples")?);
+/// assert_eq!(db.remove("apples")?, Some(3));
+/// assert!(db.is_empty()?);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct KvDatabase<K, V, Back, DeSer> {
+    db: Database<HashMap<K, V>, Back, DeSer>,
+    auto_save: bool,
+}
+
+impl<K, V, Back, DeSer> KvDatabase<K, V, Back, DeSer>
+where
+    K: Serialize + DeserializeOwned + Eq + Hash + Clone + Send,
+    V: Serialize + DeserializeOwned + Clone + Send,
+    Back: Backend,
+    DeSer: DeSerializer<HashMap<K, V>> + Send + Sync + Clone,
+{
+    /// Use the map in `db`.
+    pub fn new(db: Database<HashMap<K, V>, Back, DeSer>) -> Self {
+        Self {
+            db,
+            auto_save: false,
+        }
+    }
+
+    /// Save after every change if `auto_save` is set, like
+    /// [`Database::write_and_save`]. Disabled by default.
+    #[must_use]
+    pub fn with_auto_save(mut self, auto_save: bool) -> Self {
+        self.auto_save = auto_save;
+        self
+    }
+
+    /// Get the underlying database, for example to [`Database::save`] it.
+    pub fn database(&self) -> &Database<HashMap<K, V>, Back, DeSer> {
+        &self.db
+    }
+
+    /// Consume the `KvDatabase` and return the underlying database.
+    pub fn into_inner(self) -> Database<HashMap<K, V>, Back, DeSer> {
+        self.db
+    }
+
+    /// Change the map, and save it if auto-save is enabled.
+    fn change<T, R>(&self, task: T) -> error::Result<R>
+    where
+        T: FnOnce(&mut HashMap<K, V>) -> R,
+    {
+        if self.auto_save {
+            self.db.write_and_save(task)
+        } else {
+            self.db.write(task)
+        }
+    }
+
+    /// Get a clone of the value of `key`.
+    pub fn get<Q>(&self, key: &Q) -> error::Result<Option<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.db.read(|map| map.get(key).cloned())
+    }
+
+    /// Insert `value` at `key`, returning the previous value.
+    pub fn insert(&self, key: K, value: V) -> error::Result<Option<V>> {
+        self.change(|map| map.insert(key, value))
+    }
+
+    /// Remove `key`, returning its value.
+    pub fn remove<Q>(&self, key: &Q) -> error::Result<Option<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.change(|map| map.remove(key))
+    }
+
+    /// Whether there is a value at `key`.
+    pub fn contains_key<Q>(&self, key: &Q) -> error::Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.db.read(|map| map.contains_key(key))
+    }
+
+    /// Get clones of all keys, in no particular order.
+    pub fn keys(&self) -> error::Result<Vec<K>> {
+        self.db.read(|map| map.keys().cloned().collect())
+    }
+
+    /// The number of entries.
+    pub fn len(&self) -> error::Result<usize> {
+        self.db.read(HashMap::len)
+    }
+
+    /// Whether there are no entries.
+    pub fn is_empty(&self) -> error::Result<bool> {
+        self.db.read(HashMap::is_empty)
+    }
+}
+
+impl<K, V, Back, DeSer> From<Database<HashMap<K, V>, Back, DeSer>> for KvDatabase<K, V, Back, DeSer>
+where
+    K: Serialize + DeserializeOwned + Eq + Hash + Clone + Send,
+    V: Serialize + DeserializeOwned + Clone + Send,
+    Back: Backend,
+    DeSer: DeSerializer<HashMap<K, V>> + Send + Sync + Clone,
+{
+    fn from(db: Database<HashMap<K, V>, Back, DeSer>) -> Self {
+        Self::new(db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KvDatabase;
+    use crate::backend::Backend;
+    use crate::deser::Ron;
+    use crate::MemoryDatabase;
+    use std::collections::HashMap;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn kv_auto_save() {
+        let db = KvDatabase::from(
+            MemoryDatabase::<HashMap<u32, String>, Ron>::memory(HashMap::new())
+                .expect("could not create database"),
+        );
+        db.insert(1, "one".to_string()).expect("could not insert");
+        assert!(db.database().is_dirty());
+        assert_eq!(db.len().expect("could not get len"), 1);
+
+        let db = db.with_auto_save(true);
+        assert_eq!(
+            db.insert(1, "uno".to_string()).expect("could not insert"),
+            Some("one".to_string())
+        );
+        db.insert(2, "two".to_string()).expect("could not insert");
+        assert!(!db.database().is_dirty());
+        let mut keys = db.keys().expect("could not get keys");
+        keys.sort_unstable();
+        assert_eq!(keys, [1, 2]);
+
+        let (_, mut backend, _) = db.into_inner().into_inner().expect("could not get backend");
+        let saved: HashMap<u32, String> =
+            ron::de::from_bytes(&backend.get_data().expect("could not get data"))
+                .expect("could not deserialize");
+        assert_eq!(saved.get(&1).map(String::as_str), Some("uno"));
+    }
+}
@@ -21,9 +21,9 @@
     clippy::print_stdout,
     clippy::todo,
     //clippy::unwrap_used, // not yet in stable
-    clippy::wrong_pub_self_convention
 )]
 #![warn(clippy::pedantic)]
+#![cfg_attr(test, allow(clippy::panic))]
 // part of `clippy::pedantic`, causing many warnings
 #![allow(clippy::missing_errors_doc, clippy::module_name_repetitions)]
 
@@ -148,15 +148,21 @@
 //!
 //! ## Panics
 //!
-//! This Database implementation uses [`RwLock`] and [`Mutex`] under the hood.
-//! If either the closures given to [`Database::write`] or any of the Backend
-//! implementation methods panic the respective objects are then poisoned. This
-//! means that you *cannot panic* under any circumstances in your closures or
-//! custom backends.
+//! This Database implementation uses [`RwLock`](std::sync::RwLock) and
+//! [`Mutex`](std::sync::Mutex) under the hood. If either the closures given to
+//! [`Database::write`] or any of the Backend implementation methods panic the
+//! respective objects are then poisoned. This means that you *cannot panic*
+//! under any circumstances in your closures or custom backends.
 //!
 //! Currently there is no way to recover from a poisoned `Database` other than
 //! re-creating it.
 //!
+//! With the `parking_lot` feature the locks of [`parking_lot`][parking_lot] are used
+//! instead. They do not get poisoned, so [`RustbreakError::Poison`] is never
+//! returned, but a panic in a closure can leave the data half modified.
+//! Either way [`Database::borrow_data`] and [`Database::borrow_data_mut`]
+//! return the [`guard::ReadGuard`] and [`guard::WriteGuard`] of this crate.
+//!
 //! ## Examples
 //!
 //! There are several more or less in-depth example programs you can check out!
@@ -166,8 +172,8 @@
 //!   with rustbreak
 //! - `full.rs` shows you how the database can be used as a hashmap store
 //! - `switching.rs` show you how you can easily swap out different parts of the
-//!   Database *Note*: To run this example you need to enable the feature `yaml`
-//!   like so: `cargo run --example switching --features yaml`
+//!   Database *Note*: To run this example you need to enable the feature `safe_yaml_enc`
+//!   like so: `cargo run --example switching --features ron_enc,safe_yaml_enc`
 //! - `server/` is a fully fledged example app written with the Rocket framework
 //!   to make a form of micro-blogging website. You will need rust nightly to
 //!   start it.
@@ -177,9 +183,59 @@
 //! Rustbreak comes with following optional features:
 //!
 //! - `ron_enc` which enables the [Ron][ron] de/serialization
-//! - `yaml_enc` which enables the Yaml de/serialization
+//! - `safe_yaml_enc` which enables the `SafeYaml` de/serialization, using
+//!   the maintained `serde_yaml_ng`
+//! - `yaml_enc` which enables the deprecated `Yaml` de/serialization
 //! - `bin_enc` which enables the Bincode de/serialization
+//! - `json_enc` which enables the JSON de/serialization
+//! - `toml_enc` which enables the TOML de/serialization
+//! - `cbor_enc` which enables the CBOR de/serialization
+//! - `postcard_enc` which enables the Postcard de/serialization
+//! - `zstd_enc` which enables the `Zstd` wrapper, compressing the output of
+//!   another de/serialization
+//! - `gzip_enc` which enables the `Gzip` wrapper, likewise
+//! - `armor` which enables the `Armored` wrapper, storing the output of
+//!   another de/serialization as base64 text
+//! - `encryption` which enables the `Encrypted` wrapper, encrypting the
+//!   output of another de/serialization with XChaCha20-Poly1305
+//! - `signing` which enables the `Signed` wrapper, appending a keyed BLAKE3
+//!   hash that is checked on load
+//! - `migrations` which enables the [`migrations`] module, upgrading data
+//!   saved with older schema versions on load
+//! - `dynamic` which enables the [`dynamic`] module, editing documents whose
+//!   schema is not known at compile time by paths like `users[2].name`
+//! - `watch` which enables [`Database::watch_file`], reloading the data when
+//!   its file changes
+//! - `dirs` which enables constructors like [`Database::in_config_dir`],
+//!   storing the data in the platform specific directories
+//! - `tracing` which enables the `InstrumentedBackend`, emitting
+//!   [`tracing`][tracing] events for every read and write
 //! - 'mmap' whhich enables memory map backend.
+//! - `tokio` which enables the [`async_db`] module with an async database
+//! - `http` which enables the `HttpBackend`, storing the data at a URL with
+//!   `GET` and `PUT`
+//! - `object_store` which enables the `ObjectStoreBackend`, storing the data
+//!   in S3, Google Cloud Storage or Azure with [`object_store`][object_store]
+//! - `redis` which enables the `RedisBackend`, storing the data under a Redis
+//!   key
+//! - `sqlite` which enables the `SqliteBackend`, storing the data as a BLOB
+//!   in a `SQLite` database
+//! - `wasm` which enables the `LocalStorageBackend`, storing the data in the
+//!   `localStorage` of a browser
+//! - `embedded` which enables the `FlashBackend`, storing the data in a NOR
+//!   flash through the `embedded-storage` traits
+//! - `zip` which enables the `ZipEntryBackend`, storing the data as an entry
+//!   of a ZIP archive
+//! - `test-utils` which enables the [`testing`] module, with backends that
+//!   fail on purpose to test error handling
+//! - `signals` which makes [`Database::flush_on_shutdown`] and
+//!   [`registry::flush_on_shutdown`] save on `SIGINT` and `SIGTERM`, and
+//!   enables [`Database::reload_on_sighup`], on UNIX
+//! - `web` which enables the [`web`] module, turning errors into HTTP
+//!   responses for web frameworks like axum
+//! - `axum` which implements the traits of axum for [`web::WebError`] and
+//!   [`SharedDatabase`], see the [`web`] module
+//! - `parking_lot` which uses the locks of [`parking_lot`][parking_lot] instead of `std`
 //!
 //! [Enable them in your `Cargo.toml` file to use them.][features] You can
 //! safely have them all turned on per-default.
@@ -189,13 +245,45 @@
 //! [daybreak]: https://propublica.github.io/daybreak
 //! [examples]: https://github.com/TheNeikos/rustbreak/tree/master/examples
 //! [ron]: https://github.com/ron-rs/ron
+//! [parking_lot]: https://docs.rs/parking_lot
+//! [tracing]: https://docs.rs/tracing
+//! [object_store]: https://docs.rs/object_store
 //! [features]: https://doc.rust-lang.org/cargo/reference/specifying-dependencies.html#choosing-features
 
+#[cfg(feature = "tokio")]
+pub mod async_db;
+pub mod autosave;
 pub mod backend;
+pub mod bounded;
+pub mod builder;
+mod coalesce;
 /// Different serialization and deserialization methods one can use
 pub mod deser;
+#[cfg(feature = "dynamic")]
+pub mod dynamic;
 /// The rustbreak errors that can be returned
 pub mod error;
+pub mod events;
+pub mod expiring;
+pub mod global;
+pub mod guard;
+pub mod hooks;
+pub mod kv;
+#[cfg(feature = "migrations")]
+pub mod migrations;
+pub mod registry;
+pub mod set;
+pub mod sharded;
+pub mod shared;
+#[cfg(all(unix, feature = "signals"))]
+mod signals;
+pub mod stats;
+mod sync;
+#[cfg(feature = "test-utils")]
+pub mod testing;
+pub mod watch;
+#[cfg(feature = "web")]
+pub mod web;
 
 /// The `DeSerializer` trait used by serialization structs
 pub use crate::deser::DeSerializer;
@@ -203,22 +291,54 @@ pub use crate::deser::DeSerializer;
 use std::fmt::Debug;
 use std::ops::Deref;
 use std::path::PathBuf;
-use std::sync::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+use crate::autosave::{
+    AutoSave, AutoSaveHandle, AutoSavePolicy, SaveHandle, SaveOnDrop, SaveTicket, ShutdownGuard,
+};
 #[cfg(feature = "mmap")]
 use crate::backend::MmapStorage;
-use crate::backend::{Backend, FileBackend, MemoryBackend, PathBackend};
+use crate::backend::{
+    Backend, BackendMetadata, FileBackend, Fingerprint, MemoryBackend, PathBackend,
+};
+use crate::bounded::Evict;
+use crate::coalesce::SaveCoalescer;
+use crate::guard::{MappedReadGuard, SavingWriteGuard};
+use crate::hooks::{Event, HookId, Hooks};
+use crate::set::{StagedSave, Staging};
+use crate::stats::{CountingWriter, Stats, StatsRecorder};
+use crate::sync::{Mutex, ReadGuard, RwLock, WriteGuard};
+use crate::watch::{ChangeEvent, ChangeKind, WatcherId, Watchers};
 
+pub use crate::builder::DatabaseBuilder;
 pub use crate::error::*;
+pub use crate::registry::flush_all;
+pub use crate::shared::SharedDatabase;
+
+/// What [`Database::load_or_recover`] should do with data that could not be
+/// deserialized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecoveryAction<Data> {
+    /// Replace the data with the given value, and save it.
+    Reset(Data),
+    /// Try to deserialize these repaired bytes instead.
+    Repair(Vec<u8>),
+    /// Give up and return the error.
+    Abort,
+}
 
 /// The Central Database to Rustbreak.
 ///
 /// It has 3 Type Generics:
 ///
-/// - `Data`: Is the Data, you must specify this
+/// - `Data`: Is the Data, you must specify this. It only needs to be `Clone`
+///   for the methods that copy it, like [`Database::get_data`],
+///   [`Database::write_safe`] and [`Database::snapshot`].
 /// - `Back`: The storage backend.
 /// - `DeSer`: The Serializer/Deserializer or short `DeSer`. Check the [`deser`]
 ///   module for other strategies.
@@ -234,11 +354,84 @@ pub struct Database<Data, Back, DeSer> {
     data: RwLock<Data>,
     backend: Mutex<Back>,
     deser: DeSer,
+    saves: SaveCoalescer,
+    watchers: Mutex<Watchers<Data>>,
+    autosave: AutoSave,
+    /// Bumped on every change to `data`.
+    generation: AtomicU64,
+    /// The generation that was last saved or loaded.
+    saved_generation: AtomicU64,
+    /// The last snapshot and the generation it was taken at.
+    snapshot: Mutex<Option<(u64, Arc<Data>)>>,
+    /// Reused by saves to backends without a [`Backend::writer`].
+    buffer: Mutex<Vec<u8>>,
+    /// The fingerprint of the backend after the last load or save.
+    fingerprint: Mutex<Option<Fingerprint>>,
+    /// Whether saves check the fingerprint first.
+    detect_external_changes: AtomicBool,
+    /// The largest data loads accept, in bytes.
+    max_load_size: AtomicU64,
+    /// The largest data saves accept, in bytes.
+    max_save_size: AtomicU64,
+    merge: Mutex<MergeHook<Data>>,
+    stats: StatsRecorder,
+    hooks: Mutex<Hooks<Data>>,
+}
+
+/// A merge function, see [`Database::set_merge`].
+type MergeFn<Data> = dyn Fn(Data, Data, Data) -> Data + Send;
+
+/// The merge function of [`Database::set_merge`], and the data it uses as
+/// the base.
+struct MergeHook<Data> {
+    merge: Option<Box<MergeFn<Data>>>,
+    /// `Data::clone`, so that loads and saves can keep a base without
+    /// requiring `Data: Clone` themselves.
+    clone: Option<fn(&Data) -> Data>,
+    /// The data as it was last loaded or saved, only kept with a `merge`.
+    base: Option<Data>,
+}
+
+impl<Data> Default for MergeHook<Data> {
+    fn default() -> Self {
+        Self {
+            merge: None,
+            clone: None,
+            base: None,
+        }
+    }
+}
+
+impl<Data> Debug for MergeHook<Data> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MergeHook")
+            .field("merge", &self.merge.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Data> MergeHook<Data> {
+    /// A copy of `data`, if there is a merge function that would need it as
+    /// a base.
+    fn snapshot(&self, data: &Data) -> Option<Data> {
+        match (&self.merge, self.clone) {
+            (Some(_), Some(clone)) => Some(clone(data)),
+            _ => None,
+        }
+    }
+
+    /// Remember `data` as the base of the next merge, if there is a merge
+    /// function.
+    fn set_base(&mut self, data: &Data) {
+        if let Some(base) = self.snapshot(data) {
+            self.base = Some(base);
+        }
+    }
 }
 
 impl<Data, Back, DeSer> Database<Data, Back, DeSer>
 where
-    Data: Serialize + DeserializeOwned + Clone + Send,
+    Data: Serialize + DeserializeOwned + Send,
     Back: Backend,
     DeSer: DeSerializer<Data> + Send + Sync + Clone,
 {
@@ -295,121 +488,165 @@ where
     where
         T: FnOnce(&mut Data) -> R,
     {
-        let mut lock = self.data.write().map_err(|_| RustbreakError::Poison)?;
-        Ok(task(&mut lock))
+        let mut lock = self.data.write()?;
+        let result = task(&mut lock);
+        self.mark_dirty();
+        self.after_write(lock)?;
+        Ok(result)
     }
 
-    /// Write lock the database and get write access to the `Data` container in
-    /// a safe way.
+    /// Read lock the database and get read access to the `Data` container.
     ///
-    /// This gives you an exclusive lock on the memory object. Trying to open
-    /// the database in writing will block if it is currently being written
-    /// to.
+    /// This gives you a read-only lock on the database. You can have as many
+    /// readers in parallel as you wish.
     ///
-    /// This differs to `Database::write` in that a clone of the internal data
-    /// is made, which is then passed to the closure. Only if the closure
-    /// doesn't panic is the internal model updated.
+    /// # Errors
     ///
-    /// Depending on the size of the database this can be very costly. This is a
-    /// tradeoff to make for panic safety.
+    /// May return:
     ///
-    /// You should read the documentation about this:
-    /// [`UnwindSafe`](https://doc.rust-lang.org/std/panic/trait.UnwindSafe.html)
+    /// - [`error::RustbreakError::Backend`]
     ///
     /// # Panics
     ///
-    /// When the closure panics, it is caught and a
-    /// [`error::RustbreakError::WritePanic`] will be returned.
+    /// If you panic in the closure, the database is poisoned. This means that
+    /// any subsequent writes/reads will fail with an
+    /// [`error::RustbreakError::Poison`]. You can only recover from
+    /// this by re-creating the Database Object.
+    pub fn read<T, R>(&self, task: T) -> error::Result<R>
+    where
+        T: FnOnce(&Data) -> R,
+    {
+        let mut lock = self.data.read()?;
+        Ok(task(&mut lock))
+    }
+
+    /// Read lock the database and get read access to the `Data` container in
+    /// a safe way.
+    ///
+    /// This differs to `Database::read` in that a panic in the closure is
+    /// caught, and returned as a [`error::RustbreakError::ReadPanic`]. The
+    /// database stays usable, since a reader can not have changed the data.
+    /// Unlike [`Database::write_safe`], the data does not need to be cloned.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// # #[macro_use] extern crate serde_derive;
     /// # extern crate rustbreak;
-    /// # extern crate serde;
-    /// # extern crate tempfile;
-    /// use rustbreak::{
-    ///     deser::Ron,
-    ///     error::RustbreakError,
-    ///     FileDatabase,
-    /// };
+    /// use rustbreak::{deser::Ron, error::RustbreakError, MemoryDatabase};
     ///
-    /// #[derive(Debug, Serialize, Deserialize, Clone)]
-    /// struct Data {
-    ///     level: u32,
-    /// }
-    ///
-    /// # fn main() {
-    /// # let func = || -> Result<(), Box<dyn std::error::Error>> {
-    /// # let file = tempfile::tempfile()?;
-    /// let db = FileDatabase::<Data, Ron>::from_file(file, Data { level: 0 })?;
-    ///
-    /// let result = db
-    ///     .write_safe(|db| {
-    ///         db.level = 42;
-    ///         panic!("We panic inside the write code.");
-    ///     })
-    ///     .expect_err("This should have been caught");
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// let db = MemoryDatabase::<Vec<u32>, Ron>::memory(vec![1, 2])?;
     ///
-    /// match result {
-    ///     RustbreakError::WritePanic => {
-    ///         // We can now handle this, in this example we will just ignore it
-    ///     }
-    ///     e => {
-    ///         println!("{:#?}", e);
-    ///         // You should always have generic error catching here.
-    ///         // This future-proofs your code, and makes your code more robust.
-    ///         // In this example this is unreachable though, and to assert that we have this
-    ///         // macro here
-    ///         unreachable!();
-    ///     }
-    /// }
+    /// let result = db.read_safe(|data| data[5]);
+    /// assert!(matches!(result, Err(RustbreakError::ReadPanic)));
     ///
-    /// // We read it back out again, it has not changed
-    /// let value = db.read(|db| db.level)?;
-    /// assert_eq!(0, value);
-    /// # return Ok(());
-    /// # };
-    /// # func().unwrap();
+    /// // The database can still be used
+    /// db.write(|data| data.push(3))?;
+    /// assert_eq!(db.read_safe(|data| data[2])?, 3);
+    /// # Ok(())
     /// # }
     /// ```
-    pub fn write_safe<T>(&self, task: T) -> error::Result<()>
+    pub fn read_safe<T, R>(&self, task: T) -> error::Result<R>
     where
-        T: FnOnce(&mut Data) + std::panic::UnwindSafe,
+        T: FnOnce(&Data) -> R + std::panic::UnwindSafe,
     {
-        let mut lock = self.data.write().map_err(|_| RustbreakError::Poison)?;
-        let mut data = lock.clone();
-        std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
-            task(&mut data);
-        }))
-        .map_err(|_| RustbreakError::WritePanic)?;
-        *lock = data;
-        Ok(())
+        let lock = self.data.read()?;
+        let data: &Data = &lock;
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| task(data)))
+            .map_err(|_| RustbreakError::ReadPanic)
     }
 
-    /// Read lock the database and get read access to the `Data` container.
+    /// Like [`Database::read`], but fails with
+    /// [`error::RustbreakError::WouldBlock`] instead of waiting if the
+    /// database is being written to.
     ///
-    /// This gives you a read-only lock on the database. You can have as many
-    /// readers in parallel as you wish.
+    /// # Examples
     ///
-    /// # Errors
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// use rustbreak::{deser::Ron, error::RustbreakError, MemoryDatabase};
     ///
-    /// May return:
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// let db = MemoryDatabase::<u32, Ron>::memory(1)?;
+    /// assert_eq!(db.try_read(|data| *data)?, 1);
     ///
-    /// - [`error::RustbreakError::Backend`]
+    /// let writer = db.borrow_data_mut()?;
+    /// assert!(matches!(db.try_read(|data| *data), Err(RustbreakError::WouldBlock)));
+    /// drop(writer);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn try_read<T, R>(&self, task: T) -> error::Result<R>
+    where
+        T: FnOnce(&Data) -> R,
+    {
+        let lock = self.data.try_read()?.ok_or(RustbreakError::WouldBlock)?;
+        Ok(task(&lock))
+    }
+
+    /// Like [`Database::read`], but fails with
+    /// [`error::RustbreakError::Timeout`] if the database is being written to
+    /// for longer than `timeout`.
+    pub fn read_timeout<T, R>(&self, timeout: Duration, task: T) -> error::Result<R>
+    where
+        T: FnOnce(&Data) -> R,
+    {
+        let lock = self
+            .data
+            .read_timeout(timeout)?
+            .ok_or(RustbreakError::Timeout)?;
+        Ok(task(&lock))
+    }
+
+    /// Like [`Database::write`], but fails with
+    /// [`error::RustbreakError::WouldBlock`] instead of waiting if the
+    /// database is being read or written to. `task` is not called then.
+    pub fn try_write<T, R>(&self, task: T) -> error::Result<R>
+    where
+        T: FnOnce(&mut Data) -> R,
+    {
+        let mut lock = self.data.try_write()?.ok_or(RustbreakError::WouldBlock)?;
+        let result = task(&mut lock);
+        self.mark_dirty();
+        self.after_write(lock)?;
+        Ok(result)
+    }
+
+    /// Like [`Database::write`], but fails with
+    /// [`error::RustbreakError::Timeout`] if the database is being read or
+    /// written to for longer than `timeout`. `task` is not called then.
     ///
-    /// # Panics
+    /// # Examples
     ///
-    /// If you panic in the closure, the database is poisoned. This means that
-    /// any subsequent writes/reads will fail with an
-    /// [`error::RustbreakError::Poison`]. You can only recover from
-    /// this by re-creating the Database Object.
-    pub fn read<T, R>(&self, task: T) -> error::Result<R>
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// use rustbreak::{deser::Ron, error::RustbreakError, MemoryDatabase};
+    /// use std::time::Duration;
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// let db = MemoryDatabase::<u32, Ron>::memory(1)?;
+    /// let reader = db.borrow_data()?;
+    /// let result = db.write_timeout(Duration::from_millis(10), |data| *data += 1);
+    /// assert!(matches!(result, Err(RustbreakError::Timeout)));
+    /// drop(reader);
+    ///
+    /// db.write_timeout(Duration::from_millis(10), |data| *data += 1)?;
+    /// assert_eq!(db.read(|data| *data)?, 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_timeout<T, R>(&self, timeout: Duration, task: T) -> error::Result<R>
     where
-        T: FnOnce(&Data) -> R,
+        T: FnOnce(&mut Data) -> R,
     {
-        let mut lock = self.data.read().map_err(|_| RustbreakError::Poison)?;
-        Ok(task(&mut lock))
+        let mut lock = self
+            .data
+            .write_timeout(timeout)?
+            .ok_or(RustbreakError::Timeout)?;
+        let result = task(&mut lock);
+        self.mark_dirty();
+        self.after_write(lock)?;
+        Ok(result)
     }
 
     /// Read lock the database and get access to the underlying struct.
@@ -448,8 +685,56 @@ where
     /// # func().unwrap();
     /// # }
     /// ```
-    pub fn borrow_data<'a>(&'a self) -> error::Result<RwLockReadGuard<'a, Data>> {
-        self.data.read().map_err(|_| RustbreakError::Poison)
+    pub fn borrow_data(&self) -> error::Result<ReadGuard<'_, Data>> {
+        self.data.read()
+    }
+
+    /// Read lock the database and get access to a part of the underlying
+    /// struct.
+    ///
+    /// Like [`Database::borrow_data`], but the returned guard dereferences to
+    /// the part `map` returns, so it can be handed out without exposing the
+    /// rest of the data, or cloning the part. The database stays read locked
+    /// until the guard is dropped.
+    ///
+    /// `map` is called on every dereference of the guard, so it should be
+    /// cheap, like accessing a field.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate serde_derive;
+    /// # extern crate rustbreak;
+    /// # extern crate serde;
+    /// use rustbreak::{deser::Ron, MemoryDatabase};
+    ///
+    /// #[derive(Debug, Serialize, Deserialize, Clone)]
+    /// struct Data {
+    ///     level: u32,
+    ///     players: Vec<String>,
+    /// }
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// let db = MemoryDatabase::<Data, Ron>::memory(Data {
+    ///     level: 1,
+    ///     players: vec!["ferris".to_string()],
+    /// })?;
+    ///
+    /// let players = db.borrow_data_map(|data| &data.players)?;
+    /// assert_eq!(players.len(), 1);
+    /// assert_eq!(players[0], "ferris");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn borrow_data_map<'a, U, F>(
+        &'a self,
+        map: F,
+    ) -> error::Result<MappedReadGuard<'a, Data, U>>
+    where
+        U: ?Sized,
+        F: Fn(&Data) -> &U + 'a,
+    {
+        Ok(MappedReadGuard::new(self.data.read()?, map))
     }
 
     /// Write lock the database and get access to the underlying struct.
@@ -500,101 +785,70 @@ where
     /// # func().unwrap();
     /// # }
     /// ```
-    pub fn borrow_data_mut<'a>(&'a self) -> error::Result<RwLockWriteGuard<'a, Data>> {
-        self.data.write().map_err(|_| RustbreakError::Poison)
-    }
-
-    /// Load data from backend and return this data.
-    fn load_from_backend(backend: &mut Back, deser: &DeSer) -> error::Result<Data> {
-        let new_data = deser.deserialize(&backend.get_data()?[..])?;
-
-        Ok(new_data)
-    }
-
-    /// Like [`Self::load`] but returns the write lock to data it used.
-    fn load_get_data_lock(&self) -> error::Result<RwLockWriteGuard<'_, Data>> {
-        let mut backend_lock = self.backend.lock().map_err(|_| RustbreakError::Poison)?;
-
-        let fresh_data = Self::load_from_backend(&mut backend_lock, &self.deser)?;
-        drop(backend_lock);
-
-        let mut data_write_lock = self.data.write().map_err(|_| RustbreakError::Poison)?;
-        *data_write_lock = fresh_data;
-        Ok(data_write_lock)
-    }
-
-    /// Load the data from the backend.
-    pub fn load(&self) -> error::Result<()> {
-        self.load_get_data_lock().map(|_| ())
+    pub fn borrow_data_mut(&self) -> error::Result<WriteGuard<'_, Data>> {
+        let lock = self.data.write()?;
+        // We can not know whether the caller changes anything, so assume
+        // they do.
+        self.mark_dirty();
+        Ok(lock)
     }
 
-    /// Like [`Self::save`] but with explicit read (or write) lock to data.
-    fn save_data_locked<L: Deref<Target = Data>>(&self, lock: L) -> error::Result<()> {
-        let ser = self.deser.serialize(lock.deref())?;
-        drop(lock);
-
-        let mut backend = self.backend.lock().map_err(|_| RustbreakError::Poison)?;
-        backend.put_data(&ser)?;
-        Ok(())
-    }
-
-    /// Flush the data structure to the backend.
-    pub fn save(&self) -> error::Result<()> {
-        let data = self.data.read().map_err(|_| RustbreakError::Poison)?;
-        self.save_data_locked(data)
-    }
-
-    /// Get a clone of the data as it is in memory right now.
+    /// Write lock the database and get a guard that saves the data when it is
+    /// dropped.
     ///
-    /// To make sure you have the latest data, call this method with `load`
-    /// true.
-    pub fn get_data(&self, load: bool) -> error::Result<Data> {
-        let data = if load {
-            self.load_get_data_lock()?
-        } else {
-            self.data.write().map_err(|_| RustbreakError::Poison)?
-        };
-        Ok(data.clone())
-    }
-
-    /// Puts the data as is into memory.
+    /// This works like [`Database::borrow_data_mut`], but when the guard is
+    /// released the watchers are notified and the data is saved, like with
+    /// [`Database::write_and_save`]. Errors of that save are ignored when the
+    /// guard is dropped, call [`SavingWriteGuard::commit`] instead to handle
+    /// them. If it is dropped during a panic nothing is saved, but the
+    /// database stays dirty.
     ///
-    /// To save the data afterwards, call with `save` true.
-    pub fn put_data(&self, new_data: Data, save: bool) -> error::Result<()> {
-        let mut data = self.data.write().map_err(|_| RustbreakError::Poison)?;
-        *data = new_data;
-        if save {
-            self.save_data_locked(data)
-        } else {
-            Ok(())
-        }
-    }
-
-    /// Create a database from its constituents.
-    pub fn from_parts(data: Data, backend: Back, deser: DeSer) -> Self {
-        Self {
-            data: RwLock::new(data),
-            backend: Mutex::new(backend),
-            deser,
-        }
-    }
-
-    /// Break a database into its individual parts.
-    pub fn into_inner(self) -> error::Result<(Data, Back, DeSer)> {
-        Ok((
-            self.data.into_inner().map_err(|_| RustbreakError::Poison)?,
-            self.backend
-                .into_inner()
-                .map_err(|_| RustbreakError::Poison)?,
-            self.deser,
-        ))
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// use rustbreak::{deser::Ron, MemoryDatabase};
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// let db = MemoryDatabase::<Vec<u32>, Ron>::memory(vec![])?;
+    ///
+    /// {
+    ///     let mut data = db.borrow_data_mut_autosave()?;
+    ///     data.push(1);
+    /// } // saved here
+    ///
+    /// let mut data = db.borrow_data_mut_autosave()?;
+    /// data.push(2);
+    /// data.commit()?;
+    /// assert!(!db.is_dirty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn borrow_data_mut_autosave(
+        &self,
+    ) -> error::Result<SavingWriteGuard<'_, Data, Back, DeSer>> {
+        let lock = self.data.write()?;
+        self.mark_dirty();
+        Ok(SavingWriteGuard::new(self, lock))
     }
 
-    /// Tries to clone the Data in the Database.
+    /// Watch a part of the data for changes.
     ///
-    /// This method returns a `MemoryDatabase` which has an empty vector as a
-    /// backend initially. This means that the user is responsible for assigning
-    /// a new backend if an alternative is wanted.
+    /// `projection` extracts the part of the data you are interested in. It is
+    /// evaluated after every [`Database::write`], [`Database::write_safe`],
+    /// [`Database::put_data`] and [`Database::load`], and `callback` is called
+    /// with the new value whenever it differs from the previous one.
+    ///
+    /// Changes made through [`Database::borrow_data_mut`] are noticed the next
+    /// time one of the methods above runs.
+    ///
+    /// Returns a [`WatcherId`] which can be passed to [`Database::unwatch`].
+    ///
+    /// # Panics
+    ///
+    /// The callback is called while the database is locked, accessing the
+    /// database from within it will deadlock. If the projection or the
+    /// callback panic, the database is poisoned.
     ///
     /// # Examples
     ///
@@ -602,247 +856,374 @@ where
     /// # #[macro_use] extern crate serde_derive;
     /// # extern crate rustbreak;
     /// # extern crate serde;
-    /// # extern crate tempfile;
-    /// use rustbreak::{deser::Ron, FileDatabase};
+    /// use rustbreak::{deser::Ron, MemoryDatabase};
+    /// use std::sync::mpsc::channel;
     ///
     /// #[derive(Debug, Serialize, Deserialize, Clone)]
     /// struct Data {
-    ///     level: u32,
+    ///     theme: String,
+    ///     clicks: u32,
     /// }
     ///
     /// # fn main() {
     /// # let func = || -> Result<(), Box<dyn std::error::Error>> {
-    /// # let file = tempfile::tempfile()?;
-    /// let db = FileDatabase::<Data, Ron>::from_file(file, Data { level: 0 })?;
-    ///
-    /// db.write(|db| {
-    ///     db.level = 42;
+    /// let db = MemoryDatabase::<Data, Ron>::memory(Data {
+    ///     theme: "light".into(),
+    ///     clicks: 0,
     /// })?;
     ///
-    /// db.save()?;
+    /// let (sender, receiver) = channel();
+    /// db.watch(
+    ///     |data| data.theme.clone(),
+    ///     move |theme| sender.send(theme.clone()).unwrap(),
+    /// )?;
     ///
-    /// let other_db = db.try_clone()?;
+    /// db.write(|data| data.clicks += 1)?;
+    /// db.write(|data| data.theme = "dark".into())?;
     ///
-    /// // You can also return from a `.read()`. But don't forget that you cannot return references
-    /// // into the structure
-    /// let value = other_db.read(|db| db.level)?;
-    /// assert_eq!(42, value);
+    /// assert_eq!(vec!["dark".to_string()], receiver.try_iter().collect::<Vec<_>>());
     /// # return Ok(());
     /// # };
     /// # func().unwrap();
     /// # }
     /// ```
-    pub fn try_clone(&self) -> error::Result<MemoryDatabase<Data, DeSer>> {
-        let lock = self.data.read().map_err(|_| RustbreakError::Poison)?;
-
-        Ok(Database {
-            data: RwLock::new(lock.clone()),
-            backend: Mutex::new(MemoryBackend::new()),
-            deser: self.deser.clone(),
-        })
+    pub fn watch<P, V, F>(&self, projection: P, callback: F) -> error::Result<WatcherId>
+    where
+        P: Fn(&Data) -> V + Send + 'static,
+        V: PartialEq + Send + 'static,
+        F: FnMut(&V) + Send + 'static,
+    {
+        let data = self.data.read()?;
+        let initial = projection(&data);
+        let mut watchers = self.watchers.lock()?;
+        Ok(watchers.add(projection, initial, callback))
     }
-}
 
-/// A database backed by a file.
-pub type FileDatabase<D, DS> = Database<D, FileBackend, DS>;
+    /// Remove a watcher registered with [`Database::watch`].
+    ///
+    /// Returns whether the watcher was still registered.
+    pub fn unwatch(&self, id: WatcherId) -> error::Result<bool> {
+        let mut watchers = self.watchers.lock()?;
+        Ok(watchers.remove(id))
+    }
 
-impl<Data, DeSer> Database<Data, FileBackend, DeSer>
-where
-    Data: Serialize + DeserializeOwned + Clone + Send,
-    DeSer: DeSerializer<Data> + Send + Sync + Clone,
-{
-    /// Create new [`FileDatabase`] from the file at [`Path`](std::path::Path),
-    /// and load the contents.
-    pub fn load_from_path<S>(path: S) -> error::Result<Self>
+    /// Run `hook` with the data right before every save.
+    ///
+    /// Hooks run in the order they were registered, while the database is
+    /// locked, so they must not access the database themselves. Saves done
+    /// while loading with recovery and while merging external changes run
+    /// the hooks too, backups and exports to other backends do not.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// use rustbreak::{deser::Ron, MemoryDatabase};
+    /// use std::sync::mpsc::channel;
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// let db = MemoryDatabase::<u32, Ron>::memory(0)?;
+    /// let (sender, receiver) = channel();
+    /// db.on_after_save(move |data| sender.send(*data).unwrap())?;
+    ///
+    /// db.write_and_save(|data| *data = 3)?;
+    /// assert_eq!(receiver.try_recv(), Ok(3));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_before_save<F>(&self, hook: F) -> error::Result<HookId>
     where
-        S: AsRef<std::path::Path>,
+        F: FnMut(&Data) + Send + 'static,
     {
-        let mut backend = FileBackend::from_path_or_fail(path)?;
-        let deser = DeSer::default();
-        let data = Self::load_from_backend(&mut backend, &deser)?;
-
-        let db = Self {
-            data: RwLock::new(data),
-            backend: Mutex::new(backend),
-            deser,
-        };
-        Ok(db)
+        Ok(self.hooks.lock()?.add(Event::BeforeSave, hook))
     }
 
-    /// Load [`FileDatabase`] at `path` or initialise with `data`.
+    /// Run `hook` with the data after every successful save.
     ///
-    /// Create new [`FileDatabase`] from the file at [`Path`](std::path::Path),
-    /// and load the contents. If the file does not exist, initialise with
-    /// `data`.
-    pub fn load_from_path_or<S>(path: S, data: Data) -> error::Result<Self>
+    /// While such a hook is registered, saves keep the data locked until the
+    /// hooks ran. See [`Database::on_before_save`] for details.
+    pub fn on_after_save<F>(&self, hook: F) -> error::Result<HookId>
     where
-        S: AsRef<std::path::Path>,
+        F: FnMut(&Data) + Send + 'static,
     {
-        let (mut backend, exists) = FileBackend::from_path_or_create(path)?;
-        let deser = DeSer::default();
-        if !exists {
-            let ser = deser.serialize(&data)?;
-            backend.put_data(&ser)?;
-        }
-
-        let db = Self {
-            data: RwLock::new(data),
-            backend: Mutex::new(backend),
-            deser,
-        };
-
-        if exists {
-            db.load()?;
-        }
-
-        Ok(db)
+        Ok(self.hooks.lock()?.add(Event::AfterSave, hook))
     }
 
-    /// Load [`FileDatabase`] at `path` or initialise with `closure`.
+    /// Run `hook` with the new data after every successful load.
     ///
-    /// Create new [`FileDatabase`] from the file at [`Path`](std::path::Path),
-    /// and load the contents. If the file does not exist, `closure` is
-    /// called and the database is initialised with it's return value.
-    pub fn load_from_path_or_else<S, C>(path: S, closure: C) -> error::Result<Self>
+    /// See [`Database::on_before_save`] for details.
+    pub fn on_after_load<F>(&self, hook: F) -> error::Result<HookId>
     where
-        S: AsRef<std::path::Path>,
-        C: FnOnce() -> Data,
+        F: FnMut(&Data) + Send + 'static,
     {
-        let (mut backend, exists) = FileBackend::from_path_or_create(path)?;
-        let deser = DeSer::default();
-        let data = if exists {
-            Self::load_from_backend(&mut backend, &deser)?
-        } else {
-            let data = closure();
+        Ok(self.hooks.lock()?.add(Event::AfterLoad, hook))
+    }
 
-            let ser = deser.serialize(&data)?;
-            backend.put_data(&ser)?;
+    /// Check the data with `validator` before every save, and after every
+    /// load if `on_load` is set.
+    ///
+    /// If the validator returns an error, the save or load fails with
+    /// [`RustbreakError::Validation`]. Invalid data is not written to the
+    /// backend, and invalid loaded data does not replace the data in
+    /// memory. Note that a failed [`Database::write_and_save`] still keeps
+    /// the change in memory, use [`Database::transaction`] to roll it back.
+    /// This replaces the previous validator.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// use rustbreak::{deser::Ron, MemoryDatabase, RustbreakError};
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// let db = MemoryDatabase::<u32, Ron>::memory(0)?;
+    /// db.set_validator(false, |data| {
+    ///     if *data > 100 {
+    ///         return Err("more than 100");
+    ///     }
+    ///     Ok(())
+    /// })?;
+    ///
+    /// db.write(|data| *data = 101)?;
+    /// assert!(matches!(db.save(), Err(RustbreakError::Validation(_))));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_validator<F, E>(&self, on_load: bool, validator: F) -> error::Result<()>
+    where
+        F: Fn(&Data) -> std::result::Result<(), E> + Send + 'static,
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        self.hooks.lock()?.set_validator(on_load, validator);
+        Ok(())
+    }
 
-            data
-        };
+    /// Remove the validator set with [`Database::set_validator`].
+    pub fn clear_validator(&self) -> error::Result<()> {
+        self.hooks.lock()?.clear_validator();
+        Ok(())
+    }
 
-        let db = Self {
-            data: RwLock::new(data),
-            backend: Mutex::new(backend),
-            deser,
-        };
-        Ok(db)
+    /// Remove a hook registered with [`Database::on_before_save`],
+    /// [`Database::on_after_save`] or [`Database::on_after_load`].
+    ///
+    /// Returns whether the hook was still registered.
+    pub fn remove_hook(&self, id: HookId) -> error::Result<bool> {
+        Ok(self.hooks.lock()?.remove(id))
     }
 
-    /// Create [`FileDatabase`] at `path`. Initialise with `data` if the file
-    /// doesn't exist.
+    /// Get a channel that receives a [`ChangeEvent`] after every change to
+    /// the data.
     ///
-    /// Create new [`FileDatabase`] from the file at [`Path`](std::path::Path).
-    /// Contents are not loaded. If the file does not exist, it is
-    /// initialised with `data`. Frontend is always initialised with `data`.
-    pub fn create_at_path<S>(path: S, data: Data) -> error::Result<Self>
-    where
-        S: AsRef<std::path::Path>,
-    {
-        let (mut backend, exists) = FileBackend::from_path_or_create(path)?;
-        let deser = DeSer::default();
-        if !exists {
-            let ser = deser.serialize(&data)?;
-            backend.put_data(&ser)?;
-        }
+    /// Events are sent after successful writes, like [`Database::write`]
+    /// and [`Database::put_data`], after loads, and after external changes
+    /// were merged. Unlike [`Database::watch`], every change is reported,
+    /// whether it changed the value or not. Writes through the guard of
+    /// [`Database::borrow_data_mut`] are not reported. Drop the receiver to
+    /// unsubscribe.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// use rustbreak::{deser::Ron, watch::ChangeKind, MemoryDatabase};
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// let db = MemoryDatabase::<u32, Ron>::memory(0)?;
+    /// let changes = db.subscribe()?;
+    ///
+    /// db.write(|data| *data += 1)?;
+    /// let event = changes.try_recv().unwrap();
+    /// assert_eq!(event.kind, ChangeKind::Write);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn subscribe(&self) -> error::Result<std::sync::mpsc::Receiver<ChangeEvent>> {
+        Ok(self.watchers.lock()?.subscribe())
+    }
 
-        let db = Self {
-            data: RwLock::new(data),
-            backend: Mutex::new(backend),
-            deser,
+    /// Notify the watchers and subscribers after the data might have
+    /// changed.
+    fn notify_watchers(&self, data: &Data, kind: ChangeKind) -> error::Result<()> {
+        let event = ChangeEvent {
+            kind,
+            generation: self.generation.load(Ordering::SeqCst),
         };
-        Ok(db)
+        let mut watchers = self.watchers.lock()?;
+        watchers.notify(data, event);
+        Ok(())
     }
 
-    /// Create new [`FileDatabase`] from a file.
-    pub fn from_file(file: std::fs::File, data: Data) -> error::Result<Self> {
-        let backend = FileBackend::from_file(file);
-
-        Ok(Self {
-            data: RwLock::new(data),
-            backend: Mutex::new(backend),
-            deser: DeSer::default(),
-        })
+    /// Record that the data changed, must be called while holding the write
+    /// lock.
+    fn mark_dirty(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
     }
-}
 
-impl<Data, DeSer> Database<Data, FileBackend, DeSer>
-where
-    Data: Serialize + DeserializeOwned + Clone + Send + Default,
-    DeSer: DeSerializer<Data> + Send + Sync + Clone,
-{
-    /// Load [`FileDatabase`] at `path` or initialise with `Data::default()`.
+    /// Whether the data changed since it was last saved or loaded.
     ///
-    /// Create new [`FileDatabase`] from the file at [`Path`](std::path::Path),
-    /// and load the contents. If the file does not exist, initialise with
-    /// `Data::default`.
-    pub fn load_from_path_or_default<S>(path: S) -> error::Result<Self>
-    where
-        S: AsRef<std::path::Path>,
-    {
-        Self::load_from_path_or_else(path, Data::default)
+    /// Every [`Database::write`], [`Database::write_safe`],
+    /// [`Database::borrow_data_mut`] and [`Database::put_data`] counts as a
+    /// change, even if the data ends up the same. A database created with
+    /// [`Database::from_parts`] is dirty, since its backend might not contain
+    /// the data yet.
+    pub fn is_dirty(&self) -> bool {
+        self.generation.load(Ordering::SeqCst) != self.saved_generation.load(Ordering::SeqCst)
     }
-}
 
-/// A database backed by a file, using atomic saves.
-pub type PathDatabase<D, DS> = Database<D, PathBackend, DS>;
+    /// The version of the data in memory.
+    ///
+    /// It increases with every change that makes the database
+    /// [dirty](Database::is_dirty), and with every load, and stays the same
+    /// on reads and saves. Comparing it with an earlier value tells whether
+    /// anything changed in between, without looking at the data. It is the
+    /// same number as the [`ChangeEvent::generation`] of the watchers.
+    ///
+    /// The version only lives in memory, it starts over for every database
+    /// that is created or loaded.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// use rustbreak::{deser::Ron, MemoryDatabase};
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// let db = MemoryDatabase::<u32, Ron>::memory(0)?;
+    /// let seen = db.version();
+    /// db.read(|data| *data)?;
+    /// assert_eq!(db.version(), seen);
+    /// db.write(|data| *data += 1)?;
+    /// assert!(db.version() > seen);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn version(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
 
-impl<Data, DeSer> Database<Data, PathBackend, DeSer>
-where
-    Data: Serialize + DeserializeOwned + Clone + Send,
-    DeSer: DeSerializer<Data> + Send + Sync + Clone,
-{
-    /// Create new [`PathDatabase`] from the file at [`Path`](std::path::Path),
-    /// and load the contents.
-    pub fn load_from_path(path: PathBuf) -> error::Result<Self> {
-        let mut backend = PathBackend::from_path_or_fail(path)?;
-        let deser = DeSer::default();
-        let data = Self::load_from_backend(&mut backend, &deser)?;
+    /// Like [`Database::save`] but only saves if the database
+    /// [is dirty](Database::is_dirty).
+    ///
+    /// Returns whether a save happened.
+    pub fn save_if_dirty(&self) -> error::Result<bool> {
+        if !self.is_dirty() {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
 
-        let db = Self {
-            data: RwLock::new(data),
-            backend: Mutex::new(backend),
-            deser,
-        };
-        Ok(db)
+    /// Notify the watchers and apply the auto-save policy after a write.
+    fn after_write(&self, lock: WriteGuard<'_, Data>) -> error::Result<()> {
+        self.notify_watchers(&lock, ChangeKind::Write)?;
+        // Saving while holding the write lock could deadlock with a
+        // concurrent save waiting for a read lock.
+        drop(lock);
+        if self.autosave.record_write()? {
+            self.save()?;
+        }
+        Ok(())
     }
 
-    /// Load [`PathDatabase`] at `path` or initialise with `data`.
+    /// Set when the database should persist itself.
     ///
-    /// Create new [`PathDatabase`] from the file at [`Path`](std::path::Path),
-    /// and load the contents. If the file does not exist, initialise with
-    /// `data`.
-    pub fn load_from_path_or(path: PathBuf, data: Data) -> error::Result<Self> {
-        let (mut backend, exists) = PathBackend::from_path_or_create(path)?;
-        let deser = DeSer::default();
-        if !exists {
-            let ser = deser.serialize(&data)?;
-            backend.put_data(&ser)?;
-        }
+    /// Only [`AutoSavePolicy::AfterEveryWrite`] takes effect on its own, the
+    /// other policies need a running [`Database::start_autosave`]. Changes
+    /// made through [`Database::borrow_data_mut`] do not count as writes.
+    pub fn set_autosave_policy(&self, policy: AutoSavePolicy) -> error::Result<()> {
+        self.autosave.set_policy(policy)
+    }
 
-        let db = Self {
-            data: RwLock::new(data),
-            backend: Mutex::new(backend),
-            deser,
-        };
+    /// The current auto-save policy, [`AutoSavePolicy::Never`] by default.
+    pub fn autosave_policy(&self) -> error::Result<AutoSavePolicy> {
+        self.autosave.policy()
+    }
 
-        if exists {
-            db.load()?;
-        }
+    /// Get the [`Stats`] about the saves and loads of this database.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// use rustbreak::{deser::Ron, MemoryDatabase};
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// let db = MemoryDatabase::<u32, Ron>::memory(0)?;
+    /// db.write(|data| *data = 42)?;
+    /// db.save()?;
+    ///
+    /// let stats = db.stats()?;
+    /// assert_eq!(stats.saves, 1);
+    /// assert_eq!(stats.bytes_written, 2);
+    /// assert!(stats.last_save.is_some());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stats(&self) -> error::Result<Stats> {
+        self.stats.get()
+    }
 
-        Ok(db)
+    /// When this database was last saved, `None` if it was not saved since
+    /// it was opened.
+    pub fn last_saved_at(&self) -> error::Result<Option<SystemTime>> {
+        Ok(self.stats.get()?.last_save)
     }
 
-    /// Load [`PathDatabase`] at `path` or initialise with `closure`.
+    /// The size and modification time of the stored data, as reported by
+    /// the backend.
     ///
-    /// Create new [`PathDatabase`] from the file at [`Path`](std::path::Path),
-    /// and load the contents. If the file does not exist, `closure` is
-    /// called and the database is initialised with it's return value.
-    pub fn load_from_path_or_else<C>(path: PathBuf, closure: C) -> error::Result<Self>
+    /// This is `None` if the backend can not tell, or if nothing was stored
+    /// yet. The data is not read, so this is cheap enough for status
+    /// displays.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// use rustbreak::{deser::Ron, MemoryDatabase};
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// let db = MemoryDatabase::<u32, Ron>::memory(0)?;
+    /// assert_eq!(db.last_saved_at()?, None);
+    /// db.write(|data| *data = 42)?;
+    /// db.save()?;
+    ///
+    /// let metadata = db.backend_metadata()?.expect("memory knows its size");
+    /// assert_eq!(metadata.len, 2);
+    /// assert!(db.last_saved_at()?.is_some());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn backend_metadata(&self) -> error::Result<Option<BackendMetadata>> {
+        Ok(self.backend.lock()?.metadata()?)
+    }
+
+    /// Call `hook` with the new [`Stats`] after every save and load, for
+    /// example to export them as metrics.
+    ///
+    /// This replaces the previous hook. It runs on the thread that saved or
+    /// loaded, while the database is locked, so it should not block, and it
+    /// must not access the database other than through
+    /// [`Database::stats`].
+    pub fn set_stats_hook<F>(&self, hook: F) -> error::Result<()>
+    where
+        F: Fn(&Stats) + Send + 'static,
+    {
+        self.stats.set_hook(Some(Box::new(hook)))
+    }
+
+    /// Remove the hook set with [`Database::set_stats_hook`].
+    pub fn clear_stats_hook(&self) -> error::Result<()> {
+        self.stats.set_hook(None)
+    }
+
+    /// Load the data from `backend` if it `exists`, otherwise initialise it
+    /// with `closure` and save it.
+    fn load_or_init<C>(mut backend: Back, exists: bool, closure: C) -> error::Result<Self>
     where
         C: FnOnce() -> Data,
     {
-        let (mut backend, exists) = PathBackend::from_path_or_create(path)?;
         let deser = DeSer::default();
         let data = if exists {
             Self::load_from_backend(&mut backend, &deser)?
@@ -855,232 +1236,2873 @@ where
             data
         };
 
-        let db = Self {
-            data: RwLock::new(data),
-            backend: Mutex::new(backend),
-            deser,
-        };
+        Self::from_opened(data, backend, deser)
+    }
+
+    /// Like [`Database::from_parts`], for a `backend` that was just loaded
+    /// into `data`, or initialised with it.
+    ///
+    /// Records the fingerprint of the backend, so that external changes are
+    /// detected from the start, not only after the first load or save.
+    fn from_opened(data: Data, mut backend: Back, deser: DeSer) -> error::Result<Self> {
+        let fingerprint = backend.fingerprint()?;
+        let db = Self::from_parts(data, backend, deser);
+        *db.fingerprint.lock()? = fingerprint;
         Ok(db)
     }
 
-    /// Create [`PathDatabase`] at `path`. Initialise with `data` if the file
-    /// doesn't exist.
+    /// Load data from backend and return this data.
+    fn load_from_backend(backend: &mut Back, deser: &DeSer) -> error::Result<Data> {
+        let new_data = deser.deserialize(&backend.get_data()?[..])?;
+
+        Ok(new_data)
+    }
+
+    /// Like [`Self::load`] but returns the write lock to data it used.
+    fn load_get_data_lock(&self) -> error::Result<WriteGuard<'_, Data>> {
+        let start = Instant::now();
+        let decode = |raw: &[u8]| -> error::Result<Data> {
+            let data = self.deser.deserialize(raw)?;
+            self.hooks.lock()?.validate(&data, true)?;
+            Ok(data)
+        };
+        let saved_before = self.saved_generation.load(Ordering::SeqCst);
+        let fingerprint_before = self.fingerprint.lock()?.clone();
+        let (mut raw, mut fingerprint) = self.read_backend_for_load()?;
+        let mut fresh_data = decode(&raw)?;
+
+        let mut data_write_lock = self.data.write()?;
+        // Saves update the fingerprint and then the saved generation while
+        // holding the lock of the fingerprint.
+        let mut stored_fingerprint = self.fingerprint.lock()?;
+        if *stored_fingerprint != fingerprint_before
+            || self.saved_generation.load(Ordering::SeqCst) != saved_before
+        {
+            // A save or load went in between a detached read, which may have
+            // missed it. Read again, no save can run while the data is locked.
+            drop(stored_fingerprint);
+            let mut backend = self.backend.lock()?;
+            fingerprint = backend.fingerprint()?;
+            raw = self.read_backend(&mut backend)?;
+            drop(backend);
+            fresh_data = decode(&raw)?;
+            stored_fingerprint = self.fingerprint.lock()?;
+        }
+        self.stats.record_load(raw.len() as u64, start)?;
+        *stored_fingerprint = fingerprint;
+        drop(stored_fingerprint);
+        *data_write_lock = fresh_data;
+        self.merge.lock()?.set_base(&data_write_lock);
+        self.mark_dirty();
+        self.saved_generation
+            .store(self.generation.load(Ordering::SeqCst), Ordering::SeqCst);
+        self.notify_watchers(&data_write_lock, ChangeKind::Load)?;
+        self.hooks.lock()?.run(Event::AfterLoad, &data_write_lock);
+        Ok(data_write_lock)
+    }
+
+    /// Load the data from the backend.
+    pub fn load(&self) -> error::Result<()> {
+        self.load_get_data_lock().map(|_| ())
+    }
+
+    /// Load the data from the backend, letting `recover` decide what to do
+    /// if it can not be deserialized.
     ///
-    /// Create new [`PathDatabase`] from the file at [`Path`](std::path::Path).
-    /// Contents are not loaded. If the file does not exist, it is
-    /// initialised with `data`. Frontend is always initialised with `data`.
-    pub fn create_at_path(path: PathBuf, data: Data) -> error::Result<Self> {
-        let (mut backend, exists) = PathBackend::from_path_or_create(path)?;
-        let deser = DeSer::default();
-        if !exists {
-            let ser = deser.serialize(&data)?;
-            backend.put_data(&ser)?;
+    /// `recover` gets the raw bytes and the error, and returns a
+    /// [`RecoveryAction`]. Repaired bytes are deserialized again, and passed
+    /// to `recover` once more if that fails too. If the data was reset or
+    /// repaired, the backend gets to [quarantine](Backend::quarantine) the
+    /// bad data, a [`PathBackend`] renames the file to
+    /// `<name>.corrupt-<timestamp>`, and the recovered data is saved.
+    ///
+    /// Errors of the backend are returned right away. The database is
+    /// locked while `recover` runs.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// use rustbreak::backend::{Backend, MemoryBackend};
+    /// use rustbreak::deser::Ron;
+    /// use rustbreak::{Database, RecoveryAction};
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// let mut backend = MemoryBackend::new();
+    /// backend.put_data(b"not a number")?;
+    /// let db = Database::<u32, _, Ron>::from_parts(0, backend, Ron);
+    /// db.load_or_recover(|_raw, _error| RecoveryAction::Reset(7))?;
+    /// assert_eq!(db.get_data(false)?, 7);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_or_recover<F>(&self, mut recover: F) -> error::Result<()>
+    where
+        F: FnMut(&[u8], &DeSerError) -> RecoveryAction<Data>,
+    {
+        let start = Instant::now();
+        let mut data = self.data.write()?;
+        let mut backend = self.backend.lock()?;
+
+        let mut fingerprint = backend.fingerprint()?;
+        let mut raw = self.read_backend(&mut backend)?;
+        let bytes = raw.len() as u64;
+        let mut recovered = false;
+        let fresh_data = loop {
+            let err = match self.deser.deserialize(&raw[..]) {
+                Ok(fresh_data) => break fresh_data,
+                Err(err) => err,
+            };
+            recovered = true;
+            match recover(&raw, &err) {
+                RecoveryAction::Reset(fresh_data) => break fresh_data,
+                RecoveryAction::Repair(bytes) => raw = bytes,
+                RecoveryAction::Abort => return Err(err.into()),
+            }
+        };
+
+        self.hooks.lock()?.validate(&fresh_data, true)?;
+        *data = fresh_data;
+        self.mark_dirty();
+        self.stats.record_load(bytes, start)?;
+        if recovered {
+            backend.quarantine()?;
+            self.store_and_record(&mut *backend, &*data)?;
+            fingerprint = backend.fingerprint()?;
         }
+        drop(backend);
+        *self.fingerprint.lock()? = fingerprint;
+        self.merge.lock()?.set_base(&data);
+        self.saved_generation
+            .store(self.generation.load(Ordering::SeqCst), Ordering::SeqCst);
+        self.notify_watchers(&data, ChangeKind::Load)?;
+        self.hooks.lock()?.run(Event::AfterLoad, &data);
+        Ok(())
+    }
 
-        let db = Self {
-            data: RwLock::new(data),
-            backend: Mutex::new(backend),
-            deser,
+    /// Make saves fail with [`RustbreakError::ExternalChange`] if the data in
+    /// the backend changed since this database last loaded or saved it.
+    ///
+    /// This keeps concurrent writers, for example another process using the
+    /// same file, from silently overwriting each other's changes. After the
+    /// error, [`Database::load`] the data again and redo the change. Changes
+    /// are detected with the [`Backend::fingerprint`], backends without one
+    /// are never considered changed. Disabled by default.
+    pub fn set_external_change_detection(&self, enabled: bool) {
+        self.detect_external_changes
+            .store(enabled, Ordering::SeqCst);
+    }
+
+    /// Make loads fail with [`RustbreakError::TooLarge`] if the stored data
+    /// is larger than `limit` bytes, or accept any size with `None`, the
+    /// default.
+    ///
+    /// This protects against running out of memory when loading a corrupted
+    /// or hostile file. If the backend knows the size of the data through
+    /// [`Backend::size_hint`], it is checked before reading anything.
+    /// Compressed data is only checked before decompressing it, a limit on
+    /// the `DeSer`, like [`Bincode::with_limit`](crate::deser::Bincode::with_limit),
+    /// catches data that grows too large while deserializing.
+    pub fn set_max_load_size(&self, limit: Option<u64>) {
+        self.max_load_size
+            .store(limit.unwrap_or(u64::MAX), Ordering::SeqCst);
+    }
+
+    /// Make saves fail with [`BackendError::QuotaExceeded`] if the serialized
+    /// data is larger than `limit` bytes, or accept any size with `None`, the
+    /// default.
+    ///
+    /// Nothing is written to the backend when the quota is exceeded, so the
+    /// stored data stays as it was. With a quota, the data is always
+    /// serialized into a buffer first, even for backends that could stream
+    /// it. Use [`Database::save_evicting`] to make data implementing
+    /// [`Evict`] fit the quota before saving it.
+    pub fn set_max_save_size(&self, limit: Option<u64>) {
+        self.max_save_size
+            .store(limit.unwrap_or(u64::MAX), Ordering::SeqCst);
+    }
+
+    /// Read the data from `backend`, checking it against the limit of
+    /// [`Database::set_max_load_size`].
+    fn read_backend(&self, backend: &mut Back) -> error::Result<Vec<u8>> {
+        if let Some(size) = backend.size_hint() {
+            self.check_load_size(size)?;
+        }
+        let raw = backend.get_data()?;
+        self.check_load_size(raw.len())?;
+        Ok(raw)
+    }
+
+    /// Read the data and fingerprint of the backend for a load.
+    ///
+    /// With a [`Backend::detached_reader`], the backend is only locked to get
+    /// the reader, so saves and other loads do not wait for the read.
+    fn read_backend_for_load(&self) -> error::Result<(Vec<u8>, Option<Fingerprint>)> {
+        let mut backend = self.backend.lock()?;
+        let Some(reader) = backend.detached_reader()? else {
+            let fingerprint = backend.fingerprint()?;
+            let raw = self.read_backend(&mut backend)?;
+            return Ok((raw, fingerprint));
         };
-        Ok(db)
+        let size_hint = backend.size_hint();
+        drop(backend);
+        if let Some(size) = size_hint {
+            self.check_load_size(size)?;
+        }
+        let (raw, fingerprint) = reader.read()?;
+        self.check_load_size(raw.len())?;
+        Ok((raw, fingerprint))
     }
-}
 
-impl<Data, DeSer> Database<Data, PathBackend, DeSer>
-where
-    Data: Serialize + DeserializeOwned + Clone + Send + Default,
-    DeSer: DeSerializer<Data> + Send + Sync + Clone,
-{
-    /// Load [`PathDatabase`] at `path` or initialise with `Data::default()`.
+    /// Fail with [`RustbreakError::TooLarge`] if `size` exceeds the limit of
+    /// [`Database::set_max_load_size`].
+    fn check_load_size(&self, size: usize) -> error::Result<()> {
+        let limit = self.max_load_size.load(Ordering::SeqCst);
+        let size = size as u64;
+        if size > limit {
+            return Err(RustbreakError::TooLarge { size, limit });
+        }
+        Ok(())
+    }
+
+    /// Load the data from the backend, unless it did not change since it was
+    /// last loaded or saved.
     ///
-    /// Create new [`PathDatabase`] from the file at [`Path`](std::path::Path),
-    /// and load the contents. If the file does not exist, initialise with
-    /// `Data::default`.
-    pub fn load_from_path_or_default(path: PathBuf) -> error::Result<Self> {
-        Self::load_from_path_or_else(path, Data::default)
+    /// Whether it changed is decided by the [`Backend::fingerprint`], file
+    /// backends compare the modification time and length of the file, which
+    /// is much cheaper than deserializing it. Backends without a fingerprint
+    /// are always loaded.
+    ///
+    /// Returns whether the data was loaded. Note that loading discards
+    /// changes that were not saved.
+    pub fn load_if_modified(&self) -> error::Result<bool> {
+        let current = self.backend.lock()?.fingerprint()?;
+        if current.is_some() && current == *self.fingerprint.lock()? {
+            return Ok(false);
+        }
+        self.load()?;
+        Ok(true)
     }
-}
 
-/// A database backed by a byte vector (`Vec<u8>`).
-pub type MemoryDatabase<D, DS> = Database<D, MemoryBackend, DS>;
+    /// Load the data from the backend and read it.
+    ///
+    /// Unlike calling [`Database::load`] and [`Database::read`] one after the
+    /// other, no writer can change the data in between. The database is
+    /// write locked while `task` runs.
+    pub fn read_after_load<T, R>(&self, task: T) -> error::Result<R>
+    where
+        T: FnOnce(&Data) -> R,
+    {
+        let lock = self.load_get_data_lock()?;
+        Ok(task(&lock))
+    }
 
-impl<Data, DeSer> Database<Data, MemoryBackend, DeSer>
-where
-    Data: Serialize + DeserializeOwned + Clone + Send,
-    DeSer: DeSerializer<Data> + Send + Sync + Clone,
-{
-    /// Create new in-memory database.
-    pub fn memory(data: Data) -> error::Result<Self> {
-        let backend = MemoryBackend::new();
+    /// Like [`Self::save`] but with explicit read (or write) lock to data.
+    fn save_data_locked<L: Deref<Target = Data>>(&self, lock: L) -> error::Result<()> {
+        let generation = self.generation.load(Ordering::SeqCst);
+        // Take the backend before letting go of the data, so that a writer
+        // can not save newer data in between, which we would then overwrite.
+        let mut backend = self.backend.lock()?;
+        let mut fingerprint = self.fingerprint.lock()?;
+        let mut merge = self.merge.lock()?;
+        let detect = self.detect_external_changes.load(Ordering::SeqCst) || merge.merge.is_some();
+        if detect && fingerprint.is_some() {
+            let current = backend.fingerprint()?;
+            if current.is_some() && current != *fingerprint {
+                if merge.merge.is_none() {
+                    return Err(RustbreakError::ExternalChange);
+                }
+                drop((merge, fingerprint, backend, lock));
+                return self.merge_and_save();
+            }
+        }
+        // The data may be released while it is stored, but only becomes the
+        // base once it was stored.
+        let base = merge.snapshot(&lock);
+        self.store_and_record(&mut *backend, lock)?;
+        *fingerprint = backend.fingerprint()?;
+        if base.is_some() {
+            merge.base = base;
+        }
+        // A concurrent save might have persisted a newer generation already.
+        self.saved_generation
+            .fetch_max(generation, Ordering::SeqCst);
+        Ok(())
+    }
 
-        Ok(Self {
-            data: RwLock::new(data),
-            backend: Mutex::new(backend),
-            deser: DeSer::default(),
-        })
+    /// Serialize the data and stage it in the backend, to be committed
+    /// together with other databases by a [`DatabaseSet`](crate::set::DatabaseSet).
+    ///
+    /// The data stays read locked, and the backend locked, until the staged
+    /// save is committed or dropped. External changes fail with
+    /// [`RustbreakError::ExternalChange`], they are not merged. The after
+    /// save hooks, the [`Stats`] and the merge base only see the save once it
+    /// is committed.
+    pub(crate) fn stage_save(&self) -> error::Result<StagedSave<'_>> {
+        let lock = self.data.read()?;
+        let generation = self.generation.load(Ordering::SeqCst);
+        let mut backend = self.backend.lock()?;
+        let mut fingerprint = self.fingerprint.lock()?;
+        let mut merge = self.merge.lock()?;
+        let detect = self.detect_external_changes.load(Ordering::SeqCst) || merge.merge.is_some();
+        if detect && fingerprint.is_some() {
+            let current = backend.fingerprint()?;
+            if current.is_some() && current != *fingerprint {
+                return Err(RustbreakError::ExternalChange);
+            }
+        }
+        let start = Instant::now();
+        let limit = self.max_save_size.load(Ordering::SeqCst);
+        {
+            let mut hooks = self.hooks.lock()?;
+            hooks.validate(&lock, false)?;
+            hooks.run(Event::BeforeSave, &lock);
+        }
+        let mut staging = Staging::new(&mut *backend);
+        let bytes = self.store(&mut staging, &*lock, limit)?;
+        let pending = staging.into_pending();
+        // Everything that tells a save happened waits for the commit, the
+        // staged data is dropped if another database fails to stage.
+        Ok(StagedSave::new(move || {
+            pending.commit(&mut *backend)?;
+            *fingerprint = backend.fingerprint()?;
+            merge.set_base(&lock);
+            drop(merge);
+            self.saved_generation
+                .fetch_max(generation, Ordering::SeqCst);
+            self.hooks.lock()?.run(Event::AfterSave, &lock);
+            drop(lock);
+            self.stats.record_save(bytes, start)
+        }))
     }
-}
 
-/// A database backed by anonymous memory map.
-#[cfg(feature = "mmap")]
-pub type MmapDatabase<D, DS> = Database<D, MmapStorage, DS>;
+    /// Merge the data with the one in the backend, which was changed
+    /// externally, and save the result.
+    fn merge_and_save(&self) -> error::Result<()> {
+        let mut data = self.data.write()?;
+        let mut backend = self.backend.lock()?;
+        let mut fingerprint = self.fingerprint.lock()?;
+        let mut merge = self.merge.lock()?;
+        let hook = &*merge;
+        let (Some(merge_fn), Some(clone), Some(base)) =
+            (hook.merge.as_ref(), hook.clone, hook.base.as_ref())
+        else {
+            return Err(RustbreakError::ExternalChange);
+        };
+
+        // The base stays until the merge is saved, so that a failing read or
+        // save can be retried.
+        let theirs = self
+            .deser
+            .deserialize(&self.read_backend(&mut backend)?[..])?;
+        let merged = merge_fn(clone(base), clone(&data), theirs);
+        *data = merged;
+        self.mark_dirty();
+        self.store_and_record(&mut *backend, &*data)?;
+        *fingerprint = backend.fingerprint()?;
+        merge.set_base(&data);
+        drop(merge);
+        self.saved_generation
+            .store(self.generation.load(Ordering::SeqCst), Ordering::SeqCst);
+        self.notify_watchers(&data, ChangeKind::Merge)
+    }
+
+    /// Serialize `data` into `backend`, releasing `data` as soon as it is no
+    /// longer needed.
+    ///
+    /// Backends without a [`Backend::writer`] get the data through a buffer
+    /// that is kept between saves, so that saving repeatedly does not need a
+    /// new allocation each time. It keeps the capacity of the largest save,
+    /// and is grown to the [`Backend::size_hint`] up front.
+    ///
+    /// Fails with [`BackendError::QuotaExceeded`] without touching the
+    /// backend if the data is larger than `limit` bytes. The data is always
+    /// buffered then, to know its size before writing it.
+    ///
+    /// Returns the number of bytes written.
+    fn store<B, L>(&self, backend: &mut B, data: L, limit: u64) -> error::Result<u64>
+    where
+        B: Backend,
+        L: Deref<Target = Data>,
+    {
+        if limit == u64::MAX {
+            if let Some(mut writer) = backend.writer()? {
+                let mut counter = CountingWriter {
+                    inner: &mut writer,
+                    count: 0,
+                };
+                self.deser.serialize_into(&*data, &mut counter)?;
+                let count = counter.count;
+                drop(data);
+                writer.finish()?;
+                return Ok(count);
+            }
+        }
+
+        let mut buffer = self.buffer.lock()?;
+        buffer.clear();
+        if let Some(len) = backend.size_hint() {
+            buffer.reserve(len);
+        }
+        self.deser.serialize_into(&*data, &mut *buffer)?;
+        drop(data);
+        let size = buffer.len() as u64;
+        if size > limit {
+            return Err(BackendError::QuotaExceeded { size, limit }.into());
+        }
+        backend.put_data(&buffer)?;
+        Ok(size)
+    }
+
+    /// Like [`Self::store`], to the backend of the database, recording the
+    /// save in the [`Stats`].
+    fn store_and_record<B, L>(&self, backend: &mut B, data: L) -> error::Result<()>
+    where
+        B: Backend,
+        L: Deref<Target = Data>,
+    {
+        let start = Instant::now();
+        let limit = self.max_save_size.load(Ordering::SeqCst);
+        let mut hooks = self.hooks.lock()?;
+        hooks.validate(&data, false)?;
+        hooks.run(Event::BeforeSave, &data);
+        let bytes = if hooks.has(Event::AfterSave) {
+            // Keep the data until the hooks ran.
+            let bytes = self.store(backend, &*data, limit)?;
+            hooks.run(Event::AfterSave, &data);
+            bytes
+        } else {
+            drop(hooks);
+            self.store(backend, data, limit)?
+        };
+        self.stats.record_save(bytes, start)
+    }
+
+    /// Flush the data structure to the backend.
+    ///
+    /// Concurrent calls are coalesced: if another thread is already saving,
+    /// this waits for it to finish and then only writes again if that save
+    /// might have missed changes made before this call. Every call returns
+    /// once a save that covers the state at the time of the call succeeded.
+    ///
+    /// Backends that provide a [`Backend::writer`] are written to directly.
+    /// For all others the data is serialized into a buffer that is kept
+    /// between saves. Saving a `Vec<u64>` of 1 MiB with `Bincode`
+    /// to a [`MemoryBackend`] 100 times took about 1.1ms and 4 MiB of
+    /// allocations per save with a fresh buffer each time, and about 0.25ms
+    /// without any allocations with the reused one.
+    pub fn save(&self) -> error::Result<()> {
+        self.saves.run(|| self.save_now())
+    }
+
+    /// Save the data, without coalescing.
+    fn save_now(&self) -> error::Result<()> {
+        let data = self.data.read()?;
+        self.save_data_locked(data)
+    }
+
+    /// Write to the data and save it.
+    ///
+    /// Unlike calling [`Database::write`] and [`Database::save`] one after the
+    /// other, no other writer can change the data before it is saved. The
+    /// auto-save policy is not applied, since the data is saved anyway.
+    ///
+    /// # Panics
+    ///
+    /// If you panic in the closure, the database is poisoned, see
+    /// [`Database::write`].
+    pub fn write_and_save<T, R>(&self, task: T) -> error::Result<R>
+    where
+        T: FnOnce(&mut Data) -> R,
+    {
+        let mut lock = self.data.write()?;
+        let result = task(&mut lock);
+        self.mark_dirty();
+        self.commit_write(lock)?;
+        Ok(result)
+    }
+
+    /// Load the freshest data, change it with `task` and save it, with the
+    /// backend locked against other processes throughout.
+    ///
+    /// Calling [`Database::load`], [`Database::write`] and [`Database::save`]
+    /// one after the other loses changes that another process saves in
+    /// between. Here the backend is [locked](Backend::lock_exclusive) first,
+    /// waiting for other processes that hold the lock, and only unlocked once
+    /// the result is saved. The lock is the one of
+    /// [`PathBackend::from_path_locked`] and [`FileBackend::from_path_locked`],
+    /// so this waits forever for a process that opened the file with them.
+    /// Wrapping backends lock the backends they wrap. Backends that can not
+    /// be locked are still loaded and saved without any other writer of this
+    /// database in between.
+    ///
+    /// # Panics
+    ///
+    /// If you panic in the closure, the database is poisoned, see
+    /// [`Database::write`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// # extern crate tempfile;
+    /// use rustbreak::{deser::Ron, PathDatabase};
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// # let dir = tempfile::tempdir().unwrap();
+    /// # let path = dir.path().join("counter.ron");
+    /// let db = PathDatabase::<u32, Ron>::create_at_path(path.clone(), 0)?;
+    /// let other_process = PathDatabase::<u32, Ron>::load_from_path(path)?;
+    /// other_process.write_and_save(|counter| *counter += 1)?;
+    ///
+    /// let count = db.modify(|counter| {
+    ///     *counter += 1;
+    ///     *counter
+    /// })?;
+    /// assert_eq!(count, 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn modify<T, R>(&self, task: T) -> error::Result<R>
+    where
+        T: FnOnce(&mut Data) -> R,
+    {
+        let start = Instant::now();
+        let mut data = self.data.write()?;
+        let mut backend = self.backend.lock()?;
+        let _lock = backend.lock_exclusive()?;
+
+        let raw = self.read_backend(&mut backend)?;
+        let fresh_data = self.deser.deserialize(&raw[..])?;
+        self.hooks.lock()?.validate(&fresh_data, true)?;
+        self.stats.record_load(raw.len() as u64, start)?;
+        *data = fresh_data;
+        self.hooks.lock()?.run(Event::AfterLoad, &data);
+
+        let result = task(&mut data);
+        self.mark_dirty();
+        self.store_and_record(&mut *backend, &*data)?;
+        *self.fingerprint.lock()? = backend.fingerprint()?;
+        self.merge.lock()?.set_base(&data);
+        self.saved_generation
+            .store(self.generation.load(Ordering::SeqCst), Ordering::SeqCst);
+        // Only once saved, so that watchers do not see a change that failed.
+        self.notify_watchers(&data, ChangeKind::Write)?;
+        Ok(result)
+    }
+
+    /// Evict parts of the data until it fits the quota of
+    /// [`Database::set_max_save_size`], then save it.
+    ///
+    /// The data is serialized once more for every evicted part, to measure
+    /// it, so this suits data that only goes over the quota by a little now
+    /// and then. Without a quota nothing is evicted.
+    ///
+    /// Returns how many parts were evicted. Fails with
+    /// [`BackendError::QuotaExceeded`] if the data does not fit even with
+    /// nothing left to evict, the evicted parts stay evicted then.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// use rustbreak::bounded::{BoundedMap, EvictionPolicy};
+    /// use rustbreak::{deser::Ron, MemoryDatabase};
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// let db = MemoryDatabase::<BoundedMap<u32, String>, Ron>::memory(BoundedMap::new(
+    ///     EvictionPolicy::Fifo,
+    /// ))?;
+    /// db.set_max_save_size(Some(64));
+    /// db.write(|cache| {
+    ///     for i in 0..10 {
+    ///         cache.insert(i, "some value".to_owned());
+    ///     }
+    /// })?;
+    /// assert!(db.save().is_err());
+    ///
+    /// let evicted = db.save_evicting()?;
+    /// assert_eq!(db.read(|cache| cache.len())?, 10 - evicted);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn save_evicting(&self) -> error::Result<usize>
+    where
+        Data: Evict,
+    {
+        let mut lock = self.data.write()?;
+        let limit = self.max_save_size.load(Ordering::SeqCst);
+        let mut evicted = 0;
+        if limit != u64::MAX {
+            loop {
+                let mut counter = CountingWriter {
+                    inner: std::io::sink(),
+                    count: 0,
+                };
+                self.deser.serialize_into(&*lock, &mut counter)?;
+                if counter.count <= limit {
+                    break;
+                }
+                if !lock.evict() {
+                    if evicted > 0 {
+                        self.mark_dirty();
+                        self.notify_watchers(&lock, ChangeKind::Write)?;
+                    }
+                    let size = counter.count;
+                    return Err(BackendError::QuotaExceeded { size, limit }.into());
+                }
+                evicted += 1;
+            }
+        }
+        if evicted > 0 {
+            self.mark_dirty();
+            self.commit_write(lock)?;
+        } else {
+            self.save_data_locked(lock)?;
+        }
+        Ok(evicted)
+    }
+
+    /// Notify the watchers and save the data, releasing the write lock
+    /// afterwards.
+    pub(crate) fn commit_write(&self, lock: WriteGuard<'_, Data>) -> error::Result<()> {
+        self.notify_watchers(&lock, ChangeKind::Write)?;
+        self.save_data_locked(lock)
+    }
+
+    /// Save the data as it is in memory right now to `path`, independent of
+    /// the backend of the database.
+    ///
+    /// The file is replaced atomically, like with a [`PathBackend`]. Only a
+    /// read lock is held while the data is serialized, so other readers are
+    /// not blocked. Whether the database is dirty is not changed.
+    pub fn backup_to<P: AsRef<std::path::Path>>(&self, path: P) -> error::Result<()> {
+        let mut backend = PathBackend::new(path.as_ref().to_owned());
+        self.store(&mut backend, self.data.read()?, u64::MAX)?;
+        Ok(())
+    }
+
+    /// Save the data as it is in memory right now to another backend, in the
+    /// format of another `DeSer`.
+    ///
+    /// For example a Bincode database can be dumped to Ron for inspection.
+    /// Only a read lock is held while the data is serialized. Whether the
+    /// database is dirty is not changed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// # extern crate tempfile;
+    /// use rustbreak::backend::PathBackend;
+    /// use rustbreak::deser::{Ron, SafeYaml};
+    /// use rustbreak::MemoryDatabase;
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// # let dir = tempfile::tempdir().unwrap();
+    /// # let path = dir.path().join("dump.yaml");
+    /// let db = MemoryDatabase::<Vec<u32>, Ron>::memory(vec![1, 2, 3])?;
+    /// let (backend, _) = PathBackend::from_path_or_create(path.clone())?;
+    /// db.export_to::<SafeYaml, _>(backend)?;
+    /// assert!(std::fs::read_to_string(&path).unwrap().contains("- 2"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn export_to<DS2, B>(&self, mut backend: B) -> error::Result<()>
+    where
+        DS2: DeSerializer<Data>,
+        B: Backend,
+    {
+        let data = self.data.read()?;
+        let serialized = DS2::default().serialize(&data)?;
+        drop(data);
+        backend.put_data(&serialized)?;
+        Ok(())
+    }
+
+    /// Replace the data with the one in another backend, stored in the
+    /// format of another `DeSer`, and save it.
+    ///
+    /// This is the counterpart of [`Database::export_to`], for example to
+    /// convert a Ron config to Bincode.
+    pub fn import_from<DS2, B>(&self, mut backend: B) -> error::Result<()>
+    where
+        DS2: DeSerializer<Data>,
+        B: Backend,
+    {
+        let new_data = DS2::default().deserialize(&backend.get_data()?[..])?;
+        self.put_data(new_data, true)
+    }
+
+    /// Puts the data as is into memory.
+    ///
+    /// To save the data afterwards, call with `save` true.
+    pub fn put_data(&self, new_data: Data, save: bool) -> error::Result<()> {
+        let mut data = self.data.write()?;
+        *data = new_data;
+        self.mark_dirty();
+        if save {
+            self.notify_watchers(&data, ChangeKind::Write)?;
+            self.save_data_locked(data)
+        } else {
+            self.after_write(data)
+        }
+    }
+
+    /// Replace the data and return the previous one.
+    ///
+    /// No other reader or writer can get in between taking the old data and
+    /// putting in the new one. To save the new data afterwards, call with
+    /// `save` true.
+    ///
+    /// If the save fails, the new data stays in memory and the old data is
+    /// returned with the error, so that it is not lost. The same goes for the
+    /// other errors, where the data that is returned is `new_data` if the
+    /// database could not be locked in the first place.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// use rustbreak::{deser::Ron, MemoryDatabase};
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// let db = MemoryDatabase::<Vec<u32>, Ron>::memory(vec![1, 2])?;
+    ///
+    /// // Take all queued items, leaving an empty queue
+    /// let items = match db.swap_data(Vec::new(), true) {
+    ///     Ok(items) => items,
+    ///     Err((items, err)) => {
+    ///         // Put the items somewhere safe before giving up
+    ///         # drop(items);
+    ///         return Err(err);
+    ///     }
+    /// };
+    /// assert_eq!(items, [1, 2]);
+    /// assert!(db.read(Vec::is_empty)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn swap_data(
+        &self,
+        new_data: Data,
+        save: bool,
+    ) -> std::result::Result<Data, (Data, RustbreakError)> {
+        let mut data = match self.data.write() {
+            Ok(data) => data,
+            Err(err) => return Err((new_data, err)),
+        };
+        let old = std::mem::replace(&mut *data, new_data);
+        self.mark_dirty();
+        let result = if save {
+            self.commit_write(data)
+        } else {
+            self.after_write(data)
+        };
+        match result {
+            Ok(()) => Ok(old),
+            Err(err) => Err((old, err)),
+        }
+    }
+
+    /// Replace the data with the result of `update`, if it returns one.
+    ///
+    /// `update` gets the current data under the write lock and decides
+    /// whether to replace it, so an optimistic caller can compute the new
+    /// data from an earlier read, and only put it in if nobody changed the
+    /// data since. Returning `None` leaves the data as it is and does not
+    /// count as a change.
+    ///
+    /// Returns whether the data was replaced.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// use rustbreak::{deser::Ron, MemoryDatabase};
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// let db = MemoryDatabase::<u32, Ron>::memory(1)?;
+    ///
+    /// loop {
+    ///     let seen = db.read(|data| *data)?;
+    ///     let doubled = seen * 2; // expensive, done without holding the lock
+    ///     if db.update_if(|current| (*current == seen).then_some(doubled))? {
+    ///         break;
+    ///     }
+    /// }
+    /// assert_eq!(db.read(|data| *data)?, 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn update_if<F>(&self, update: F) -> error::Result<bool>
+    where
+        F: FnOnce(&Data) -> Option<Data>,
+    {
+        let mut data = self.data.write()?;
+        let Some(new_data) = update(&data) else {
+            return Ok(false);
+        };
+        *data = new_data;
+        self.mark_dirty();
+        self.after_write(data)?;
+        Ok(true)
+    }
+
+    /// Save the database whenever it is dropped.
+    ///
+    /// This protects against forgetting the final [`Database::save`], for
+    /// example at the end of `main`. The returned [`SaveOnDrop`] can be used
+    /// just like the database itself.
+    pub fn auto_save_on_drop(self) -> SaveOnDrop<Data, Back, DeSer> {
+        SaveOnDrop::new(self)
+    }
+
+    /// Create a database from its constituents.
+    pub fn from_parts(data: Data, backend: Back, deser: DeSer) -> Self {
+        Self {
+            data: RwLock::new(data),
+            backend: Mutex::new(backend),
+            deser,
+            saves: SaveCoalescer::default(),
+            watchers: Mutex::default(),
+            autosave: AutoSave::default(),
+            generation: AtomicU64::new(1),
+            saved_generation: AtomicU64::new(0),
+            snapshot: Mutex::default(),
+            buffer: Mutex::default(),
+            fingerprint: Mutex::default(),
+            detect_external_changes: AtomicBool::new(false),
+            max_load_size: AtomicU64::new(u64::MAX),
+            max_save_size: AtomicU64::new(u64::MAX),
+            merge: Mutex::default(),
+            stats: StatsRecorder::default(),
+            hooks: Mutex::default(),
+        }
+    }
+
+    /// Break a database into its individual parts.
+    pub fn into_inner(self) -> error::Result<(Data, Back, DeSer)> {
+        Ok((
+            self.data.into_inner()?,
+            self.backend.into_inner()?,
+            self.deser,
+        ))
+    }
+}
+
+impl<Data, Back, DeSer> Database<Data, Back, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Clone + Send,
+    Back: Backend,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    /// Write lock the database and get write access to the `Data` container in
+    /// a safe way.
+    ///
+    /// This gives you an exclusive lock on the memory object. Trying to open
+    /// the database in writing will block if it is currently being written
+    /// to.
+    ///
+    /// This differs to `Database::write` in that a clone of the internal data
+    /// is made, which is then passed to the closure. Only if the closure
+    /// doesn't panic is the internal model updated.
+    ///
+    /// Depending on the size of the database this can be very costly. This is a
+    /// tradeoff to make for panic safety.
+    ///
+    /// You should read the documentation about this:
+    /// [`UnwindSafe`](https://doc.rust-lang.org/std/panic/trait.UnwindSafe.html)
+    ///
+    /// # Panics
+    ///
+    /// When the closure panics, it is caught and a
+    /// [`error::RustbreakError::WritePanic`] will be returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate serde_derive;
+    /// # extern crate rustbreak;
+    /// # extern crate serde;
+    /// # extern crate tempfile;
+    /// use rustbreak::{
+    ///     deser::Ron,
+    ///     error::RustbreakError,
+    ///     FileDatabase,
+    /// };
+    ///
+    /// #[derive(Debug, Serialize, Deserialize, Clone)]
+    /// struct Data {
+    ///     level: u32,
+    /// }
+    ///
+    /// # fn main() {
+    /// # let func = || -> Result<(), Box<dyn std::error::Error>> {
+    /// # let file = tempfile::tempfile()?;
+    /// let db = FileDatabase::<Data, Ron>::from_file(file, Data { level: 0 })?;
+    ///
+    /// let result = db
+    ///     .write_safe(|db| {
+    ///         db.level = 42;
+    ///         panic!("We panic inside the write code.");
+    ///     })
+    ///     .expect_err("This should have been caught");
+    ///
+    /// match result {
+    ///     RustbreakError::WritePanic => {
+    ///         // We can now handle this, in this example we will just ignore it
+    ///     }
+    ///     e => {
+    ///         println!("{:#?}", e);
+    ///         // You should always have generic error catching here.
+    ///         // This future-proofs your code, and makes your code more robust.
+    ///         // In this example this is unreachable though, and to assert that we have this
+    ///         // macro here
+    ///         unreachable!();
+    ///     }
+    /// }
+    ///
+    /// // We read it back out again, it has not changed
+    /// let value = db.read(|db| db.level)?;
+    /// assert_eq!(0, value);
+    /// # return Ok(());
+    /// # };
+    /// # func().unwrap();
+    /// # }
+    /// ```
+    pub fn write_safe<T>(&self, task: T) -> error::Result<()>
+    where
+        T: FnOnce(&mut Data) + std::panic::UnwindSafe,
+    {
+        let mut lock = self.data.write()?;
+        let mut data = lock.clone();
+        std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+            task(&mut data);
+        }))
+        .map_err(|_| RustbreakError::WritePanic)?;
+        *lock = data;
+        self.mark_dirty();
+        self.after_write(lock)
+    }
+
+    /// Write lock the database and run a fallible closure on the data.
+    ///
+    /// If `task` returns an error, the data is left as it was before the call
+    /// and the error is returned as [`RustbreakError::Aborted`]. To get your
+    /// own error type back instead, use [`Database::transaction`].
+    ///
+    /// Like [`Database::write_safe`] this clones the whole data, which can be
+    /// costly for large databases.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// use rustbreak::{deser::Ron, MemoryDatabase, RustbreakError};
+    ///
+    /// # fn main() {
+    /// # let func = || -> Result<(), Box<dyn std::error::Error>> {
+    /// let db = MemoryDatabase::<Vec<u32>, Ron>::memory(vec![1, 2])?;
+    ///
+    /// let result = db.write_fallible(|data| {
+    ///     data.clear();
+    ///     let parsed: u32 = "not a number".parse()?;
+    ///     data.push(parsed);
+    ///     Ok::<_, std::num::ParseIntError>(())
+    /// });
+    /// assert!(matches!(result, Err(RustbreakError::Aborted(_))));
+    ///
+    /// // The data was not cleared
+    /// assert_eq!(vec![1, 2], db.get_data(false)?);
+    /// # return Ok(());
+    /// # };
+    /// # func().unwrap();
+    /// # }
+    /// ```
+    pub fn write_fallible<T, R, E>(&self, task: T) -> error::Result<R>
+    where
+        T: FnOnce(&mut Data) -> std::result::Result<R, E>,
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        self.transaction(false, |data| {
+            task(data).map_err(|e| RustbreakError::Aborted(e.into()))
+        })
+    }
+
+    /// Run a fallible change on the data, which is only applied if it
+    /// succeeds.
+    ///
+    /// `task` gets a clone of the data. If it returns `Ok`, the clone replaces
+    /// the data, and is also saved to the backend when `save` is true. If it
+    /// returns `Err`, or the save fails, neither the data nor the backend are
+    /// changed. The write lock is held throughout, so no other writer can
+    /// slip in between.
+    ///
+    /// If external changes are detected, see
+    /// [`Database::set_external_change_detection`], a save fails with
+    /// [`RustbreakError::ExternalChange`] when the backend was changed
+    /// externally. The changes are not merged then, even with
+    /// [`Database::set_merge`].
+    ///
+    /// Like [`Database::write_safe`] this clones the whole data, which can be
+    /// costly for large databases.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `task`, or a [`RustbreakError`] converted into
+    /// `E` if locking or saving failed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate serde_derive;
+    /// # extern crate rustbreak;
+    /// # extern crate serde;
+    /// use rustbreak::{deser::Ron, MemoryDatabase, RustbreakError};
+    ///
+    /// #[derive(Debug, Serialize, Deserialize, Clone)]
+    /// struct Account {
+    ///     balance: u32,
+    /// }
+    ///
+    /// #[derive(Debug)]
+    /// enum Error {
+    ///     InsufficientFunds,
+    ///     Database(RustbreakError),
+    /// }
+    ///
+    /// impl From<RustbreakError> for Error {
+    ///     fn from(e: RustbreakError) -> Self {
+    ///         Error::Database(e)
+    ///     }
+    /// }
+    ///
+    /// # fn main() {
+    /// # let func = || -> Result<(), Error> {
+    /// let db = MemoryDatabase::<Account, Ron>::memory(Account { balance: 10 })?;
+    ///
+    /// let result = db.transaction(true, |account| {
+    ///     account.balance = account
+    ///         .balance
+    ///         .checked_sub(20)
+    ///         .ok_or(Error::InsufficientFunds)?;
+    ///     Ok(())
+    /// });
+    /// assert!(matches!(result, Err(Error::InsufficientFunds)));
+    ///
+    /// // Nothing was changed
+    /// assert_eq!(10, db.read(|account| account.balance)?);
+    /// # return Ok(());
+    /// # };
+    /// # func().unwrap();
+    /// # }
+    /// ```
+    pub fn transaction<T, R, E>(&self, save: bool, task: T) -> std::result::Result<R, E>
+    where
+        T: FnOnce(&mut Data) -> std::result::Result<R, E>,
+        E: From<RustbreakError>,
+    {
+        let mut lock = self.data.write()?;
+        let mut data = lock.clone();
+        let result = task(&mut data)?;
+
+        if save {
+            let mut backend = self.backend.lock()?;
+            let mut fingerprint = self.fingerprint.lock()?;
+            let mut merge = self.merge.lock()?;
+            let detect =
+                self.detect_external_changes.load(Ordering::SeqCst) || merge.merge.is_some();
+            if detect && fingerprint.is_some() {
+                let current = backend.fingerprint().map_err(RustbreakError::from)?;
+                if current.is_some() && current != *fingerprint {
+                    return Err(RustbreakError::ExternalChange.into());
+                }
+            }
+            self.store_and_record(&mut *backend, &data)?;
+            merge.set_base(&data);
+            drop(merge);
+            *fingerprint = backend.fingerprint().map_err(RustbreakError::from)?;
+        }
+
+        *lock = data;
+        self.mark_dirty();
+        if save {
+            // We still hold the write lock, so nothing changed since.
+            self.saved_generation
+                .store(self.generation.load(Ordering::SeqCst), Ordering::SeqCst);
+            self.notify_watchers(&lock, ChangeKind::Write)?;
+        } else {
+            self.after_write(lock)?;
+        }
+        Ok(result)
+    }
+
+    /// Get an immutable snapshot of the data.
+    ///
+    /// The data is only cloned the first time a snapshot is taken after it
+    /// changed, until the next change every call returns the same [`Arc`].
+    /// This makes it cheap for many readers to hold on to the data without
+    /// keeping the database locked, while writers keep using the database as
+    /// usual.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// use rustbreak::{deser::Ron, MemoryDatabase};
+    /// use std::sync::Arc;
+    ///
+    /// # fn main() {
+    /// # let func = || -> Result<(), Box<dyn std::error::Error>> {
+    /// let db = MemoryDatabase::<Vec<u32>, Ron>::memory(vec![1, 2, 3])?;
+    ///
+    /// let first = db.snapshot()?;
+    /// assert!(Arc::ptr_eq(&first, &db.snapshot()?));
+    ///
+    /// db.write(|data| data.push(4))?;
+    /// // The old snapshot is unaffected by the write
+    /// assert_eq!(vec![1, 2, 3], *first);
+    /// assert_eq!(vec![1, 2, 3, 4], *db.snapshot()?);
+    /// # return Ok(());
+    /// # };
+    /// # func().unwrap();
+    /// # }
+    /// ```
+    pub fn snapshot(&self) -> error::Result<Arc<Data>> {
+        let data = self.data.read()?;
+        // Changes happen under the write lock, the generation can not move
+        // while we hold the read lock.
+        let generation = self.generation.load(Ordering::SeqCst);
+        let mut snapshot = self.snapshot.lock()?;
+        match &*snapshot {
+            Some((taken_at, cached)) if *taken_at == generation => Ok(Arc::clone(cached)),
+            _ => {
+                let fresh = Arc::new(data.clone());
+                *snapshot = Some((generation, Arc::clone(&fresh)));
+                Ok(fresh)
+            }
+        }
+    }
+
+    /// Merge the data with the one in the backend, instead of failing with
+    /// [`RustbreakError::ExternalChange`], when a save finds that it was
+    /// changed externally.
+    ///
+    /// `merge` gets the data as it was last loaded or saved, the data of this
+    /// database, and the data in the backend, in that order. It returns the
+    /// merged data, which replaces the data of this database and is saved.
+    /// External changes are detected like with
+    /// [`Database::set_external_change_detection`], which is implied.
+    ///
+    /// To have a base to merge from, every load and save keeps a clone of the
+    /// data from now on. Until the next load or save, there is no base and
+    /// saves fail like without a merge function.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// # use std::collections::BTreeSet;
+    /// use rustbreak::deser::Ron;
+    /// use rustbreak::FileDatabase;
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// # let dir = tempfile::tempdir().unwrap();
+    /// # let path = dir.path().join("set.ron");
+    /// let db = FileDatabase::<BTreeSet<u32>, Ron>::create_at_path(&path, BTreeSet::new())?;
+    /// // Keep everything either side added
+    /// db.set_merge(|_base, mine, theirs| mine.union(&theirs).copied().collect())?;
+    /// db.save()?;
+    ///
+    /// let other = FileDatabase::<BTreeSet<u32>, Ron>::load_from_path(&path)?;
+    /// other.write(|set| set.insert(1))?;
+    /// other.save()?;
+    ///
+    /// db.write(|set| set.insert(2))?;
+    /// db.save()?;
+    /// assert_eq!(db.get_data(false)?, vec![1, 2].into_iter().collect());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_merge<F>(&self, merge: F) -> error::Result<()>
+    where
+        F: Fn(Data, Data, Data) -> Data + Send + 'static,
+    {
+        let mut hook = self.merge.lock()?;
+        hook.merge = Some(Box::new(merge));
+        hook.clone = Some(Data::clone);
+        Ok(())
+    }
+
+    /// Get a clone of the data as it is in memory right now.
+    ///
+    /// To make sure you have the latest data, call this method with `load`
+    /// true.
+    pub fn get_data(&self, load: bool) -> error::Result<Data> {
+        if load {
+            Ok(self.load_get_data_lock()?.clone())
+        } else {
+            Ok(self.data.read()?.clone())
+        }
+    }
+
+    /// Tries to clone the Data in the Database.
+    ///
+    /// This method returns a `MemoryDatabase` which has an empty vector as a
+    /// backend initially. This means that the user is responsible for assigning
+    /// a new backend if an alternative is wanted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate serde_derive;
+    /// # extern crate rustbreak;
+    /// # extern crate serde;
+    /// # extern crate tempfile;
+    /// use rustbreak::{deser::Ron, FileDatabase};
+    ///
+    /// #[derive(Debug, Serialize, Deserialize, Clone)]
+    /// struct Data {
+    ///     level: u32,
+    /// }
+    ///
+    /// # fn main() {
+    /// # let func = || -> Result<(), Box<dyn std::error::Error>> {
+    /// # let file = tempfile::tempfile()?;
+    /// let db = FileDatabase::<Data, Ron>::from_file(file, Data { level: 0 })?;
+    ///
+    /// db.write(|db| {
+    ///     db.level = 42;
+    /// })?;
+    ///
+    /// db.save()?;
+    ///
+    /// let other_db = db.try_clone()?;
+    ///
+    /// // You can also return from a `.read()`. But don't forget that you cannot return references
+    /// // into the structure
+    /// let value = other_db.read(|db| db.level)?;
+    /// assert_eq!(42, value);
+    /// # return Ok(());
+    /// # };
+    /// # func().unwrap();
+    /// # }
+    /// ```
+    pub fn try_clone(&self) -> error::Result<MemoryDatabase<Data, DeSer>> {
+        let lock = self.data.read()?;
+
+        Ok(Database::from_parts(
+            lock.clone(),
+            MemoryBackend::new(),
+            self.deser.clone(),
+        ))
+    }
+}
+
+/// A database backed by a file.
+pub type FileDatabase<D, DS> = Database<D, FileBackend, DS>;
+
+impl<Data, DeSer> Database<Data, FileBackend, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    /// Create new [`FileDatabase`] from the file at [`Path`](std::path::Path),
+    /// and load the contents.
+    pub fn load_from_path<S>(path: S) -> error::Result<Self>
+    where
+        S: AsRef<std::path::Path>,
+    {
+        let mut backend = FileBackend::from_path_or_fail(path)?;
+        let deser = DeSer::default();
+        let data = Self::load_from_backend(&mut backend, &deser)?;
+
+        let db = Self::from_opened(data, backend, deser)?;
+        Ok(db)
+    }
+
+    /// Load [`FileDatabase`] at `path` or initialise with `data`.
+    ///
+    /// Create new [`FileDatabase`] from the file at [`Path`](std::path::Path),
+    /// and load the contents. If the file does not exist, initialise with
+    /// `data`.
+    pub fn load_from_path_or<S>(path: S, data: Data) -> error::Result<Self>
+    where
+        S: AsRef<std::path::Path>,
+    {
+        let (mut backend, exists) = FileBackend::from_path_or_create(path)?;
+        let deser = DeSer::default();
+        if !exists {
+            let ser = deser.serialize(&data)?;
+            backend.put_data(&ser)?;
+        }
+
+        let db = Self::from_opened(data, backend, deser)?;
+
+        if exists {
+            db.load()?;
+        }
+
+        Ok(db)
+    }
+
+    /// Load [`FileDatabase`] at `path` or initialise with `closure`.
+    ///
+    /// Create new [`FileDatabase`] from the file at [`Path`](std::path::Path),
+    /// and load the contents. If the file does not exist, `closure` is
+    /// called and the database is initialised with it's return value.
+    pub fn load_from_path_or_else<S, C>(path: S, closure: C) -> error::Result<Self>
+    where
+        S: AsRef<std::path::Path>,
+        C: FnOnce() -> Data,
+    {
+        let (backend, exists) = FileBackend::from_path_or_create(path)?;
+        Self::load_or_init(backend, exists, closure)
+    }
+
+    /// Like [`FileDatabase::load_from_path_or_else`], but takes an exclusive
+    /// advisory lock on the file.
+    ///
+    /// Fails with [`BackendError::Locked`] if another process holds the lock.
+    /// See [`FileBackend::from_path_locked`] for details.
+    pub fn load_from_path_locked_or_else<S, C>(path: S, closure: C) -> error::Result<Self>
+    where
+        S: AsRef<std::path::Path>,
+        C: FnOnce() -> Data,
+    {
+        let (backend, exists) = FileBackend::from_path_locked(path)?;
+        Self::load_or_init(backend, exists, closure)
+    }
+
+    /// Create [`FileDatabase`] at `path`. Initialise with `data` if the file
+    /// doesn't exist.
+    ///
+    /// Create new [`FileDatabase`] from the file at [`Path`](std::path::Path).
+    /// Contents are not loaded. If the file does not exist, it is
+    /// initialised with `data`. Frontend is always initialised with `data`.
+    pub fn create_at_path<S>(path: S, data: Data) -> error::Result<Self>
+    where
+        S: AsRef<std::path::Path>,
+    {
+        let (mut backend, exists) = FileBackend::from_path_or_create(path)?;
+        let deser = DeSer::default();
+        if !exists {
+            let ser = deser.serialize(&data)?;
+            backend.put_data(&ser)?;
+        }
+
+        let db = Self::from_opened(data, backend, deser)?;
+        Ok(db)
+    }
+
+    /// Like [`Database::create_at_path`], but also creates the directories
+    /// leading to the file if they don't yet exist.
+    pub fn create_at_path_recursive<S>(path: S, data: Data) -> error::Result<Self>
+    where
+        S: AsRef<std::path::Path>,
+    {
+        backend::create_parent_dirs(path.as_ref())?;
+        Self::create_at_path(path, data)
+    }
+
+    /// Create new [`FileDatabase`] from a file.
+    pub fn from_file(file: std::fs::File, data: Data) -> error::Result<Self> {
+        let backend = FileBackend::from_file(file);
+
+        Ok(Self::from_parts(data, backend, DeSer::default()))
+    }
+}
+
+impl<Data, DeSer> Database<Data, FileBackend, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send + Default,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    /// Load [`FileDatabase`] at `path` or initialise with `Data::default()`.
+    ///
+    /// Create new [`FileDatabase`] from the file at [`Path`](std::path::Path),
+    /// and load the contents. If the file does not exist, initialise with
+    /// `Data::default`.
+    pub fn load_from_path_or_default<S>(path: S) -> error::Result<Self>
+    where
+        S: AsRef<std::path::Path>,
+    {
+        Self::load_from_path_or_else(path, Data::default)
+    }
+}
+
+/// A database backed by a file, using atomic saves.
+pub type PathDatabase<D, DS> = Database<D, PathBackend, DS>;
+
+impl<Data, DeSer> Database<Data, PathBackend, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    /// Create new [`PathDatabase`] from the file at [`Path`](std::path::Path),
+    /// and load the contents.
+    pub fn load_from_path(path: PathBuf) -> error::Result<Self> {
+        let mut backend = PathBackend::from_path_or_fail(path)?;
+        let deser = DeSer::default();
+        let data = Self::load_from_backend(&mut backend, &deser)?;
+
+        let db = Self::from_opened(data, backend, deser)?;
+        Ok(db)
+    }
+
+    /// Load [`PathDatabase`] at `path` or initialise with `data`.
+    ///
+    /// Create new [`PathDatabase`] from the file at [`Path`](std::path::Path),
+    /// and load the contents. If the file does not exist, initialise with
+    /// `data`.
+    pub fn load_from_path_or(path: PathBuf, data: Data) -> error::Result<Self> {
+        let (mut backend, exists) = PathBackend::from_path_or_create(path)?;
+        let deser = DeSer::default();
+        if !exists {
+            let ser = deser.serialize(&data)?;
+            backend.put_data(&ser)?;
+        }
+
+        let db = Self::from_opened(data, backend, deser)?;
+
+        if exists {
+            db.load()?;
+        }
+
+        Ok(db)
+    }
+
+    /// Load [`PathDatabase`] at `path` or initialise with `closure`.
+    ///
+    /// Create new [`PathDatabase`] from the file at [`Path`](std::path::Path),
+    /// and load the contents. If the file does not exist, `closure` is
+    /// called and the database is initialised with it's return value.
+    pub fn load_from_path_or_else<C>(path: PathBuf, closure: C) -> error::Result<Self>
+    where
+        C: FnOnce() -> Data,
+    {
+        let (backend, exists) = PathBackend::from_path_or_create(path)?;
+        Self::load_or_init(backend, exists, closure)
+    }
+
+    /// Like [`PathDatabase::load_from_path_or_else`], but takes an exclusive
+    /// advisory lock.
+    ///
+    /// Fails with [`BackendError::Locked`] if another process holds the lock.
+    /// See [`PathBackend::from_path_locked`] for details.
+    pub fn load_from_path_locked_or_else<C>(path: PathBuf, closure: C) -> error::Result<Self>
+    where
+        C: FnOnce() -> Data,
+    {
+        let (backend, exists) = PathBackend::from_path_locked(path)?;
+        Self::load_or_init(backend, exists, closure)
+    }
+
+    /// Like [`PathDatabase::load_from_path_or_else`], but only one process at
+    /// a time can save the database, see
+    /// [`PathBackend::from_path_single_writer`].
+    ///
+    /// If another process writes the database, the database is read-only
+    /// and saving it fails with [`BackendError::ReadOnly`]. It is initialised
+    /// with `closure` without saving it if the writer did not save yet.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// # extern crate tempfile;
+    /// use rustbreak::{deser::Ron, PathDatabase};
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// # let dir = tempfile::tempdir().unwrap();
+    /// # let path = dir.path().join("app.ron");
+    /// let db = PathDatabase::<Vec<String>, Ron>::load_from_path_single_writer_or_else(
+    ///     path.clone(),
+    ///     Vec::new,
+    /// )?;
+    /// // The app was started a second time
+    /// let second = PathDatabase::<Vec<String>, Ron>::load_from_path_single_writer_or_else(
+    ///     path,
+    ///     Vec::new,
+    /// )?;
+    /// assert!(!db.is_read_only()?);
+    /// assert!(second.is_read_only()?);
+    /// assert!(second.save().is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_from_path_single_writer_or_else<C>(path: PathBuf, closure: C) -> error::Result<Self>
+    where
+        C: FnOnce() -> Data,
+    {
+        let (backend, exists) = PathBackend::from_path_single_writer(path)?;
+        if backend.is_read_only() && !exists {
+            return Ok(Self::from_parts(closure(), backend, DeSer::default()));
+        }
+        Self::load_or_init(backend, exists, closure)
+    }
+
+    /// Create [`PathDatabase`] at `path`. Initialise with `data` if the file
+    /// doesn't exist.
+    ///
+    /// Create new [`PathDatabase`] from the file at [`Path`](std::path::Path).
+    /// Contents are not loaded. If the file does not exist, it is
+    /// initialised with `data`. Frontend is always initialised with `data`.
+    pub fn create_at_path(path: PathBuf, data: Data) -> error::Result<Self> {
+        let (mut backend, exists) = PathBackend::from_path_or_create(path)?;
+        let deser = DeSer::default();
+        if !exists {
+            let ser = deser.serialize(&data)?;
+            backend.put_data(&ser)?;
+        }
+
+        let db = Self::from_opened(data, backend, deser)?;
+        Ok(db)
+    }
+
+    /// Like [`Database::create_at_path`], but also creates the directories
+    /// leading to the file if they don't yet exist.
+    pub fn create_at_path_recursive(path: PathBuf, data: Data) -> error::Result<Self> {
+        backend::create_parent_dirs(&path)?;
+        Self::create_at_path(path, data)
+    }
+
+    /// The path of the database file.
+    pub fn path(&self) -> error::Result<PathBuf> {
+        Ok(self.backend.lock()?.path().to_owned())
+    }
+
+    /// Whether another process writes the database, see
+    /// [`PathDatabase::load_from_path_single_writer_or_else`].
+    pub fn is_read_only(&self) -> error::Result<bool> {
+        Ok(self.backend.lock()?.is_read_only())
+    }
+}
+
+impl<Data, DeSer> Database<Data, PathBackend, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send + Default,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    /// Load [`PathDatabase`] at `path` or initialise with `Data::default()`.
+    ///
+    /// Create new [`PathDatabase`] from the file at [`Path`](std::path::Path),
+    /// and load the contents. If the file does not exist, initialise with
+    /// `Data::default`.
+    pub fn load_from_path_or_default(path: PathBuf) -> error::Result<Self> {
+        Self::load_from_path_or_else(path, Data::default)
+    }
+
+    /// Load [`PathDatabase`] from `file` in the configuration directory of
+    /// `app`, or initialise with `Data::default()`.
+    ///
+    /// The directory is `$XDG_CONFIG_HOME/<app>` or `~/.config/<app>` on
+    /// Linux, `~/Library/Application Support/<app>` on macOS and
+    /// `%APPDATA%\<app>` on Windows. It is created if it does not exist.
+    ///
+    /// **Important**: This is only available with the `dirs` feature.
+    #[cfg(feature = "dirs")]
+    pub fn in_config_dir(app: &str, file: &str) -> error::Result<Self> {
+        Self::in_platform_dir(dirs::config_dir(), app, file)
+    }
+
+    /// Load [`PathDatabase`] from `file` in the data directory of `app`, or
+    /// initialise with `Data::default()`.
+    ///
+    /// The directory is `$XDG_DATA_HOME/<app>` or `~/.local/share/<app>` on
+    /// Linux, `~/Library/Application Support/<app>` on macOS and
+    /// `%APPDATA%\<app>` on Windows. It is created if it does not exist.
+    ///
+    /// **Important**: This is only available with the `dirs` feature.
+    #[cfg(feature = "dirs")]
+    pub fn in_data_dir(app: &str, file: &str) -> error::Result<Self> {
+        Self::in_platform_dir(dirs::data_dir(), app, file)
+    }
+
+    #[cfg(feature = "dirs")]
+    fn in_platform_dir(base: Option<PathBuf>, app: &str, file: &str) -> error::Result<Self> {
+        let base = base.ok_or_else(|| {
+            BackendError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "the platform directory could not be determined",
+            ))
+        })?;
+        let dir = base.join(app);
+        std::fs::create_dir_all(&dir).map_err(BackendError::Io)?;
+        Self::load_from_path_or_default(dir.join(file))
+    }
+}
+
+/// A database backed by a byte vector (`Vec<u8>`).
+pub type MemoryDatabase<D, DS> = Database<D, MemoryBackend, DS>;
+
+impl<Data, DeSer> Database<Data, MemoryBackend, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    /// Create new in-memory database.
+    pub fn memory(data: Data) -> error::Result<Self> {
+        let backend = MemoryBackend::new();
+
+        Ok(Self::from_parts(data, backend, DeSer::default()))
+    }
+}
+
+/// A database backed by anonymous memory map.
+#[cfg(feature = "mmap")]
+pub type MmapDatabase<D, DS> = Database<D, MmapStorage, DS>;
+
+#[cfg(feature = "mmap")]
+impl<Data, DeSer> Database<Data, MmapStorage, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    /// Create new [`MmapDatabase`].
+    pub fn mmap(data: Data) -> error::Result<Self> {
+        let backend = MmapStorage::new()?;
+
+        Ok(Self::from_parts(data, backend, DeSer::default()))
+    }
+
+    /// Create new [`MmapDatabase`] with specified initial size.
+    pub fn mmap_with_size(data: Data, size: usize) -> error::Result<Self> {
+        let backend = MmapStorage::with_size(size)?;
+
+        Ok(Self::from_parts(data, backend, DeSer::default()))
+    }
+}
+
+impl<Data, Back, DeSer> Database<Data, Back, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send + Sync + 'static,
+    Back: Backend + Send + 'static,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone + 'static,
+{
+    /// Start the background thread that applies the auto-save policy.
+    ///
+    /// The thread handles [`AutoSavePolicy::Debounced`] and
+    /// [`AutoSavePolicy::Periodic`], and follows changes made with
+    /// [`Database::set_autosave_policy`]. It is stopped when the returned
+    /// handle is shut down or dropped, which also persists any changes the
+    /// policy still owes, see [`AutoSaveHandle::shutdown`].
+    ///
+    /// The handle keeps the database alive.
+    pub fn start_autosave(self: &Arc<Self>) -> AutoSaveHandle {
+        let db: Arc<dyn registry::Flush> = self.clone();
+        self.autosave.start(db)
+    }
+
+    /// Make sure the data is saved one last time when the program shuts
+    /// down.
+    ///
+    /// The returned guard saves the database when it is dropped, if it
+    /// [is dirty](Database::is_dirty), so holding it in `main` covers a
+    /// normal exit and unwinding. With the `signals` feature on UNIX, it
+    /// also saves on `SIGINT` and `SIGTERM`, after which the signal
+    /// terminates the process as usual. Stop an [`AutoSaveHandle`] before the
+    /// guard is dropped, so that its final save is not lost either.
+    ///
+    /// Fails if the signal handlers could not be installed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// use rustbreak::{deser::Ron, MemoryDatabase};
+    /// use std::sync::Arc;
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// let db = Arc::new(MemoryDatabase::<Vec<u32>, Ron>::memory(vec![])?);
+    /// let shutdown = db.flush_on_shutdown()?;
+    ///
+    /// db.write(|data| data.push(1))?;
+    ///
+    /// // Run the daemon, then save what is left
+    /// shutdown.flush()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn flush_on_shutdown(self: &Arc<Self>) -> error::Result<ShutdownGuard> {
+        let db: Arc<dyn registry::Flush> = self.clone();
+        ShutdownGuard::new(db)
+    }
+
+    /// Save the data on a background thread, like [`Database::save`], and
+    /// return right away.
+    ///
+    /// The data is serialized and written to the backend on the thread, so
+    /// the caller does not wait for slow file systems or `fsync`. Writes made
+    /// before the save is finished may or may not be part of it. Concurrent
+    /// saves are coalesced as usual.
+    ///
+    /// The handle keeps the database alive until the save is done.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// use rustbreak::{deser::Ron, MemoryDatabase};
+    /// use std::sync::Arc;
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// let db = Arc::new(MemoryDatabase::<Vec<u32>, Ron>::memory(vec![])?);
+    /// db.write(|data| data.push(1))?;
+    ///
+    /// let save = db.save_async();
+    /// // Do something else in the meantime
+    /// save.wait()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn save_async(self: &Arc<Self>) -> SaveHandle {
+        let db = Arc::clone(self);
+        SaveHandle::spawn(move || db.save())
+    }
+
+    /// Ask the background writer to save the data, and return right away.
+    ///
+    /// This is meant for data that changes faster than it can be saved. There
+    /// is at most one background writer per database, started on demand, and
+    /// each of its saves writes the newest data. Saves queued while it is
+    /// busy are merged into its next one, the intermediate states are never
+    /// written.
+    ///
+    /// The returned [`SaveTicket`] tells when the data as it was at the time
+    /// of the call, or newer data, is saved. If a background save fails, the
+    /// writer stops, and waiting on a ticket saves in the calling thread to
+    /// report the error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// use rustbreak::{deser::Ron, MemoryDatabase};
+    /// use std::sync::Arc;
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// let db = Arc::new(MemoryDatabase::<u64, Ron>::memory(0)?);
+    ///
+    /// let mut ticket = None;
+    /// for frame in 0..1000 {
+    ///     db.write(|data| *data = frame)?;
+    ///     ticket = Some(db.queue_save()?);
+    /// }
+    ///
+    /// // Frame 999, or something newer, is saved
+    /// ticket.unwrap().wait()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn queue_save(self: &Arc<Self>) -> error::Result<SaveTicket> {
+        let (ticket, start_writer) = self.saves.queue()?;
+        if start_writer {
+            let db = Arc::clone(self);
+            std::thread::spawn(move || db.write_queued());
+        }
+        let db: Arc<dyn coalesce::AwaitSave> = self.clone();
+        Ok(SaveTicket::new(db, ticket))
+    }
+
+    /// The loop of the background writer of [`Database::queue_save`].
+    fn write_queued(&self) {
+        while let Ok(Some(ticket)) = self.saves.next_queued() {
+            if self.saves.run_for(ticket, || self.save_now()).is_err() {
+                self.saves.stop_writer();
+                return;
+            }
+        }
+    }
+
+    /// Reload the data whenever the file at `path` changes on disk, and call
+    /// `callback` with the new data, or the error if loading failed.
+    ///
+    /// `path` should be the file the backend stores the data in. Saves of
+    /// this database change the file too, so they also cause a reload.
+    /// Changes that were not saved yet are lost on a reload.
+    ///
+    /// The file is watched until the returned [`FileWatcher`] is dropped. It
+    /// does not keep the database alive.
+    ///
+    /// **Important**: This is only available with the `watch` feature.
+    ///
+    /// [`FileWatcher`]: watch::FileWatcher
+    #[cfg(feature = "watch")]
+    pub fn watch_file<P, F>(
+        self: &Arc<Self>,
+        path: P,
+        mut callback: F,
+    ) -> error::Result<watch::FileWatcher>
+    where
+        P: AsRef<std::path::Path>,
+        F: FnMut(error::Result<&Data>) + Send + 'static,
+    {
+        let db = Arc::downgrade(self);
+        let watcher = watch::FileWatcher::new(path.as_ref(), move || {
+            let Some(db) = db.upgrade() else { return };
+            let result = db.load().and_then(|()| db.read(|data| callback(Ok(data))));
+            if let Err(err) = result {
+                callback(Err(err));
+            }
+        })?;
+        Ok(watcher)
+    }
+
+    /// Reload the data whenever the process receives `SIGHUP`, and call
+    /// `callback` with the new data, or the error if loading failed.
+    ///
+    /// This is the usual way to tell a UNIX daemon to reread its
+    /// configuration, for example with `systemctl reload` or `kill -HUP`.
+    /// Changes that were not saved yet are lost on a reload.
+    ///
+    /// The signal reloads the database until the returned [`SignalWatcher`]
+    /// is dropped. It does not keep the database alive. Without any
+    /// watcher, `SIGHUP` terminates the process as usual.
+    ///
+    /// **Important**: This is only available with the `signals` feature, on
+    /// UNIX.
+    ///
+    /// [`SignalWatcher`]: watch::SignalWatcher
+    #[cfg(all(unix, feature = "signals"))]
+    pub fn reload_on_sighup<F>(
+        self: &Arc<Self>,
+        mut callback: F,
+    ) -> error::Result<watch::SignalWatcher>
+    where
+        F: FnMut(error::Result<&Data>) + Send + 'static,
+    {
+        let db = Arc::downgrade(self);
+        let watcher = watch::SignalWatcher::new(move || {
+            let Some(db) = db.upgrade() else { return };
+            let result = db.load().and_then(|()| db.read(|data| callback(Ok(data))));
+            if let Err(err) = result {
+                callback(Err(err));
+            }
+        })?;
+        Ok(watcher)
+    }
+}
+
+impl<Data, Back, DeSer> Database<Data, Back, DeSer> {
+    /// Exchanges the `DeSerialization` strategy with the new one.
+    pub fn with_deser<T>(self, deser: T) -> Database<Data, Back, T> {
+        Database {
+            backend: self.backend,
+            data: self.data,
+            deser,
+            saves: self.saves,
+            watchers: self.watchers,
+            autosave: self.autosave,
+            generation: self.generation,
+            saved_generation: self.saved_generation,
+            snapshot: self.snapshot,
+            buffer: self.buffer,
+            fingerprint: self.fingerprint,
+            detect_external_changes: self.detect_external_changes,
+            max_load_size: self.max_load_size,
+            max_save_size: self.max_save_size,
+            merge: self.merge,
+            stats: self.stats,
+            hooks: self.hooks,
+        }
+    }
+}
+
+impl<Data, Back, DeSer> Database<Data, Back, DeSer> {
+    /// Exchanges the `Backend` with the new one.
+    ///
+    /// The new backend does not necessarily have the latest data saved to it,
+    /// so a `.save` should be called to make sure that it is saved.
+    pub fn with_backend<T>(self, backend: T) -> Database<Data, T, DeSer> {
+        // The new backend does not have the data yet.
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        Database {
+            backend: Mutex::new(backend),
+            data: self.data,
+            deser: self.deser,
+            saves: SaveCoalescer::default(),
+            watchers: self.watchers,
+            autosave: self.autosave,
+            generation: self.generation,
+            saved_generation: self.saved_generation,
+            snapshot: self.snapshot,
+            buffer: self.buffer,
+            fingerprint: Mutex::default(),
+            detect_external_changes: self.detect_external_changes,
+            max_load_size: self.max_load_size,
+            max_save_size: self.max_save_size,
+            merge: self.merge,
+            stats: self.stats,
+            hooks: self.hooks,
+        }
+    }
+}
+
+impl<Data, Back, DeSer> Database<Data, Back, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send,
+    Back: Backend,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    /// Converts from one data type to another.
+    ///
+    /// This method is useful to migrate from one datatype to another.
+    pub fn convert_data<C, OutputData>(
+        self,
+        convert: C,
+    ) -> error::Result<Database<OutputData, Back, DeSer>>
+    where
+        OutputData: Serialize + DeserializeOwned + Send,
+        C: FnOnce(Data) -> OutputData,
+        DeSer: DeSerializer<OutputData> + Send + Sync,
+    {
+        let (data, backend, deser) = self.into_inner()?;
+        Ok(Database::from_parts(convert(data), backend, deser))
+    }
+
+    /// Converts the data in the backend to another `DeSerialization`
+    /// strategy.
+    ///
+    /// Unlike [`Database::with_deser`], which only changes how the next save
+    /// is written, this loads the data with the current strategy and saves it
+    /// with `deser` right away, so the backend never holds data the returned
+    /// database can not load. If the database has changes that were not saved
+    /// yet, the data in memory is saved instead, so they are not lost.
+    ///
+    /// If loading fails, the backend is not touched. The data is completely
+    /// serialized before it replaces the old data, so a failing `deser`
+    /// leaves the old data in place too, and a [`PathBackend`] only replaces
+    /// its file once the new one is written. On failure the database is
+    /// returned unchanged with the error, still using the old strategy.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # extern crate rustbreak;
+    /// use rustbreak::backend::{Backend, MemoryBackend};
+    /// use rustbreak::deser::{Json, Ron};
+    /// use rustbreak::Database;
+    ///
+    /// # fn main() -> rustbreak::error::Result<()> {
+    /// let db = Database::<Vec<u32>, _, Ron>::from_parts(vec![1, 2], MemoryBackend::new(), Ron);
+    /// db.save()?;
+    ///
+    /// let db = db.convert_format(Json::compact()).map_err(|(_, e)| e)?;
+    /// let (_, mut backend, _) = db.into_inner()?;
+    /// assert_eq!(backend.get_data()?, b"[1,2]");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[allow(clippy::result_large_err)] // The database is handed back on failure
+    pub fn convert_format<T>(
+        self,
+        deser: T,
+    ) -> std::result::Result<Database<Data, Back, T>, (Self, RustbreakError)>
+    where
+        T: DeSerializer<Data> + Send + Sync + Clone,
+    {
+        if !self.is_dirty() {
+            if let Err(e) = self.load() {
+                return Err((self, e));
+            }
+        }
+        let old = self.deser.clone();
+        let db = self.with_deser(deser);
+        match db.save() {
+            Ok(()) => Ok(db),
+            Err(e) => Err((db.with_deser(old), e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tempfile::NamedTempFile;
+
+    type TestData = HashMap<usize, String>;
+    type TestDb<B> = Database<TestData, B, crate::deser::Ron>;
+    type TestMemDb = TestDb<MemoryBackend>;
+
+    fn test_data() -> TestData {
+        let mut data = HashMap::new();
+        data.insert(1, "Hello World".to_string());
+        data.insert(100, "Rustbreak".to_string());
+        data
+    }
+
+    /// Used to test that `Default::default` isn't called.
+    #[derive(Clone, Debug, Serialize, serde::Deserialize)]
+    struct PanicDefault;
+    impl Default for PanicDefault {
+        fn default() -> Self {
+            panic!("`default` was called but should not")
+        }
+    }
+
+    #[test]
+    fn create_db_and_read() {
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        assert_eq!(
+            "Hello World",
+            db.read(|d| d.get(&1).cloned())
+                .expect("Rustbreak read error")
+                .expect("Should be `Some` but was `None`")
+        );
+        assert_eq!(
+            "Rustbreak",
+            db.read(|d| d.get(&100).cloned())
+                .expect("Rustbreak read error")
+                .expect("Should be `Some` but was `None`")
+        );
+    }
+
+    #[test]
+    fn write_twice() {
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        db.write(|d| d.insert(3, "Write to db".to_string()))
+            .expect("Rustbreak write error");
+        db.write(|d| d.insert(3, "Second write".to_string()))
+            .expect("Rustbreak write error");
+        assert_eq!(
+            "Hello World",
+            db.read(|d| d.get(&1).cloned())
+                .expect("Rustbreak read error")
+                .expect("Should be `Some` but was `None`")
+        );
+        assert_eq!(
+            "Rustbreak",
+            db.read(|d| d.get(&100).cloned())
+                .expect("Rustbreak read error")
+                .expect("Should be `Some` but was `None`")
+        );
+        assert_eq!(
+            "Second write",
+            db.read(|d| d.get(&3).cloned())
+                .expect("Rustbreak read error")
+                .expect("Should be `Some` but was `None`")
+        );
+    }
+
+    #[test]
+    fn transaction_commits_or_rolls_back() {
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        db.save().expect("Rustbreak save error");
+
+        let result: error::Result<()> = db.transaction(true, |d| {
+            d.clear();
+            Err(RustbreakError::WritePanic)
+        });
+        assert!(matches!(result, Err(RustbreakError::WritePanic)));
+        assert_eq!(test_data(), db.get_data(false).expect("could not get data"));
+        assert_eq!(test_data(), db.get_data(true).expect("could not get data"));
+
+        db.transaction::<_, _, RustbreakError>(true, |d| {
+            d.remove(&1);
+            Ok(())
+        })
+        .expect("Rustbreak transaction error");
+        assert!(!db.is_dirty());
+        let mut expected = test_data();
+        expected.remove(&1);
+        assert_eq!(expected, db.get_data(true).expect("could not get data"));
+    }
+
+    #[test]
+    fn saving_transactions_detect_external_changes() {
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        db.set_external_change_detection(true);
+        db.save().expect("Rustbreak save error");
+        let changes = db.subscribe().expect("could not subscribe");
+
+        db.transaction::<_, _, RustbreakError>(true, |d| {
+            d.remove(&1);
+            Ok(())
+        })
+        .expect("Rustbreak transaction error");
+        assert_eq!(
+            changes.try_recv().expect("no change").kind,
+            ChangeKind::Write
+        );
+        // The transaction recorded the fingerprint of what it saved.
+        db.save().expect("Rustbreak save error");
+
+        db.backend.lock().unwrap().put_data(b"{}").unwrap();
+        let result = db.transaction::<_, _, RustbreakError>(true, |d| {
+            d.remove(&100);
+            Ok(())
+        });
+        assert!(matches!(result, Err(RustbreakError::ExternalChange)));
+        assert!(db.borrow_data().unwrap().contains_key(&100));
+        assert!(changes.try_recv().is_err());
+    }
+
+    #[test]
+    fn write_fallible_aborts_on_error() {
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        let err = db
+            .write_fallible::<_, (), _>(|d| {
+                d.clear();
+                Err("nope")
+            })
+            .expect_err("write should have been aborted");
+        assert_eq!(
+            "nope",
+            std::error::Error::source(&err)
+                .expect("error should have a source")
+                .to_string()
+        );
+        assert_eq!(test_data(), db.get_data(false).expect("could not get data"));
+
+        let len = db
+            .write_fallible(|d| {
+                d.remove(&1);
+                Ok::<_, std::fmt::Error>(d.len())
+            })
+            .expect("Rustbreak write error");
+        assert_eq!(1, len);
+    }
+
+    #[test]
+    fn write_and_save_then_read_after_load() {
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        let len = db
+            .write_and_save(|d| {
+                d.remove(&1);
+                d.len()
+            })
+            .expect("Rustbreak write error");
+        assert_eq!(1, len);
+        assert!(!db.is_dirty());
+
+        db.write(HashMap::clear).expect("Rustbreak write error");
+        let len = db
+            .read_after_load(HashMap::len)
+            .expect("Rustbreak read error");
+        assert_eq!(1, len);
+    }
+
+    #[test]
+    fn timed_locks_wait_for_the_holder() {
+        let db = Arc::new(TestMemDb::memory(test_data()).expect("Could not create database"));
+        let (locked, wait) = std::sync::mpsc::channel();
+        let holder = {
+            let db = Arc::clone(&db);
+            std::thread::spawn(move || {
+                let guard = db.borrow_data().expect("Rustbreak read error");
+                locked.send(()).unwrap();
+                std::thread::sleep(Duration::from_millis(50));
+                drop(guard);
+            })
+        };
+        wait.recv().unwrap();
+
+        assert_eq!(db.try_read(HashMap::len).expect("Rustbreak read error"), 2);
+        assert!(matches!(
+            db.try_write(HashMap::clear),
+            Err(RustbreakError::WouldBlock)
+        ));
+        assert!(matches!(
+            db.write_timeout(Duration::from_millis(1), HashMap::clear),
+            Err(RustbreakError::Timeout)
+        ));
+        db.write_timeout(Duration::from_secs(10), HashMap::clear)
+            .expect("Rustbreak write error");
+        assert_eq!(
+            db.read_timeout(Duration::ZERO, HashMap::len)
+                .expect("Rustbreak read error"),
+            0
+        );
+        holder.join().unwrap();
+    }
+
+    #[test]
+    fn borrow_data_map_holds_the_lock() {
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        let key = 100;
+        let value = db
+            .borrow_data_map(|data| data[&key].as_str())
+            .expect("Rustbreak read error");
+        assert_eq!(&*value, "Rustbreak");
+        assert!(matches!(
+            db.try_write(HashMap::clear),
+            Err(RustbreakError::WouldBlock)
+        ));
+        drop(value);
+        db.try_write(HashMap::clear).expect("Rustbreak write error");
+    }
+
+    #[test]
+    fn borrowed_data_uses_the_guards_of_the_crate() {
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        let data: guard::ReadGuard<'_, TestData> = db.borrow_data().expect("Rustbreak lock error");
+        assert_eq!(*data, test_data());
+        drop(data);
+
+        let mut data: guard::WriteGuard<'_, TestData> =
+            db.borrow_data_mut().expect("Rustbreak lock error");
+        data.clear();
+        drop(data);
+        assert!(db.read(HashMap::is_empty).expect("Rustbreak read error"));
+    }
+
+    #[test]
+    fn saving_write_guard_saves_on_release() {
+        let db = TestMemDb::memory(HashMap::new()).expect("Could not create database");
+        db.save().expect("Rustbreak save error");
+        {
+            let mut data = db.borrow_data_mut_autosave().expect("Rustbreak lock error");
+            data.insert(1, "one".to_owned());
+        }
+        assert!(!db.is_dirty());
+        let mut data = db.borrow_data_mut_autosave().expect("Rustbreak lock error");
+        data.insert(2, "two".to_owned());
+        data.commit().expect("Rustbreak save error");
+        assert!(!db.is_dirty());
+
+        db.write(HashMap::clear).expect("Rustbreak write error");
+        db.load().expect("Rustbreak load error");
+        assert_eq!(db.read(HashMap::len).expect("Rustbreak read error"), 2);
+    }
+
+    #[test]
+    fn saving_write_guard_stays_dirty_on_panic() {
+        let db = TestMemDb::memory(HashMap::new()).expect("Could not create database");
+        db.save().expect("Rustbreak save error");
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let mut data = db.borrow_data_mut_autosave().expect("Rustbreak lock error");
+            data.insert(1, "one".to_owned());
+            panic!("in the middle of a change");
+        }));
+        assert!(result.is_err());
+        assert!(db.is_dirty());
+
+        #[cfg(feature = "parking_lot")]
+        {
+            assert!(db.save_if_dirty().expect("Rustbreak save error"));
+            db.write(HashMap::clear).expect("Rustbreak write error");
+            db.load().expect("Rustbreak load error");
+            assert_eq!(db.read(HashMap::len).expect("Rustbreak read error"), 1);
+        }
+        #[cfg(not(feature = "parking_lot"))]
+        assert!(matches!(db.save_if_dirty(), Err(RustbreakError::Poison)));
+    }
+
+    #[test]
+    fn data_does_not_need_clone() {
+        #[derive(Serialize, serde::Deserialize)]
+        struct Unique(Vec<u32>);
+
+        let db = MemoryDatabase::<Unique, crate::deser::Ron>::memory(Unique(vec![1]))
+            .expect("Could not create database");
+        db.write(|data| data.0.push(2))
+            .expect("Rustbreak write error");
+        db.save().expect("Rustbreak save error");
+        db.write(|data| data.0.clear())
+            .expect("Rustbreak write error");
+        db.load().expect("Rustbreak load error");
+        assert_eq!(
+            db.read(|data| data.0.len()).expect("Rustbreak read error"),
+            2
+        );
+        db.borrow_data_mut_autosave()
+            .expect("Rustbreak lock error")
+            .commit()
+            .expect("Rustbreak save error");
+    }
+
+    #[test]
+    fn swap_data_returns_the_old_data() {
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        let old = db
+            .swap_data(HashMap::new(), true)
+            .expect("Rustbreak swap error");
+        assert_eq!(old, test_data());
+        assert!(!db.is_dirty());
+
+        let old = db
+            .swap_data(test_data(), false)
+            .expect("Rustbreak swap error");
+        assert!(old.is_empty());
+        assert!(db.is_dirty());
+        db.load().expect("Rustbreak load error");
+        assert!(db.read(HashMap::is_empty).expect("Rustbreak read error"));
+    }
+
+    #[test]
+    fn swap_data_returns_the_old_data_if_saving_fails() {
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        db.set_max_save_size(Some(0));
+        let (old, err) = db
+            .swap_data(HashMap::new(), true)
+            .expect_err("the save should exceed the quota");
+        assert!(matches!(
+            err,
+            RustbreakError::Backend(BackendError::QuotaExceeded { .. })
+        ));
+        assert_eq!(old, test_data());
+        assert!(db.read(HashMap::is_empty).expect("Rustbreak read error"));
+        assert!(db.is_dirty());
+    }
+
+    #[test]
+    fn update_if_only_changes_when_asked() {
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        db.save().expect("Rustbreak save error");
+        let updated = db.update_if(|_| None).expect("Rustbreak update error");
+        assert!(!updated);
+        assert!(!db.is_dirty());
+
+        let updated = db
+            .update_if(|current| current.contains_key(&1).then(HashMap::new))
+            .expect("Rustbreak update error");
+        assert!(updated);
+        assert!(db.is_dirty());
+        assert!(db.read(HashMap::is_empty).expect("Rustbreak read error"));
+    }
+
+    #[test]
+    fn version_follows_changes() {
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        let start = db.version();
+        db.save().expect("Rustbreak save error");
+        db.read(HashMap::len).expect("Rustbreak read error");
+        assert_eq!(db.version(), start);
+
+        db.write(HashMap::clear).expect("Rustbreak write error");
+        let written = db.version();
+        assert!(written > start);
+        assert!(!db.update_if(|_| None).expect("Rustbreak update error"));
+        assert_eq!(db.version(), written);
+        db.load().expect("Rustbreak load error");
+        assert!(db.version() > written);
+    }
+
+    #[test]
+    fn snapshot_follows_load() {
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        db.save().expect("Rustbreak save error");
+        db.write(HashMap::clear).expect("Rustbreak write error");
+        assert!(db.snapshot().expect("Rustbreak snapshot error").is_empty());
+
+        db.load().expect("Rustbreak load error");
+        assert_eq!(
+            test_data(),
+            *db.snapshot().expect("Rustbreak snapshot error")
+        );
+    }
+
+    #[test]
+    fn load_or_recover_repairs_or_aborts() {
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        db.save().expect("Rustbreak save error");
+        let good = db.backend.lock().unwrap().get_data().unwrap();
+        db.put_data(HashMap::new(), false)
+            .expect("Rustbreak put error");
+        db.backend.lock().unwrap().put_data(b"{").unwrap();
+
+        let err = db.load_or_recover(|_, _| RecoveryAction::Abort);
+        assert!(matches!(err, Err(RustbreakError::DeSerialization(_))));
+        assert!(db.borrow_data().unwrap().is_empty());
+
+        let mut calls = 0;
+        db.load_or_recover(|raw, _| {
+            calls += 1;
+            assert_eq!(raw, b"{");
+            RecoveryAction::Repair(good.clone())
+        })
+        .expect("Rustbreak recover error");
+        assert_eq!(calls, 1);
+        assert_eq!(test_data(), *db.borrow_data().unwrap());
+        assert!(!db.is_dirty());
+        db.load().expect("Rustbreak load error");
+        assert_eq!(test_data(), *db.borrow_data().unwrap());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn backup_to_writes_current_data() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let path = dir.path().join("backup.ron");
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+
+        db.backup_to(&path).expect("Rustbreak backup error");
+        assert!(db.is_dirty());
+        let backup = TestDb::<PathBackend>::load_from_path(path).expect("Could not load backup");
+        assert_eq!(test_data(), backup.get_data(false).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "bin_enc")]
+    fn export_and_import_between_formats() {
+        use crate::deser::Bincode;
+
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let dump = dir.path().join("dump.bin");
+        let (backend, _) =
+            PathBackend::from_path_or_create(dump.clone()).expect("could not create backend");
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        db.export_to::<Bincode, _>(backend)
+            .expect("Rustbreak export error");
+
+        let other = TestMemDb::memory(HashMap::new()).expect("Could not create database");
+        let backend = PathBackend::from_path_or_fail(dump).expect("could not open backend");
+        other
+            .import_from::<Bincode, _>(backend)
+            .expect("Rustbreak import error");
+        assert_eq!(test_data(), other.get_data(false).unwrap());
+        assert!(!other.is_dirty());
+    }
+
+    #[test]
+    #[cfg(feature = "watch")]
+    #[cfg_attr(miri, ignore)]
+    fn watch_file_reloads_external_changes() {
+        use std::time::Duration;
+
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let path = dir.path().join("watched.ron");
+        let db = Arc::new(
+            TestDb::<PathBackend>::create_at_path(path.clone(), HashMap::new())
+                .expect("Could not create database"),
+        );
+        let (tx, rx) = std::sync::mpsc::channel();
+        let _watcher = db
+            .watch_file(&path, move |data| {
+                if let Ok(data) = data {
+                    let _ = tx.send(data.clone());
+                }
+            })
+            .expect("Could not watch file");
+
+        let other = TestDb::<PathBackend>::load_from_path(path).expect("Could not open database");
+        other
+            .put_data(test_data(), true)
+            .expect("Rustbreak put error");
+        let reloaded = rx
+            .recv_timeout(Duration::from_secs(5))
+            .expect("The watcher did not fire");
+        assert_eq!(test_data(), reloaded);
+        assert_eq!(test_data(), db.get_data(false).unwrap());
+    }
+
+    #[test]
+    #[cfg(all(unix, feature = "signals"))]
+    #[cfg_attr(miri, ignore)]
+    #[allow(unsafe_code)]
+    fn sighup_reloads_the_data() {
+        use std::time::Duration;
+
+        let db = Arc::new(TestMemDb::memory(HashMap::new()).expect("Could not create database"));
+        let (tx, rx) = std::sync::mpsc::channel();
+        let watcher = db
+            .reload_on_sighup(move |data| {
+                let _ = tx.send(data.ok().cloned());
+            })
+            .expect("Could not handle SIGHUP");
+
+        db.backend
+            .lock()
+            .unwrap()
+            .put_data(b"{1: \"Hello World\", 100: \"Rustbreak\"}")
+            .unwrap();
+        // SAFETY: The watcher handles the signal.
+        unsafe { libc::raise(libc::SIGHUP) };
+        let reloaded = rx
+            .recv_timeout(Duration::from_secs(5))
+            .expect("The signal was not handled");
+        assert_eq!(Some(test_data()), reloaded);
+        assert_eq!(test_data(), db.get_data(false).unwrap());
+        drop(watcher);
+    }
+
+    #[test]
+    fn load_if_modified_skips_unchanged_data() {
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        db.save().expect("Rustbreak save error");
+        db.put_data(HashMap::new(), false)
+            .expect("Rustbreak put error");
+        assert!(!db.load_if_modified().expect("Rustbreak load error"));
+        assert!(db.borrow_data().unwrap().is_empty());
+
+        db.backend.lock().unwrap().put_data(b"{}").unwrap();
+        assert!(db.load_if_modified().expect("Rustbreak load error"));
+        assert!(!db.load_if_modified().expect("Rustbreak load error"));
+    }
+
+    #[test]
+    fn save_detects_external_changes() {
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        db.set_external_change_detection(true);
+        db.save().expect("Rustbreak save error");
+        db.save().expect("Rustbreak save error");
+
+        db.backend.lock().unwrap().put_data(b"{}").unwrap();
+        db.write(|d| d.insert(3, "Mine".to_string())).unwrap();
+        let err = db.save();
+        assert!(matches!(err, Err(RustbreakError::ExternalChange)));
+
+        db.load().expect("Rustbreak load error");
+        assert!(db.borrow_data().unwrap().is_empty());
+        db.save().expect("Rustbreak save error");
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn opened_databases_detect_external_changes() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let path = dir.path().join("db.ron");
+        let db = TestDb::<PathBackend>::load_from_path_or(path.clone(), test_data())
+            .expect("could not create");
+        db.set_external_change_detection(true);
+
+        let other = TestDb::<PathBackend>::load_from_path(path).expect("could not load");
+        other
+            .put_data(HashMap::new(), true)
+            .expect("could not save");
+        assert!(matches!(db.save(), Err(RustbreakError::ExternalChange)));
+    }
+
+    #[test]
+    fn merge_on_external_change() {
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        db.set_merge(|base, mut mine, theirs| {
+            assert_eq!(base, test_data());
+            mine.extend(theirs);
+            mine
+        })
+        .expect("Rustbreak merge error");
+        db.save().expect("Rustbreak save error");
+
+        db.backend
+            .lock()
+            .unwrap()
+            .put_data(b"{7: \"Theirs\"}")
+            .unwrap();
+        db.write(|d| d.insert(3, "Mine".to_string())).unwrap();
+        db.save().expect("Rustbreak save error");
+
+        let mut expected = test_data();
+        expected.insert(3, "Mine".to_string());
+        expected.insert(7, "Theirs".to_string());
+        assert_eq!(expected, db.get_data(false).unwrap());
+        assert!(!db.is_dirty());
+        db.load().expect("Rustbreak load error");
+        assert_eq!(expected, db.get_data(false).unwrap());
+    }
+
+    #[test]
+    fn failed_saves_keep_the_merge_base() {
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        db.set_merge(|_, mine, _| mine)
+            .expect("Rustbreak merge error");
+        db.save().expect("Rustbreak save error");
+        let base = || db.merge.lock().unwrap().base.clone();
+        assert_eq!(base(), Some(test_data()));
+
+        db.write(|d| d.insert(3, "Mine".to_string())).unwrap();
+        db.set_max_save_size(Some(0));
+        db.save().expect_err("the save should exceed the quota");
+        assert_eq!(base(), Some(test_data()));
+        db.modify(|d| d.insert(4, "Mine".to_string()))
+            .expect_err("the save should exceed the quota");
+        assert_eq!(base(), Some(test_data()));
+
+        // Garbage written by someone else can not be merged, but the base
+        // is kept for when it is fixed.
+        db.set_max_save_size(None);
+        db.backend.lock().unwrap().put_data(b"not ron").unwrap();
+        db.write(|d| d.insert(3, "Mine".to_string())).unwrap();
+        db.save().expect_err("the external data is not valid");
+        assert_eq!(base(), Some(test_data()));
+        db.backend
+            .lock()
+            .unwrap()
+            .put_data(b"{7: \"Theirs\"}")
+            .unwrap();
+        db.save().expect("Rustbreak save error");
+        assert_eq!(base(), Some(db.get_data(false).unwrap()));
+    }
+
+    #[test]
+    #[cfg(all(feature = "dirs", target_os = "linux"))]
+    #[cfg_attr(miri, ignore)]
+    fn in_data_dir_creates_the_directory() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        // Only this test reads the variable
+        std::env::set_var("XDG_DATA_HOME", dir.path());
+        let db = TestDb::<PathBackend>::in_data_dir("rustbreak-test", "state.ron")
+            .expect("Could not create database");
+        db.put_data(test_data(), true).expect("Rustbreak put error");
+        assert!(dir
+            .path()
+            .join("rustbreak-test")
+            .join("state.ron")
+            .is_file());
+    }
+
+    #[test]
+    fn stats_count_saves_and_loads() {
+        use std::sync::atomic::AtomicU64;
+        use std::sync::Arc;
 
-#[cfg(feature = "mmap")]
-impl<Data, DeSer> Database<Data, MmapStorage, DeSer>
-where
-    Data: Serialize + DeserializeOwned + Clone + Send,
-    DeSer: DeSerializer<Data> + Send + Sync + Clone,
-{
-    /// Create new [`MmapDatabase`].
-    pub fn mmap(data: Data) -> error::Result<Self> {
-        let backend = MmapStorage::new()?;
+        let db = TestDb::<MemoryBackend>::memory(test_data()).expect("Could not create database");
+        let hook_saves = Arc::new(AtomicU64::new(0));
+        let counter = hook_saves.clone();
+        db.set_stats_hook(move |stats| counter.store(stats.saves, Ordering::SeqCst))
+            .expect("Could not set hook");
 
-        Ok(Self {
-            data: RwLock::new(data),
-            backend: Mutex::new(backend),
-            deser: DeSer::default(),
-        })
+        db.save().expect("Could not save");
+        db.write_and_save(|data| data.insert(3, "New".to_string()))
+            .expect("Could not write");
+        db.load().expect("Could not load");
+
+        let stats = db.stats().expect("Could not get stats");
+        assert_eq!(stats.saves, 2);
+        assert_eq!(stats.loads, 1);
+        assert!(stats.bytes_read > 0 && stats.bytes_written > stats.bytes_read);
+        assert!(stats.last_save_duration.is_some());
+        assert!(stats.last_load.is_some());
+        assert_eq!(hook_saves.load(Ordering::SeqCst), 2);
     }
 
-    /// Create new [`MmapDatabase`] with specified initial size.
-    pub fn mmap_with_size(data: Data, size: usize) -> error::Result<Self> {
-        let backend = MmapStorage::with_size(size)?;
+    #[test]
+    fn max_load_size_rejects_large_data() {
+        let db = TestDb::<MemoryBackend>::memory(test_data()).expect("Could not create database");
+        db.save().expect("Could not save");
+        db.set_max_load_size(Some(4));
+        assert!(matches!(
+            db.load(),
+            Err(RustbreakError::TooLarge { limit: 4, .. })
+        ));
+        assert_eq!(db.get_data(false).expect("Could not get data"), test_data());
 
-        Ok(Self {
-            data: RwLock::new(data),
-            backend: Mutex::new(backend),
-            deser: DeSer::default(),
-        })
+        db.set_max_load_size(None);
+        db.load().expect("Could not load");
     }
-}
 
-impl<Data, Back, DeSer> Database<Data, Back, DeSer> {
-    /// Exchanges the `DeSerialization` strategy with the new one.
-    pub fn with_deser<T>(self, deser: T) -> Database<Data, Back, T> {
-        Database {
-            backend: self.backend,
-            data: self.data,
-            deser,
+    #[test]
+    fn save_quota_evicts_or_fails() {
+        use crate::bounded::{BoundedMap, EvictionPolicy};
+
+        let mut cache = BoundedMap::new(EvictionPolicy::Fifo);
+        for (key, value) in test_data() {
+            cache.insert(key, value);
         }
+        let db = Database::<_, MemoryBackend, crate::deser::Ron>::memory(cache)
+            .expect("Could not create database");
+        db.save().expect("Could not save");
+        let saved = db.backend.lock().unwrap().get_data().unwrap();
+
+        db.set_max_save_size(Some(saved.len() as u64 - 1));
+        assert!(matches!(
+            db.save(),
+            Err(RustbreakError::Backend(BackendError::QuotaExceeded { .. }))
+        ));
+        assert_eq!(db.backend.lock().unwrap().get_data().unwrap(), saved);
+
+        assert_eq!(db.save_evicting().expect("Could not save"), 1);
+        assert_eq!(db.read(BoundedMap::len).unwrap(), 1);
+        assert!(!db.is_dirty());
+
+        db.set_max_save_size(Some(0));
+        assert!(matches!(
+            db.save_evicting(),
+            Err(RustbreakError::Backend(BackendError::QuotaExceeded {
+                limit: 0,
+                ..
+            }))
+        ));
+        assert!(db.read(BoundedMap::is_empty).unwrap());
     }
-}
 
-impl<Data, Back, DeSer> Database<Data, Back, DeSer> {
-    /// Exchanges the `Backend` with the new one.
-    ///
-    /// The new backend does not necessarily have the latest data saved to it,
-    /// so a `.save` should be called to make sure that it is saved.
-    pub fn with_backend<T>(self, backend: T) -> Database<Data, T, DeSer> {
-        Database {
-            backend: Mutex::new(backend),
-            data: self.data,
-            deser: self.deser,
-        }
+    #[test]
+    #[cfg(feature = "json_enc")]
+    #[cfg_attr(miri, ignore)]
+    fn convert_format_rewrites_the_file() {
+        use crate::deser::Json;
+
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let path = dir.path().join("convert");
+        let db = TestDb::<PathBackend>::create_at_path(path.clone(), test_data())
+            .expect("Could not create database");
+        let db = db
+            .convert_format(Json::compact())
+            .map_err(|(_, e)| e)
+            .expect("Could not convert");
+        assert_eq!(db.get_data(false).expect("Could not get data"), test_data());
+        let json: TestData =
+            serde_json::from_slice(&std::fs::read(&path).expect("Could not read file"))
+                .expect("File is not JSON");
+        assert_eq!(json, test_data());
+
+        // Data the old format can not load is left alone.
+        std::fs::write(&path, b"not json").expect("Could not write file");
+        assert!(db.convert_format(crate::deser::Ron).is_err());
+        assert_eq!(
+            std::fs::read(&path).expect("Could not read file"),
+            b"not json"
+        );
     }
-}
 
-impl<Data, Back, DeSer> Database<Data, Back, DeSer>
-where
-    Data: Serialize + DeserializeOwned + Clone + Send,
-    Back: Backend,
-    DeSer: DeSerializer<Data> + Send + Sync + Clone,
-{
-    /// Converts from one data type to another.
-    ///
-    /// This method is useful to migrate from one datatype to another.
-    pub fn convert_data<C, OutputData>(
-        self,
-        convert: C,
-    ) -> error::Result<Database<OutputData, Back, DeSer>>
-    where
-        OutputData: Serialize + DeserializeOwned + Clone + Send,
-        C: FnOnce(Data) -> OutputData,
-        DeSer: DeSerializer<OutputData> + Send + Sync,
-    {
-        let (data, backend, deser) = self.into_inner()?;
-        Ok(Database {
-            data: RwLock::new(convert(data)),
-            backend: Mutex::new(backend),
-            deser,
+    #[test]
+    #[cfg(feature = "json_enc")]
+    fn failed_conversions_return_the_database() {
+        use crate::deser::Json;
+
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        db.save().expect("Rustbreak save error");
+        db.write(|data| {
+            data.insert(3, "Hello".to_string());
         })
+        .expect("Rustbreak write error");
+        db.set_max_save_size(Some(0));
+        let Err((db, err)) = db.convert_format(Json::compact()) else {
+            panic!("the save should exceed the quota");
+        };
+        assert!(matches!(
+            err,
+            RustbreakError::Backend(BackendError::QuotaExceeded { .. })
+        ));
+        assert!(db.is_dirty());
+        assert!(db
+            .read(|data| data.contains_key(&3))
+            .expect("Rustbreak read error"));
+
+        // Unsaved changes are converted too, instead of being loaded over.
+        db.set_max_save_size(None);
+        let db = db
+            .convert_format(Json::compact())
+            .map_err(|(_, e)| e)
+            .expect("Could not convert");
+        let (_, mut backend, _) = db.into_inner().expect("Could not take the database apart");
+        let json: TestData = serde_json::from_slice(&backend.get_data().expect("Could not read"))
+            .expect("Data is not JSON");
+        assert_eq!(json.get(&3).map(String::as_str), Some("Hello"));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::collections::HashMap;
-    use tempfile::NamedTempFile;
+    #[test]
+    fn hooks_run_on_save_and_load() {
+        use std::sync::mpsc::channel;
 
-    type TestData = HashMap<usize, String>;
-    type TestDb<B> = Database<TestData, B, crate::deser::Ron>;
-    type TestMemDb = TestDb<MemoryBackend>;
+        let db = TestDb::<MemoryBackend>::memory(test_data()).expect("Could not create database");
+        let (sender, receiver) = channel();
+        let before = sender.clone();
+        let id = db
+            .on_before_save(move |data| before.send(("before", data.len())).unwrap())
+            .expect("Could not add hook");
+        let after = sender.clone();
+        db.on_after_save(move |data| after.send(("after", data.len())).unwrap())
+            .expect("Could not add hook");
+        db.on_after_load(move |data| sender.send(("load", data.len())).unwrap())
+            .expect("Could not add hook");
 
-    fn test_data() -> TestData {
-        let mut data = HashMap::new();
-        data.insert(1, "Hello World".to_string());
-        data.insert(100, "Rustbreak".to_string());
-        data
+        db.save().expect("Could not save");
+        db.load().expect("Could not load");
+        assert!(db.remove_hook(id).expect("Could not remove hook"));
+        assert!(!db.remove_hook(id).expect("Could not remove hook"));
+        db.write_and_save(|data| data.insert(3, "New".to_string()))
+            .expect("Could not write");
+
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            [("before", 2), ("after", 2), ("load", 2), ("after", 3)]
+        );
     }
 
-    /// Used to test that `Default::default` isn't called.
-    #[derive(Clone, Debug, Serialize, serde::Deserialize)]
-    struct PanicDefault;
-    impl Default for PanicDefault {
-        fn default() -> Self {
-            panic!("`default` was called but should not")
-        }
+    #[test]
+    fn validator_rejects_saves_and_loads() {
+        let db = TestDb::<MemoryBackend>::memory(test_data()).expect("Could not create database");
+        db.save().expect("Could not save");
+        db.set_validator(true, |data: &TestData| {
+            if data.len() > 2 {
+                return Err("too many entries");
+            }
+            Ok(())
+        })
+        .expect("Could not set validator");
+
+        let result = db.transaction(true, |data| {
+            data.insert(3, "New".to_string());
+            Ok::<_, RustbreakError>(())
+        });
+        assert!(matches!(result, Err(RustbreakError::Validation(_))));
+        assert_eq!(db.get_data(false).expect("Could not get data").len(), 2);
+
+        db.write(|data| data.insert(3, "New".to_string()))
+            .expect("Could not write");
+        db.clear_validator().expect("Could not clear validator");
+        db.save().expect("Could not save");
+        db.set_validator(true, |data: &TestData| {
+            if data.len() > 2 {
+                return Err("too many entries");
+            }
+            Ok(())
+        })
+        .expect("Could not set validator");
+        db.write(HashMap::clear).expect("Could not write");
+        assert!(matches!(db.load(), Err(RustbreakError::Validation(_))));
+        assert!(db.get_data(false).expect("Could not get data").is_empty());
     }
 
     #[test]
-    fn create_db_and_read() {
-        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+    fn subscribe_receives_changes() {
+        use crate::watch::ChangeKind;
+
+        let db = TestDb::<MemoryBackend>::memory(test_data()).expect("Could not create database");
+        let changes = db.subscribe().expect("Could not subscribe");
+        db.write(|data| data.insert(3, "New".to_string()))
+            .expect("Could not write");
+        db.save().expect("Could not save");
+        db.load().expect("Could not load");
+        db.put_data(test_data(), false).expect("Could not put data");
+
+        let events: Vec<_> = changes.try_iter().collect();
         assert_eq!(
-            "Hello World",
-            db.read(|d| d.get(&1).cloned())
-                .expect("Rustbreak read error")
-                .expect("Should be `Some` but was `None`")
+            events.iter().map(|event| event.kind).collect::<Vec<_>>(),
+            [ChangeKind::Write, ChangeKind::Load, ChangeKind::Write]
         );
+        assert!(events.windows(2).all(|w| w[0].generation < w[1].generation));
+
+        drop(changes);
+        db.write(HashMap::clear).expect("Could not write");
         assert_eq!(
-            "Rustbreak",
-            db.read(|d| d.get(&100).cloned())
-                .expect("Rustbreak read error")
-                .expect("Should be `Some` but was `None`")
+            db.watchers
+                .lock()
+                .expect("Could not lock")
+                .subscribers_len(),
+            0
         );
     }
 
     #[test]
-    fn write_twice() {
+    fn dirty_tracking() {
         let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        assert!(db.is_dirty());
+        assert!(db.save_if_dirty().expect("Rustbreak save error"));
+        assert!(!db.is_dirty());
+        assert!(!db.save_if_dirty().expect("Rustbreak save error"));
+
         db.write(|d| d.insert(3, "Write to db".to_string()))
             .expect("Rustbreak write error");
-        db.write(|d| d.insert(3, "Second write".to_string()))
-            .expect("Rustbreak write error");
-        assert_eq!(
-            "Hello World",
-            db.read(|d| d.get(&1).cloned())
-                .expect("Rustbreak read error")
-                .expect("Should be `Some` but was `None`")
-        );
-        assert_eq!(
-            "Rustbreak",
-            db.read(|d| d.get(&100).cloned())
-                .expect("Rustbreak read error")
-                .expect("Should be `Some` but was `None`")
-        );
-        assert_eq!(
-            "Second write",
-            db.read(|d| d.get(&3).cloned())
-                .expect("Rustbreak read error")
-                .expect("Should be `Some` but was `None`")
-        );
+        assert!(db.is_dirty());
+        db.load().expect("Rustbreak load error");
+        assert!(!db.is_dirty());
+
+        drop(db.borrow_data_mut().expect("Rustbreak borrow error"));
+        assert!(db.is_dirty());
+        db.save().expect("Rustbreak save error");
+        assert!(!db.is_dirty());
     }
 
     #[test]
     fn save_load() {
         let db = TestMemDb::memory(test_data()).expect("Could not create database");
         db.save().expect("Rustbreak save error");
-        db.write(|d| d.clear()).expect("Rustbreak write error");
+        db.write(HashMap::clear).expect("Rustbreak write error");
         db.load().expect("Rustbreak load error");
         assert_eq!(
             "Hello World",
@@ -1217,7 +4239,7 @@ mod tests {
     fn get_data_load() {
         let db = TestMemDb::memory(test_data()).expect("Could not create database");
         db.save().expect("Rustbreak save error");
-        db.write(|d| d.clear()).expect("Rustbreak write error");
+        db.write(HashMap::clear).expect("Rustbreak write error");
         let data = db.get_data(true).expect("could not get data");
         assert_eq!(test_data(), data);
     }
@@ -1300,6 +4322,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn watch_only_fires_on_change() {
+        use std::sync::mpsc::channel;
+
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        let (sender, receiver) = channel();
+        db.watch(
+            |d| d.get(&1).cloned(),
+            move |v| sender.send(v.clone()).expect("could not send"),
+        )
+        .expect("Could not watch");
+
+        db.write(|d| d.insert(3, "Unrelated".to_string()))
+            .expect("Rustbreak write error");
+        assert!(receiver.try_recv().is_err());
+
+        db.write(|d| d.insert(1, "Changed".to_string()))
+            .expect("Rustbreak write error");
+        assert_eq!(Some("Changed".to_string()), receiver.try_recv().unwrap());
+
+        db.put_data(test_data(), false).expect("could not put data");
+        assert_eq!(
+            Some("Hello World".to_string()),
+            receiver.try_recv().unwrap()
+        );
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn watch_load_and_unwatch() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let db = TestMemDb::memory(test_data()).expect("Could not create database");
+        db.save().expect("Rustbreak save error");
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let id = db
+            .watch(TestData::len, move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .expect("Could not watch");
+
+        db.write(HashMap::clear).expect("Rustbreak write error");
+        db.load().expect("Rustbreak load error");
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+
+        assert!(db.unwatch(id).expect("Could not unwatch"));
+        assert!(!db.unwatch(id).expect("Could not unwatch"));
+        db.write(HashMap::clear).expect("Rustbreak write error");
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+    }
+
     /// Since `save` only needs read-access to the data we should be able to
     /// save while holding a readlock.
     #[test]
@@ -1368,6 +4443,205 @@ mod tests {
         dir.close().expect("Error while deleting temp directory!");
     }
 
+    /// Increment a counter with [`Database::modify`] from four databases at
+    /// once, each opened by `open` like in another process.
+    fn modify_concurrently<B, F>(open: F) -> u32
+    where
+        B: Backend + Send + 'static,
+        F: Fn() -> Database<u32, B, crate::deser::Ron>,
+    {
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let db = open();
+                std::thread::spawn(move || {
+                    for _ in 0..10 {
+                        db.modify(|counter| *counter += 1)
+                            .expect("could not modify");
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().expect("thread panicked");
+        }
+        open().get_data(false).expect("could not read")
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn modify_does_not_lose_concurrent_changes() {
+        type Db<B> = Database<u32, B, crate::deser::Ron>;
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let path = dir.path().join("counter.ron");
+        Db::<PathBackend>::create_at_path(path.clone(), 0).expect("could not create");
+
+        let count = modify_concurrently(|| {
+            Db::<PathBackend>::load_from_path(path.clone()).expect("could not load")
+        });
+        assert_eq!(count, 40);
+
+        let count = modify_concurrently(|| {
+            Db::<FileBackend>::load_from_path(&path).expect("could not load")
+        });
+        assert_eq!(count, 80);
+
+        // Wrappers lock the backend they wrap.
+        let count = modify_concurrently(|| {
+            let backend = PathBackend::from_path_or_fail(path.clone()).expect("could not open");
+            let db = Db::from_parts(
+                0,
+                crate::backend::CachedBackend::new(backend),
+                crate::deser::Ron,
+            );
+            db.load().expect("could not load");
+            db
+        });
+        assert_eq!(count, 120);
+    }
+
+    #[test]
+    fn modify_notifies_watchers_once_saved() {
+        let db = Database::from_parts(0_u32, MemoryBackend::new(), crate::deser::Ron);
+        db.save().expect("could not save");
+        let changes = db.subscribe().expect("could not subscribe");
+        db.set_max_save_size(Some(0));
+        assert!(db.modify(|counter| *counter += 1).is_err());
+        assert!(changes.try_recv().is_err());
+
+        db.set_max_save_size(None);
+        db.modify(|counter| *counter += 1)
+            .expect("could not modify");
+        assert_eq!(
+            changes.try_recv().expect("no change").kind,
+            ChangeKind::Write
+        );
+    }
+
+    /// A backend whose detached reads read the data as it was when they
+    /// were created, and wait until `proceed` lets them finish.
+    struct GatedBackend {
+        data: Vec<u8>,
+        started: std::sync::mpsc::Sender<()>,
+        proceed: Arc<std::sync::Mutex<std::sync::mpsc::Receiver<()>>>,
+    }
+
+    struct GatedRead {
+        data: Vec<u8>,
+        proceed: Arc<std::sync::Mutex<std::sync::mpsc::Receiver<()>>>,
+    }
+
+    impl crate::backend::DetachedReader for GatedRead {
+        fn read(self: Box<Self>) -> error::BackendResult<(Vec<u8>, Option<Fingerprint>)> {
+            let _ = self.proceed.lock().unwrap().recv();
+            let fingerprint = Fingerprint::from_contents(&self.data);
+            Ok((self.data, Some(fingerprint)))
+        }
+    }
+
+    impl Backend for GatedBackend {
+        fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+            Ok(self.data.clone())
+        }
+
+        fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+            self.data = data.to_vec();
+            Ok(())
+        }
+
+        fn fingerprint(&mut self) -> error::BackendResult<Option<Fingerprint>> {
+            Ok(Some(Fingerprint::from_contents(&self.data)))
+        }
+
+        fn detached_reader(
+            &mut self,
+        ) -> error::BackendResult<Option<Box<dyn crate::backend::DetachedReader>>> {
+            let _ = self.started.send(());
+            Ok(Some(Box::new(GatedRead {
+                data: self.data.clone(),
+                proceed: self.proceed.clone(),
+            })))
+        }
+    }
+
+    #[test]
+    fn saves_during_a_detached_load_are_not_undone() {
+        use std::time::Duration;
+
+        let (started, started_rx) = std::sync::mpsc::channel();
+        let (proceed, proceed_rx) = std::sync::mpsc::channel();
+        let backend = GatedBackend {
+            data: Vec::new(),
+            started,
+            proceed: Arc::new(std::sync::Mutex::new(proceed_rx)),
+        };
+        let db = Database::from_parts(1_u32, backend, crate::deser::Ron);
+        db.set_external_change_detection(true);
+        db.save().expect("could not save");
+
+        std::thread::scope(|scope| {
+            let load = scope.spawn(|| db.load());
+            started_rx
+                .recv_timeout(Duration::from_secs(5))
+                .expect("the load did not start");
+            db.write(|counter| *counter = 2).expect("could not write");
+            db.save().expect("could not save");
+            proceed.send(()).expect("the load stopped");
+            load.join().unwrap().expect("could not load");
+        });
+        assert_eq!(db.get_data(false).expect("could not get data"), 2);
+        assert!(!db.is_dirty());
+        db.write(|counter| *counter = 3).expect("could not write");
+        db.save()
+            .expect("the load should have kept the fingerprint");
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn pathdb_reports_path_and_metadata() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let file_path = dir.path().join("rustbreak_path_db.db");
+        let db = TestDb::<PathBackend>::load_from_path_or_default(file_path.clone())
+            .expect("could not load from path");
+        assert_eq!(db.path().expect("no path"), file_path);
+        assert_eq!(db.last_saved_at().expect("no stats"), None);
+
+        db.put_data(test_data(), true).expect("could not save");
+        let metadata = db
+            .backend_metadata()
+            .expect("could not get metadata")
+            .expect("the file exists");
+        let on_disk = std::fs::metadata(&file_path).expect("could not stat");
+        assert_eq!(metadata.len, on_disk.len());
+        assert_eq!(metadata.modified, on_disk.modified().ok());
+        assert!(db.last_saved_at().expect("no stats").is_some());
+
+        std::fs::remove_file(&file_path).expect("could not remove");
+        assert_eq!(db.backend_metadata().expect("could not get metadata"), None);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn wrapped_backends_report_metadata() {
+        use crate::backend::{CachedBackend, Enveloped};
+
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let file_path = dir.path().join("rustbreak_wrapped_db.db");
+        let backend = PathBackend::from_path_or_create(file_path.clone())
+            .expect("could not create backend")
+            .0;
+        let backend = CachedBackend::new(Enveloped::new(backend, *b"TEST"));
+        let db = TestDb::from_parts(test_data(), backend, crate::deser::Ron);
+        db.save().expect("could not save");
+
+        let metadata = db
+            .backend_metadata()
+            .expect("could not get metadata")
+            .expect("the file exists");
+        let on_disk = std::fs::metadata(&file_path).expect("could not stat");
+        assert_eq!(metadata.len, on_disk.len());
+        assert_eq!(metadata.modified, on_disk.modified().ok());
+    }
+
     #[test]
     #[cfg_attr(miri, ignore)]
     fn pathdb_from_path_or_else_new() {
@@ -1382,6 +4656,47 @@ mod tests {
         dir.close().expect("Error while deleting temp directory!");
     }
 
+    /// A number that can not be serialized if it is zero.
+    #[derive(Debug, PartialEq)]
+    struct NonZero(u32);
+
+    impl Serialize for NonZero {
+        fn serialize<S: serde::Serializer>(
+            &self,
+            serializer: S,
+        ) -> std::result::Result<S::Ok, S::Error> {
+            if self.0 == 0 {
+                return Err(serde::ser::Error::custom("zero"));
+            }
+            self.0.serialize(serializer)
+        }
+    }
+
+    impl<'de> serde::Deserialize<'de> for NonZero {
+        fn deserialize<D: serde::Deserializer<'de>>(
+            deserializer: D,
+        ) -> std::result::Result<Self, D::Error> {
+            u32::deserialize(deserializer).map(NonZero)
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn filedb_keeps_the_file_if_serializing_fails() {
+        let file = NamedTempFile::new().expect("could not create temporary file");
+        let db =
+            FileDatabase::<NonZero, crate::deser::Ron>::create_at_path(file.path(), NonZero(7))
+                .expect("could not create");
+        db.save().expect("could not save");
+        let before = std::fs::read(file.path()).expect("could not read");
+
+        db.write(|number| number.0 = 0).expect("could not write");
+        assert!(db.save().is_err());
+        assert_eq!(std::fs::read(file.path()).expect("could not read"), before);
+        db.load().expect("could not load");
+        assert_eq!(db.read(|number| number.0).expect("could not read"), 7);
+    }
+
     #[test]
     #[cfg_attr(miri, ignore)]
     fn filedb_from_path_or_new() {
@@ -1422,7 +4737,7 @@ mod tests {
             assert_eq!(std::io::ErrorKind::NotFound, io_err.kind());
         } else {
             panic!("Wrong error: {}", err)
-        };
+        }
 
         dir.close().expect("Error while deleting temp directory!");
     }
@@ -1439,7 +4754,7 @@ mod tests {
             assert_eq!(std::io::ErrorKind::NotFound, io_err.kind());
         } else {
             panic!("Wrong error: {}", err)
-        };
+        }
 
         dir.close().expect("Error while deleting temp directory!");
     }
@@ -0,0 +1,262 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Upgrading persisted data to newer schema versions.
+//!
+//! [`Database::convert_data`](crate::Database::convert_data) changes the
+//! data of a running database, but it can not help with files saved by an
+//! older version of your program. A [`Migrator`] is a `DeSer` that tags the
+//! saved data with a schema version, and upgrades older data step by step
+//! while loading it.
+//!
+//! Each registered step upgrades the data by one version, the first one from
+//! version `0` to `1`, and so on. Data saved without a version tag, for
+//! example before a `Migrator` was used, is taken to be version `0`.
+//!
+//! The steps work on a [`serde_json::Value`], so the wrapped `DeSer` has to
+//! be a self describing format, like Ron, Yaml or JSON. Bincode and Postcard
+//! can not be used.
+//!
+//! # Example
+//!
+//! ```rust
+//! # #[macro_use] extern crate serde_derive;
+//! # extern crate rustbreak;
+//! # extern crate serde;
+//! # extern crate serde_json;
+//! use rustbreak::backend::{Backend, MemoryBackend};
+//! use rustbreak::deser::Json;
+//! use rustbreak::migrations::Migrator;
+//! use rustbreak::Database;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct V1 {
+//!     name: String,
+//! }
+//!
+//! #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+//! struct Config {
+//!     first: String,
+//!     last: String,
+//! }
+//!
+//! # fn main() -> rustbreak::error::Result<()> {
+//! let migrator = Migrator::<Config, _>::new(Json::compact())
+//!     // 0 -> 1: the field used to be called `username`
+//!     .step(|mut value| {
+//!         let name = value["username"].take();
+//!         serde_json::json!({ "name": name })
+//!     })
+//!     // 1 -> 2: split the name
+//!     .typed(|old: V1| {
+//!         let (first, last) = old.name.split_once(' ').unwrap_or((&old.name, ""));
+//!         Config { first: first.into(), last: last.into() }
+//!     });
+//!
+//! let mut backend = MemoryBackend::new();
+//! backend.put_data(br#"{"username":"Ada Lovelace"}"#)?;
+//! let db = Database::from_parts(Config { first: String::new(), last: String::new() }, backend, migrator);
+//! db.load()?;
+//! assert_eq!(db.read(|c| c.last.clone())?, "Lovelace");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::Read;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::deser::DeSerializer;
+use crate::error::{self, DeSerError};
+
+type Step = dyn Fn(Value) -> error::DeSerResult<Value> + Send + Sync;
+
+/// A `DeSer` that versions the data of another one, and migrates old data
+/// to the current version on load.
+///
+/// The current version is the number of registered steps. See the
+/// [module documentation](self) for details.
+///
+/// The default value has no steps, create it with [`Migrator::new`] and hand
+/// it to [`Database::from_parts`](crate::Database::from_parts) or
+/// [`Database::with_deser`](crate::Database::with_deser) instead.
+pub struct Migrator<Data, D> {
+    inner: D,
+    steps: Vec<Arc<Step>>,
+    _data: PhantomData<fn() -> Data>,
+}
+
+impl<Data, D> Migrator<Data, D> {
+    /// Version the output of `inner`, starting at version `0`.
+    pub fn new(inner: D) -> Self {
+        Self {
+            inner,
+            steps: Vec::new(),
+            _data: PhantomData,
+        }
+    }
+
+    /// Register a step that upgrades the data by one version.
+    #[must_use]
+    pub fn step<F>(mut self, step: F) -> Self
+    where
+        F: Fn(Value) -> Value + Send + Sync + 'static,
+    {
+        self.steps.push(Arc::new(move |value| Ok(step(value))));
+        self
+    }
+
+    /// Register a step that upgrades the data by one version, from the type
+    /// it had at the previous version to the type of the new one.
+    #[must_use]
+    pub fn typed<Old, New, F>(mut self, step: F) -> Self
+    where
+        Old: DeserializeOwned,
+        New: Serialize,
+        F: Fn(Old) -> New + Send + Sync + 'static,
+    {
+        self.steps.push(Arc::new(move |value| {
+            let old = serde_json::from_value(value)?;
+            Ok(serde_json::to_value(step(old))?)
+        }));
+        self
+    }
+
+    /// The version data is saved with.
+    #[must_use]
+    pub fn version(&self) -> u64 {
+        self.steps.len() as u64
+    }
+
+    /// Upgrade `value` from `version` to the current version.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `version` is newer than the current one, or a step fails.
+    pub fn migrate(&self, mut value: Value, version: u64) -> error::DeSerResult<Value> {
+        let Ok(first) = usize::try_from(version) else {
+            return Err(newer_version(version));
+        };
+        let Some(steps) = self.steps.get(first..) else {
+            return Err(newer_version(version));
+        };
+        for step in steps {
+            value = step(value)?;
+        }
+        Ok(value)
+    }
+}
+
+fn newer_version(version: u64) -> DeSerError {
+    DeSerError::Migration(format!("version {version} is newer than this program"))
+}
+
+/// Split a tagged value into its version and the data.
+fn untag(value: Value) -> (u64, Value) {
+    match value {
+        Value::Object(mut map) if map.len() == 2 => {
+            match (
+                map.get("version").and_then(Value::as_u64),
+                map.remove("data"),
+            ) {
+                (Some(version), Some(data)) => (version, data),
+                (_, data) => {
+                    if let Some(data) = data {
+                        map.insert("data".to_string(), data);
+                    }
+                    (0, Value::Object(map))
+                }
+            }
+        }
+        value => (0, value),
+    }
+}
+
+impl<Data, D: Clone> Clone for Migrator<Data, D> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            steps: self.steps.clone(),
+            _data: PhantomData,
+        }
+    }
+}
+
+impl<Data, D: Default> Default for Migrator<Data, D> {
+    fn default() -> Self {
+        Self::new(D::default())
+    }
+}
+
+impl<Data, D: fmt::Debug> fmt::Debug for Migrator<Data, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Migrator")
+            .field("inner", &self.inner)
+            .field("version", &self.version())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Data, D> DeSerializer<Data> for Migrator<Data, D>
+where
+    Data: Serialize + DeserializeOwned,
+    D: DeSerializer<Value>,
+{
+    fn serialize(&self, val: &Data) -> error::DeSerResult<Vec<u8>> {
+        let tagged = serde_json::json!({
+            "version": self.version(),
+            "data": serde_json::to_value(val)?,
+        });
+        self.inner.serialize(&tagged)
+    }
+    fn deserialize<R: Read>(&self, s: R) -> error::DeSerResult<Data> {
+        let (version, data) = untag(self.inner.deserialize(s)?);
+        Ok(serde_json::from_value(self.migrate(data, version)?)?)
+    }
+}
+
+#[cfg(all(test, feature = "ron_enc"))]
+mod tests {
+    use super::Migrator;
+    use crate::deser::{DeSerializer, Ron};
+    use crate::error::DeSerError;
+
+    fn migrator() -> Migrator<Vec<u32>, Ron> {
+        Migrator::new(Ron)
+            .step(|value| serde_json::json!([value]))
+            .typed(|old: Vec<u32>| old.into_iter().map(|x| x * 2).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn upgrades_untagged_and_old_data() {
+        let migrator = migrator();
+        let data: Vec<u32> = migrator.deserialize(&b"21"[..]).unwrap();
+        assert_eq!(data, vec![42]);
+
+        let old = Migrator::<Vec<u32>, Ron>::new(Ron).step(|v| serde_json::json!([v]));
+        let saved = old.serialize(&vec![4, 5]).unwrap();
+        assert_eq!(migrator.deserialize(&saved[..]).unwrap(), vec![8, 10]);
+    }
+
+    #[test]
+    fn current_data_is_not_migrated() {
+        let migrator = migrator();
+        let saved = migrator.serialize(&vec![1, 2]).unwrap();
+        assert_eq!(migrator.deserialize(&saved[..]).unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn newer_data_is_rejected() {
+        let saved = migrator().serialize(&vec![1]).unwrap();
+        let older = Migrator::<Vec<u32>, Ron>::new(Ron);
+        let err = older.deserialize(&saved[..]);
+        assert!(matches!(err, Err(DeSerError::Migration(_))));
+    }
+}
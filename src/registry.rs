@@ -0,0 +1,190 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A process wide registry of databases that should be persisted on shutdown.
+//!
+//! Applications with many stores can [`register`] each of them once, and then
+//! call [`flush_all`] from their shutdown path instead of remembering to save
+//! every database by hand. Holding the guard returned by
+//! [`flush_on_shutdown`] in `main` does this when `main` returns or unwinds,
+//! and with the `signals` feature on UNIX also on `SIGINT` and `SIGTERM`.
+//!
+//! The registry only keeps weak references, so registering a database does
+//! not keep it alive. Databases that have been dropped are skipped.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # extern crate rustbreak;
+//! use std::sync::Arc;
+//! use rustbreak::{deser::Ron, registry, MemoryDatabase};
+//!
+//! # fn main() {
+//! # let func = || -> Result<(), Box<dyn std::error::Error>> {
+//! let _shutdown = registry::flush_on_shutdown()?;
+//!
+//! let db = Arc::new(MemoryDatabase::<Vec<u32>, Ron>::memory(vec![])?);
+//! registry::register(&db);
+//!
+//! db.write(|db| db.push(42))?;
+//!
+//! // Saves every registered database that is still alive.
+//! rustbreak::flush_all()?;
+//! # return Ok(()); };
+//! # func().unwrap();
+//! # }
+//! ```
+
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+
+use crate::error;
+use crate::ShutdownGuard;
+
+/// Something that can persist its in-memory state.
+///
+/// This is implemented for every [`Database`](crate::Database) that can be
+/// shared between threads, and is what the registry stores. Databases are
+/// only saved if they [are dirty](crate::Database::is_dirty).
+pub trait Flush: Send + Sync {
+    /// Persist the current state.
+    fn flush(&self) -> error::Result<()>;
+}
+
+impl<Data, Back, DeSer> Flush for crate::Database<Data, Back, DeSer>
+where
+    Data: serde::Serialize + serde::de::DeserializeOwned + Send + Sync,
+    Back: crate::backend::Backend + Send,
+    DeSer: crate::DeSerializer<Data> + Send + Sync + Clone,
+{
+    fn flush(&self) -> error::Result<()> {
+        self.save_if_dirty().map(|_| ())
+    }
+}
+
+static REGISTRY: Mutex<Vec<Weak<dyn Flush>>> = Mutex::new(Vec::new());
+
+/// Add a database to the global registry.
+///
+/// Only a weak reference is kept, dropping the last [`Arc`] removes the
+/// database from the registry.
+pub fn register<F: Flush + 'static>(db: &Arc<F>) {
+    let db: Arc<dyn Flush> = db.clone();
+    // The registry only holds weak pointers, a panic while holding the lock
+    // can not leave it in an inconsistent state.
+    let mut registry = REGISTRY.lock().unwrap_or_else(PoisonError::into_inner);
+    registry.retain(|entry| entry.strong_count() > 0);
+    registry.push(Arc::downgrade(&db));
+}
+
+/// Flush every registered database that is still alive.
+///
+/// All databases are flushed, even if one of them fails. The first error
+/// encountered is returned.
+pub fn flush_all() -> error::Result<()> {
+    // Collect the databases first, so that flushing does not hold the
+    // registry lock.
+    let databases: Vec<Arc<dyn Flush>> = {
+        let mut registry = REGISTRY.lock().unwrap_or_else(PoisonError::into_inner);
+        registry.retain(|entry| entry.strong_count() > 0);
+        registry.iter().filter_map(Weak::upgrade).collect()
+    };
+
+    let mut result = Ok(());
+    for db in databases {
+        if let Err(e) = db.flush() {
+            if result.is_ok() {
+                result = Err(e);
+            }
+        }
+    }
+    result
+}
+
+/// Every registered database, flushed with [`flush_all`].
+struct Registered;
+
+impl Flush for Registered {
+    fn flush(&self) -> error::Result<()> {
+        flush_all()
+    }
+}
+
+/// Make sure every registered database is saved one last time when the
+/// program shuts down.
+///
+/// This works like [`Database::flush_on_shutdown`](crate::Database::flush_on_shutdown)
+/// for the whole registry: the returned guard calls [`flush_all`] when it is
+/// dropped, so holding it in `main` covers a normal exit and unwinding. With
+/// the `signals` feature on UNIX, it also flushes on `SIGINT` and `SIGTERM`,
+/// after which the signal terminates the process as usual. Databases
+/// registered after the guard was created are flushed too.
+///
+/// Nothing is flushed if the process ends through [`std::process::exit`] or
+/// an aborting panic, call [`flush_all`] before those.
+///
+/// Fails if the signal handlers could not be installed.
+pub fn flush_on_shutdown() -> error::Result<ShutdownGuard> {
+    ShutdownGuard::new(Arc::new(Registered))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{flush_all, flush_on_shutdown, register, Flush};
+    use crate::{deser::Ron, error, MemoryDatabase};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Counts its flushes in a counter that outlives it.
+    struct Counting(Arc<AtomicUsize>);
+
+    impl Flush for Counting {
+        fn flush(&self) -> error::Result<()> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn flush_registered_database() {
+        let db = Arc::new(
+            MemoryDatabase::<Vec<u32>, Ron>::memory(vec![]).expect("Could not create database"),
+        );
+        register(&db);
+        db.write(|d| d.push(42)).expect("Rustbreak write error");
+
+        flush_all().expect("Could not flush databases");
+
+        db.write(Vec::clear).expect("Rustbreak write error");
+        db.load().expect("Rustbreak load error");
+        assert_eq!(vec![42], db.get_data(false).expect("could not get data"));
+    }
+
+    #[test]
+    fn dropped_databases_are_skipped() {
+        let flushes = Arc::new(AtomicUsize::new(0));
+        let db = Arc::new(Counting(flushes.clone()));
+        register(&db);
+        let weak = Arc::downgrade(&db);
+        drop(db);
+
+        flush_all().expect("Could not flush databases");
+        assert_eq!(flushes.load(Ordering::SeqCst), 0);
+        let registry = super::REGISTRY.lock().unwrap();
+        let weak: std::sync::Weak<dyn Flush> = weak;
+        assert!(!registry.iter().any(|entry| entry.ptr_eq(&weak)));
+    }
+
+    #[test]
+    fn shutdown_guard_flushes_the_registry() {
+        let flushes = Arc::new(AtomicUsize::new(0));
+        let db = Arc::new(Counting(flushes.clone()));
+        register(&db);
+        let shutdown = flush_on_shutdown().expect("Could not create guard");
+
+        // Other tests may flush the registry at any time.
+        let before = flushes.load(Ordering::SeqCst);
+        drop(shutdown);
+        assert!(flushes.load(Ordering::SeqCst) > before);
+    }
+}
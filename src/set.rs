@@ -0,0 +1,456 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A set of databases of different types that are saved together.
+//!
+//! Applications often keep their configuration, their cache and the data of
+//! the user in separate files. A [`DatabaseSet`] owns such databases, hands
+//! them out by a typed [`Key`], and saves all of them with
+//! [`DatabaseSet::save_all`], which writes every file before replacing any.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # extern crate rustbreak;
+//! # extern crate tempfile;
+//! use rustbreak::set::{DatabaseSet, Key};
+//! use rustbreak::{deser::Ron, PathDatabase};
+//! use std::collections::HashMap;
+//!
+//! type Config = PathDatabase<HashMap<String, String>, Ron>;
+//! type History = PathDatabase<Vec<String>, Ron>;
+//!
+//! const CONFIG: Key<Config> = Key::new("config");
+//! const HISTORY: Key<History> = Key::new("history");
+//!
+//! # fn main() -> rustbreak::error::Result<()> {
+//! # let dir = tempfile::tempdir().unwrap();
+//! let mut set = DatabaseSet::new();
+//! set.insert(&CONFIG, Config::load_from_path_or_default(dir.path().join("config.ron"))?);
+//! set.insert(&HISTORY, History::load_from_path_or_default(dir.path().join("history.ron"))?);
+//!
+//! let config = set.get(&CONFIG).expect("config was inserted");
+//! config.write(|config| config.insert("theme".to_owned(), "dark".to_owned()))?;
+//! set.get(&HISTORY)
+//!     .expect("history was inserted")
+//!     .write(|history| history.push("changed the theme".to_owned()))?;
+//!
+//! // Writes both files, then renames both into place
+//! set.save_all()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::any::Any;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::backend::{Backend, StagedWrite};
+use crate::{error, Database, DeSerializer};
+
+/// A save that has been written, but does not replace the stored data until
+/// it is committed.
+///
+/// It is returned by [`GroupSave::stage_save`]. The database it belongs to
+/// stays read locked until it is committed or dropped, dropping it discards
+/// the save.
+pub struct StagedSave<'a> {
+    commit: Box<dyn FnOnce() -> error::Result<()> + 'a>,
+}
+
+impl<'a> StagedSave<'a> {
+    pub(crate) fn new<F>(commit: F) -> Self
+    where
+        F: FnOnce() -> error::Result<()> + 'a,
+    {
+        Self {
+            commit: Box::new(commit),
+        }
+    }
+
+    /// Replace the stored data with the staged one.
+    pub fn commit(self) -> error::Result<()> {
+        (self.commit)()
+    }
+}
+
+impl fmt::Debug for StagedSave<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StagedSave").finish_non_exhaustive()
+    }
+}
+
+/// A database that can be saved in two steps, see [`DatabaseSet::save_all`].
+///
+/// This is implemented for every [`Database`] that can be shared between
+/// threads.
+pub trait GroupSave: Send + Sync {
+    /// Serialize the data and stage it in the backend with
+    /// [`Backend::stage`].
+    ///
+    /// Validators, the quota and the save hooks apply like for any other
+    /// save, the after save hooks only run once the save is committed. An
+    /// external change is not merged, but fails with
+    /// [`RustbreakError::ExternalChange`](crate::RustbreakError::ExternalChange).
+    fn stage_save(&self) -> error::Result<StagedSave<'_>>;
+}
+
+impl<Data, Back, DeSer> GroupSave for Database<Data, Back, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send + Sync,
+    Back: Backend + Send,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone,
+{
+    fn stage_save(&self) -> error::Result<StagedSave<'_>> {
+        Database::stage_save(self)
+    }
+}
+
+/// What a [`Staging`] backend does on commit.
+pub(crate) enum Pending {
+    /// Nothing was written.
+    Nothing,
+    /// The backend staged the data.
+    Staged(Box<dyn StagedWrite>),
+    /// The backend can not stage, the data is put on commit.
+    Data(Vec<u8>),
+}
+
+impl Pending {
+    pub(crate) fn commit<B: Backend>(self, backend: &mut B) -> error::BackendResult<()> {
+        match self {
+            Pending::Nothing => Ok(()),
+            Pending::Staged(staged) => staged.commit(),
+            Pending::Data(data) => backend.put_data(&data),
+        }
+    }
+}
+
+/// A backend that stages the data written to it in another backend.
+pub(crate) struct Staging<'a, B> {
+    inner: &'a mut B,
+    pending: Pending,
+}
+
+impl<'a, B: Backend> Staging<'a, B> {
+    pub(crate) fn new(inner: &'a mut B) -> Self {
+        Self {
+            inner,
+            pending: Pending::Nothing,
+        }
+    }
+
+    pub(crate) fn into_pending(self) -> Pending {
+        self.pending
+    }
+}
+
+impl<B: Backend> Backend for Staging<'_, B> {
+    fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        self.inner.get_data()
+    }
+
+    fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        self.pending = match self.inner.stage(data)? {
+            Some(staged) => Pending::Staged(staged),
+            None => Pending::Data(data.to_vec()),
+        };
+        Ok(())
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+/// Names a database of type `T` in a [`DatabaseSet`].
+///
+/// Keys are usually constants, so that the name and the type of each
+/// database are defined in one place.
+pub struct Key<T> {
+    name: &'static str,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T> Key<T> {
+    /// A key for the database named `name`.
+    #[must_use]
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            _type: PhantomData,
+        }
+    }
+
+    /// The name of the database.
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<T> Clone for Key<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Key<T> {}
+
+impl<T> fmt::Debug for Key<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Key").field(&self.name).finish()
+    }
+}
+
+struct Member {
+    name: String,
+    db: Arc<dyn Any + Send + Sync>,
+    save: Arc<dyn GroupSave>,
+}
+
+/// Owns several databases, of any type, and saves them together.
+///
+/// See the [module documentation](self) for an example.
+#[derive(Default)]
+pub struct DatabaseSet {
+    members: Vec<Member>,
+}
+
+impl DatabaseSet {
+    /// An empty set.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a database under the name of `key`, replacing the one that had
+    /// that name before.
+    ///
+    /// Returns a handle to the database, for sharing it with other threads.
+    pub fn insert<T: GroupSave + 'static>(&mut self, key: &Key<T>, db: T) -> Arc<T> {
+        let db = Arc::new(db);
+        let member = Member {
+            name: key.name.to_owned(),
+            db: db.clone(),
+            save: db.clone(),
+        };
+        match self.members.iter_mut().find(|m| m.name == key.name) {
+            Some(old) => *old = member,
+            None => self.members.push(member),
+        }
+        db
+    }
+
+    /// The database of `key`.
+    ///
+    /// Returns `None` if there is none, or if it has another type.
+    #[must_use]
+    pub fn get<T: 'static>(&self, key: &Key<T>) -> Option<&T> {
+        self.get_by_name(key.name)
+    }
+
+    /// The database named `name`, if it has the type `T`.
+    #[must_use]
+    pub fn get_by_name<T: 'static>(&self, name: &str) -> Option<&T> {
+        self.member(name)?.db.downcast_ref()
+    }
+
+    /// A handle to the database of `key`, for sharing it with other threads.
+    #[must_use]
+    pub fn shared<T: Send + Sync + 'static>(&self, key: &Key<T>) -> Option<Arc<T>> {
+        self.member(key.name)?.db.clone().downcast().ok()
+    }
+
+    /// Remove the database named `name` from the set, returning whether
+    /// there was one.
+    ///
+    /// The database is dropped once there are no more handles to it.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.members.len();
+        self.members.retain(|m| m.name != name);
+        self.members.len() < before
+    }
+
+    /// The names of the databases, in the order they were inserted.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.members.iter().map(|m| m.name.as_str())
+    }
+
+    /// The number of databases.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Whether there are no databases.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Save every database, as close to all at once as the backends allow.
+    ///
+    /// First the data of every database is serialized and staged with
+    /// [`Backend::stage`], a [`PathBackend`](crate::backend::PathBackend)
+    /// writes it to a temporary file. If any of that fails, nothing is saved
+    /// and the error is returned. Only then are the staged saves committed,
+    /// which renames the temporary files into place. Backends that can not
+    /// stage are written to during this step.
+    ///
+    /// A failing commit does not stop the others, the first error is
+    /// returned. The databases stay read locked until all of them are saved,
+    /// so do not call this while holding a lock of one of them.
+    pub fn save_all(&self) -> error::Result<()> {
+        let staged = self
+            .members
+            .iter()
+            .map(|m| m.save.stage_save())
+            .collect::<error::Result<Vec<_>>>()?;
+        let mut result = Ok(());
+        for save in staged {
+            if let Err(e) = save.commit() {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+
+    fn member(&self, name: &str) -> Option<&Member> {
+        self.members.iter().find(|m| m.name == name)
+    }
+}
+
+impl fmt::Debug for DatabaseSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DatabaseSet")
+            .field("names", &self.names().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "ron_enc"))]
+mod tests {
+    use super::{DatabaseSet, Key};
+    use crate::{deser::Ron, MemoryDatabase, PathDatabase};
+
+    type Numbers = PathDatabase<Vec<u32>, Ron>;
+    type Name = PathDatabase<String, Ron>;
+
+    const NUMBERS: Key<Numbers> = Key::new("numbers");
+    const NAME: Key<Name> = Key::new("name");
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn save_all_saves_nothing_if_one_fails() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let numbers_path = dir.path().join("numbers.ron");
+        let name_path = dir.path().join("name.ron");
+        let mut set = DatabaseSet::new();
+        set.insert(
+            &NUMBERS,
+            Numbers::create_at_path(numbers_path.clone(), vec![1]).expect("could not create"),
+        );
+        let name = set.insert(
+            &NAME,
+            Name::create_at_path(name_path.clone(), "a".to_owned()).expect("could not create"),
+        );
+        set.save_all().expect("could not save");
+        let saved = std::fs::read(&numbers_path).expect("could not read");
+
+        set.get(&NUMBERS)
+            .expect("numbers were inserted")
+            .write(|numbers| numbers.push(2))
+            .expect("could not write");
+        name.write(|name| name.push_str("bcdef"))
+            .expect("could not write");
+        name.set_max_save_size(Some(3));
+        assert!(set.save_all().is_err());
+        assert_eq!(std::fs::read(&numbers_path).expect("could not read"), saved);
+        assert_eq!(
+            std::fs::read_dir(dir.path())
+                .expect("could not list")
+                .count(),
+            2
+        );
+
+        name.set_max_save_size(None);
+        set.save_all().expect("could not save");
+        assert!(!set.get(&NUMBERS).expect("numbers were inserted").is_dirty());
+        assert_eq!(
+            Name::load_from_path_or_default(name_path)
+                .expect("could not load")
+                .get_data(false)
+                .expect("could not get data"),
+            "abcdef"
+        );
+    }
+
+    #[test]
+    fn failed_staging_does_not_count_as_a_save() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let mut set = DatabaseSet::new();
+        let first = set.insert(
+            &Key::new("first"),
+            MemoryDatabase::<Vec<u32>, Ron>::memory(vec![1]).expect("could not create"),
+        );
+        let second = set.insert(
+            &Key::new("second"),
+            MemoryDatabase::<String, Ron>::memory(String::new()).expect("could not create"),
+        );
+        first
+            .set_merge(|_, mine, _| mine)
+            .expect("could not set merge");
+        set.save_all().expect("could not save");
+        let after_saves = Arc::new(AtomicUsize::new(0));
+        let counter = after_saves.clone();
+        first
+            .on_after_save(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+            .expect("could not add hook");
+        let saves = first.stats().expect("could not get stats").saves;
+
+        first
+            .write(|numbers| numbers.push(2))
+            .expect("could not write");
+        second
+            .write(|name| name.push_str("too long"))
+            .expect("could not write");
+        second.set_max_save_size(Some(3));
+        assert!(set.save_all().is_err());
+        assert_eq!(after_saves.load(Ordering::SeqCst), 0);
+        assert_eq!(first.stats().expect("could not get stats").saves, saves);
+        assert_eq!(first.merge.lock().unwrap().base, Some(vec![1]));
+        assert!(first.is_dirty());
+
+        second.set_max_save_size(None);
+        set.save_all().expect("could not save");
+        assert_eq!(after_saves.load(Ordering::SeqCst), 1);
+        assert_eq!(first.stats().expect("could not get stats").saves, saves + 1);
+        assert_eq!(first.merge.lock().unwrap().base, Some(vec![1, 2]));
+    }
+
+    #[test]
+    fn lookups_check_the_type() {
+        let mut set = DatabaseSet::new();
+        let db = MemoryDatabase::<u8, Ron>::memory(1).expect("could not create");
+        set.insert(&Key::new("byte"), db);
+        assert!(set.get_by_name::<Numbers>("byte").is_none());
+        assert!(set
+            .shared(&Key::<MemoryDatabase<u8, Ron>>::new("byte"))
+            .is_some());
+        assert_eq!(set.names().collect::<Vec<_>>(), ["byte"]);
+        assert!(set.remove("byte"));
+        assert!(set.is_empty());
+    }
+}
@@ -0,0 +1,707 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Map databases whose keys can be written concurrently.
+//!
+//! See [`ShardedMapDatabase`] for one that is stored as a single map, and
+//! [`ShardedDatabase`] for one that stores every shard in its own file.
+
+use std::borrow::Borrow;
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::hash::{BuildHasher, BuildHasherDefault, Hash};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::backend::{Backend, PathBackend};
+use crate::error::{BackendError, RustbreakError};
+use crate::sync::{Mutex, RwLock};
+use crate::{error, DeSerializer};
+
+/// A database holding a `HashMap` that is split into shards, each behind its
+/// own lock.
+///
+/// A [`Database`](crate::Database) has a single lock, so all writers wait for
+/// each other. Here, writers of keys in different shards do not. Every key
+/// belongs to one shard, chosen by its hash.
+///
+/// The shards are merged when saving, so the stored data is the same as
+/// that of a `Database<HashMap<K, V>, _, _>`, and the two can be used on the
+/// same data. Saving clones the whole map, while holding the read lock of
+/// every shard.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::{backend::MemoryBackend, deser::Ron, sharded::ShardedMapDatabase};
+/// use std::collections::HashMap;
+/// use std::sync::Arc;
+///
+/// # fn main() -> rustbreak::error::Result<()> {
+/// let db = Arc::new(ShardedMapDatabase::<u32, u32, _, _>::from_parts(
+///     HashMap::new(),
+///     8,
+///     MemoryBackend::new(),
+///     Ron,
+/// ));
+///
+/// let threads: Vec<_> = (0..4)
+///     .map(|thread| {
+///         let db = db.clone();
+///         std::thread::spawn(move || {
+///             for i in 0..100 {
+///                 db.insert(thread * 100 + i, i)?;
+///             }
+///             Ok::<_, rustbreak::RustbreakError>(())
+///         })
+///     })
+///     .collect();
+/// for thread in threads {
+///     thread.join().unwrap()?;
+/// }
+///
+/// assert_eq!(db.len()?, 400);
+/// db.save()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ShardedMapDatabase<K, V, Back, DeSer> {
+    shards: Vec<RwLock<HashMap<K, V>>>,
+    hasher: RandomState,
+    backend: Mutex<Back>,
+    deser: DeSer,
+}
+
+impl<K, V, Back, DeSer> ShardedMapDatabase<K, V, Back, DeSer>
+where
+    K: Serialize + DeserializeOwned + Eq + Hash + Clone + Send,
+    V: Serialize + DeserializeOwned + Clone + Send,
+    Back: Backend,
+    DeSer: DeSerializer<HashMap<K, V>> + Send + Sync + Clone,
+{
+    /// Create a database from its constituents, splitting `map` into
+    /// `shards` shards. At least one shard is used.
+    pub fn from_parts(map: HashMap<K, V>, shards: usize, backend: Back, deser: DeSer) -> Self {
+        let hasher = RandomState::new();
+        let mut maps: Vec<HashMap<K, V>> = (0..shards.max(1)).map(|_| HashMap::new()).collect();
+        let count = maps.len();
+        for (key, value) in map {
+            maps[shard_index(&hasher, count, &key)].insert(key, value);
+        }
+        Self {
+            shards: maps.into_iter().map(RwLock::new).collect(),
+            hasher,
+            backend: Mutex::new(backend),
+            deser,
+        }
+    }
+
+    /// The number of shards.
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    fn shard<Q: Hash + ?Sized>(&self, key: &Q) -> &RwLock<HashMap<K, V>> {
+        &self.shards[shard_index(&self.hasher, self.shards.len(), key)]
+    }
+
+    /// Get a clone of the value of `key`.
+    pub fn get<Q>(&self, key: &Q) -> error::Result<Option<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        Ok(self.shard(key).read()?.get(key).cloned())
+    }
+
+    /// Whether there is a value at `key`.
+    pub fn contains_key<Q>(&self, key: &Q) -> error::Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        Ok(self.shard(key).read()?.contains_key(key))
+    }
+
+    /// Insert `value` at `key`, returning the previous value.
+    pub fn insert(&self, key: K, value: V) -> error::Result<Option<V>> {
+        Ok(self.shard(&key).write()?.insert(key, value))
+    }
+
+    /// Remove `key`, returning its value.
+    pub fn remove<Q>(&self, key: &Q) -> error::Result<Option<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        Ok(self.shard(key).write()?.remove(key))
+    }
+
+    /// Run `task` on the value of `key`, while only its shard is write
+    /// locked.
+    ///
+    /// # Panics
+    ///
+    /// If you panic in the closure, the shard is poisoned, like in
+    /// [`Database::write`](crate::Database::write).
+    pub fn update<Q, T, R>(&self, key: &Q, task: T) -> error::Result<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        T: FnOnce(Option<&mut V>) -> R,
+    {
+        Ok(task(self.shard(key).write()?.get_mut(key)))
+    }
+
+    /// The number of entries in all shards.
+    pub fn len(&self) -> error::Result<usize> {
+        let mut len = 0;
+        for shard in &self.shards {
+            len += shard.read()?.len();
+        }
+        Ok(len)
+    }
+
+    /// Whether there are no entries in any shard.
+    pub fn is_empty(&self) -> error::Result<bool> {
+        for shard in &self.shards {
+            if !shard.read()?.is_empty() {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Merge the shards into one map.
+    fn merged(&self) -> error::Result<HashMap<K, V>> {
+        // Lock all shards first, so the map is consistent.
+        let shards = self
+            .shards
+            .iter()
+            .map(RwLock::read)
+            .collect::<error::Result<Vec<_>>>()?;
+        let mut map = HashMap::with_capacity(shards.iter().map(|shard| shard.len()).sum());
+        for shard in &shards {
+            map.extend(shard.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        Ok(map)
+    }
+
+    /// Merge the shards and save the map to the backend.
+    pub fn save(&self) -> error::Result<()> {
+        let mut backend = self.backend.lock()?;
+        let map = self.merged()?;
+        let ser = self.deser.serialize(&map)?;
+        drop(map);
+        backend.put_data(&ser)?;
+        Ok(())
+    }
+
+    /// Load the map from the backend and split it into the shards.
+    pub fn load(&self) -> error::Result<()> {
+        let mut backend = self.backend.lock()?;
+        let map = self.deser.deserialize(&backend.get_data()?[..])?;
+        drop(backend);
+
+        let mut shards = self
+            .shards
+            .iter()
+            .map(RwLock::write)
+            .collect::<error::Result<Vec<_>>>()?;
+        for shard in &mut shards {
+            shard.clear();
+        }
+        for (key, value) in map {
+            shards[shard_index(&self.hasher, self.shards.len(), &key)].insert(key, value);
+        }
+        Ok(())
+    }
+
+    /// Get a clone of the whole map.
+    pub fn get_data(&self) -> error::Result<HashMap<K, V>> {
+        self.merged()
+    }
+
+    /// Break the database into the merged map, the backend and the `DeSer`.
+    pub fn into_inner(self) -> error::Result<(HashMap<K, V>, Back, DeSer)> {
+        let mut map = HashMap::new();
+        for shard in self.shards {
+            map.extend(shard.into_inner()?);
+        }
+        Ok((map, self.backend.into_inner()?, self.deser))
+    }
+}
+
+/// A hasher that is the same in every process, so that keys stay in their
+/// shard file.
+type StableHasher = BuildHasherDefault<DefaultHasher>;
+
+/// A shard of a [`ShardedDatabase`].
+#[derive(Debug)]
+struct Shard<K, V, DeSer> {
+    map: RwLock<HashMap<K, V>>,
+    /// Whether the map changed since it was last saved or loaded.
+    dirty: AtomicBool,
+    backend: Mutex<PathBackend>,
+    deser: DeSer,
+}
+
+impl<K, V, DeSer> Shard<K, V, DeSer>
+where
+    K: Serialize + DeserializeOwned + Eq + Hash + Sync,
+    V: Serialize + DeserializeOwned + Sync,
+    DeSer: DeSerializer<HashMap<K, V>>,
+{
+    /// Save the map if it is dirty, returns whether it was.
+    fn save_if_dirty(&self) -> error::Result<bool> {
+        let mut backend = self.backend.lock()?;
+        let map = self.map.read()?;
+        // Writers need the write lock to mark the shard dirty again, so no
+        // change can be missed.
+        if !self.dirty.swap(false, Ordering::SeqCst) {
+            return Ok(false);
+        }
+        let result = self
+            .deser
+            .serialize(&map)
+            .map_err(Into::into)
+            .and_then(|ser| backend.put_data(&ser).map_err(Into::into));
+        if result.is_err() {
+            self.dirty.store(true, Ordering::SeqCst);
+        }
+        result.map(|()| true)
+    }
+}
+
+/// A map database that stores every shard in its own file, and saves them
+/// in parallel.
+///
+/// Like with a [`ShardedMapDatabase`], writers of keys in different shards
+/// do not wait for each other. Additionally, only the shards that changed
+/// since they were last saved are written, each on its own thread. This
+/// makes saving a few changes to a large map much faster than writing all
+/// of it.
+///
+/// The shards are stored in a directory, as `shard-0`, `shard-1`, and so
+/// on. Which shard a key goes to depends on its hash, so the number of
+/// shards has to stay the same for a directory: it is kept in a file named
+/// `shards` next to them, and opening the directory with another number
+/// fails with [`RustbreakError::ShardCount`]. The hash uses the
+/// [`DefaultHasher`] of `std`, which might change between Rust versions;
+/// keys found in the wrong shard when loading are moved to the right one,
+/// and both shards are saved again.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::{deser::Ron, sharded::ShardedDatabase};
+///
+/// # fn main() -> rustbreak::error::Result<()> {
+/// # let dir = tempfile::tempdir().unwrap();
+/// let db = ShardedDatabase::<String, u32, Ron>::open(dir.path(), 16)?;
+/// db.insert("apples".to_string(), 3)?;
+/// db.insert("pears".to_string(), 5)?;
+///
+/// // Only the shards holding apples and pears are written.
+/// assert!(db.save()? <= 2);
+/// assert_eq!(db.save()?, 0);
+///
+/// let db = ShardedDatabase::<String, u32, Ron>::open(dir.path(), 16)?;
+/// assert_eq!(db.get("pears")?, Some(5));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ShardedDatabase<K, V, DeSer> {
+    shards: Vec<Shard<K, V, DeSer>>,
+    dir: PathBuf,
+}
+
+impl<K, V, DeSer> ShardedDatabase<K, V, DeSer>
+where
+    K: Serialize + DeserializeOwned + Eq + Hash + Clone + Send + Sync,
+    V: Serialize + DeserializeOwned + Clone + Send + Sync,
+    DeSer: DeSerializer<HashMap<K, V>> + Send + Sync + Clone,
+{
+    /// Open the database in `dir` with `shards` shards, creating the
+    /// directory if needed, and load the shards that exist. At least one
+    /// shard is used.
+    ///
+    /// Fails with [`RustbreakError::ShardCount`] if the directory holds
+    /// another number of shards.
+    pub fn open<P: AsRef<Path>>(dir: P, shards: usize) -> error::Result<Self> {
+        let dir = dir.as_ref().to_owned();
+        std::fs::create_dir_all(&dir).map_err(BackendError::from)?;
+        check_shard_count(&dir, shards.max(1))?;
+        let db = Self {
+            shards: (0..shards.max(1))
+                .map(|i| Shard {
+                    map: RwLock::new(HashMap::new()),
+                    dirty: AtomicBool::new(false),
+                    backend: Mutex::new(PathBackend::new(dir.join(format!("shard-{i}")))),
+                    deser: DeSer::default(),
+                })
+                .collect(),
+            dir,
+        };
+        db.load()?;
+        Ok(db)
+    }
+
+    /// The directory the shards are stored in.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The number of shards.
+    #[must_use]
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    fn shard<Q: Hash + ?Sized>(&self, key: &Q) -> &Shard<K, V, DeSer> {
+        &self.shards[shard_index(&StableHasher::default(), self.shards.len(), key)]
+    }
+
+    /// Get a clone of the value of `key`.
+    pub fn get<Q>(&self, key: &Q) -> error::Result<Option<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        Ok(self.shard(key).map.read()?.get(key).cloned())
+    }
+
+    /// Whether there is a value at `key`.
+    pub fn contains_key<Q>(&self, key: &Q) -> error::Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        Ok(self.shard(key).map.read()?.contains_key(key))
+    }
+
+    /// Insert `value` at `key`, returning the previous value.
+    pub fn insert(&self, key: K, value: V) -> error::Result<Option<V>> {
+        let shard = self.shard(&key);
+        let mut map = shard.map.write()?;
+        shard.dirty.store(true, Ordering::SeqCst);
+        Ok(map.insert(key, value))
+    }
+
+    /// Remove `key`, returning its value.
+    pub fn remove<Q>(&self, key: &Q) -> error::Result<Option<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let shard = self.shard(key);
+        let mut map = shard.map.write()?;
+        let value = map.remove(key);
+        if value.is_some() {
+            shard.dirty.store(true, Ordering::SeqCst);
+        }
+        Ok(value)
+    }
+
+    /// Run `task` on the value of `key`, while only its shard is write
+    /// locked. The shard counts as changed afterwards.
+    ///
+    /// # Panics
+    ///
+    /// If you panic in the closure, the shard is poisoned, like in
+    /// [`Database::write`](crate::Database::write).
+    pub fn update<Q, T, R>(&self, key: &Q, task: T) -> error::Result<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        T: FnOnce(Option<&mut V>) -> R,
+    {
+        let shard = self.shard(key);
+        let mut map = shard.map.write()?;
+        shard.dirty.store(true, Ordering::SeqCst);
+        Ok(task(map.get_mut(key)))
+    }
+
+    /// The number of entries in all shards.
+    pub fn len(&self) -> error::Result<usize> {
+        let mut len = 0;
+        for shard in &self.shards {
+            len += shard.map.read()?.len();
+        }
+        Ok(len)
+    }
+
+    /// Whether there are no entries in any shard.
+    pub fn is_empty(&self) -> error::Result<bool> {
+        for shard in &self.shards {
+            if !shard.map.read()?.is_empty() {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Whether any shard changed since it was last saved or loaded.
+    #[must_use]
+    pub fn is_dirty(&self) -> bool {
+        self.shards
+            .iter()
+            .any(|shard| shard.dirty.load(Ordering::SeqCst))
+    }
+
+    /// Save the shards that changed, each on its own thread.
+    ///
+    /// Returns the number of shards written. If saving some of them fails,
+    /// the others are still saved, and the first error is returned.
+    pub fn save(&self) -> error::Result<usize> {
+        let results: Vec<error::Result<bool>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .shards
+                .iter()
+                .filter(|shard| shard.dirty.load(Ordering::SeqCst))
+                .map(|shard| scope.spawn(move || shard.save_if_dirty()))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap_or(Err(error::RustbreakError::Poison)))
+                .collect()
+        });
+        let mut written = 0;
+        for result in results {
+            if result? {
+                written += 1;
+            }
+        }
+        Ok(written)
+    }
+
+    /// Load all shards from their files, discarding unsaved changes.
+    ///
+    /// Missing files are loaded as empty shards.
+    pub fn load(&self) -> error::Result<()> {
+        let mut maps = Vec::with_capacity(self.shards.len());
+        for shard in &self.shards {
+            let map = match shard.backend.lock()?.get_data() {
+                Ok(data) => shard.deser.deserialize(&data[..])?,
+                Err(BackendError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                    HashMap::new()
+                }
+                Err(e) => return Err(e.into()),
+            };
+            maps.push(map);
+        }
+
+        let mut locked = self
+            .shards
+            .iter()
+            .map(|shard| shard.map.write())
+            .collect::<error::Result<Vec<_>>>()?;
+        for (shard, map) in locked.iter_mut().zip(maps) {
+            **shard = map;
+        }
+        for shard in &self.shards {
+            shard.dirty.store(false, Ordering::SeqCst);
+        }
+        // Move keys that were stored in the wrong shard.
+        let hasher = StableHasher::default();
+        let count = self.shards.len();
+        for i in 0..count {
+            let misplaced: Vec<K> = locked[i]
+                .keys()
+                .filter(|key| shard_index(&hasher, count, *key) != i)
+                .cloned()
+                .collect();
+            for key in misplaced {
+                if let Some(value) = locked[i].remove(&key) {
+                    let target = shard_index(&hasher, count, &key);
+                    locked[target].insert(key, value);
+                    self.shards[i].dirty.store(true, Ordering::SeqCst);
+                    self.shards[target].dirty.store(true, Ordering::SeqCst);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Get a clone of the whole map.
+    pub fn get_data(&self) -> error::Result<HashMap<K, V>> {
+        let mut map = HashMap::new();
+        for shard in &self.shards {
+            let shard = shard.map.read()?;
+            map.extend(shard.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        Ok(map)
+    }
+}
+
+/// Check that the [`ShardedDatabase`] in `dir` has `count` shards, and
+/// record the count if the directory does not say yet.
+///
+/// Directories without the record are checked for shard files past `count`.
+fn check_shard_count(dir: &Path, count: usize) -> error::Result<()> {
+    let manifest = dir.join("shards");
+    let found = match std::fs::read_to_string(&manifest) {
+        Ok(text) => text.trim().parse().map_err(|_| {
+            BackendError::Corrupt(format!("{} is not a shard count", manifest.display()))
+        })?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let mut found = count;
+            for entry in std::fs::read_dir(dir).map_err(BackendError::from)? {
+                let name = entry.map_err(BackendError::from)?.file_name();
+                let index = name
+                    .to_str()
+                    .and_then(|name| name.strip_prefix("shard-"))
+                    .and_then(|index| index.parse::<usize>().ok());
+                if let Some(index) = index {
+                    found = found.max(index + 1);
+                }
+            }
+            if found == count {
+                std::fs::write(&manifest, count.to_string()).map_err(BackendError::from)?;
+            }
+            found
+        }
+        Err(e) => return Err(BackendError::from(e).into()),
+    };
+    if found == count {
+        Ok(())
+    } else {
+        Err(RustbreakError::ShardCount {
+            found,
+            expected: count,
+        })
+    }
+}
+
+/// The index of the shard `key` belongs to, out of `count`.
+fn shard_index<H, Q>(hasher: &H, count: usize, key: &Q) -> usize
+where
+    H: BuildHasher,
+    Q: Hash + ?Sized,
+{
+    // The remainder is less than `count`, so it fits.
+    usize::try_from(hasher.hash_one(key) % count as u64).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ShardedDatabase, ShardedMapDatabase};
+    use crate::backend::MemoryBackend;
+    use crate::deser::Ron;
+    use crate::error::RustbreakError;
+    use crate::MemoryDatabase;
+    use std::collections::HashMap;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn sharded_save_and_load() {
+        let map: HashMap<u32, String> = (0..50).map(|i| (i, i.to_string())).collect();
+        let db = ShardedMapDatabase::from_parts(map.clone(), 4, MemoryBackend::new(), Ron);
+        assert_eq!(db.shard_count(), 4);
+        assert_eq!(db.len().expect("could not get len"), 50);
+        assert_eq!(db.get(&7).expect("could not get"), Some("7".to_string()));
+        db.update(&7, |value| value.expect("value is missing").push('!'))
+            .expect("could not update");
+        db.remove(&8).expect("could not remove");
+        db.save().expect("could not save");
+
+        db.insert(100, "100".to_string()).expect("could not insert");
+        db.load().expect("could not load");
+        assert!(!db.contains_key(&100).expect("could not check key"));
+
+        // The stored data is a plain map.
+        let (merged, backend, _) = db.into_inner().expect("could not get parts");
+        assert_eq!(merged.len(), 49);
+        let plain =
+            MemoryDatabase::<HashMap<u32, String>, Ron>::from_parts(HashMap::new(), backend, Ron);
+        plain.load().expect("could not load");
+        assert_eq!(plain.get_data(false).expect("could not get data"), merged);
+        assert_eq!(merged.get(&7).map(String::as_str), Some("7!"));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn sharded_files_only_save_dirty_shards() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let db = ShardedDatabase::<u32, String, Ron>::open(dir.path(), 4)
+            .expect("could not open database");
+        assert!(db.is_empty().expect("could not check"));
+        for i in 0..40 {
+            db.insert(i, i.to_string()).expect("could not insert");
+        }
+        assert_eq!(db.save().expect("could not save"), 4);
+        assert!(!db.is_dirty());
+
+        db.update(&3, |value| value.expect("value is missing").push('!'))
+            .expect("could not update");
+        assert_eq!(db.save().expect("could not save"), 1);
+        assert_eq!(db.remove(&100).expect("could not remove"), None);
+        assert_eq!(db.save().expect("could not save"), 0);
+
+        let db = ShardedDatabase::<u32, String, Ron>::open(dir.path(), 4)
+            .expect("could not open database");
+        assert_eq!(db.len().expect("could not get len"), 40);
+        assert_eq!(db.get(&3).expect("could not get"), Some("3!".to_string()));
+
+        // Keys in the wrong shard are moved when loading.
+        let shard = |i| dir.path().join(format!("shard-{i}"));
+        std::fs::rename(shard(0), dir.path().join("tmp")).expect("could not rename");
+        std::fs::rename(shard(1), shard(0)).expect("could not rename");
+        std::fs::rename(dir.path().join("tmp"), shard(1)).expect("could not rename");
+        db.load().expect("could not load");
+        assert_eq!(db.get(&3).expect("could not get"), Some("3!".to_string()));
+        assert_eq!(db.get_data().expect("could not get data").len(), 40);
+        assert!(db.is_dirty());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn sharded_files_keep_their_shard_count() {
+        let dir = tempfile::tempdir().expect("could not create temporary directory");
+        let db = ShardedDatabase::<u32, String, Ron>::open(dir.path(), 4)
+            .expect("could not open database");
+        for i in 0..40 {
+            db.insert(i, i.to_string()).expect("could not insert");
+        }
+        db.save().expect("could not save");
+
+        let open = |shards| ShardedDatabase::<u32, String, Ron>::open(dir.path(), shards);
+        assert!(matches!(
+            open(2),
+            Err(RustbreakError::ShardCount {
+                found: 4,
+                expected: 2
+            })
+        ));
+        assert!(matches!(
+            open(8),
+            Err(RustbreakError::ShardCount {
+                found: 4,
+                expected: 8
+            })
+        ));
+
+        // Without the record, the shard files give the count away.
+        std::fs::remove_file(dir.path().join("shards")).expect("could not remove the record");
+        assert!(matches!(
+            open(2),
+            Err(RustbreakError::ShardCount {
+                found: 4,
+                expected: 2
+            })
+        ));
+        let db = open(4).expect("could not open database");
+        assert_eq!(db.len().expect("could not get len"), 40);
+        assert!(dir.path().join("shards").is_file());
+    }
+}
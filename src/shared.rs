@@ -0,0 +1,170 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A database handle that can be cloned, to share it between threads.
+//!
+//! See [`SharedDatabase`] for details.
+
+use std::ops::Deref;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::autosave::{AutoSaveHandle, SaveHandle, SaveTicket};
+use crate::backend::Backend;
+use crate::{error, Database, DeSerializer};
+
+/// A cheaply cloneable handle to a [`Database`].
+///
+/// All clones refer to the same data and backend, so they can be moved into
+/// threads or async tasks without wrapping the database in an `Arc`
+/// yourself. It dereferences to the [`Database`], so all its methods can be
+/// called on the handle directly.
+///
+/// The database is dropped with the last handle. Use
+/// [`SharedDatabase::try_into_inner`] to get it back instead.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::{deser::Ron, MemoryDatabase, SharedDatabase};
+///
+/// # fn main() -> rustbreak::error::Result<()> {
+/// let db = SharedDatabase::new(MemoryDatabase::<Vec<u32>, Ron>::memory(vec![])?);
+///
+/// let threads: Vec<_> = (0..4)
+///     .map(|i| {
+///         let db = db.clone();
+///         std::thread::spawn(move || db.write(|data| data.push(i)))
+///     })
+///     .collect();
+/// for thread in threads {
+///     thread.join().unwrap()?;
+/// }
+/// db.save()?;
+/// assert_eq!(db.read(|data| data.len())?, 4);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SharedDatabase<Data, Back, DeSer>(Arc<Database<Data, Back, DeSer>>);
+
+impl<Data, Back, DeSer> SharedDatabase<Data, Back, DeSer> {
+    /// Share `db`.
+    pub fn new(db: Database<Data, Back, DeSer>) -> Self {
+        Self(Arc::new(db))
+    }
+
+    /// Get the `Arc` the database is held in.
+    #[must_use]
+    pub fn as_arc(&self) -> &Arc<Database<Data, Back, DeSer>> {
+        &self.0
+    }
+
+    /// Get back the `Arc` the database is held in.
+    #[must_use]
+    pub fn into_arc(self) -> Arc<Database<Data, Back, DeSer>> {
+        self.0
+    }
+
+    /// Get back the database if this is the last handle to it, or the handle
+    /// otherwise.
+    pub fn try_into_inner(self) -> Result<Database<Data, Back, DeSer>, Self> {
+        Arc::try_unwrap(self.0).map_err(Self)
+    }
+
+    /// The number of handles to the database, including this one.
+    #[must_use]
+    pub fn handle_count(&self) -> usize {
+        Arc::strong_count(&self.0)
+    }
+
+    /// Whether `self` and `other` are handles to the same database.
+    #[must_use]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<Data, Back, DeSer> SharedDatabase<Data, Back, DeSer>
+where
+    Data: Serialize + DeserializeOwned + Send + Sync + 'static,
+    Back: Backend + Send + 'static,
+    DeSer: DeSerializer<Data> + Send + Sync + Clone + 'static,
+{
+    /// Start the background thread that applies the auto-save policy, see
+    /// [`Database::start_autosave`].
+    pub fn start_autosave(&self) -> AutoSaveHandle {
+        self.0.start_autosave()
+    }
+
+    /// Save the data on a background thread, see [`Database::save_async`].
+    pub fn save_async(&self) -> SaveHandle {
+        self.0.save_async()
+    }
+
+    /// Ask the background writer to save the data, see
+    /// [`Database::queue_save`].
+    pub fn queue_save(&self) -> error::Result<SaveTicket> {
+        self.0.queue_save()
+    }
+}
+
+impl<Data, Back, DeSer> Clone for SharedDatabase<Data, Back, DeSer> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<Data, Back, DeSer> Deref for SharedDatabase<Data, Back, DeSer> {
+    type Target = Database<Data, Back, DeSer>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<Data, Back, DeSer> From<Database<Data, Back, DeSer>> for SharedDatabase<Data, Back, DeSer> {
+    fn from(db: Database<Data, Back, DeSer>) -> Self {
+        Self::new(db)
+    }
+}
+
+impl<Data, Back, DeSer> From<Arc<Database<Data, Back, DeSer>>>
+    for SharedDatabase<Data, Back, DeSer>
+{
+    fn from(db: Arc<Database<Data, Back, DeSer>>) -> Self {
+        Self(db)
+    }
+}
+
+#[cfg(all(test, feature = "ron_enc"))]
+mod tests {
+    use super::SharedDatabase;
+    use crate::deser::Ron;
+    use crate::MemoryDatabase;
+
+    #[test]
+    fn shared_handles() {
+        let db = SharedDatabase::new(
+            MemoryDatabase::<u32, Ron>::memory(0).expect("could not create database"),
+        );
+        let other = db.clone();
+        assert!(db.ptr_eq(&other));
+        assert_eq!(db.handle_count(), 2);
+
+        std::thread::spawn(move || other.write(|data| *data += 1).unwrap())
+            .join()
+            .unwrap();
+        assert_eq!(db.read(|data| *data).unwrap(), 1);
+
+        let other = db.clone();
+        let db = db.try_into_inner().expect_err("there is another handle");
+        drop(other);
+        let db = db.try_into_inner().expect("this is the last handle");
+        assert_eq!(db.into_inner().unwrap().0, 1);
+    }
+}
@@ -0,0 +1,191 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Running code when the process receives a UNIX signal.
+//!
+//! Signal handlers may only do very little, so the handler installed here
+//! just writes the number of the signal to a pipe. A background thread reads
+//! it and calls the listeners of the signal, which can then lock databases
+//! and save them like any other code.
+//!
+//! Once a signal has a listener, its handler stays installed and replaces
+//! whatever handled the signal before. Signals without listeners behave as
+//! if no handler were installed, and so do `SIGINT` and `SIGTERM` after
+//! their listeners ran: the process is terminated by the signal.
+
+// Installing a signal handler and the pipe it writes to needs `libc`.
+#![allow(unsafe_code)]
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::unix::io::FromRawFd;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// The signals listeners can be added for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Signal {
+    /// `SIGHUP`, used to ask daemons to reload their configuration.
+    Hangup,
+    /// `SIGINT`, sent by Ctrl-C.
+    Interrupt,
+    /// `SIGTERM`, sent by `kill` and service managers.
+    Terminate,
+}
+
+impl Signal {
+    fn number(self) -> libc::c_int {
+        match self {
+            Signal::Hangup => libc::SIGHUP,
+            Signal::Interrupt => libc::SIGINT,
+            Signal::Terminate => libc::SIGTERM,
+        }
+    }
+
+    /// Whether the process ends after the listeners ran.
+    fn terminates(number: libc::c_int) -> bool {
+        number == libc::SIGINT || number == libc::SIGTERM
+    }
+}
+
+/// Identifies a listener, to remove it with [`unlisten`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ListenerId(u64);
+
+type Callback = Arc<dyn Fn() + Send + Sync>;
+
+struct State {
+    /// Whether the pipe and the thread reading it exist.
+    started: bool,
+    /// The signals the handler is installed for.
+    installed: Vec<libc::c_int>,
+    listeners: Vec<(ListenerId, libc::c_int, Callback)>,
+    next_id: u64,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    started: false,
+    installed: Vec::new(),
+    listeners: Vec::new(),
+    next_id: 0,
+});
+
+/// The end of the pipe the handler writes to, `-1` before it exists.
+static PIPE: AtomicI32 = AtomicI32::new(-1);
+
+/// Call `callback` on the background thread whenever `signal` arrives.
+pub(crate) fn listen<F>(signal: Signal, callback: F) -> io::Result<ListenerId>
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    // The lock only guards plain lists, a panic can not leave them
+    // inconsistent.
+    let mut state = STATE.lock().unwrap_or_else(PoisonError::into_inner);
+    if !state.started {
+        start()?;
+        state.started = true;
+    }
+    let number = signal.number();
+    if !state.installed.contains(&number) {
+        let handler: extern "C" fn(libc::c_int) = on_signal;
+        install(number, handler as libc::sighandler_t)?;
+        state.installed.push(number);
+    }
+    let id = ListenerId(state.next_id);
+    state.next_id += 1;
+    state.listeners.push((id, number, Arc::new(callback)));
+    Ok(id)
+}
+
+/// Remove the listener `id`, which is not called for signals arriving later.
+pub(crate) fn unlisten(id: ListenerId) {
+    let mut state = STATE.lock().unwrap_or_else(PoisonError::into_inner);
+    state.listeners.retain(|(other, _, _)| *other != id);
+}
+
+/// Create the pipe and spawn the thread reading it.
+fn start() -> io::Result<()> {
+    let mut fds = [0; 2];
+    // SAFETY: `fds` has room for the two descriptors `pipe` writes.
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let [read, write] = fds;
+    for (fd, flags) in [(read, 0), (write, libc::O_NONBLOCK)] {
+        // SAFETY: `fd` was just created by `pipe`.
+        unsafe {
+            if libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) != 0
+                || libc::fcntl(fd, libc::F_SETFL, flags) != 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+        }
+    }
+    // SAFETY: Nothing else owns the read end of the pipe.
+    let mut pipe = unsafe { File::from_raw_fd(read) };
+    std::thread::Builder::new()
+        .name("rustbreak-signals".into())
+        .spawn(move || {
+            let mut byte = [0];
+            while pipe.read_exact(&mut byte).is_ok() {
+                dispatch(libc::c_int::from(byte[0]));
+            }
+        })?;
+    PIPE.store(write, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Call the listeners of the signal `number`.
+fn dispatch(number: libc::c_int) {
+    let callbacks: Vec<Callback> = {
+        let state = STATE.lock().unwrap_or_else(PoisonError::into_inner);
+        state
+            .listeners
+            .iter()
+            .filter(|(_, signal, _)| *signal == number)
+            .map(|(_, _, callback)| callback.clone())
+            .collect()
+    };
+    for callback in &callbacks {
+        callback();
+    }
+    if callbacks.is_empty() || Signal::terminates(number) {
+        // Do what would have happened without a handler. If the process
+        // survives it, the next listener installs the handler again.
+        let mut state = STATE.lock().unwrap_or_else(PoisonError::into_inner);
+        state.installed.retain(|installed| *installed != number);
+        drop(state);
+        if install(number, libc::SIG_DFL).is_ok() {
+            // SAFETY: Raising a signal has no memory safety requirements.
+            unsafe { libc::raise(number) };
+        }
+    }
+}
+
+/// Set the handler of the signal `number`.
+fn install(number: libc::c_int, handler: libc::sighandler_t) -> io::Result<()> {
+    // SAFETY: A zeroed `sigaction` is a valid empty one, and `on_signal`
+    // only does what is allowed in a signal handler.
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = handler;
+        action.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&raw mut action.sa_mask);
+        if libc::sigaction(number, &raw const action, std::ptr::null_mut()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// The signal handler, passing the signal on to the background thread.
+extern "C" fn on_signal(number: libc::c_int) {
+    let byte = u8::try_from(number).unwrap_or(0);
+    // SAFETY: `write` is allowed in signal handlers, the pipe does not block
+    // and stays open.
+    unsafe {
+        libc::write(PIPE.load(Ordering::SeqCst), (&raw const byte).cast(), 1);
+    }
+}
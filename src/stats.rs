@@ -0,0 +1,120 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Counters of the saves and loads of a database.
+//!
+//! See [`Database::stats`](crate::Database::stats) and
+//! [`Database::set_stats_hook`](crate::Database::set_stats_hook).
+
+use std::fmt;
+use std::io::{self, Write};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::error;
+use crate::sync::Mutex;
+
+/// Statistics about the saves and loads of a database.
+///
+/// Only successful operations are counted. Saves include the ones done while
+/// loading with recovery and while merging external changes, but not
+/// backups and exports to other backends.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Stats {
+    /// The number of saves.
+    pub saves: u64,
+    /// The number of loads.
+    pub loads: u64,
+    /// The total number of bytes written by saves.
+    pub bytes_written: u64,
+    /// The total number of bytes read by loads.
+    pub bytes_read: u64,
+    /// How long the last save took, including serialization.
+    pub last_save_duration: Option<Duration>,
+    /// When the last save finished.
+    pub last_save: Option<SystemTime>,
+    /// How long the last load took, including deserialization.
+    pub last_load_duration: Option<Duration>,
+    /// When the last load finished.
+    pub last_load: Option<SystemTime>,
+}
+
+/// A hook called with the new [`Stats`], see
+/// [`Database::set_stats_hook`](crate::Database::set_stats_hook).
+type StatsHook = dyn Fn(&Stats) + Send;
+
+/// Records the [`Stats`] of a database and calls the hook.
+#[derive(Default)]
+pub(crate) struct StatsRecorder {
+    stats: Mutex<Stats>,
+    hook: Mutex<Option<Box<StatsHook>>>,
+}
+
+impl fmt::Debug for StatsRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StatsRecorder")
+            .field("stats", &self.stats)
+            .finish_non_exhaustive()
+    }
+}
+
+impl StatsRecorder {
+    pub(crate) fn get(&self) -> error::Result<Stats> {
+        Ok(self.stats.lock()?.clone())
+    }
+
+    pub(crate) fn set_hook(&self, hook: Option<Box<StatsHook>>) -> error::Result<()> {
+        *self.hook.lock()? = hook;
+        Ok(())
+    }
+
+    /// Record a save of `bytes` that started at `start`.
+    pub(crate) fn record_save(&self, bytes: u64, start: Instant) -> error::Result<()> {
+        self.record(|stats| {
+            stats.saves += 1;
+            stats.bytes_written += bytes;
+            stats.last_save_duration = Some(start.elapsed());
+            stats.last_save = Some(SystemTime::now());
+        })
+    }
+
+    /// Record a load of `bytes` that started at `start`.
+    pub(crate) fn record_load(&self, bytes: u64, start: Instant) -> error::Result<()> {
+        self.record(|stats| {
+            stats.loads += 1;
+            stats.bytes_read += bytes;
+            stats.last_load_duration = Some(start.elapsed());
+            stats.last_load = Some(SystemTime::now());
+        })
+    }
+
+    fn record<F: FnOnce(&mut Stats)>(&self, update: F) -> error::Result<()> {
+        let mut stats = self.stats.lock()?;
+        update(&mut stats);
+        let snapshot = stats.clone();
+        drop(stats);
+        if let Some(hook) = &*self.hook.lock()? {
+            hook(&snapshot);
+        }
+        Ok(())
+    }
+}
+
+/// A writer counting the bytes written through it.
+pub(crate) struct CountingWriter<W> {
+    pub(crate) inner: W,
+    pub(crate) count: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
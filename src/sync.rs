@@ -0,0 +1,265 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The locks guarding the database.
+//!
+//! These are thin wrappers around the `std` locks, or the `parking_lot` ones
+//! if that feature is enabled. Locking returns a [`error::Result`] either
+//! way, `parking_lot` locks can not be poisoned and never fail.
+//!
+//! The `try_` and `_timeout` variants return `None` if the lock could not be
+//! taken right away or in time. The `std` locks can not wait with a timeout,
+//! so they are polled until the deadline.
+//!
+//! The guards of the [`RwLock`] are handed out by the public API, so they
+//! are wrapped in [`ReadGuard`] and [`WriteGuard`], which are the same types
+//! with and without the feature.
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+pub(crate) use self::imp::{Mutex, RwLock};
+
+/// A read lock on the data of a database.
+///
+/// It is returned by [`Database::borrow_data`](crate::Database::borrow_data)
+/// and dereferences to the data. The database is read locked until it is
+/// dropped.
+pub struct ReadGuard<'a, T>(imp::RwLockReadGuard<'a, T>);
+
+impl<T> Deref for ReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ReadGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for ReadGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+/// A write lock on the data of a database.
+///
+/// It is returned by
+/// [`Database::borrow_data_mut`](crate::Database::borrow_data_mut) and
+/// dereferences to the data. The database is write locked until it is
+/// dropped.
+pub struct WriteGuard<'a, T>(imp::RwLockWriteGuard<'a, T>);
+
+impl<T> Deref for WriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for WriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for WriteGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for WriteGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+#[cfg(not(feature = "parking_lot"))]
+mod imp {
+    pub(super) use std::sync::{MutexGuard, RwLockReadGuard, RwLockWriteGuard};
+
+    use std::sync::{TryLockError, TryLockResult};
+    use std::time::{Duration, Instant};
+
+    use super::{ReadGuard, WriteGuard};
+    use crate::error::{self, RustbreakError};
+
+    /// The longest pause between two polls of a lock.
+    const MAX_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+    fn try_result<G>(result: TryLockResult<G>) -> error::Result<Option<G>> {
+        match result {
+            Ok(guard) => Ok(Some(guard)),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Poisoned(_)) => Err(RustbreakError::Poison),
+        }
+    }
+
+    /// Call `try_lock` until it succeeds or `timeout` passed.
+    fn poll<G>(
+        timeout: Duration,
+        mut try_lock: impl FnMut() -> TryLockResult<G>,
+    ) -> error::Result<Option<G>> {
+        let deadline = Instant::now().checked_add(timeout);
+        let mut interval = Duration::from_micros(10);
+        loop {
+            if let Some(guard) = try_result(try_lock())? {
+                return Ok(Some(guard));
+            }
+            let now = Instant::now();
+            let left = match deadline {
+                Some(deadline) if deadline <= now => return Ok(None),
+                Some(deadline) => deadline - now,
+                None => interval,
+            };
+            std::thread::sleep(interval.min(left));
+            interval = (interval * 2).min(MAX_POLL_INTERVAL);
+        }
+    }
+
+    #[derive(Debug, Default)]
+    pub(crate) struct RwLock<T>(std::sync::RwLock<T>);
+
+    impl<T> RwLock<T> {
+        pub(crate) fn new(value: T) -> Self {
+            Self(std::sync::RwLock::new(value))
+        }
+
+        pub(crate) fn read(&self) -> error::Result<ReadGuard<'_, T>> {
+            self.0
+                .read()
+                .map(ReadGuard)
+                .map_err(|_| RustbreakError::Poison)
+        }
+
+        pub(crate) fn write(&self) -> error::Result<WriteGuard<'_, T>> {
+            self.0
+                .write()
+                .map(WriteGuard)
+                .map_err(|_| RustbreakError::Poison)
+        }
+
+        pub(crate) fn try_read(&self) -> error::Result<Option<ReadGuard<'_, T>>> {
+            Ok(try_result(self.0.try_read())?.map(ReadGuard))
+        }
+
+        pub(crate) fn try_write(&self) -> error::Result<Option<WriteGuard<'_, T>>> {
+            Ok(try_result(self.0.try_write())?.map(WriteGuard))
+        }
+
+        pub(crate) fn read_timeout(
+            &self,
+            timeout: Duration,
+        ) -> error::Result<Option<ReadGuard<'_, T>>> {
+            Ok(poll(timeout, || self.0.try_read())?.map(ReadGuard))
+        }
+
+        pub(crate) fn write_timeout(
+            &self,
+            timeout: Duration,
+        ) -> error::Result<Option<WriteGuard<'_, T>>> {
+            Ok(poll(timeout, || self.0.try_write())?.map(WriteGuard))
+        }
+
+        pub(crate) fn into_inner(self) -> error::Result<T> {
+            self.0.into_inner().map_err(|_| RustbreakError::Poison)
+        }
+    }
+
+    #[derive(Debug, Default)]
+    pub(crate) struct Mutex<T>(std::sync::Mutex<T>);
+
+    impl<T> Mutex<T> {
+        pub(crate) fn new(value: T) -> Self {
+            Self(std::sync::Mutex::new(value))
+        }
+
+        pub(crate) fn lock(&self) -> error::Result<MutexGuard<'_, T>> {
+            self.0.lock().map_err(|_| RustbreakError::Poison)
+        }
+
+        pub(crate) fn into_inner(self) -> error::Result<T> {
+            self.0.into_inner().map_err(|_| RustbreakError::Poison)
+        }
+    }
+}
+
+#[cfg(feature = "parking_lot")]
+// The signatures have to match the `std` variant, which can fail.
+#[allow(clippy::unnecessary_wraps)]
+mod imp {
+    pub(super) use parking_lot::{MutexGuard, RwLockReadGuard, RwLockWriteGuard};
+
+    use std::time::Duration;
+
+    use super::{ReadGuard, WriteGuard};
+    use crate::error;
+
+    #[derive(Debug, Default)]
+    pub(crate) struct RwLock<T>(parking_lot::RwLock<T>);
+
+    impl<T> RwLock<T> {
+        pub(crate) fn new(value: T) -> Self {
+            Self(parking_lot::RwLock::new(value))
+        }
+
+        pub(crate) fn read(&self) -> error::Result<ReadGuard<'_, T>> {
+            Ok(ReadGuard(self.0.read()))
+        }
+
+        pub(crate) fn write(&self) -> error::Result<WriteGuard<'_, T>> {
+            Ok(WriteGuard(self.0.write()))
+        }
+
+        pub(crate) fn try_read(&self) -> error::Result<Option<ReadGuard<'_, T>>> {
+            Ok(self.0.try_read().map(ReadGuard))
+        }
+
+        pub(crate) fn try_write(&self) -> error::Result<Option<WriteGuard<'_, T>>> {
+            Ok(self.0.try_write().map(WriteGuard))
+        }
+
+        pub(crate) fn read_timeout(
+            &self,
+            timeout: Duration,
+        ) -> error::Result<Option<ReadGuard<'_, T>>> {
+            Ok(self.0.try_read_for(timeout).map(ReadGuard))
+        }
+
+        pub(crate) fn write_timeout(
+            &self,
+            timeout: Duration,
+        ) -> error::Result<Option<WriteGuard<'_, T>>> {
+            Ok(self.0.try_write_for(timeout).map(WriteGuard))
+        }
+
+        pub(crate) fn into_inner(self) -> error::Result<T> {
+            Ok(self.0.into_inner())
+        }
+    }
+
+    #[derive(Debug, Default)]
+    pub(crate) struct Mutex<T>(parking_lot::Mutex<T>);
+
+    impl<T> Mutex<T> {
+        pub(crate) fn new(value: T) -> Self {
+            Self(parking_lot::Mutex::new(value))
+        }
+
+        pub(crate) fn lock(&self) -> error::Result<MutexGuard<'_, T>> {
+            Ok(self.0.lock())
+        }
+
+        pub(crate) fn into_inner(self) -> error::Result<T> {
+            Ok(self.0.into_inner())
+        }
+    }
+}
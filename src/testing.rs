@@ -0,0 +1,484 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Backends that misbehave on purpose, to test how a program handles failing
+//! saves and loads.
+//!
+//! Each of them wraps another backend, usually a
+//! [`MemoryBackend`](crate::backend::MemoryBackend), and passes everything
+//! through to it, except for the failures it injects:
+//!
+//! - [`FailingBackend`] fails or corrupts chosen reads and writes,
+//! - [`FlakyBackend`] fails at random, but reproducibly,
+//! - [`SlowBackend`] waits before every read and write.
+//!
+//! Injected failures are [`BackendError::Io`] errors, with the
+//! [`std::io::ErrorKind::Other`] kind unless configured otherwise.
+//!
+//! **Important**: This module is only available with the `test-utils`
+//! feature, which is meant for `dev-dependencies`.
+
+use std::collections::BTreeSet;
+use std::io;
+use std::time::Duration;
+
+use crate::backend::{Backend, BackendLock, BackendMetadata, Fingerprint};
+use crate::error::{self, BackendError};
+
+/// The error of an injected failure.
+fn injected(kind: io::ErrorKind, op: &str, count: usize) -> BackendError {
+    BackendError::Io(io::Error::new(
+        kind,
+        format!("injected failure of {op} number {count}"),
+    ))
+}
+
+/// Which calls, counted from 1, should misbehave.
+#[derive(Debug, Clone, Default)]
+struct Schedule {
+    at: BTreeSet<usize>,
+    from: Option<usize>,
+}
+
+impl Schedule {
+    fn hits(&self, count: usize) -> bool {
+        self.at.contains(&count) || self.from.is_some_and(|from| count >= from)
+    }
+}
+
+/// A backend that fails or corrupts chosen calls.
+///
+/// Calls to [`Backend::get_data`] and [`Backend::put_data`] are counted
+/// separately, starting at 1. A failing write leaves the wrapped backend
+/// untouched, a corrupted read flips the bits of one byte of the data, or
+/// returns a single `0xFF` byte if it is empty.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::backend::{Backend, MemoryBackend};
+/// use rustbreak::testing::FailingBackend;
+///
+/// let mut backend = FailingBackend::new(MemoryBackend::new()).fail_put(2);
+/// assert!(backend.put_data(b"first").is_ok());
+/// assert!(backend.put_data(b"second").is_err());
+/// assert_eq!(backend.get_data().unwrap(), b"first");
+/// assert_eq!(backend.puts(), 2);
+/// ```
+#[derive(Debug)]
+pub struct FailingBackend<B> {
+    inner: B,
+    kind: io::ErrorKind,
+    fail_gets: Schedule,
+    fail_puts: Schedule,
+    corrupt_gets: Schedule,
+    gets: usize,
+    puts: usize,
+}
+
+impl<B> FailingBackend<B> {
+    /// Pass everything through to `inner`, until failures are added.
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            kind: io::ErrorKind::Other,
+            fail_gets: Schedule::default(),
+            fail_puts: Schedule::default(),
+            corrupt_gets: Schedule::default(),
+            gets: 0,
+            puts: 0,
+        }
+    }
+
+    /// Fail the `n`-th read.
+    #[must_use]
+    pub fn fail_get(mut self, n: usize) -> Self {
+        self.fail_gets.at.insert(n);
+        self
+    }
+
+    /// Fail the `n`-th read and all the ones after it.
+    #[must_use]
+    pub fn fail_gets_from(mut self, n: usize) -> Self {
+        self.fail_gets.from = Some(n);
+        self
+    }
+
+    /// Fail the `n`-th write.
+    #[must_use]
+    pub fn fail_put(mut self, n: usize) -> Self {
+        self.fail_puts.at.insert(n);
+        self
+    }
+
+    /// Fail the `n`-th write and all the ones after it.
+    #[must_use]
+    pub fn fail_puts_from(mut self, n: usize) -> Self {
+        self.fail_puts.from = Some(n);
+        self
+    }
+
+    /// Corrupt the data returned by the `n`-th read.
+    #[must_use]
+    pub fn corrupt_get(mut self, n: usize) -> Self {
+        self.corrupt_gets.at.insert(n);
+        self
+    }
+
+    /// Fail with errors of `kind` instead of [`io::ErrorKind::Other`].
+    #[must_use]
+    pub fn with_error_kind(mut self, kind: io::ErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// The number of reads so far, including failed ones.
+    #[must_use]
+    pub fn gets(&self) -> usize {
+        self.gets
+    }
+
+    /// The number of writes so far, including failed ones.
+    #[must_use]
+    pub fn puts(&self) -> usize {
+        self.puts
+    }
+
+    /// Get a reference to the wrapped backend.
+    pub fn get_ref(&self) -> &B {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the wrapped backend.
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.inner
+    }
+
+    /// Get back the wrapped backend.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B: Backend> Backend for FailingBackend<B> {
+    fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        self.gets += 1;
+        if self.fail_gets.hits(self.gets) {
+            return Err(injected(self.kind, "get_data", self.gets));
+        }
+        let mut data = self.inner.get_data()?;
+        if self.corrupt_gets.hits(self.gets) {
+            let middle = data.len() / 2;
+            match data.get_mut(middle) {
+                Some(byte) => *byte = !*byte,
+                None => data.push(0xFF),
+            }
+        }
+        Ok(data)
+    }
+
+    fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        self.puts += 1;
+        if self.fail_puts.hits(self.puts) {
+            return Err(injected(self.kind, "put_data", self.puts));
+        }
+        self.inner.put_data(data)
+    }
+
+    fn quarantine(&mut self) -> error::BackendResult<()> {
+        self.inner.quarantine()
+    }
+
+    fn fingerprint(&mut self) -> error::BackendResult<Option<Fingerprint>> {
+        self.inner.fingerprint()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+
+    fn lock_exclusive(&mut self) -> error::BackendResult<Option<BackendLock>> {
+        self.inner.lock_exclusive()
+    }
+
+    fn metadata(&mut self) -> error::BackendResult<Option<BackendMetadata>> {
+        self.inner.metadata()
+    }
+}
+
+/// The seed of a [`FlakyBackend`] unless another one is chosen.
+const DEFAULT_SEED: u64 = 0x853c_49e6_748f_ea9b;
+
+/// A backend that fails reads and writes at random.
+///
+/// On average one in `one_in` calls fails. The failures come from a seeded
+/// generator, so a test sees the same ones on every run, and another seed
+/// can be chosen with [`FlakyBackend::with_seed`].
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::backend::{Backend, MemoryBackend};
+/// use rustbreak::testing::FlakyBackend;
+///
+/// let mut backend = FlakyBackend::new(MemoryBackend::new(), 3).with_seed(7);
+/// let failures = (0..300).filter(|_| backend.put_data(b"data").is_err()).count();
+/// assert!(failures > 50 && failures < 150);
+/// ```
+#[derive(Debug)]
+pub struct FlakyBackend<B> {
+    inner: B,
+    one_in: u32,
+    state: u64,
+    kind: io::ErrorKind,
+    calls: usize,
+}
+
+impl<B> FlakyBackend<B> {
+    /// Fail one in `one_in` reads and writes of `inner`. With `0` or `1`,
+    /// every call fails.
+    pub fn new(inner: B, one_in: u32) -> Self {
+        Self {
+            inner,
+            one_in,
+            state: DEFAULT_SEED,
+            kind: io::ErrorKind::Other,
+            calls: 0,
+        }
+    }
+
+    /// Use another seed for the failures.
+    #[must_use]
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        // Xorshift gets stuck at zero.
+        self.state = if seed == 0 { DEFAULT_SEED } else { seed };
+        self
+    }
+
+    /// Fail with errors of `kind` instead of [`io::ErrorKind::Other`].
+    #[must_use]
+    pub fn with_error_kind(mut self, kind: io::ErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Get a reference to the wrapped backend.
+    pub fn get_ref(&self) -> &B {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the wrapped backend.
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.inner
+    }
+
+    /// Get back the wrapped backend.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    /// Whether the next call should fail.
+    fn fails(&mut self) -> bool {
+        self.calls += 1;
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state.is_multiple_of(u64::from(self.one_in.max(1)))
+    }
+}
+
+impl<B: Backend> Backend for FlakyBackend<B> {
+    fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        if self.fails() {
+            return Err(injected(self.kind, "call", self.calls));
+        }
+        self.inner.get_data()
+    }
+
+    fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        if self.fails() {
+            return Err(injected(self.kind, "call", self.calls));
+        }
+        self.inner.put_data(data)
+    }
+
+    fn quarantine(&mut self) -> error::BackendResult<()> {
+        self.inner.quarantine()
+    }
+
+    fn fingerprint(&mut self) -> error::BackendResult<Option<Fingerprint>> {
+        self.inner.fingerprint()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+
+    fn lock_exclusive(&mut self) -> error::BackendResult<Option<BackendLock>> {
+        self.inner.lock_exclusive()
+    }
+
+    fn metadata(&mut self) -> error::BackendResult<Option<BackendMetadata>> {
+        self.inner.metadata()
+    }
+}
+
+/// A backend that waits before every read and write, like a slow disk or
+/// network would.
+///
+/// # Example
+///
+/// ```rust
+/// # extern crate rustbreak;
+/// use rustbreak::backend::{Backend, MemoryBackend};
+/// use rustbreak::testing::SlowBackend;
+/// use std::time::{Duration, Instant};
+///
+/// let mut backend = SlowBackend::new(MemoryBackend::new(), Duration::from_millis(20));
+/// let start = Instant::now();
+/// backend.put_data(b"data").unwrap();
+/// assert!(start.elapsed() >= Duration::from_millis(20));
+/// ```
+#[derive(Debug)]
+pub struct SlowBackend<B> {
+    inner: B,
+    get_delay: Duration,
+    put_delay: Duration,
+}
+
+impl<B> SlowBackend<B> {
+    /// Wait `delay` before every read and write of `inner`.
+    pub fn new(inner: B, delay: Duration) -> Self {
+        Self {
+            inner,
+            get_delay: delay,
+            put_delay: delay,
+        }
+    }
+
+    /// Wait `delay` before every read instead.
+    #[must_use]
+    pub fn with_get_delay(mut self, delay: Duration) -> Self {
+        self.get_delay = delay;
+        self
+    }
+
+    /// Wait `delay` before every write instead.
+    #[must_use]
+    pub fn with_put_delay(mut self, delay: Duration) -> Self {
+        self.put_delay = delay;
+        self
+    }
+
+    /// Get a reference to the wrapped backend.
+    pub fn get_ref(&self) -> &B {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the wrapped backend.
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.inner
+    }
+
+    /// Get back the wrapped backend.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B: Backend> Backend for SlowBackend<B> {
+    fn get_data(&mut self) -> error::BackendResult<Vec<u8>> {
+        std::thread::sleep(self.get_delay);
+        self.inner.get_data()
+    }
+
+    fn put_data(&mut self, data: &[u8]) -> error::BackendResult<()> {
+        std::thread::sleep(self.put_delay);
+        self.inner.put_data(data)
+    }
+
+    fn quarantine(&mut self) -> error::BackendResult<()> {
+        self.inner.quarantine()
+    }
+
+    fn fingerprint(&mut self) -> error::BackendResult<Option<Fingerprint>> {
+        self.inner.fingerprint()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+
+    fn lock_exclusive(&mut self) -> error::BackendResult<Option<BackendLock>> {
+        self.inner.lock_exclusive()
+    }
+
+    fn metadata(&mut self) -> error::BackendResult<Option<BackendMetadata>> {
+        self.inner.metadata()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FailingBackend, FlakyBackend};
+    use crate::backend::{Backend, MemoryBackend};
+    use crate::error::BackendError;
+
+    #[test]
+    fn failing_backend_schedules() {
+        let mut backend = FailingBackend::new(MemoryBackend::new())
+            .fail_get(1)
+            .corrupt_get(3)
+            .fail_puts_from(3)
+            .with_error_kind(std::io::ErrorKind::PermissionDenied);
+        assert!(matches!(
+            backend.get_data(),
+            Err(BackendError::Io(e)) if e.kind() == std::io::ErrorKind::PermissionDenied
+        ));
+        assert_eq!(backend.get_data().unwrap(), b"");
+        assert_eq!(backend.get_data().unwrap(), [0xFF]);
+
+        backend.put_data(b"abc").unwrap();
+        backend.put_data(b"abcd").unwrap();
+        assert!(backend.put_data(b"lost").is_err());
+        assert!(backend.put_data(b"lost").is_err());
+        assert_eq!(backend.get_data().unwrap(), b"abcd");
+        assert_eq!((backend.gets(), backend.puts()), (4, 4));
+        assert_eq!(backend.into_inner().get_data().unwrap(), b"abcd");
+    }
+
+    #[test]
+    #[cfg(feature = "ron_enc")]
+    fn failing_backend_with_database() {
+        use crate::deser::Ron;
+        use crate::Database;
+
+        let backend = FailingBackend::new(MemoryBackend::new())
+            .fail_put(2)
+            .corrupt_get(1);
+        let db = Database::from_parts(vec![1_u32], backend, Ron);
+        db.save().expect("could not save");
+        db.write(|data| data.push(2)).unwrap();
+        assert!(db.save().is_err());
+        assert!(db.load().is_err());
+        db.load().expect("could not load");
+        assert_eq!(db.borrow_data().unwrap().as_slice(), [1]);
+    }
+
+    #[test]
+    fn flaky_backend_is_reproducible() {
+        let run = |seed| {
+            let mut backend = FlakyBackend::new(MemoryBackend::new(), 4).with_seed(seed);
+            (0..64)
+                .map(|_| backend.put_data(b"data").is_ok())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(run(1), run(1));
+        assert_ne!(run(1), run(2));
+        let mut backend = FlakyBackend::new(MemoryBackend::new(), 1);
+        assert!(backend.get_data().is_err());
+    }
+}
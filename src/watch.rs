@@ -0,0 +1,245 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Watchers that get notified when a part of the data changes.
+//!
+//! See [`Database::watch`](crate::Database::watch) for details, and
+//! [`Database::subscribe`](crate::Database::subscribe) to get notified of
+//! every change through a channel instead. With the
+//! `watch` feature, [`Database::watch_file`](crate::Database::watch_file)
+//! also reloads the data when its file changes on disk, and with the
+//! `signals` feature,
+//! [`Database::reload_on_sighup`](crate::Database::reload_on_sighup) when the
+//! process receives `SIGHUP`.
+
+use std::fmt;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// Identifies a watcher registered with
+/// [`Database::watch`](crate::Database::watch).
+///
+/// Pass it to [`Database::unwatch`](crate::Database::unwatch) to remove the
+/// watcher again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WatcherId(u64);
+
+/// What changed the data, see [`ChangeEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChangeKind {
+    /// The data was written to in memory.
+    Write,
+    /// The data was loaded from the backend.
+    Load,
+    /// Changes in the backend were merged into the data, see
+    /// [`Database::set_merge`](crate::Database::set_merge).
+    Merge,
+}
+
+/// Sent to the receivers of
+/// [`Database::subscribe`](crate::Database::subscribe) after the data
+/// changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ChangeEvent {
+    /// What changed the data.
+    pub kind: ChangeKind,
+    /// The generation of the data after the change. It increases with every
+    /// change, so a later event always has a larger generation.
+    pub generation: u64,
+}
+
+/// A type erased watcher.
+trait Watch<Data>: Send {
+    /// Re-evaluate the projection and call the callback if its value changed.
+    fn check(&mut self, data: &Data);
+}
+
+struct Watcher<P, V, F> {
+    projection: P,
+    last: V,
+    callback: F,
+}
+
+impl<Data, P, V, F> Watch<Data> for Watcher<P, V, F>
+where
+    P: Fn(&Data) -> V + Send,
+    V: PartialEq + Send,
+    F: FnMut(&V) + Send,
+{
+    fn check(&mut self, data: &Data) {
+        let value = (self.projection)(data);
+        if value != self.last {
+            (self.callback)(&value);
+            self.last = value;
+        }
+    }
+}
+
+/// The watchers registered on a database.
+pub(crate) struct Watchers<Data> {
+    next_id: u64,
+    entries: Vec<(WatcherId, Box<dyn Watch<Data>>)>,
+    subscribers: Vec<Sender<ChangeEvent>>,
+}
+
+impl<Data> Default for Watchers<Data> {
+    fn default() -> Self {
+        Self {
+            next_id: 0,
+            entries: Vec::new(),
+            subscribers: Vec::new(),
+        }
+    }
+}
+
+impl<Data> fmt::Debug for Watchers<Data> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watchers")
+            .field("len", &self.entries.len())
+            .field("subscribers", &self.subscribers.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Data> Watchers<Data> {
+    /// Register a new watcher, `initial` is the current value of the
+    /// projection.
+    pub(crate) fn add<P, V, F>(&mut self, projection: P, initial: V, callback: F) -> WatcherId
+    where
+        P: Fn(&Data) -> V + Send + 'static,
+        V: PartialEq + Send + 'static,
+        F: FnMut(&V) + Send + 'static,
+    {
+        let id = WatcherId(self.next_id);
+        self.next_id += 1;
+        self.entries.push((
+            id,
+            Box::new(Watcher {
+                projection,
+                last: initial,
+                callback,
+            }),
+        ));
+        id
+    }
+
+    /// Remove a watcher, returns whether it was registered.
+    pub(crate) fn remove(&mut self, id: WatcherId) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|(entry, _)| *entry != id);
+        len != self.entries.len()
+    }
+
+    /// Get a receiver for every following [`ChangeEvent`].
+    pub(crate) fn subscribe(&mut self) -> Receiver<ChangeEvent> {
+        let (sender, receiver) = channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    #[cfg(test)]
+    pub(crate) fn subscribers_len(&self) -> usize {
+        self.subscribers.len()
+    }
+
+    /// Notify every watcher whose projection changed, and send `event` to
+    /// the subscribers. Subscribers whose receiver was dropped are removed.
+    pub(crate) fn notify(&mut self, data: &Data, event: ChangeEvent) {
+        for (_, watcher) in &mut self.entries {
+            watcher.check(data);
+        }
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event).is_ok());
+    }
+}
+
+/// Watches a database file for changes, returned by
+/// [`Database::watch_file`](crate::Database::watch_file).
+///
+/// The file is no longer watched once this is dropped.
+#[cfg(feature = "watch")]
+pub struct FileWatcher {
+    _watcher: notify::RecommendedWatcher,
+}
+
+#[cfg(feature = "watch")]
+impl FileWatcher {
+    /// Call `on_change` whenever the file at `path` is created or modified.
+    ///
+    /// The directory of the file is watched, rather than the file itself,
+    /// since atomic saves replace the file.
+    pub(crate) fn new<F>(
+        path: &std::path::Path,
+        mut on_change: F,
+    ) -> crate::error::BackendResult<Self>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        use notify::{EventKind, RecursiveMode, Watcher};
+
+        let name = path.file_name().map(ToOwned::to_owned);
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => std::path::Path::new("."),
+        };
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let Ok(event) = event else { return };
+                let relevant = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                    && event
+                        .paths
+                        .iter()
+                        .any(|changed| changed.file_name() == name.as_deref());
+                if relevant {
+                    on_change();
+                }
+            })?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(Self { _watcher: watcher })
+    }
+}
+
+#[cfg(feature = "watch")]
+impl fmt::Debug for FileWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileWatcher").finish_non_exhaustive()
+    }
+}
+
+/// Reloads a database when the process receives `SIGHUP`, returned by
+/// [`Database::reload_on_sighup`](crate::Database::reload_on_sighup).
+///
+/// The signal no longer reloads the database once this is dropped.
+#[cfg(all(unix, feature = "signals"))]
+#[derive(Debug)]
+pub struct SignalWatcher {
+    listener: crate::signals::ListenerId,
+}
+
+#[cfg(all(unix, feature = "signals"))]
+impl SignalWatcher {
+    /// Call `on_signal` whenever the process receives `SIGHUP`.
+    pub(crate) fn new<F>(on_signal: F) -> crate::error::BackendResult<Self>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        use std::sync::{Mutex, PoisonError};
+
+        let on_signal = Mutex::new(on_signal);
+        let listener = crate::signals::listen(crate::signals::Signal::Hangup, move || {
+            // A panic of the callback does not leave anything half done.
+            let mut on_signal = on_signal.lock().unwrap_or_else(PoisonError::into_inner);
+            on_signal();
+        })?;
+        Ok(Self { listener })
+    }
+}
+
+#[cfg(all(unix, feature = "signals"))]
+impl Drop for SignalWatcher {
+    fn drop(&mut self) {
+        crate::signals::unlisten(self.listener);
+    }
+}
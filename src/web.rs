@@ -0,0 +1,278 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Helpers for using a database from the handlers of a web framework.
+//!
+//! **Important**: This module is only available if the `web` feature is
+//! enabled.
+//!
+//! Put a [`SharedDatabase`](crate::SharedDatabase) in the state of the
+//! application. It is cheap to clone, which is all frameworks ask of their
+//! state: it works as axum `State` directly, with `#[derive(FromRef)]` on a
+//! larger state struct, or wrapped in `actix_web::web::Data`. Handlers then
+//! turn errors of the database into HTTP responses with [`WebError`], which
+//! builds on the [`http`](http_types) crate that the frameworks share.
+//!
+//! With the `axum` feature, [`WebError`] implements `IntoResponse`, so
+//! handlers can return `Result<_, WebError>` as is. A
+//! [`SharedDatabase`](crate::SharedDatabase) is then also an extractor of its
+//! own: handlers can take it as an argument, without `State`, from any state
+//! it can be taken out of with `FromRef`.
+//!
+//! # Example
+//!
+//! An axum handler, written against the `http` types only:
+//!
+//! ```rust
+//! # extern crate rustbreak;
+//! # extern crate http_types as http;
+//! use rustbreak::web::WebError;
+//! use rustbreak::{deser::Ron, MemoryDatabase, SharedDatabase};
+//!
+//! type Visits = SharedDatabase<u64, rustbreak::backend::MemoryBackend, Ron>;
+//!
+//! // With axum: `async fn visit(State(db): State<Visits>) -> Result<String, http::Response<String>>`
+//! fn visit(db: Visits) -> Result<String, http::Response<String>> {
+//!     let count = db
+//!         .write_and_save(|visits| {
+//!             *visits += 1;
+//!             *visits
+//!         })
+//!         .map_err(WebError::into_response)?;
+//!     Ok(format!("visit number {count}"))
+//! }
+//!
+//! # fn main() -> rustbreak::error::Result<()> {
+//! let db = SharedDatabase::new(MemoryDatabase::memory(0)?);
+//! assert_eq!(visit(db.clone()).unwrap(), "visit number 1");
+//!
+//! db.set_max_save_size(Some(0));
+//! let response = visit(db).unwrap_err();
+//! assert_eq!(response.status(), http::StatusCode::INSUFFICIENT_STORAGE);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+
+use http_types::{header, Response, StatusCode};
+
+use crate::error::{BackendError, RustbreakError};
+
+/// An error of the database, with the HTTP status it should be answered
+/// with.
+///
+/// | Error | Status |
+/// |-------|--------|
+/// | [`RustbreakError::WouldBlock`], [`RustbreakError::Timeout`], [`BackendError::Locked`], [`BackendError::ReadOnly`] | 503 Service Unavailable |
+/// | [`RustbreakError::ExternalChange`], [`BackendError::Conflict`] | 409 Conflict |
+/// | [`RustbreakError::Validation`] | 422 Unprocessable Content |
+/// | [`RustbreakError::Aborted`] | 400 Bad Request |
+/// | [`BackendError::QuotaExceeded`] | 507 Insufficient Storage |
+/// | Everything else | 500 Internal Server Error |
+///
+/// Handlers can use `?` on database calls when they return a
+/// `Result<_, WebError>`, and turn the error into a response with
+/// [`WebError::into_response`] at the end.
+#[derive(Debug)]
+pub struct WebError {
+    status: StatusCode,
+    error: RustbreakError,
+}
+
+impl WebError {
+    /// Wrap `error` with the status of the table above.
+    #[must_use]
+    pub fn new(error: RustbreakError) -> Self {
+        Self {
+            status: status_of(&error),
+            error,
+        }
+    }
+
+    /// Wrap `error` with another status.
+    #[must_use]
+    pub fn with_status(error: RustbreakError, status: StatusCode) -> Self {
+        Self { status, error }
+    }
+
+    /// The status the error should be answered with.
+    #[must_use]
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The error of the database.
+    #[must_use]
+    pub fn error(&self) -> &RustbreakError {
+        &self.error
+    }
+
+    /// Get back the error of the database.
+    #[must_use]
+    pub fn into_error(self) -> RustbreakError {
+        self.error
+    }
+
+    /// A plain text response with the status, and the message of the error as
+    /// the body.
+    ///
+    /// Errors of the server only say so, without the details, which belong
+    /// into the log rather than to the client.
+    pub fn into_response<E: Into<Self>>(error: E) -> Response<String> {
+        let error = error.into();
+        let body = if error.status.is_server_error() {
+            error
+                .status
+                .canonical_reason()
+                .unwrap_or("Server error")
+                .to_owned()
+        } else {
+            error.error.to_string()
+        };
+        let mut response = Response::new(body);
+        *response.status_mut() = error.status;
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("text/plain; charset=utf-8"),
+        );
+        response
+    }
+}
+
+impl From<RustbreakError> for WebError {
+    fn from(error: RustbreakError) -> Self {
+        Self::new(error)
+    }
+}
+
+impl From<WebError> for Response<String> {
+    fn from(error: WebError) -> Self {
+        WebError::into_response(error)
+    }
+}
+
+#[cfg(feature = "axum")]
+impl axum_core::response::IntoResponse for WebError {
+    /// The response of [`WebError::into_response`].
+    fn into_response(self) -> axum_core::response::Response {
+        WebError::into_response(self).into_response()
+    }
+}
+
+#[cfg(feature = "axum")]
+impl<S, Data, Back, DeSer> axum_core::extract::FromRequestParts<S>
+    for crate::SharedDatabase<Data, Back, DeSer>
+where
+    Self: axum_core::extract::FromRef<S> + Send,
+    S: Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    /// Take the database out of the state, with `FromRef`.
+    fn from_request_parts(
+        _parts: &mut http_types::request::Parts,
+        state: &S,
+    ) -> impl std::future::Future<Output = Result<Self, Self::Rejection>> + Send {
+        std::future::ready(Ok(axum_core::extract::FromRef::from_ref(state)))
+    }
+}
+
+impl fmt::Display for WebError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.status, self.error)
+    }
+}
+
+impl std::error::Error for WebError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// The status of the table of [`WebError`].
+fn status_of(error: &RustbreakError) -> StatusCode {
+    match error {
+        RustbreakError::WouldBlock
+        | RustbreakError::Timeout
+        | RustbreakError::Backend(BackendError::Locked | BackendError::ReadOnly) => {
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+        RustbreakError::ExternalChange | RustbreakError::Backend(BackendError::Conflict) => {
+            StatusCode::CONFLICT
+        }
+        RustbreakError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        RustbreakError::Aborted(_) => StatusCode::BAD_REQUEST,
+        RustbreakError::Backend(BackendError::QuotaExceeded { .. }) => {
+            StatusCode::INSUFFICIENT_STORAGE
+        }
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WebError;
+    use crate::error::RustbreakError;
+    use http_types::StatusCode;
+
+    #[test]
+    fn responses_hide_server_errors() {
+        let response = WebError::into_response(RustbreakError::Poison);
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(response.body(), "Internal Server Error");
+
+        let response = WebError::into_response(RustbreakError::ExternalChange);
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(
+            response.body(),
+            "The data in the backend was changed externally"
+        );
+        assert_eq!(
+            WebError::with_status(RustbreakError::WouldBlock, StatusCode::TOO_MANY_REQUESTS)
+                .status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "axum")]
+    fn axum_handlers_can_use_the_database() {
+        use crate::backend::MemoryBackend;
+        use crate::deser::Ron;
+        use crate::{MemoryDatabase, SharedDatabase};
+        use axum_core::extract::{FromRef, FromRequestParts};
+        use axum_core::response::IntoResponse;
+        use std::future::Future;
+
+        type Visits = SharedDatabase<u64, MemoryBackend, Ron>;
+
+        #[derive(Clone)]
+        struct AppState {
+            visits: Visits,
+        }
+
+        impl FromRef<AppState> for Visits {
+            fn from_ref(state: &AppState) -> Self {
+                state.visits.clone()
+            }
+        }
+
+        let state = AppState {
+            visits: SharedDatabase::new(MemoryDatabase::memory(3).expect("could not create")),
+        };
+        let (mut parts, ()) = http_types::Request::new(()).into_parts();
+        let mut extract = std::pin::pin!(Visits::from_request_parts(&mut parts, &state));
+        let std::task::Poll::Ready(Ok(db)) = extract
+            .as_mut()
+            .poll(&mut std::task::Context::from_waker(std::task::Waker::noop()))
+        else {
+            panic!("the database is extracted right away");
+        };
+        assert_eq!(db.read(|visits| *visits).expect("could not read"), 3);
+
+        let response = WebError::new(RustbreakError::ExternalChange).into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+}
@@ -1,5 +1,8 @@
 use rustbreak::backend::Backend;
-use rustbreak::deser::{Bincode, DeSerializer, Ron, Yaml};
+// The deprecated `Yaml` is tested until it is removed.
+#[allow(deprecated)]
+type Yaml = rustbreak::deser::Yaml;
+use rustbreak::deser::{Bincode, Cbor, DeSerializer, Gzip, Json, Postcard, Ron, SafeYaml, Zstd};
 use rustbreak::{Database, FileDatabase, MemoryDatabase, MmapDatabase, PathDatabase};
 use std::fmt::Debug;
 use std::ops::Deref;
@@ -155,24 +158,46 @@ macro_rules! test_basic_save_load {
 
 test_basic_save_load!(file_ron, create_filedb(), Ron);
 test_basic_save_load!(file_yaml, create_filedb(), Yaml);
+test_basic_save_load!(file_safe_yaml, create_filedb(), SafeYaml);
 test_basic_save_load!(file_bincode, create_filedb(), Bincode);
+test_basic_save_load!(file_json, create_filedb(), Json);
+test_basic_save_load!(file_cbor, create_filedb(), Cbor);
+test_basic_save_load!(file_postcard, create_filedb(), Postcard);
 
 test_basic_save_load!(filepath_ron, create_filedb_from_path(), Ron);
 test_basic_save_load!(filepath_yaml, create_filedb_from_path(), Yaml);
+test_basic_save_load!(filepath_safe_yaml, create_filedb_from_path(), SafeYaml);
 test_basic_save_load!(filepath_bincode, create_filedb_from_path(), Bincode);
+test_basic_save_load!(filepath_json, create_filedb_from_path(), Json);
+test_basic_save_load!(filepath_cbor, create_filedb_from_path(), Cbor);
+test_basic_save_load!(filepath_postcard, create_filedb_from_path(), Postcard);
 
 test_basic_save_load!(mem_ron, create_memdb(), Ron, miri = true);
 test_basic_save_load!(mem_yaml, create_memdb(), Yaml, miri = true);
+test_basic_save_load!(mem_safe_yaml, create_memdb(), SafeYaml, miri = true);
 test_basic_save_load!(mem_bincode, create_memdb(), Bincode, miri = true);
+test_basic_save_load!(mem_json, create_memdb(), Json, miri = true);
+test_basic_save_load!(mem_cbor, create_memdb(), Cbor, miri = true);
+test_basic_save_load!(mem_postcard, create_memdb(), Postcard, miri = true);
+test_basic_save_load!(mem_zstd_ron, create_memdb(), Zstd<Ron>);
+test_basic_save_load!(mem_gzip_bincode, create_memdb(), Gzip<Bincode>, miri = true);
 
 test_basic_save_load!(mmap_ron, create_mmapdb(), Ron);
 test_basic_save_load!(mmap_yaml, create_mmapdb(), Yaml);
+test_basic_save_load!(mmap_safe_yaml, create_mmapdb(), SafeYaml);
 test_basic_save_load!(mmap_bincode, create_mmapdb(), Bincode);
 
 test_basic_save_load!(mmapsize_ron, create_mmapdb_with_size(10), Ron);
 test_basic_save_load!(mmapsize_yaml, create_mmapdb_with_size(10), Yaml);
+test_basic_save_load!(mmapsize_safe_yaml, create_mmapdb_with_size(10), SafeYaml);
 test_basic_save_load!(mmapsize_bincode, create_mmapdb_with_size(10), Bincode);
 
 test_basic_save_load!(path_ron, create_pathdb(), Ron);
 test_basic_save_load!(path_yaml, create_pathdb(), Yaml);
+test_basic_save_load!(path_safe_yaml, create_pathdb(), SafeYaml);
 test_basic_save_load!(path_bincode, create_pathdb(), Bincode);
+test_basic_save_load!(path_json, create_pathdb(), Json);
+test_basic_save_load!(path_cbor, create_pathdb(), Cbor);
+test_basic_save_load!(path_postcard, create_pathdb(), Postcard);
+test_basic_save_load!(path_zstd_ron, create_pathdb(), Zstd<Ron>);
+test_basic_save_load!(path_gzip_bincode, create_pathdb(), Gzip<Bincode>);